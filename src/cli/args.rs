@@ -1,36 +1,479 @@
 //! Command-line argument definitions and parsing
 
-use clap::Parser;
+use crate::cli::validation::{self, Problem};
+use crate::config::Settings;
+use crate::downloader::rate_limit::parse_rate;
+use crate::extractor::WaitRange;
+use crate::file_system::{CleanupPolicy, CollisionPolicy};
+use crate::models::TimeRange;
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(name = "downloader")]
 #[command(about = "High-performance YouTube video downloader")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true, arg_required_else_help = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    
+    // A bare URL and download options are shorthand for `download URL`
+    /// YouTube video URL to download
+    #[arg(value_name = "URL", required = true)]
+    pub url: Option<String>,
+    
+    /// List available formats without downloading
+    #[arg(short = 'F', long)]
+    pub list_formats: bool,
+    
+    #[command(flatten)]
+    pub options: DownloadOptions,
+    
+    /// Verbose output
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+}
+
+impl Cli {
+    /// The command to run, treating a bare URL as `download`
+    pub fn into_command(self) -> Command {
+        match self.command {
+            Some(command) => command,
+            None => Command::Download(Args {
+                url: self.url.expect("clap requires a URL without a subcommand"),
+                list_formats: self.list_formats,
+                options: self.options,
+            }),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Download a video (the default when only a URL is given)
+    Download(Args),
+    
+    /// Show details about a video without downloading it
+    Info {
+        /// YouTube video URL
+        #[arg(value_name = "URL")]
+        url: String,
+        
+        /// Print the video details as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// List the formats available for a video
+    Formats {
+        /// YouTube video URL
+        #[arg(value_name = "URL")]
+        url: String,
+        
+        /// Print the format list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Download several videos one after another
+    Queue(QueueArgs),
+    
+    /// List previously downloaded videos
+    History(HistoryArgs),
+    
+    /// Show, locate or create the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    
+    /// Check that downloaded files still exist and are unchanged
+    Verify {
+        /// Files to check (default: every file in the download history)
+        #[arg(value_name = "FILE")]
+        paths: Vec<PathBuf>,
+    },
+    
+    /// Accept downloads over a local HTTP API
+    Serve(ServeArgs),
+    
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    
+    /// Print the man page in roff format
+    Manpage,
+}
+
+/// Arguments of `download`
+#[derive(clap::Args, Debug)]
 pub struct Args {
     /// YouTube video URL to download
     #[arg(value_name = "URL")]
     pub url: String,
     
-    /// Output directory (optional)
-    #[arg(short, long)]
+    /// List available formats without downloading
+    #[arg(short = 'F', long)]
+    pub list_formats: bool,
+    
+    #[command(flatten)]
+    pub options: DownloadOptions,
+}
+
+/// Arguments of `queue`
+#[derive(clap::Args, Debug)]
+pub struct QueueArgs {
+    /// YouTube video URLs to download
+    #[arg(value_name = "URL", required_unless_present = "batch_file")]
+    pub urls: Vec<String>,
+    
+    /// Also read URLs from this file, one per line ("-" for standard input);
+    /// blank lines and lines starting with '#' are ignored
+    #[arg(long, value_name = "FILE")]
+    pub batch_file: Option<PathBuf>,
+    
+    /// Show a full-screen dashboard to follow, pause, cancel and reorder the downloads
+    #[arg(long, conflicts_with_all = ["json", "progress_json", "print", "get_url"])]
+    pub dashboard: bool,
+    
+    #[command(flatten)]
+    pub options: DownloadOptions,
+}
+
+/// Arguments of `history`
+#[derive(clap::Args, Debug)]
+pub struct HistoryArgs {
+    /// Only show the most recent downloads
+    #[arg(short = 'n', long, value_name = "N")]
+    pub limit: Option<usize>,
+    
+    /// Print the history as JSON
+    #[arg(long)]
+    pub json: bool,
+    
+    /// Forget every recorded download (files are kept)
+    #[arg(long, conflicts_with_all = ["limit", "json"])]
+    pub clear: bool,
+}
+
+/// Actions of `config`
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Print the settings in effect, as TOML
+    Show,
+    /// Print where the configuration file is read from
+    Path,
+    /// Write a commented sample configuration file
+    Init,
+}
+
+/// Arguments of `serve`
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8765")]
+    pub bind: SocketAddr,
+    
+    #[command(flatten)]
+    pub options: DownloadOptions,
+}
+
+/// Options shared by every command that downloads
+#[derive(clap::Args, Debug)]
+pub struct DownloadOptions {
+    /// Output filename template (e.g. "%(uploader)s/%(title).80s [%(id)s].%(ext)s")
+    #[arg(short, long, value_name = "TEMPLATE")]
     pub output: Option<String>,
     
+    /// Output directory (defaults to the configured download directory)
+    #[arg(short = 'P', long, value_name = "DIR")]
+    pub output_dir: Option<String>,
+    
     /// Skip interactive selection and use best quality
-    #[arg(short, long)]
+    #[arg(long)]
     pub auto: bool,
     
+    /// Show what would be downloaded and where, without downloading or writing anything
+    #[arg(short, long)]
+    pub simulate: bool,
+    
+    /// Print this template for each video instead of downloading, e.g. "%(title)s|%(duration)s" (repeatable)
+    #[arg(long, value_name = "TEMPLATE")]
+    pub print: Vec<String>,
+    
+    /// Print the direct media URL of the selected format instead of downloading
+    #[arg(short = 'g', long)]
+    pub get_url: bool,
+    
     /// Force audio-only download
     #[arg(short = 'a', long)]
     pub audio_only: bool,
     
-    /// Verbose output
-    #[arg(short, long)]
-    pub verbose: bool,
+    /// Maximum download rate in bytes per second, shared by all downloads (e.g. "500K", "2.5M")
+    #[arg(short = 'r', long, value_name = "RATE", value_parser = parse_rate)]
+    pub limit_rate: Option<u64>,
+    
+    /// Record a live stream from the start of its DVR window instead of the live edge
+    #[arg(long)]
+    pub live_from_start: bool,
+    
+    /// Stop recording a live stream after this long (e.g. "90m", "1h30m", "01:30:00")
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub live_duration: Option<Duration>,
+    
+    /// Write title, uploader, date, description and URL tags into the output file
+    #[arg(long)]
+    pub embed_metadata: bool,
+    
+    /// Embed the video thumbnail as cover art
+    #[arg(long)]
+    pub embed_thumbnail: bool,
+    
+    /// Embed chapter markers into the output file (MP4 only)
+    #[arg(long)]
+    pub embed_chapters: bool,
+    
+    /// Embed caption tracks into the output file (MP4 only)
+    #[arg(long)]
+    pub embed_subs: bool,
+    
+    /// Caption languages to embed, comma separated (default: all non-automatic tracks)
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    pub sub_langs: Vec<String>,
+    
+    /// Only download the given time range, e.g. "*10:15-12:30" or "*1h-inf" (repeatable)
+    #[arg(long, value_name = "*START-END", value_parser = parse_section)]
+    pub download_sections: Vec<TimeRange>,
+    
+    /// Also write one file per chapter, cut without re-encoding
+    #[arg(long)]
+    pub split_chapters: bool,
+    
+    /// Filename template for chapter files (adds section_title, section_number,
+    /// section_start and section_end fields)
+    #[arg(long, value_name = "TEMPLATE")]
+    pub chapter_output: Option<String>,
+    
+    /// Copy the streams into another container with ffmpeg after downloading (mp4, mkv, webm, mov, m4a, mka)
+    #[arg(long, value_name = "FORMAT")]
+    pub remux_video: Option<String>,
+    
+    /// Move finished files into this directory
+    #[arg(long, value_name = "DIR")]
+    pub move_to: Option<String>,
+    
+    /// What to do when the output file already exists
+    #[arg(long, value_name = "POLICY", value_enum)]
+    pub on_collision: Option<CollisionPolicy>,
+    
+    /// When to remove partial and temporary files
+    #[arg(long, value_name = "WHEN", value_enum)]
+    pub temp_cleanup: Option<CleanupPolicy>,
+    
+    /// Run a command on each finished file: "{}" is the path, %(field)s are template fields (repeatable)
+    #[arg(long, value_name = "CMD")]
+    pub exec: Vec<String>,
+    
+    /// Run a command before each download starts; "{}" is the planned output path (repeatable)
+    #[arg(long, value_name = "CMD")]
+    pub exec_before_download: Vec<String>,
+    
+    /// Run a command once after all downloads finish, with every file as "{}" (repeatable)
+    #[arg(long, value_name = "CMD")]
+    pub exec_after_queue: Vec<String>,
+    
+    /// Mark SponsorBlock segments of these categories as chapters, comma separated ("all" for every category)
+    #[arg(long, value_name = "CATS", value_delimiter = ',')]
+    pub sponsorblock_mark: Vec<String>,
+    
+    /// Cut SponsorBlock segments of these categories out of the download, comma separated
+    #[arg(long, value_name = "CATS", value_delimiter = ',')]
+    pub sponsorblock_remove: Vec<String>,
+    
+    /// SponsorBlock API base URL
+    #[arg(long, value_name = "URL")]
+    pub sponsorblock_api: Option<String>,
+    
+    /// Print the video, chosen format and saved path as JSON on stdout (with -F, the format list);
+    /// other messages go to stderr
+    #[arg(long, visible_alias = "print-json")]
+    pub json: bool,
+    
+    /// Stream newline-delimited JSON events (started, progress, finished, error) on stdout
+    #[arg(long)]
+    pub progress_json: bool,
+    
+    /// Only print errors and results: no status messages or progress
+    #[arg(short = 'q', long)]
+    pub quiet: bool,
+    
+    /// Hide progress bars (and the progress lines printed when stderr is not a terminal)
+    #[arg(long)]
+    pub no_progress: bool,
+    
+    /// Show which fragment each connection is fetching below the progress bar
+    #[arg(long, conflicts_with = "no_progress")]
+    pub show_connections: bool,
+    
+    /// Wait for upcoming premieres and streams, re-polling every MIN[-MAX] (e.g. "60", "1m-10m")
+    #[arg(long, value_name = "MIN[-MAX]", value_parser = parse_wait_range)]
+    pub wait_for_video: Option<WaitRange>,
 }
 
 impl Args {
-    // TODO: Add validation methods
-    pub fn validate(&self) -> crate::Result<()> {
-        todo!("Implement argument validation")
+    /// Check the URL and options against each other and `settings`.
+    ///
+    /// Fails with every problem found; otherwise returns warnings about
+    /// options that will be ignored or settings that look wrong.
+    pub fn validate(&self, settings: &Settings) -> crate::Result<Vec<Problem>> {
+        let mut validation = validation::validate_options(&self.options, settings);
+        validation::validate_url(&self.url, &self.options, &mut validation);
+        validation.into_result()
+    }
+}
+
+impl DownloadOptions {
+    /// Like [`Args::validate`], for commands that take their URLs elsewhere
+    pub fn validate(&self, settings: &Settings) -> crate::Result<Vec<Problem>> {
+        validation::validate_options(self, settings).into_result()
+    }
+    
+    /// Whether videos are only looked at: `--simulate`, `--print` or `--get-url`
+    pub fn skips_download(&self) -> bool {
+        self.simulate || self.prints_lines()
+    }
+    
+    /// Whether `--print` or `--get-url` replace the download with lines on stdout
+    pub fn prints_lines(&self) -> bool {
+        self.get_url || !self.print.is_empty()
+    }
+}
+
+/// Parse a duration given as seconds ("90"), units ("1h30m", "45s") or clock time ("01:30:00")
+pub fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("duration cannot be empty".to_string());
+    }
+    let invalid = || format!("invalid duration '{}'", value);
+    let too_long = || format!("duration '{}' is too long", value);
+    
+    if value.contains(':') {
+        let mut seconds = 0u64;
+        for part in value.split(':') {
+            let part = part.parse::<u64>().map_err(|_| invalid())?;
+            seconds = seconds.checked_mul(60).and_then(|seconds| seconds.checked_add(part)).ok_or_else(too_long)?;
+        }
+        return Ok(Duration::from_secs(seconds));
+    }
+    
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+    
+    let mut seconds = 0u64;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let count = number.parse::<u64>().map_err(|_| invalid())?;
+        seconds = count.checked_mul(unit).and_then(|part| seconds.checked_add(part)).ok_or_else(too_long)?;
+        number.clear();
+    }
+    
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parse a `MIN[-MAX]` wait range; each bound accepts the same syntax as `parse_duration`
+pub fn parse_wait_range(value: &str) -> std::result::Result<WaitRange, String> {
+    let (min, max) = match value.split_once('-') {
+        Some((min, max)) => (parse_duration(min)?, parse_duration(max)?),
+        None => {
+            let min = parse_duration(value)?;
+            (min, min)
+        }
+    };
+    
+    if min > max {
+        return Err(format!("minimum wait is longer than maximum in '{}'", value));
+    }
+    Ok(WaitRange { min, max })
+}
+
+/// Parse a `*START-END` section; END may be `inf` for the end of the video
+pub fn parse_section(value: &str) -> std::result::Result<TimeRange, String> {
+    let range = value
+        .strip_prefix('*')
+        .ok_or_else(|| format!("section '{}' must be a time range like \"*10:15-12:30\"", value))?;
+    let (start, end) = range
+        .split_once('-')
+        .ok_or_else(|| format!("section '{}' is missing an end time", value))?;
+    
+    let start = parse_duration(start)?.as_secs_f64();
+    let end = match end.trim() {
+        "inf" | "infinite" => f64::INFINITY,
+        end => parse_duration(end)?.as_secs_f64(),
+    };
+    if start >= end {
+        return Err(format!("section '{}' ends before it starts", value));
+    }
+    Ok(TimeRange { start, end })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("01:30:00"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2:05"), Ok(Duration::from_secs(125)));
+        assert!(parse_duration("1x").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert_eq!(parse_duration(""), Err("duration cannot be empty".to_string()));
+        assert_eq!(parse_duration("99999999999999999h"), Err("duration '99999999999999999h' is too long".to_string()));
+        assert!(parse_duration("99999999999999999:0:0").unwrap_err().contains("too long"));
+        assert!(parse_section("*-1:00").is_err());
+    }
+    
+    #[test]
+    fn test_parse_wait_range() {
+        let range = parse_wait_range("1m-10m").unwrap();
+        assert_eq!((range.min, range.max), (Duration::from_secs(60), Duration::from_secs(600)));
+        
+        let range = parse_wait_range("30").unwrap();
+        assert_eq!((range.min, range.max), (Duration::from_secs(30), Duration::from_secs(30)));
+        
+        assert!(parse_wait_range("10m-1m").is_err());
+    }
+    
+    #[test]
+    fn test_parse_section() {
+        assert_eq!(parse_section("*10:15-12:30"), Ok(TimeRange { start: 615.0, end: 750.0 }));
+        assert_eq!(parse_section("*1h-inf"), Ok(TimeRange { start: 3600.0, end: f64::INFINITY }));
+        assert!(parse_section("10:15-12:30").is_err());
+        assert!(parse_section("*12:30-10:15").is_err());
+        assert!(parse_section("*10:15").is_err());
     }
 }
\ No newline at end of file
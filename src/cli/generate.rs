@@ -0,0 +1,89 @@
+//! Shell completions and the man page, generated from the argument definitions
+
+use crate::cli::args::Cli;
+use crate::config::Settings;
+use crate::Result;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use clap_mangen::roff::{bold, italic, roman, Roff};
+use clap_mangen::Man;
+use std::io::Write;
+
+/// Name the completions are registered for
+const BIN_NAME: &str = "downloader";
+
+/// Write the completion script for `shell`
+pub fn completions(shell: Shell, out: &mut dyn Write) -> Result<()> {
+    clap_complete::generate(shell, &mut Cli::command(), BIN_NAME, out);
+    Ok(())
+}
+
+/// Write the man page, including every documented configuration key
+pub fn manpage(out: &mut dyn Write) -> Result<()> {
+    let man = Man::new(Cli::command().name(BIN_NAME));
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    man.render_options_section(out)?;
+    man.render_subcommands_section(out)?;
+    configuration_section().to_writer(out)?;
+    man.render_version_section(out)?;
+    Ok(())
+}
+
+fn configuration_section() -> Roff {
+    let mut roff = Roff::new();
+    roff.control("SH", ["CONFIGURATION"]);
+    roff.text([
+        roman("Settings are read from "),
+        italic("config.toml"),
+        roman(" in the user configuration directory; "),
+        bold("downloader config path"),
+        roman(" prints its location and "),
+        bold("downloader config init"),
+        roman(" writes a commented sample. Every key is optional and command-line options take precedence."),
+    ]);
+    for key in Settings::documented_keys() {
+        roff.control("TP", []);
+        roff.text([bold(key.name), roman(" = "), italic(key.example)]);
+        roff.text([roman(key.description)]);
+    }
+    roff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_every_setting_is_documented() {
+        let documented: Vec<String> = Settings::documented_keys().into_iter().map(|key| key.name).collect();
+        let defaults = toml::Value::try_from(Settings::default()).unwrap();
+        
+        for (name, value) in defaults.as_table().unwrap() {
+            match value.as_table() {
+                Some(table) => {
+                    for nested in table.keys() {
+                        let name = format!("{}.{}", name, nested);
+                        assert!(documented.contains(&name), "{} is not documented", name);
+                    }
+                }
+                None => assert!(documented.contains(name), "{} is not documented", name),
+            }
+        }
+    }
+    
+    #[test]
+    fn test_manpage() {
+        let mut page = Vec::new();
+        manpage(&mut page).unwrap();
+        let page = String::from_utf8(page).unwrap();
+        
+        assert!(page.starts_with(".ie"));
+        assert!(page.contains(".TH downloader"));
+        assert!(page.contains(".SH CONFIGURATION"));
+        assert!(page.contains("max_concurrent_downloads"));
+        assert!(page.contains("hooks.after_file"));
+    }
+}
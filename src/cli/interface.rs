@@ -1,36 +1,84 @@
 //! Interactive command-line interface components
 
-use crate::models::{Format, FormatType};
+use crate::error::DownloaderError;
+use crate::extractor::FormatExtractor;
+use crate::models::{DownloadProgress, Format, FormatType, VideoInfo};
+use crate::ui::{FormatPicker, FormatSelection, SelectionUI};
 use crate::Result;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal};
 
-pub struct InteractiveInterface;
+pub struct InteractiveInterface {
+    ui: SelectionUI,
+}
+
+impl Default for InteractiveInterface {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl InteractiveInterface {
     pub fn new() -> Self {
-        Self
+        Self { ui: SelectionUI::new() }
+    }
+    
+    /// Let the user choose the streams to download, starting from `format_type`
+    /// when given: a full-screen picker on a terminal, numbered prompts otherwise
+    pub fn select_formats(&self, video_info: &VideoInfo, format_type: Option<FormatType>) -> Result<FormatSelection> {
+        if video_info.available_formats.is_empty() {
+            return Err(DownloaderError::NoFormatsFound);
+        }
+        if io::stdout().is_terminal() && io::stdin().is_terminal() {
+            return FormatPicker::new(video_info).with_type_filter(format_type).run();
+        }
+        
+        self.ui.display_video_info(video_info)?;
+        let format_type = match format_type {
+            Some(format_type) => format_type,
+            None => self.select_format_type()?,
+        };
+        let format = self.select_quality(&FormatExtractor::filter_by_type(&video_info.available_formats, format_type.clone()))?;
+        
+        // Video-only streams need a separate audio stream to have sound
+        let audio = if format.format_type == FormatType::Video && format.is_adaptive {
+            let mut audio = FormatExtractor::filter_by_type(&video_info.available_formats, FormatType::Audio);
+            FormatExtractor::sort_by_quality(&mut audio);
+            self.ui.select_audio(&audio)?.map(|index| audio.swap_remove(index))
+        } else {
+            None
+        };
+        
+        let mut selection = FormatSelection::single(format);
+        selection.audio = selection.audio.or(audio);
+        Ok(selection)
     }
     
     /// Present format selection to user (Audio vs Video)
     pub fn select_format_type(&self) -> Result<FormatType> {
-        // TODO: Implement interactive format type selection
-        todo!("Implement format type selection UI")
+        self.ui.select_format_type()
     }
     
-    /// Present quality selection to user
+    /// Present quality selection to user, best first
     pub fn select_quality(&self, available_formats: &[Format]) -> Result<Format> {
-        // TODO: Implement interactive quality selection
-        todo!("Implement quality selection UI")
+        if available_formats.is_empty() {
+            return Err(DownloaderError::NoFormatsFound);
+        }
+        let mut formats = available_formats.to_vec();
+        FormatExtractor::sort_by_quality(&mut formats);
+        let index = self.ui.select_quality(&formats)?;
+        Ok(formats.swap_remove(index))
     }
     
     /// Confirm download for large files
     pub fn confirm_large_download(&self, file_size: u64) -> Result<bool> {
-        // TODO: Implement download confirmation for large files
-        todo!("Implement large download confirmation")
+        let answer = self.get_user_input(&format!(
+            "This download is about {}. Continue? [y/N] ",
+            DownloadProgress::format_bytes(file_size)
+        ))?;
+        Ok(matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes"))
     }
     
-    // TODO: Add helper methods for user input
     fn get_user_input(&self, prompt: &str) -> Result<String> {
-        todo!("Implement user input helper")
+        self.ui.read_input(prompt)
     }
 }
\ No newline at end of file
@@ -2,4 +2,8 @@
 //! Handles argument parsing and user interaction
 
 pub mod args;
-pub mod interface;
\ No newline at end of file
+pub mod generate;
+pub mod interface;
+pub mod output;
+pub mod server;
+pub mod validation;
@@ -0,0 +1,397 @@
+//! Where messages and results are printed
+//!
+//! With `--json` or `--progress-json`, stdout only carries JSON objects, one
+//! per line, so scripts can read it directly; messages meant for people are
+//! written to stderr instead. The same goes for the lines of `--print` and
+//! `--get-url`.
+
+use crate::cli::args::{Command, DownloadOptions};
+use crate::downloader::QueueHandle;
+use crate::models::{DownloadProgress, DownloadTask, Format, VideoInfo};
+use crate::postprocess::StageFailure;
+use crate::ui::{ProgressBarUI, ProgressMode};
+use crate::Result;
+use serde::Serialize;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Progress updates buffered before older ones are dropped
+const PROGRESS_BUFFER: usize = 64;
+
+/// What was downloaded, as printed by `--json`
+#[derive(Debug, Serialize)]
+pub struct DownloadReport<'a> {
+    pub video: &'a VideoInfo,
+    pub format: &'a Format,
+    pub path: &'a Path,
+}
+
+impl<'a> From<&'a DownloadTask> for DownloadReport<'a> {
+    fn from(task: &'a DownloadTask) -> Self {
+        Self {
+            video: &task.video_info,
+            format: &task.selected_format,
+            path: &task.output_path,
+        }
+    }
+}
+
+/// What `--simulate` found a download would do
+#[derive(Debug, Serialize)]
+pub struct DownloadPlan<'a> {
+    pub video: &'a VideoInfo,
+    pub format: &'a Format,
+    /// Where the download would be written, after handling an existing file
+    pub path: PathBuf,
+    /// Where the file would end up after remuxing and `--move-to`
+    pub final_path: PathBuf,
+    /// Expected size in bytes, if YouTube reports one
+    pub size: Option<u64>,
+    pub action: PlannedAction,
+    /// The video is already in the download history
+    pub previously_downloaded: bool,
+    /// Whether `size` fits in the free space; absent when either is unknown
+    pub enough_space: Option<bool>,
+}
+
+/// How a simulated download would treat its output path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedAction {
+    Download,
+    /// Replace the existing file
+    Overwrite,
+    /// Write next to the existing file under a new name
+    Rename,
+    /// Leave the existing file alone and download nothing
+    Skip,
+}
+
+/// One line of `--progress-json` output
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    Started {
+        video_id: &'a str,
+        title: &'a str,
+        quality: &'a str,
+        itag: Option<u32>,
+        path: &'a Path,
+    },
+    Progress {
+        video_id: &'a str,
+        /// Absent while the total size is unknown, e.g. for live streams
+        percent: Option<f64>,
+        #[serde(flatten)]
+        progress: &'a DownloadProgress,
+    },
+    Finished(DownloadReport<'a>),
+    /// A post-processing stage failed; the file is kept as the earlier stages left it
+    StepFailed {
+        video_id: &'a str,
+        stage: String,
+        error: String,
+    },
+    Simulated(&'a DownloadPlan<'a>),
+    Error {
+        url: &'a str,
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Output {
+    json: bool,
+    progress_json: bool,
+    /// `--print` or `--get-url` lines go to stdout
+    lines: bool,
+    /// Status messages are dropped, e.g. with `--quiet` or while a dashboard owns the terminal
+    quiet: bool,
+    progress: ProgressMode,
+    /// Per-connection bars below each video's bar
+    show_connections: bool,
+}
+
+impl Output {
+    pub fn new(options: &DownloadOptions) -> Self {
+        Self {
+            json: options.json,
+            progress_json: options.progress_json,
+            lines: options.prints_lines(),
+            quiet: options.quiet,
+            // `--progress-json` already reports progress
+            progress: if options.quiet || options.no_progress || options.progress_json {
+                ProgressMode::Hidden
+            } else {
+                ProgressMode::detect()
+            },
+            show_connections: options.show_connections,
+        }
+    }
+    
+    /// Drop status messages and progress
+    pub fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self.progress = ProgressMode::Hidden;
+        self
+    }
+    
+    /// Output mode for a command; only downloads and `info`/`formats --json` print JSON
+    pub fn for_command(command: &Command) -> Self {
+        match command {
+            Command::Download(args) => Self::new(&args.options),
+            Command::Queue(args) => Self::new(&args.options),
+            Command::Serve(args) => Self::new(&args.options),
+            Command::Info { json, .. } | Command::Formats { json, .. } => Self {
+                json: *json,
+                ..Self::default()
+            },
+            _ => Self::default(),
+        }
+    }
+    
+    /// Whether stdout is reserved for JSON
+    pub fn is_json(&self) -> bool {
+        self.json || self.progress_json
+    }
+    
+    /// Print a message for people: stdout normally, stderr when stdout carries JSON or printed lines
+    pub fn status(&self, message: impl Display) {
+        if self.quiet {
+            return;
+        }
+        ProgressBarUI::suspend(|| {
+            if self.is_json() || self.lines {
+                eprintln!("{}", message);
+            } else {
+                println!("{}", message);
+            }
+        });
+    }
+    
+    /// A download is about to start writing `task.output_path`
+    pub fn started(&self, task: &DownloadTask) -> Result<()> {
+        if self.progress_json {
+            Self::print(&ProgressEvent::Started {
+                video_id: &task.video_info.video_id,
+                title: &task.video_info.title,
+                quality: &task.selected_format.quality,
+                itag: task.selected_format.itag,
+                path: &task.output_path,
+            })?;
+        }
+        Ok(())
+    }
+    
+    /// A download is complete, including post-processing
+    pub fn finished(&self, task: &DownloadTask) -> Result<()> {
+        if self.progress_json {
+            Self::print(&ProgressEvent::Finished(task.into()))
+        } else if self.json {
+            Self::print(&DownloadReport::from(task))
+        } else {
+            Ok(())
+        }
+    }
+    
+    /// A post-processing stage failed on a downloaded file
+    pub fn step_failed(&self, task: &DownloadTask, failure: &StageFailure) -> Result<()> {
+        if self.progress_json {
+            return Self::print(&ProgressEvent::StepFailed {
+                video_id: &task.video_info.video_id,
+                stage: failure.stage.to_string(),
+                error: failure.error.to_string(),
+            });
+        }
+        ProgressBarUI::suspend(|| eprintln!("Post-processing step '{}' failed: {}", failure.stage, failure.error));
+        Ok(())
+    }
+    
+    /// Describe a simulated download
+    pub fn simulated(&self, plan: &DownloadPlan) -> Result<()> {
+        if self.progress_json {
+            return Self::print(&ProgressEvent::Simulated(plan));
+        }
+        if self.json {
+            return Self::print(plan);
+        }
+        
+        let verb = if plan.action == PlannedAction::Skip { "Would skip" } else { "Would download" };
+        println!("{}: {} [{}]", verb, plan.video.title, plan.video.video_id);
+        let itag = plan.format.itag.map(|itag| format!(", itag {}", itag)).unwrap_or_default();
+        println!("  Format: {} ({}{})", plan.format.quality_description(), plan.format.file_extension, itag);
+        println!("  Size:   {}", plan.size.map(DownloadProgress::format_bytes).unwrap_or_else(|| "unknown".to_string()));
+        println!("  Path:   {}", plan.path.display());
+        if plan.final_path != plan.path {
+            println!("  Final:  {}", plan.final_path.display());
+        }
+        
+        let note = match plan.action {
+            PlannedAction::Download => None,
+            PlannedAction::Overwrite => Some("the existing file would be overwritten"),
+            PlannedAction::Rename => Some("a file with the planned name exists, so a new name was picked"),
+            PlannedAction::Skip => Some("the file already exists"),
+        };
+        let notes = [
+            note,
+            plan.previously_downloaded.then_some("downloaded before, according to the download history"),
+            (plan.enough_space == Some(false)).then_some("not enough free disk space"),
+        ];
+        for note in notes.into_iter().flatten() {
+            println!("  Note:   {}", note);
+        }
+        Ok(())
+    }
+    
+    /// One line of `--print` or `--get-url` output
+    pub fn line(&self, line: impl Display) {
+        println!("{}", line);
+    }
+    
+    /// A download failed; in the JSON modes the error is reported on stdout
+    pub fn failed(&self, url: &str, error: impl Display) -> Result<()> {
+        if self.progress_json {
+            Self::print(&ProgressEvent::Error {
+                url,
+                error: error.to_string(),
+            })
+        } else if self.json {
+            Self::print(&serde_json::json!({ "url": url, "error": error.to_string() }))
+        } else {
+            ProgressBarUI::suspend(|| eprintln!("Failed to download {}: {}", url, error));
+            Ok(())
+        }
+    }
+    
+    /// An error that ended the program, printed as `{"error": ...}` in the JSON modes
+    pub fn error(&self, error: impl Display) -> Result<()> {
+        if self.is_json() {
+            Self::print(&serde_json::json!({ "error": error.to_string() }))?;
+        }
+        Ok(())
+    }
+    
+    /// A sender whose updates are passed to the queue item, shown as progress
+    /// bars or lines, and printed as `progress` events with `--progress-json`,
+    /// until every clone of it is dropped. `connections` is how many fragments
+    /// are fetched at once, for `--show-connections`.
+    pub fn progress(
+        &self,
+        item: &QueueHandle,
+        video_info: &VideoInfo,
+        connections: usize,
+    ) -> (mpsc::Sender<DownloadProgress>, JoinHandle<()>) {
+        let (sender, mut receiver) = mpsc::channel::<DownloadProgress>(PROGRESS_BUFFER);
+        let (item, video_id, progress_json) = (item.clone(), video_info.video_id.clone(), self.progress_json);
+        let connections = if self.show_connections { connections } else { 0 };
+        let mut bars = ProgressBarUI::new(self.progress, &video_info.title, connections);
+        let printer = tokio::spawn(async move {
+            let mut complete = false;
+            while let Some(progress) = receiver.recv().await {
+                complete = progress.is_complete;
+                bars.update(&progress);
+                if connections > 0 {
+                    bars.update_connections(&item.chunks());
+                }
+                if progress_json {
+                    let _ = Self::print(&ProgressEvent::Progress {
+                        video_id: &video_id,
+                        percent: (progress.total_size > 0).then(|| progress.percentage()),
+                        progress: &progress,
+                    });
+                }
+                item.set_progress(progress);
+            }
+            // Live recordings never know their size, so they end "stopped" too
+            bars.finish(if complete { "done" } else { "stopped" });
+        });
+        (sender, printer)
+    }
+    
+    fn print(value: &impl Serialize) -> Result<()> {
+        println!("{}", serde_json::to_string(value)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FormatType;
+    
+    #[test]
+    fn test_event_shapes() {
+        let mut format = Format::new(
+            "1080p".to_string(),
+            FormatType::Video,
+            "mp4".to_string(),
+            "https://example.com/video".to_string(),
+        );
+        format.itag = Some(137);
+        let task = DownloadTask::new(
+            VideoInfo::new("Test".to_string(), "3:32".to_string(), "dQw4w9WgXcQ".to_string()),
+            format,
+            PathBuf::from("/videos/Test.mp4"),
+        );
+        
+        let started = serde_json::to_value(ProgressEvent::Started {
+            video_id: "dQw4w9WgXcQ",
+            title: "Test",
+            quality: "1080p",
+            itag: Some(137),
+            path: &task.output_path,
+        })
+        .unwrap();
+        assert_eq!(started["event"], "started");
+        assert_eq!(started["path"], "/videos/Test.mp4");
+        
+        let progress = DownloadProgress {
+            total_size: 200,
+            downloaded_size: 50,
+            download_speed: 10.0,
+            eta_seconds: 15,
+            is_complete: false,
+        };
+        let event = serde_json::to_value(ProgressEvent::Progress {
+            video_id: "dQw4w9WgXcQ",
+            percent: Some(progress.percentage()),
+            progress: &progress,
+        })
+        .unwrap();
+        assert_eq!(event["event"], "progress");
+        assert_eq!(event["percent"], 25.0);
+        assert_eq!(event["downloaded_size"], 50);
+        
+        let finished = serde_json::to_value(ProgressEvent::Finished((&task).into())).unwrap();
+        assert_eq!(finished["event"], "finished");
+        assert_eq!(finished["video"]["video_id"], "dQw4w9WgXcQ");
+        assert_eq!(finished["format"]["itag"], 137);
+        
+        let step = serde_json::to_value(ProgressEvent::StepFailed {
+            video_id: "dQw4w9WgXcQ",
+            stage: "tag".to_string(),
+            error: "unsupported container".to_string(),
+        })
+        .unwrap();
+        assert_eq!(step["event"], "step_failed");
+        assert_eq!(step["stage"], "tag");
+        
+        let plan = DownloadPlan {
+            video: &task.video_info,
+            format: &task.selected_format,
+            path: PathBuf::from("/videos/Test (1).mp4"),
+            final_path: PathBuf::from("/library/Test (1).mkv"),
+            size: None,
+            action: PlannedAction::Rename,
+            previously_downloaded: true,
+            enough_space: None,
+        };
+        let simulated = serde_json::to_value(ProgressEvent::Simulated(&plan)).unwrap();
+        assert_eq!(simulated["event"], "simulated");
+        assert_eq!(simulated["action"], "rename");
+        assert_eq!(simulated["final_path"], "/library/Test (1).mkv");
+        assert!(simulated["size"].is_null());
+    }
+}
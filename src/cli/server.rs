@@ -0,0 +1,148 @@
+//! Local HTTP API for queueing downloads
+//!
+//! A deliberately small HTTP/1.1 server meant for localhost scripts and
+//! browser extensions. Every request gets a JSON response and the connection
+//! is closed afterwards.
+//!
+//! - `GET /health` reports that the server is up
+//! - `GET /history` lists finished downloads
+//! - `POST /download` queues the URL given as the body, either as plain text
+//!   or as `{"url": "..."}`
+
+use crate::error::DownloaderError;
+use crate::file_system::DownloadHistory;
+use crate::utils::UrlValidator;
+use crate::Result;
+use log::{debug, warn};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// Largest request body accepted
+const MAX_BODY: usize = 64 * 1024;
+
+pub struct ApiServer {
+    listener: TcpListener,
+    queue: mpsc::UnboundedSender<String>,
+    history: Arc<DownloadHistory>,
+}
+
+impl ApiServer {
+    /// Listen on `addr`, sending queued URLs to `queue`
+    pub async fn bind(addr: SocketAddr, queue: mpsc::UnboundedSender<String>, history: DownloadHistory) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(Self {
+            listener,
+            queue,
+            history: Arc::new(history),
+        })
+    }
+    
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+    
+    /// Serve requests until the listener fails
+    pub async fn run(self) -> Result<()> {
+        loop {
+            let (stream, peer) = self.listener.accept().await?;
+            let (queue, history) = (self.queue.clone(), self.history.clone());
+            tokio::spawn(async move {
+                if let Err(e) = Self::handle(stream, &queue, &history).await {
+                    warn!("Request from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+    
+    async fn handle(stream: TcpStream, queue: &mpsc::UnboundedSender<String>, history: &DownloadHistory) -> Result<()> {
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).await?;
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+        debug!("{} {}", method, path);
+        
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        
+        let (status, body) = if content_length > MAX_BODY {
+            (413, json!({ "error": "Request body too large" }))
+        } else {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).await?;
+            Self::respond(method, path, &String::from_utf8_lossy(&body), queue, history)
+        };
+        
+        let body = body.to_string();
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            Self::reason(status),
+            body.len(),
+            body
+        );
+        let mut stream = reader.into_inner();
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+    
+    /// Status code and JSON body for a request
+    fn respond(
+        method: &str,
+        path: &str,
+        body: &str,
+        queue: &mpsc::UnboundedSender<String>,
+        history: &DownloadHistory,
+    ) -> (u16, Value) {
+        match (method, path) {
+            ("GET", "/health") => (200, json!({ "status": "ok" })),
+            ("GET", "/history") => match history.entries() {
+                Ok(entries) => (200, json!(entries)),
+                Err(e) => (500, json!({ "error": e.to_string() })),
+            },
+            ("POST", "/download") => {
+                let url = match serde_json::from_str::<Value>(body) {
+                    Ok(value) => value.get("url").and_then(Value::as_str).unwrap_or_default().to_string(),
+                    Err(_) => body.trim().to_string(),
+                };
+                if !UrlValidator::is_valid_youtube_url(&url) {
+                    return (400, json!({ "error": DownloaderError::InvalidUrl(url).to_string() }));
+                }
+                match queue.send(url.clone()) {
+                    Ok(()) => (202, json!({ "queued": url })),
+                    Err(_) => (503, json!({ "error": "Download queue is closed" })),
+                }
+            }
+            (_, "/health" | "/history" | "/download") => (405, json!({ "error": "Method not allowed" })),
+            _ => (404, json!({ "error": "Not found" })),
+        }
+    }
+    
+    fn reason(status: u16) -> &'static str {
+        match status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Checks on command-line options before anything is downloaded
+//!
+//! Every rule runs, so a single invocation reports all problems at once.
+//! Options are checked together with the configuration file, since a
+//! conflict can come from either side.
+
+use crate::config::Settings;
+use crate::error::DownloaderError;
+use crate::extractor::SponsorBlockClient;
+use crate::file_system::OutputTemplate;
+use crate::postprocess::{remux, ExecHook, Remuxer};
+use crate::utils::UrlValidator;
+use crate::Result;
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+use super::args::DownloadOptions;
+
+/// Containers that can only hold audio
+const AUDIO_CONTAINERS: &[&str] = &["m4a", "mka"];
+
+/// Containers chapters and captions can be embedded into
+const EMBEDDABLE_CONTAINERS: &[&str] = &["mp4", "mov", "m4a"];
+
+/// Something wrong with the command line, with a hint for fixing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl Problem {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+    
+    pub fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, "\n  help: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Problems found by [`validate_options`], split by whether they stop the download
+#[derive(Debug, Default)]
+pub struct Validation {
+    pub errors: Vec<Problem>,
+    pub warnings: Vec<Problem>,
+}
+
+impl Validation {
+    /// Fail with every error at once, or return the warnings
+    pub fn into_result(self) -> Result<Vec<Problem>> {
+        if self.errors.is_empty() {
+            return Ok(self.warnings);
+        }
+        let report: Vec<String> = self.errors.iter().map(|problem| format!("  - {}", problem.to_string().replace('\n', "\n  "))).collect();
+        Err(DownloaderError::InvalidArguments(report.join("\n")))
+    }
+}
+
+/// Options as they apply after merging the command line with the config file
+struct Effective<'a> {
+    options: &'a DownloadOptions,
+    audio_only: bool,
+    quality: Option<&'a str>,
+    remux_video: Option<String>,
+    embed_chapters: bool,
+    embed_subs: bool,
+}
+
+/// Two options that cannot be used together, each side checked on its own
+struct Conflict {
+    first: &'static str,
+    second: &'static str,
+    has_first: fn(&Effective) -> bool,
+    has_second: fn(&Effective) -> bool,
+    suggestion: &'static str,
+}
+
+impl Conflict {
+    fn applies(&self, effective: &Effective) -> bool {
+        (self.has_first)(effective) && (self.has_second)(effective)
+    }
+}
+
+const CONFLICTS: &[Conflict] = &[
+    Conflict {
+        first: "--audio-only",
+        second: "a video quality in `default_quality`",
+        has_first: |effective| effective.audio_only,
+        has_second: |effective| effective.quality.is_some_and(is_video_quality),
+        suggestion: "drop --audio-only, or set `default_quality` to \"best\" in the config file",
+    },
+    Conflict {
+        first: "--audio-only",
+        second: "--remux-video into a video container",
+        has_first: |effective| effective.audio_only,
+        has_second: |effective| {
+            effective.remux_video.as_deref().is_some_and(|container| !AUDIO_CONTAINERS.contains(&container))
+        },
+        suggestion: "use --remux-video m4a or --remux-video mka for audio",
+    },
+    Conflict {
+        first: "--audio-only",
+        second: "--embed-subs",
+        has_first: |effective| effective.audio_only,
+        has_second: |effective| effective.embed_subs,
+        suggestion: "captions can only be embedded into videos; drop --embed-subs",
+    },
+    Conflict {
+        first: "--embed-chapters",
+        second: "--remux-video into a container other than mp4, mov or m4a",
+        has_first: |effective| effective.embed_chapters,
+        has_second: |effective| !effective.embeddable(),
+        suggestion: "chapters are embedded after remuxing and need an MP4 container; use --remux-video mp4",
+    },
+    Conflict {
+        first: "--embed-subs",
+        second: "--remux-video into a container other than mp4, mov or m4a",
+        has_first: |effective| effective.embed_subs,
+        has_second: |effective| !effective.embeddable(),
+        suggestion: "captions are embedded after remuxing and need an MP4 container; use --remux-video mp4",
+    },
+    Conflict {
+        first: "--print or --get-url",
+        second: "--json or --progress-json",
+        has_first: |effective| effective.options.get_url || !effective.options.print.is_empty(),
+        has_second: |effective| effective.options.json || effective.options.progress_json,
+        suggestion: "both write to stdout; use --print with fields such as %(id)s instead of JSON",
+    },
+    Conflict {
+        first: "--chapter-output",
+        second: "--download-sections",
+        has_first: |effective| effective.options.chapter_output.is_some(),
+        has_second: |effective| !effective.options.download_sections.is_empty(),
+        suggestion: "chapter times do not match a file cut into sections; drop one of them",
+    },
+];
+
+impl<'a> Effective<'a> {
+    /// The command line merged with `settings`, flags taking precedence
+    fn merged(options: &'a DownloadOptions, settings: &'a Settings) -> Self {
+        Self {
+            options,
+            audio_only: options.audio_only || settings.prefer_audio_only,
+            quality: settings.default_quality.as_deref(),
+            remux_video: options
+                .remux_video
+                .as_ref()
+                .or(settings.remux_video.as_ref())
+                .map(|container| container.trim().to_ascii_lowercase()),
+            embed_chapters: options.embed_chapters || settings.embed_chapters,
+            embed_subs: options.embed_subs || settings.embed_subs,
+        }
+    }
+    
+    /// Only what was given on the command line
+    fn command_line(options: &'a DownloadOptions) -> Self {
+        Self {
+            options,
+            audio_only: options.audio_only,
+            quality: None,
+            remux_video: options.remux_video.as_ref().map(|container| container.trim().to_ascii_lowercase()),
+            embed_chapters: options.embed_chapters,
+            embed_subs: options.embed_subs,
+        }
+    }
+    
+    fn embeddable(&self) -> bool {
+        self.remux_video
+            .as_deref()
+            .is_none_or(|container| EMBEDDABLE_CONTAINERS.contains(&container))
+    }
+}
+
+/// Check a video URL given on the command line; playlists are only listed, with `--print` or `--get-url`
+pub fn validate_url(url: &str, options: &DownloadOptions, validation: &mut Validation) {
+    if UrlValidator::is_valid_youtube_url(url) {
+        return;
+    }
+    if UrlValidator::is_playlist_url(url) {
+        if !options.prints_lines() {
+            validation.errors.push(
+                Problem::new(format!("{} is a playlist, which can only be listed with --print or --get-url", url))
+                    .suggest("list the videos with --get-url, then download them with `downloader queue`"),
+            );
+        }
+        return;
+    }
+    let problem = Problem::new(DownloaderError::InvalidUrl(url.to_string()).to_string());
+    let suggestion = if url.contains("youtube.com/watch") && !url.contains("v=") {
+        "the shell may have cut the URL at '&'; put it in quotes"
+    } else {
+        "expected a link like https://www.youtube.com/watch?v=<id> or https://youtu.be/<id>"
+    };
+    validation.errors.push(problem.suggest(suggestion));
+}
+
+/// Check download options on their own and against `settings`.
+///
+/// Problems in the config file alone only produce warnings, matching
+/// [`Settings::validate`], while problems in the command line are errors.
+/// A conflict is an error as soon as one of its sides is a flag.
+pub fn validate_options(options: &DownloadOptions, settings: &Settings) -> Validation {
+    let mut validation = Validation {
+        errors: Vec::new(),
+        warnings: settings.validate().into_iter().map(Problem::new).collect(),
+    };
+    let errors = &mut validation.errors;
+    
+    for template in &options.print {
+        if let Err(e) = OutputTemplate::parse(template) {
+            errors.push(Problem::new(e.to_string()).suggest("fields are written like %(title)s; use %% for a literal %"));
+        }
+    }
+    for (flag, template) in [("--output", &options.output), ("--chapter-output", &options.chapter_output)] {
+        let Some(template) = template else { continue };
+        match OutputTemplate::parse(template) {
+            Err(e) => errors.push(Problem::new(e.to_string()).suggest("fields are written like %(title)s; use %% for a literal %")),
+            Ok(_) if !UrlValidator::is_valid_output_path(&without_fields(template)) => {
+                errors.push(invalid_path(flag, template));
+            }
+            Ok(_) => {}
+        }
+    }
+    for (flag, directory) in [("--output-dir", &options.output_dir), ("--move-to", &options.move_to)] {
+        if let Some(directory) = directory {
+            if !UrlValidator::is_valid_output_path(directory) {
+                errors.push(invalid_path(flag, directory));
+            }
+        }
+    }
+    
+    if let Some(Err(e)) = options.remux_video.as_deref().map(Remuxer::new) {
+        errors.push(Problem::new(e.to_string()).suggest(format!("pick one of: {}", remux::CONTAINERS.join(", "))));
+    }
+    
+    for command in options.exec.iter().chain(&options.exec_before_download) {
+        if let Err(e) = ExecHook::parse(command) {
+            errors.push(Problem::new(e.to_string()).suggest("quote the whole command, e.g. --exec \"mv {} ~/Videos\""));
+        }
+    }
+    for command in &options.exec_after_queue {
+        if let Err(e) = ExecHook::parse_without_fields(command) {
+            errors.push(Problem::new(e.to_string()).suggest("only {} is available after the queue; move %(field)s to --exec"));
+        }
+    }
+    
+    let mark = SponsorBlockClient::parse_categories(&options.sponsorblock_mark);
+    let remove = SponsorBlockClient::parse_categories(&options.sponsorblock_remove);
+    for result in [&mark, &remove] {
+        if let Err(e) = result {
+            errors.push(Problem::new(e.to_string()));
+        }
+    }
+    
+    let effective = Effective::merged(options, settings);
+    let command_line = Effective::command_line(options);
+    for conflict in CONFLICTS.iter().filter(|conflict| conflict.applies(&effective)) {
+        let problem = Problem::new(format!("{} cannot be used with {}", conflict.first, conflict.second)).suggest(conflict.suggestion);
+        if (conflict.has_first)(&command_line) || (conflict.has_second)(&command_line) {
+            errors.push(problem);
+        } else {
+            validation.warnings.push(Problem {
+                message: format!("{} (both set in the config file)", problem.message),
+                ..problem
+            });
+        }
+    }
+    
+    let warnings = &mut validation.warnings;
+    if let (Ok(mark), Ok(remove)) = (&mark, &remove) {
+        let both: Vec<&str> = mark.iter().filter(|category| remove.contains(category)).map(String::as_str).collect();
+        if !both.is_empty() {
+            warnings.push(
+                Problem::new(format!("SponsorBlock categories {} are both marked and removed", both.join(", ")))
+                    .suggest("removed segments cannot be marked; list them only in --sponsorblock-remove"),
+            );
+        }
+    }
+    let ignored = [
+        ("--chapter-output", "--split-chapters", options.chapter_output.is_some() && !(options.split_chapters || settings.split_chapters)),
+        ("--sub-langs", "--embed-subs", !options.sub_langs.is_empty() && !effective.embed_subs),
+        (
+            "--sponsorblock-api",
+            "--sponsorblock-mark or --sponsorblock-remove",
+            options.sponsorblock_api.is_some()
+                && [&options.sponsorblock_mark, &options.sponsorblock_remove, &settings.sponsorblock_mark, &settings.sponsorblock_remove]
+                    .iter()
+                    .all(|categories| categories.is_empty()),
+        ),
+    ];
+    for (flag, needs, _) in ignored.iter().filter(|(_, _, applies)| *applies) {
+        warnings.push(Problem::new(format!("{} has no effect without {}", flag, needs)).suggest(format!("add {} or drop {}", needs, flag)));
+    }
+    
+    validation
+}
+
+/// Whether a quality names a video resolution, such as "1080p" or "4k"
+fn is_video_quality(quality: &str) -> bool {
+    let quality = quality.trim().to_ascii_lowercase();
+    let number = quality.strip_suffix('p').or_else(|| quality.strip_suffix('k'));
+    number.is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// A template with each field replaced by a placeholder, so only its literal text is checked
+fn without_fields(template: &str) -> String {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    let field = FIELD.get_or_init(|| Regex::new(r"%\([^)]*\)[-0]*\d*(\.\d+)?[sd]|%%").expect("Field regex should be valid"));
+    field.replace_all(template, "x").into_owned()
+}
+
+fn invalid_path(flag: &str, path: &str) -> Problem {
+    Problem::new(format!("{} '{}' is not a valid path", flag, path))
+        .suggest("avoid < > : \" | ? * in names (a drive such as C: is fine), reserved names such as CON, and names ending in a space or '.'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use crate::cli::args::Cli;
+    
+    fn options(args: &[&str]) -> DownloadOptions {
+        let mut argv = vec!["downloader"];
+        argv.extend(args);
+        argv.push("https://youtu.be/dQw4w9WgXcQ");
+        Cli::try_parse_from(argv).unwrap().options
+    }
+    
+    #[test]
+    fn test_all_problems_are_reported() {
+        let settings = Settings::default();
+        let validation = validate_options(
+            &options(&["-a", "--remux-video", "mkv", "-o", "%(title)s?.%(ext)s", "--sponsorblock-mark", "sponsr"]),
+            &settings,
+        );
+        let messages: Vec<&str> = validation.errors.iter().map(|problem| problem.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].starts_with("--output"));
+        assert!(messages[1].contains("Unknown SponsorBlock category 'sponsr'"));
+        assert_eq!(messages[2], "--audio-only cannot be used with --remux-video into a video container");
+        assert!(validation.errors[2].suggestion.as_deref().unwrap().contains("m4a"));
+        
+        let error = validation.into_result().unwrap_err().to_string();
+        assert!(error.starts_with("Invalid arguments:\n  - --output"));
+        assert!(error.contains("\n    help: use --remux-video m4a"));
+    }
+    
+    #[test]
+    fn test_conflicts_with_settings() {
+        let mut settings = Settings {
+            default_quality: Some("1080p".to_string()),
+            ..Settings::default()
+        };
+        let validation = validate_options(&options(&["--audio-only"]), &settings);
+        assert_eq!(validation.errors.len(), 1);
+        assert!(validation.errors[0].message.contains("default_quality"));
+        
+        settings.default_quality = Some("best".to_string());
+        settings.remux_video = Some("webm".to_string());
+        let validation = validate_options(&options(&["--embed-chapters"]), &settings);
+        assert_eq!(validation.errors.len(), 1);
+        assert!(validation.errors[0].message.starts_with("--embed-chapters"));
+        
+        assert!(validate_options(&options(&["--embed-chapters", "--remux-video", "mp4"]), &settings)
+            .into_result()
+            .unwrap()
+            .is_empty());
+    }
+    
+    #[test]
+    fn test_conflicts_only_in_settings_are_warnings() {
+        let settings = Settings {
+            prefer_audio_only: true,
+            default_quality: Some("1080p".to_string()),
+            embed_subs: true,
+            ..Settings::default()
+        };
+        let validation = validate_options(&options(&[]), &settings);
+        assert!(validation.errors.is_empty(), "{:?}", validation.errors);
+        let conflicts: Vec<&str> = validation
+            .warnings
+            .iter()
+            .map(|problem| problem.message.as_str())
+            .filter(|message| message.contains("cannot be used with"))
+            .collect();
+        assert_eq!(
+            conflicts,
+            [
+                "--audio-only cannot be used with a video quality in `default_quality` (both set in the config file)",
+                "--audio-only cannot be used with --embed-subs (both set in the config file)",
+            ]
+        );
+        
+        let validation = validate_options(&options(&["--embed-subs"]), &settings);
+        assert_eq!(validation.errors.len(), 1);
+        assert_eq!(validation.errors[0].message, "--audio-only cannot be used with --embed-subs");
+    }
+    
+    #[test]
+    fn test_ignored_options_are_warnings() {
+        let validation = validate_options(
+            &options(&["--sub-langs", "en", "--sponsorblock-mark", "sponsor", "--sponsorblock-remove", "all"]),
+            &Settings::default(),
+        );
+        assert!(validation.errors.is_empty());
+        let messages: Vec<&str> = validation.warnings.iter().map(|problem| problem.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "SponsorBlock categories sponsor are both marked and removed",
+                "--sub-langs has no effect without --embed-subs",
+            ]
+        );
+    }
+    
+    #[test]
+    fn test_url_and_template_paths() {
+        let mut validation = Validation::default();
+        let (download, print) = (options(&[]), options(&["--print", "%(id)s"]));
+        validate_url("https://www.youtube.com/watch?v=dQw4w9WgXcQ", &download, &mut validation);
+        validate_url("https://www.youtube.com/watch?feature=share", &download, &mut validation);
+        assert_eq!(validation.errors.len(), 1);
+        assert!(validation.errors[0].suggestion.as_deref().unwrap().contains("quotes"));
+        
+        let playlist = "https://www.youtube.com/playlist?list=PLrAXtmErZgOeiKm4sgNOknGvNjby9efdf";
+        validate_url(playlist, &print, &mut validation);
+        assert_eq!(validation.errors.len(), 1);
+        validate_url(playlist, &download, &mut validation);
+        assert!(validation.errors[1].message.contains("is a playlist"));
+        
+        assert_eq!(without_fields("%(uploader)s/%(upload_date>%Y)s/%(title).80s 100%%.%(ext)s"), "x/x/x 100x.x");
+        assert!(validate_options(&options(&["-o", "Concerts/%(upload_date>%Y)s/%(title)s.%(ext)s"]), &Settings::default())
+            .errors
+            .is_empty());
+    }
+}
@@ -2,4 +2,4 @@
 
 pub mod settings;
 
-pub use settings::Settings;
\ No newline at end of file
+pub use settings::{HookSettings, Settings};
\ No newline at end of file
@@ -3,10 +3,105 @@
 use serde::{Deserialize, Serialize};
 use crate::Result;
 use crate::error::DownloaderError;
+use crate::extractor::sponsorblock::{self, SponsorBlockClient};
+use crate::extractor::youtube;
+use crate::file_system::{CleanupPolicy, CollisionPolicy, FileOrganizer, OutputTemplate};
+use crate::postprocess::{ExecHook, Remuxer};
 use std::path::PathBuf;
 use std::fs;
 use log::{debug, warn};
 
+/// Commented configuration written by `config init`; also the source of
+/// [`Settings::documented_keys`]
+const SAMPLE_CONFIG: &str = r#"# YouTube Downloader Configuration
+# All settings are optional - remove any line to use the default value
+
+# Directory where videos will be downloaded
+# If not specified, defaults to ~/Downloads/YouTube
+# default_output_directory = "/path/to/your/downloads"
+
+# Preferred video quality: "best", "worst", "1080p", "720p", "480p", etc.
+# If not specified, defaults to "best"
+default_quality = "best"
+
+# Maximum number of concurrent chunk downloads (1-8 recommended)
+# Higher values = faster downloads but more CPU/memory usage
+max_concurrent_downloads = 4
+
+# Size of each download chunk in bytes (1MB = 1048576)
+# Larger chunks = fewer HTTP requests but more memory usage
+chunk_size = 1048576
+
+# Automatically resume interrupted downloads
+auto_resume = true
+
+# Ask for confirmation before downloading large files
+confirm_large_downloads = true
+
+# Threshold for considering a download "large" in bytes (100MB = 104857600)
+large_download_threshold = 104857600
+
+# Maximum retries for failed downloads
+max_retries = 3
+
+# Timeout for network requests in seconds
+request_timeout = 30
+
+# Prefer audio-only downloads by default
+prefer_audio_only = false
+
+# Output filename template, relative to the output directory
+# Fields: id, title, uploader, upload_date, duration, ext, format_id, format_note,
+# resolution, width, height, fps, vcodec, acodec, language
+# If not specified, defaults to "%(title)s [%(id)s].%(ext)s"
+# output_template = "%(uploader)s/%(upload_date>%Y)s/%(title).80s [%(id)s].%(ext)s"
+
+# Filename template for --split-chapters, which adds the fields section_title,
+# section_number, section_start and section_end
+# If not specified, defaults to "%(title)s - %(section_number)03d %(section_title)s [%(id)s].%(ext)s"
+# chapter_output_template = "%(title)s/%(section_number)02d - %(section_title)s.%(ext)s"
+
+# Post-processing applied to every download, in addition to command-line options
+# embed_metadata = true
+# embed_thumbnail = true
+# embed_chapters = true
+# embed_subs = true
+# split_chapters = false
+
+# Copy streams into another container with ffmpeg: mp4, mkv, webm, mov, m4a, mka
+# remux_video = "mkv"
+
+# Move finished files into this directory
+# move_to = "/path/to/library"
+
+# SponsorBlock categories to mark as chapters or cut out: sponsor, intro, outro,
+# selfpromo, preview, filler, interaction, music_offtopic, or "all"
+# sponsorblock_mark = ["all"]
+# sponsorblock_remove = ["sponsor", "selfpromo"]
+
+# SponsorBlock API, defaults to "https://sponsor.ajay.app"
+# sponsorblock_api = "https://sponsor.ajay.app"
+
+# Site watch pages are fetched from, defaults to "https://www.youtube.com"
+# youtube_url = "https://www.youtube.com"
+
+# When the output file already exists: "skip" it, "overwrite" it, or "rename"
+# the new download to "name (1).ext". Defaults to "skip"
+# on_collision = "rename"
+
+# Downloads are written to "<name>.part" and renamed when complete. Remove
+# partial and temporary files "always", "on-success" (keeping failed downloads'
+# partial files) or "never". Defaults to "on-success"
+# temp_cleanup = "always"
+
+# External commands, run without a shell. "{}" is replaced by the file path
+# (appended when missing) and fields such as %(title)s by video details.
+# [hooks]
+# after_file = ["notify-send 'Downloaded %(title)s' {}"]
+# before_download = ["logger 'Starting %(id)s'"]
+# after_queue = ["scan-library {}"]
+"#;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
     /// Default directory for downloaded videos
@@ -29,6 +124,79 @@ pub struct Settings {
     pub request_timeout: u64,
     /// Whether to prefer audio-only downloads by default
     pub prefer_audio_only: bool,
+    /// Output filename template (e.g. "%(uploader)s/%(title)s [%(id)s].%(ext)s")
+    #[serde(default)]
+    pub output_template: Option<String>,
+    /// Filename template for files written by --split-chapters
+    #[serde(default)]
+    pub chapter_output_template: Option<String>,
+    /// Tag downloads with title, uploader, date, description and URL
+    #[serde(default)]
+    pub embed_metadata: bool,
+    /// Embed the thumbnail as cover art
+    #[serde(default)]
+    pub embed_thumbnail: bool,
+    /// Embed chapter markers (MP4 only)
+    #[serde(default)]
+    pub embed_chapters: bool,
+    /// Embed caption tracks (MP4 only)
+    #[serde(default)]
+    pub embed_subs: bool,
+    /// Also write one file per chapter
+    #[serde(default)]
+    pub split_chapters: bool,
+    /// Container to remux downloads into with ffmpeg
+    #[serde(default)]
+    pub remux_video: Option<String>,
+    /// Directory finished files are moved into
+    #[serde(default)]
+    pub move_to: Option<PathBuf>,
+    /// SponsorBlock categories to mark as chapters
+    #[serde(default)]
+    pub sponsorblock_mark: Vec<String>,
+    /// SponsorBlock categories to cut out of downloads
+    #[serde(default)]
+    pub sponsorblock_remove: Vec<String>,
+    /// SponsorBlock API base URL
+    #[serde(default)]
+    pub sponsorblock_api: Option<String>,
+    /// Base URL watch pages are fetched from
+    #[serde(default)]
+    pub youtube_url: Option<String>,
+    /// What to do when the output file already exists: "skip", "overwrite" or "rename"
+    #[serde(default)]
+    pub on_collision: CollisionPolicy,
+    /// When to remove partial and temporary files: "always", "on-success" or "never"
+    #[serde(default)]
+    pub temp_cleanup: CleanupPolicy,
+    /// External commands run around downloads; kept last so it serializes as a trailing table
+    #[serde(default)]
+    pub hooks: HookSettings,
+}
+
+/// Commands from the `[hooks]` table, in the `--exec` syntax
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HookSettings {
+    /// Run on each file once it is finished
+    #[serde(default)]
+    pub after_file: Vec<String>,
+    /// Run before each download starts, with the planned output path
+    #[serde(default)]
+    pub before_download: Vec<String>,
+    /// Run once with every finished file after all downloads
+    #[serde(default)]
+    pub after_queue: Vec<String>,
+}
+
+/// A setting as documented in the sample configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigKey {
+    /// Key name, prefixed with its table for nested keys (e.g. "hooks.after_file")
+    pub name: String,
+    /// Example value from the sample configuration
+    pub example: String,
+    /// The comment above the key, joined into one paragraph
+    pub description: String,
 }
 
 impl Default for Settings {
@@ -44,6 +212,22 @@ impl Default for Settings {
             max_retries: 3,
             request_timeout: 30,
             prefer_audio_only: false,
+            output_template: None,
+            chapter_output_template: None,
+            embed_metadata: false,
+            embed_thumbnail: false,
+            embed_chapters: false,
+            embed_subs: false,
+            split_chapters: false,
+            remux_video: None,
+            move_to: None,
+            sponsorblock_mark: Vec::new(),
+            sponsorblock_remove: Vec::new(),
+            sponsorblock_api: None,
+            youtube_url: None,
+            on_collision: CollisionPolicy::default(),
+            temp_cleanup: CleanupPolicy::default(),
+            hooks: HookSettings::default(),
         }
     }
 }
@@ -96,7 +280,7 @@ impl Settings {
     }
     
     /// Get the path to the config file
-    fn get_config_path() -> Result<PathBuf> {
+    pub fn get_config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| DownloaderError::Configuration("Could not find config directory".to_string()))?;
         
@@ -110,11 +294,15 @@ impl Settings {
             return Ok(custom_dir.clone());
         }
         
-        // Default to ~/Downloads/YouTube
-        let downloads_dir = dirs::download_dir()
-            .ok_or_else(|| DownloaderError::Configuration("Could not find downloads directory".to_string()))?;
-        
-        Ok(downloads_dir.join("YouTube"))
+        FileOrganizer::get_download_directory()
+    }
+    
+    /// Effective output directory without creating it; downloads create it when they start
+    pub fn resolve_output_directory(&self) -> Result<PathBuf> {
+        match self.default_output_directory {
+            Some(ref custom_dir) => Ok(custom_dir.clone()),
+            None => FileOrganizer::default_download_directory(),
+        }
     }
     
     /// Create a sample config file with comments
@@ -130,50 +318,59 @@ impl Settings {
             fs::create_dir_all(parent)?;
         }
         
-        let sample_config = r#"# YouTube Downloader Configuration
-# All settings are optional - remove any line to use the default value
-
-# Directory where videos will be downloaded
-# If not specified, defaults to ~/Downloads/YouTube
-# default_output_directory = "/path/to/your/downloads"
-
-# Preferred video quality: "best", "worst", "1080p", "720p", "480p", etc.
-# If not specified, defaults to "best"
-default_quality = "best"
-
-# Maximum number of concurrent chunk downloads (1-8 recommended)
-# Higher values = faster downloads but more CPU/memory usage
-max_concurrent_downloads = 4
-
-# Size of each download chunk in bytes (1MB = 1048576)
-# Larger chunks = fewer HTTP requests but more memory usage
-chunk_size = 1048576
-
-# Automatically resume interrupted downloads
-auto_resume = true
-
-# Ask for confirmation before downloading large files
-confirm_large_downloads = true
-
-# Threshold for considering a download "large" in bytes (100MB = 104857600)
-large_download_threshold = 104857600
-
-# Maximum retries for failed downloads
-max_retries = 3
-
-# Timeout for network requests in seconds
-request_timeout = 30
-
-# Prefer audio-only downloads by default
-prefer_audio_only = false
-"#;
-        
-        fs::write(&config_path, sample_config)?;
+        fs::write(&config_path, SAMPLE_CONFIG)?;
         println!("Sample configuration created at: {}", config_path.display());
         
         Ok(())
     }
     
+    /// Every key in the sample configuration with its comment, e.g. for the man page.
+    ///
+    /// Keys listed under one comment share its description.
+    pub fn documented_keys() -> Vec<ConfigKey> {
+        let mut keys = Vec::new();
+        let mut comment: Vec<&str> = Vec::new();
+        let mut table = String::new();
+        
+        for line in SAMPLE_CONFIG.lines() {
+            let text = line.trim().trim_start_matches('#').trim();
+            if text.is_empty() {
+                comment.clear();
+                continue;
+            }
+            if let Some(name) = text.strip_prefix('[').and_then(|text| text.strip_suffix(']')) {
+                table = format!("{}.", name);
+                continue;
+            }
+            match text.split_once(" = ") {
+                Some((name, example)) if name.chars().all(|c| c.is_ascii_lowercase() || c == '_') => keys.push(ConfigKey {
+                    name: format!("{}{}", table, name),
+                    example: example.to_string(),
+                    description: Self::join_comment(&comment),
+                }),
+                _ => comment.push(text),
+            }
+        }
+        keys
+    }
+    
+    /// Join wrapped comment lines, ending a sentence before a capitalised line and at the end
+    fn join_comment(lines: &[&str]) -> String {
+        let mut description = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            let sentence_ends = lines.get(i + 1)
+                .is_none_or(|next| next.starts_with(|c: char| c.is_uppercase()));
+            description.push_str(line);
+            if sentence_ends && !line.ends_with(['.', ':']) {
+                description.push('.');
+            }
+            if i + 1 < lines.len() {
+                description.push(' ');
+            }
+        }
+        description
+    }
+    
     /// Validate settings and return warnings for problematic values
     pub fn validate(&self) -> Vec<String> {
         let mut warnings = Vec::new();
@@ -194,6 +391,33 @@ prefer_audio_only = false
             warnings.push("request_timeout < 5 seconds may cause timeouts on slow connections".to_string());
         }
         
+        for template in [&self.output_template, &self.chapter_output_template].into_iter().flatten() {
+            if let Err(e) = OutputTemplate::parse(template) {
+                warnings.push(e.to_string());
+            }
+        }
+        
+        for command in self.hooks.after_file.iter().chain(&self.hooks.before_download) {
+            if let Err(e) = ExecHook::parse(command) {
+                warnings.push(e.to_string());
+            }
+        }
+        for command in &self.hooks.after_queue {
+            if let Err(e) = ExecHook::parse_without_fields(command) {
+                warnings.push(e.to_string());
+            }
+        }
+        
+        if let Some(Err(e)) = self.remux_video.as_deref().map(Remuxer::new) {
+            warnings.push(e.to_string());
+        }
+        
+        for categories in [&self.sponsorblock_mark, &self.sponsorblock_remove] {
+            if let Err(e) = SponsorBlockClient::parse_categories(categories) {
+                warnings.push(e.to_string());
+            }
+        }
+        
         if let Some(ref output_dir) = self.default_output_directory {
             if !output_dir.exists() {
                 warnings.push(format!("Output directory does not exist: {}", output_dir.display()));
@@ -203,6 +427,26 @@ prefer_audio_only = false
         warnings
     }
     
+    /// Get the configured output template, falling back to the default
+    pub fn get_output_template(&self) -> Result<OutputTemplate> {
+        OutputTemplate::parse(self.output_template.as_deref().unwrap_or(OutputTemplate::DEFAULT))
+    }
+    
+    /// Get the configured chapter filename template, falling back to the default
+    pub fn get_chapter_output_template(&self) -> Result<OutputTemplate> {
+        OutputTemplate::parse(self.chapter_output_template.as_deref().unwrap_or(OutputTemplate::DEFAULT_CHAPTER))
+    }
+    
+    /// Get the configured SponsorBlock API base URL, falling back to the public API
+    pub fn get_sponsorblock_api(&self) -> &str {
+        self.sponsorblock_api.as_deref().unwrap_or(sponsorblock::DEFAULT_API)
+    }
+    
+    /// Get the configured base URL for watch pages, falling back to YouTube
+    pub fn get_youtube_url(&self) -> &str {
+        self.youtube_url.as_deref().unwrap_or(youtube::DEFAULT_URL)
+    }
+    
     /// Get effective max concurrent downloads (ensuring it's at least 1)
     pub fn effective_max_concurrent_downloads(&self) -> usize {
         std::cmp::max(1, self.max_concurrent_downloads)
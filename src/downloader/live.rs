@@ -0,0 +1,305 @@
+//! Live stream recording by repeatedly polling a DASH/HLS manifest
+
+use crate::downloader::{ProgressTracker, QueueHandle, SegmentDownloader};
+use crate::error::DownloaderError;
+use crate::extractor::{DashParser, HlsParser};
+use crate::file_system::FileOrganizer;
+use crate::models::{DownloadProgress, Format, FormatType, Fragment, Protocol};
+use crate::utils::NetworkUtils;
+use crate::Result;
+use log::{debug, info, warn};
+use reqwest::Client;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Number of segments behind the live edge to start from
+const LIVE_EDGE_SEGMENTS: usize = 3;
+/// Consecutive polls without new segments before the stream is assumed to be over
+const MAX_IDLE_POLLS: u32 = 10;
+
+/// Why a recording stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The manifest reported the end of the stream
+    StreamEnded,
+    /// The requested recording duration elapsed
+    DurationElapsed,
+    /// The user pressed Ctrl-C or cancelled the queue item
+    Interrupted,
+    /// The manifest stopped updating or could no longer be fetched
+    StreamStalled,
+}
+
+/// Summary of a finished live recording
+#[derive(Debug, Clone)]
+pub struct LiveRecording {
+    pub output_path: PathBuf,
+    pub fragments: usize,
+    pub bytes_written: u64,
+    pub stop_reason: StopReason,
+}
+
+pub struct LiveRecorder {
+    client: Client,
+    concurrency: usize,
+    max_retries: u32,
+    from_start: bool,
+    max_duration: Option<Duration>,
+    poll_interval: Option<Duration>,
+    progress_sender: Option<mpsc::Sender<DownloadProgress>>,
+    queue_item: Option<QueueHandle>,
+}
+
+impl LiveRecorder {
+    pub fn new(client: Client, concurrency: usize, max_retries: u32) -> Self {
+        Self {
+            client,
+            concurrency,
+            max_retries,
+            from_start: false,
+            max_duration: None,
+            poll_interval: None,
+            progress_sender: None,
+            queue_item: None,
+        }
+    }
+    
+    /// Start from the earliest segment the DVR window still has instead of the live edge
+    pub fn from_start(mut self, from_start: bool) -> Self {
+        self.from_start = from_start;
+        self
+    }
+    
+    /// Stop recording after this much wall-clock time
+    pub fn max_duration(mut self, max_duration: Option<Duration>) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+    
+    /// Override the manifest polling interval (derived from segment durations by default)
+    pub fn poll_interval(mut self, poll_interval: Option<Duration>) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+    
+    /// Report the bytes recorded so far after each batch of segments; the total is unknown
+    pub fn progress(mut self, sender: Option<mpsc::Sender<DownloadProgress>>) -> Self {
+        self.progress_sender = sender;
+        self
+    }
+    
+    /// Record as part of a queue; cancelling the item stops the recording like Ctrl-C
+    pub fn queue_item(mut self, item: Option<QueueHandle>) -> Self {
+        self.queue_item = item;
+        self
+    }
+    
+    /// Pick the best format to record: muxed streams first, then highest resolution
+    pub fn select_format(formats: &[Format]) -> Option<&Format> {
+        formats
+            .iter()
+            .filter(|f| f.protocol != Protocol::Https && f.format_type == FormatType::Video)
+            .max_by_key(|f| (!f.is_adaptive, f.height.unwrap_or(0), f.bitrate.unwrap_or(0)))
+    }
+    
+    /// Record a live format into `output_path` until the stream ends, the
+    /// duration limit is reached or the user interrupts with Ctrl-C.
+    ///
+    /// Segments are written to a `.part` file that is moved into place when recording stops.
+    pub async fn record(&self, format: &Format, output_path: &Path) -> Result<LiveRecording> {
+        if let Some(parent) = output_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        
+        let part_path = FileOrganizer::part_path(output_path);
+        let mut file = File::create(&part_path).await?;
+        let downloader = SegmentDownloader::new(self.client.clone(), self.concurrency, self.max_retries)
+            .with_queue_item(self.queue_item.clone());
+        let deadline = self.max_duration.map(|duration| Instant::now() + duration);
+        
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut fragments = 0usize;
+        let mut bytes_written = 0u64;
+        let mut idle_polls = 0u32;
+        let mut first_poll = true;
+        let mut tracker = ProgressTracker::new();
+        
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+        
+        let stop_reason = loop {
+            if let Some(ref item) = self.queue_item {
+                if item.checkpoint().await.is_err() {
+                    break StopReason::Interrupted;
+                }
+            }
+            
+            let snapshot = match self.poll(format).await {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    warn!("Failed to refresh live manifest, stopping: {}", e);
+                    break StopReason::StreamStalled;
+                }
+            };
+            
+            let mut new_fragments: Vec<Fragment> = snapshot
+                .fragments
+                .into_iter()
+                .filter(|fragment| !seen.contains(&fragment.url))
+                .collect();
+            
+            if first_poll && !self.from_start {
+                let edge = Self::live_edge(&new_fragments);
+                // Segments before the live edge must not be picked up by later polls
+                seen.extend(new_fragments.iter().filter(|f| !edge.contains(f)).map(|f| f.url.clone()));
+                new_fragments = edge;
+            }
+            first_poll = false;
+            
+            if new_fragments.is_empty() {
+                idle_polls += 1;
+            } else {
+                idle_polls = 0;
+                debug!("Fetching {} new live fragments", new_fragments.len());
+                
+                let written = tokio::select! {
+                    result = downloader.download_into(&new_fragments, &mut file, None) => match result {
+                        Err(DownloaderError::UserCancelled) => break StopReason::Interrupted,
+                        result => result?,
+                    },
+                    _ = &mut ctrl_c => break StopReason::Interrupted,
+                };
+                
+                bytes_written += written;
+                fragments += new_fragments.len();
+                seen.extend(new_fragments.into_iter().map(|fragment| fragment.url));
+                info!("Recorded {} fragments ({} bytes)", fragments, bytes_written);
+                if let Some(ref sender) = self.progress_sender {
+                    let _ = sender.try_send(tracker.update(bytes_written, 0));
+                }
+            }
+            
+            if snapshot.is_ended {
+                break StopReason::StreamEnded;
+            }
+            if idle_polls >= MAX_IDLE_POLLS {
+                break StopReason::StreamStalled;
+            }
+            
+            let mut wait = snapshot.poll_interval;
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    break StopReason::DurationElapsed;
+                }
+                wait = wait.min(deadline - now);
+            }
+            
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = &mut ctrl_c => break StopReason::Interrupted,
+            }
+        };
+        
+        // Whatever was written so far is a complete sequence of segments,
+        // so syncing it and moving it into place leaves a playable file
+        file.flush().await?;
+        drop(file);
+        FileOrganizer::finalize(&part_path, output_path)?;
+        
+        info!("Live recording stopped ({:?}) after {} fragments", stop_reason, fragments);
+        Ok(LiveRecording {
+            output_path: output_path.to_path_buf(),
+            fragments,
+            bytes_written,
+            stop_reason,
+        })
+    }
+    
+    /// Keep initialization segments plus the last few media segments
+    fn live_edge(fragments: &[Fragment]) -> Vec<Fragment> {
+        let (init, media): (Vec<&Fragment>, Vec<&Fragment>) =
+            fragments.iter().partition(|fragment| fragment.duration.is_none());
+        let skip = media.len().saturating_sub(LIVE_EDGE_SEGMENTS);
+        init.into_iter().chain(media.into_iter().skip(skip)).cloned().collect()
+    }
+    
+    /// Fetch the current state of the live manifest for `format`
+    async fn poll(&self, format: &Format) -> Result<ManifestSnapshot> {
+        let (fragments, is_ended) = match format.protocol {
+            Protocol::Hls => {
+                let text = NetworkUtils::fetch_text(&self.client, &format.download_url, self.max_retries).await?;
+                let playlist = HlsParser::parse_media(&text, &format.download_url)?;
+                (playlist.fragments, playlist.is_ended)
+            }
+            Protocol::Dash => {
+                let manifest_url = format
+                    .manifest_url
+                    .as_deref()
+                    .ok_or_else(|| DownloaderError::FormatUnsupported("DASH format has no manifest URL".to_string()))?;
+                let xml = NetworkUtils::fetch_text(&self.client, manifest_url, self.max_retries).await?;
+                let manifest = DashParser::parse(&xml, manifest_url)?;
+                let fragments = manifest
+                    .formats
+                    .into_iter()
+                    .find(|f| f.itag == format.itag && f.format_type == format.format_type)
+                    .map(|f| f.fragments)
+                    .unwrap_or_default();
+                (fragments, !manifest.is_live)
+            }
+            Protocol::Https => {
+                return Err(DownloaderError::FormatUnsupported("Live recording requires a DASH or HLS format".to_string()));
+            }
+        };
+        
+        let poll_interval = self.poll_interval.unwrap_or_else(|| {
+            let segment = fragments.iter().rev().find_map(|f| f.duration).unwrap_or(5.0);
+            Duration::from_secs_f64(segment.clamp(1.0, 10.0))
+        });
+        
+        Ok(ManifestSnapshot {
+            fragments,
+            is_ended,
+            poll_interval,
+        })
+    }
+}
+
+struct ManifestSnapshot {
+    fragments: Vec<Fragment>,
+    is_ended: bool,
+    poll_interval: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn media(name: &str) -> Fragment {
+        Fragment {
+            url: name.to_string(),
+            byte_range: None,
+            duration: Some(2.0),
+        }
+    }
+    
+    #[test]
+    fn test_live_edge_keeps_init_and_last_segments() {
+        let fragments = vec![
+            Fragment::new("init".to_string()),
+            media("1"),
+            media("2"),
+            media("3"),
+            media("4"),
+            media("5"),
+        ];
+        
+        let urls: Vec<String> = LiveRecorder::live_edge(&fragments).into_iter().map(|f| f.url).collect();
+        assert_eq!(urls, ["init", "3", "4", "5"]);
+    }
+}
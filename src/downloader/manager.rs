@@ -1,31 +1,412 @@
 //! Main download coordination and management
 
-use crate::models::{DownloadTask, DownloadProgress};
+use crate::config::Settings;
+use crate::downloader::{QueueHandle, SegmentDownloader};
+use crate::error::DownloaderError;
+use crate::extractor::{HlsParser, SidxParser};
+use crate::file_system::{CleanupPolicy, CollisionPolicy, FileOrganizer, SpaceLedger, SpaceReservation};
+use crate::media::MediaCutter;
+use crate::models::{DownloadTask, DownloadProgress, Format, Fragment, Protocol, TimeRange};
+use crate::postprocess::{PostProcessorPipeline, Remuxer, StageFailure};
+use crate::utils::NetworkUtils;
 use crate::Result;
-use std::path::PathBuf;
+use log::{debug, info, warn};
+use reqwest::Client;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
+/// Size of the byte ranges a progressive download is split into; YouTube
+/// throttles single requests for whole files
+pub const PROGRESSIVE_CHUNK_SIZE: u64 = 10 * 1024 * 1024;
+
+/// A download that reached its final path
+#[derive(Debug)]
+pub struct FinishedDownload {
+    /// The task, with the path post-processing left the file at
+    pub task: DownloadTask,
+    /// Post-processing stages that failed; the file is kept as the earlier stages left it
+    pub failures: Vec<StageFailure>,
+    /// The output already existed and the collision policy kept it
+    pub skipped: bool,
+}
+
 pub struct DownloadManager {
-    // TODO: Add fields for managing downloads
+    client: Client,
+    max_concurrent_downloads: usize,
+    max_retries: u32,
     progress_sender: Option<mpsc::Sender<DownloadProgress>>,
+    post_processors: Arc<PostProcessorPipeline>,
+    collision_policy: CollisionPolicy,
+    cleanup_policy: CleanupPolicy,
+    space: SpaceLedger,
+    queue_item: Option<QueueHandle>,
+    chunk_size: u64,
 }
 
 impl DownloadManager {
     pub fn new() -> Self {
+        Self::with_settings(&Settings::default())
+    }
+    
+    /// Create a manager using concurrency, retry and file handling settings
+    pub fn with_settings(settings: &Settings) -> Self {
         Self {
+            client: NetworkUtils::create_client().unwrap_or_default(),
+            max_concurrent_downloads: settings.effective_max_concurrent_downloads(),
+            max_retries: settings.max_retries,
             progress_sender: None,
+            post_processors: Arc::new(PostProcessorPipeline::new()),
+            collision_policy: settings.on_collision,
+            cleanup_policy: settings.temp_cleanup,
+            space: SpaceLedger::new(),
+            queue_item: None,
+            chunk_size: PROGRESSIVE_CHUNK_SIZE,
+        }
+    }
+    
+    /// Run `pipeline` on every finished download
+    pub fn with_post_processors(mut self, pipeline: PostProcessorPipeline) -> Self {
+        self.post_processors = Arc::new(pipeline);
+        self
+    }
+    
+    /// Collision and temporary file handling
+    pub fn with_file_policies(mut self, collision: CollisionPolicy, cleanup: CleanupPolicy) -> Self {
+        self.collision_policy = collision;
+        self.cleanup_policy = cleanup;
+        self
+    }
+    
+    /// Account disk space together with other managers sharing `ledger`, such as the rest of a queue
+    pub fn with_space_ledger(mut self, ledger: SpaceLedger) -> Self {
+        self.space = ledger;
+        self
+    }
+    
+    /// Run as part of a queue, which can pause, cancel and throttle the download
+    pub fn with_queue_item(mut self, item: Option<QueueHandle>) -> Self {
+        self.queue_item = item;
+        self
+    }
+    
+    /// Split progressive downloads into byte ranges of `bytes` each
+    pub fn with_chunk_size(mut self, bytes: u64) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+    
+    /// Download into a `.part` file next to the output, move it into place
+    /// once complete, then post-process it.
+    ///
+    /// The returned task holds the final path, which post-processors may have
+    /// changed. An existing file skipped by the collision policy is returned unchanged.
+    pub async fn download(&mut self, mut task: DownloadTask) -> Result<FinishedDownload> {
+        let Some(output_path) = FileOrganizer::resolve_collision(&task.output_path, self.collision_policy) else {
+            info!("{} already exists, skipping download", task.output_path.display());
+            return Ok(FinishedDownload {
+                task,
+                failures: Vec::new(),
+                skipped: true,
+            });
+        };
+        task.output_path = output_path;
+        if let Some(parent) = task.output_path.parent() {
+            FileOrganizer::ensure_directory_exists(parent)?;
+        }
+        
+        let part_path = FileOrganizer::part_path(&task.output_path);
+        let reservation = if task.sections.is_empty() {
+            self.reserve_space(&task, &part_path)?
+        } else {
+            None
+        };
+        let result = match task.audio_format.clone() {
+            Some(audio) => self.download_merged(&task, audio, &part_path).await,
+            None => self.download_stream(&task, &part_path).await,
+        };
+        let result = result.and_then(|_| FileOrganizer::finalize(&part_path, &task.output_path));
+        
+        let cleanup = match result {
+            Ok(()) => self.cleanup_policy != CleanupPolicy::Never,
+            Err(_) => self.cleanup_policy == CleanupPolicy::Always,
+        };
+        if cleanup {
+            if let Err(e) = FileOrganizer::cleanup_temp_files(&task.output_path) {
+                warn!("Could not remove temporary files for {}: {}", task.output_path.display(), e);
+            }
+        }
+        drop(reservation);
+        result?;
+        
+        let (task, failures) = self.post_process(task).await?;
+        Ok(FinishedDownload {
+            task,
+            failures,
+            skipped: false,
+        })
+    }
+    
+    /// Check there is room for the download and preallocate its `.part` file.
+    ///
+    /// Other downloads sharing the ledger cannot count on the same space
+    /// until the returned reservation is dropped.
+    fn reserve_space(&self, task: &DownloadTask, part_path: &Path) -> Result<Option<SpaceReservation>> {
+        let Some(required) = FileOrganizer::required_space(&task.formats()) else {
+            debug!("Size of {} format is unknown, skipping disk space check", task.selected_format.quality);
+            return Ok(None);
+        };
+        let directory = part_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let mut reservation = self.space.reserve(directory, required)?;
+        if task.audio_format.is_some() {
+            // Merged streams are written to files of their own, and the `.part` file by ffmpeg
+            return Ok(Some(reservation));
+        }
+        
+        let file = std::fs::OpenOptions::new().write(true).create(true).truncate(false).open(part_path)?;
+        if let Err(e) = reservation.preallocate(&file) {
+            drop(file);
+            let _ = std::fs::remove_file(part_path);
+            return Err(e);
+        }
+        Ok(Some(reservation))
+    }
+    
+    /// Run the post-processors on a finished file, returning the stages that failed
+    async fn post_process(&self, mut task: DownloadTask) -> Result<(DownloadTask, Vec<StageFailure>)> {
+        if self.post_processors.is_empty() {
+            return Ok((task, Vec::new()));
         }
+        
+        let pipeline = self.post_processors.clone();
+        tokio::task::spawn_blocking(move || {
+            let failures = pipeline.run(&mut task);
+            (task, failures)
+        })
+        .await
+        .map_err(|e| DownloaderError::PostProcessing(format!("Post-processing task failed: {}", e)))
     }
     
-    /// Start multi-threaded download
-    pub async fn download(&mut self, task: DownloadTask) -> Result<PathBuf> {
-        // TODO: Implement multi-threaded download coordination
-        // 1. Check for existing partial download (resume capability)
-        // 2. Split download into chunks
-        // 3. Spawn worker tasks for each chunk
-        // 4. Coordinate progress reporting
-        // 5. Combine chunks into final file
-        todo!("Implement multi-threaded download")
+    /// Download the selected format, or the requested sections of it, into `destination`
+    async fn download_stream(&mut self, task: &DownloadTask, destination: &Path) -> Result<()> {
+        if !task.sections.is_empty() {
+            return self.download_sections(task, destination).await;
+        }
+        match task.selected_format.protocol {
+            Protocol::Dash | Protocol::Hls => self.download_fragmented(task, destination).await,
+            Protocol::Https => self.download_progressive(task, destination).await,
+        }
+    }
+    
+    /// Download the video and `audio` next to the output, one after the other,
+    /// then merge them into `destination` with ffmpeg
+    async fn download_merged(&mut self, task: &DownloadTask, audio: Format, destination: &Path) -> Result<()> {
+        let video_path = FileOrganizer::temp_path(&task.output_path, "video");
+        let audio_path = FileOrganizer::temp_path(&task.output_path, "audio");
+        let video_task = DownloadTask {
+            audio_format: None,
+            ..task.clone()
+        };
+        self.download_stream(&video_task, &video_path).await?;
+        let audio_task = DownloadTask {
+            selected_format: audio,
+            ..video_task
+        };
+        self.download_stream(&audio_task, &audio_path).await?;
+        
+        // ffmpeg picks the container from the extension, which `.part` would hide
+        let merged = FileOrganizer::merge_path(&task.output_path);
+        info!("Merging video and audio into {}", task.output_path.display());
+        let (video, audio, output) = (video_path.clone(), audio_path.clone(), merged.clone());
+        let result = match tokio::task::spawn_blocking(move || Remuxer::merge(&video, &audio, &output)).await {
+            Ok(Ok(())) => tokio::fs::rename(&merged, destination).await.map_err(DownloaderError::from),
+            Ok(Err(e)) => Err(e),
+            Err(e) => Err(DownloaderError::Media(format!("Merging task failed: {}", e))),
+        };
+        if result.is_err() {
+            // Unlike the streams, a half-merged file cannot be resumed, so it goes whatever the cleanup policy
+            let _ = tokio::fs::remove_file(&merged).await;
+        }
+        result?;
+        
+        let _ = tokio::fs::remove_file(&video_path).await;
+        let _ = tokio::fs::remove_file(&audio_path).await;
+        Ok(())
+    }
+    
+    /// Download a plain HTTPS format in parallel byte-range chunks
+    async fn download_progressive(&mut self, task: &DownloadTask, destination: &Path) -> Result<()> {
+        let format = &task.selected_format;
+        let fragments = Self::byte_ranges(format, self.chunk_size);
+        info!("Downloading {} in {} chunks to {}", format.quality, fragments.len(), task.output_path.display());
+        
+        let downloader = SegmentDownloader::new(self.client.clone(), self.max_concurrent_downloads, self.max_retries)
+            .with_queue_item(self.queue_item.clone());
+        let written = downloader
+            .download(&fragments, destination, self.progress_sender.as_ref())
+            .await?;
+        
+        if let Some(size) = format.file_size.filter(|&size| size != written) {
+            return Err(DownloaderError::DownloadFailed(format!("Expected {} bytes, got {}", size, written)));
+        }
+        debug!("Wrote {} bytes to {}", written, destination.display());
+        Ok(())
+    }
+    
+    /// Ranges of `chunk_size` bytes covering a progressive format, or a single
+    /// request for the whole file when its size is unknown
+    fn byte_ranges(format: &Format, chunk_size: u64) -> Vec<Fragment> {
+        let Some(size) = format.file_size.filter(|&size| size > 0) else {
+            return vec![Fragment::new(format.download_url.clone())];
+        };
+        (0..size)
+            .step_by(chunk_size as usize)
+            .map(|start| Fragment {
+                url: format.download_url.clone(),
+                byte_range: Some((start, (start + chunk_size).min(size) - 1)),
+                duration: None,
+            })
+            .collect()
+    }
+    
+    /// Download a DASH/HLS format fragment by fragment and concatenate the result into `destination`
+    async fn download_fragmented(&mut self, task: &DownloadTask, destination: &Path) -> Result<()> {
+        let fragments = self.resolve_fragments(&task.selected_format).await?;
+        info!("Downloading {} fragments to {}", fragments.len(), task.output_path.display());
+        
+        let downloader = SegmentDownloader::new(self.client.clone(), self.max_concurrent_downloads, self.max_retries)
+            .with_queue_item(self.queue_item.clone());
+        let written = downloader
+            .download(&fragments, destination, self.progress_sender.as_ref())
+            .await?;
+        
+        debug!("Wrote {} bytes to {}", written, destination.display());
+        Ok(())
+    }
+    
+    /// Download only the fragments covering `task.sections` and join them into one playable file at `destination`
+    async fn download_sections(&mut self, task: &DownloadTask, destination: &Path) -> Result<()> {
+        let downloader = SegmentDownloader::new(self.client.clone(), self.max_concurrent_downloads, self.max_retries)
+            .with_queue_item(self.queue_item.clone());
+        let fragments = self.resolve_timed_fragments(&downloader, &task.selected_format).await?;
+        let (selected, first_start, contiguous) = Self::select_fragments(&fragments, &task.sections)?;
+        info!(
+            "Downloading {} of {} fragments for {} sections to {}",
+            selected.len(),
+            fragments.len(),
+            task.sections.len(),
+            task.output_path.display()
+        );
+        
+        let sections_path = FileOrganizer::temp_path(&task.output_path, "sections");
+        downloader
+            .download(&selected, &sections_path, self.progress_sender.as_ref())
+            .await?;
+        
+        if contiguous {
+            tokio::fs::rename(&sections_path, destination).await?;
+        } else {
+            // Close the gaps between sections; times are relative to the first fragment fetched
+            let ranges: Vec<(f64, f64)> = task
+                .sections
+                .iter()
+                .map(|section| (section.start - first_start, section.end - first_start))
+                .collect();
+            let (input, output) = (sections_path.clone(), destination.to_path_buf());
+            let result = tokio::task::spawn_blocking(move || MediaCutter::keep_ranges(&input, &output, &ranges))
+                .await
+                .map_err(|e| DownloaderError::DownloadFailed(format!("Cutting task failed: {}", e)))
+                .and_then(|result| result);
+            let _ = tokio::fs::remove_file(&sections_path).await;
+            result?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Fragments with durations for a format, reading the segment index of single-file formats
+    async fn resolve_timed_fragments(&self, downloader: &SegmentDownloader, format: &Format) -> Result<Vec<Fragment>> {
+        let single_file = format.protocol == Protocol::Https || format.fragments.len() <= 1;
+        if let (true, Some((index_start, index_end))) = (single_file, format.index_range) {
+            let url = format.fragments.first().map_or(format.download_url.as_str(), |f| f.url.as_str());
+            let index = Fragment {
+                url: url.to_string(),
+                byte_range: Some((index_start, index_end)),
+                duration: None,
+            };
+            let data = downloader.fetch_fragment_with_retry(&index).await?;
+            
+            let (init_start, init_end) = format.init_range.unwrap_or((0, index_start.saturating_sub(1)));
+            let mut fragments = vec![Fragment {
+                url: url.to_string(),
+                byte_range: Some((init_start, init_end)),
+                duration: None,
+            }];
+            fragments.extend(SidxParser::parse(&data, index_start, url)?);
+            return Ok(fragments);
+        }
+        
+        let fragments = self.resolve_fragments(format).await?;
+        if !fragments.iter().any(|fragment| fragment.duration.is_some()) {
+            return Err(DownloaderError::FormatUnsupported(
+                "This format has no segment index, so sections cannot be downloaded; choose a DASH or HLS format".to_string(),
+            ));
+        }
+        Ok(fragments)
+    }
+    
+    /// Pick the fragments overlapping `sections`, keeping untimed initialization fragments.
+    ///
+    /// Also returns the start time of the first selected media fragment and
+    /// whether the selected media fragments follow each other without gaps.
+    fn select_fragments(fragments: &[Fragment], sections: &[TimeRange]) -> Result<(Vec<Fragment>, f64, bool)> {
+        let mut selected = Vec::new();
+        let mut first_start = None;
+        let mut contiguous = true;
+        let mut previous_selected = None;
+        let mut time = 0.0;
+        
+        for (index, fragment) in fragments.iter().enumerate() {
+            let Some(duration) = fragment.duration else {
+                selected.push(fragment.clone());
+                continue;
+            };
+            
+            let (start, end) = (time, time + duration);
+            time = end;
+            if !sections.iter().any(|section| section.overlaps(start, end)) {
+                continue;
+            }
+            
+            first_start.get_or_insert(start);
+            if previous_selected.is_some_and(|previous| previous + 1 != index) {
+                contiguous = false;
+            }
+            previous_selected = Some(index);
+            selected.push(fragment.clone());
+        }
+        
+        let first_start = first_start
+            .ok_or_else(|| DownloaderError::InvalidArguments("  - --download-sections are outside the video".to_string()))?;
+        Ok((selected, first_start, contiguous))
+    }
+    
+    /// Get the ordered fragment list for a format, fetching the HLS media playlist if needed
+    pub async fn resolve_fragments(&self, format: &Format) -> Result<Vec<Fragment>> {
+        let fragments = match format.protocol {
+            Protocol::Dash => format.fragments.clone(),
+            Protocol::Hls => {
+                let text = NetworkUtils::fetch_text(&self.client, &format.download_url, self.max_retries).await?;
+                HlsParser::parse_media(&text, &format.download_url)?.fragments
+            }
+            Protocol::Https => vec![Fragment::new(format.download_url.clone())],
+        };
+        
+        if fragments.is_empty() {
+            return Err(DownloaderError::FormatUnsupported("Manifest lists no fragments for this format".to_string()));
+        }
+        
+        Ok(fragments)
     }
     
     /// Check if download can be resumed
@@ -38,4 +419,4 @@ impl DownloadManager {
     pub fn set_progress_callback(&mut self, sender: mpsc::Sender<DownloadProgress>) {
         self.progress_sender = Some(sender);
     }
-}
\ No newline at end of file
+}
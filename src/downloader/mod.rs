@@ -3,7 +3,15 @@
 pub mod manager;
 pub mod chunk;
 pub mod progress;
+pub mod segment;
+pub mod live;
+pub mod queue;
+pub mod rate_limit;
 
-pub use manager::DownloadManager;
+pub use manager::{DownloadManager, FinishedDownload};
 pub use chunk::ChunkDownloader;
-pub use progress::ProgressTracker;
\ No newline at end of file
+pub use progress::ProgressTracker;
+pub use segment::SegmentDownloader;
+pub use live::{LiveRecorder, LiveRecording, StopReason};
+pub use queue::{ChunkState, DownloadQueue, ItemState, QueueHandle, QueueItem};
+pub use rate_limit::RateLimiter;
\ No newline at end of file
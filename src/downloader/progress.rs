@@ -80,7 +80,7 @@ impl ProgressTracker {
         
         if recent_samples.len() < 2 {
             // Fall back to all samples if we don't have enough recent ones
-            return self.calculate_speed_from_samples(&self.speed_samples.iter().cloned().collect());
+            return self.calculate_speed_from_samples(&self.speed_samples.iter().cloned().collect::<Vec<_>>());
         }
         
         self.calculate_speed_from_samples(&recent_samples)
@@ -0,0 +1,433 @@
+//! Download queue state shared by the workers and the dashboard
+
+use crate::downloader::RateLimiter;
+use crate::error::DownloaderError;
+use crate::file_system::SpaceLedger;
+use crate::models::DownloadProgress;
+use crate::Result;
+use std::path::PathBuf;
+use std::pin::pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+/// Where a queued download is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemState {
+    Queued,
+    Downloading,
+    /// Held before starting, or suspended between chunks
+    Paused,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl ItemState {
+    /// Whether the item will not change any more
+    pub fn is_done(self) -> bool {
+        matches!(self, ItemState::Finished | ItemState::Failed | ItemState::Cancelled)
+    }
+}
+
+/// Progress of one chunk (fragment) of a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkState {
+    Pending,
+    Active,
+    Done,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueueItem {
+    pub id: usize,
+    pub url: String,
+    /// Video title, once extracted
+    pub title: Option<String>,
+    pub state: ItemState,
+    /// Latest update from the item's `ProgressTracker`
+    pub progress: DownloadProgress,
+    pub chunks: Vec<ChunkState>,
+    pub path: Option<PathBuf>,
+    pub error: Option<String>,
+    /// Set while paused after starting, so resuming continues the download
+    started: bool,
+}
+
+/// Downloads waiting, running and done, in priority order.
+///
+/// Clones share the same queue. Workers take items with [`DownloadQueue::start_next`]
+/// and report through the returned [`QueueHandle`]; pausing, cancelling and
+/// reordering take effect at the next chunk.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadQueue {
+    items: Arc<Mutex<Vec<QueueItem>>>,
+    changed: Arc<Notify>,
+    limiter: RateLimiter,
+    space: SpaceLedger,
+}
+
+impl DownloadQueue {
+    /// A queue whose downloads share `limiter`
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self {
+            limiter,
+            ..Self::default()
+        }
+    }
+    
+    /// Add `url` at the end of the queue, returning its id
+    pub fn push(&self, url: String) -> usize {
+        let mut items = self.lock();
+        let id = items.len();
+        items.push(QueueItem {
+            id,
+            url,
+            title: None,
+            state: ItemState::Queued,
+            progress: DownloadProgress::new(),
+            chunks: Vec::new(),
+            path: None,
+            error: None,
+            started: false,
+        });
+        id
+    }
+    
+    /// Current state of every item, in priority order
+    pub fn snapshot(&self) -> Vec<QueueItem> {
+        self.lock().clone()
+    }
+    
+    /// The rate limit shared by all downloads in the queue
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+    
+    /// Disk space reserved by the downloads in the queue, so they are checked against each other
+    pub fn space(&self) -> &SpaceLedger {
+        &self.space
+    }
+    
+    /// Mark the first queued item as downloading and hand it to the caller
+    pub fn start_next(&self) -> Option<QueueHandle> {
+        let mut items = self.lock();
+        let item = items.iter_mut().find(|item| item.state == ItemState::Queued)?;
+        Some(self.begin(item))
+    }
+    
+    /// Add `url` and start it right away, for downloads that do not wait their turn
+    pub fn start(&self, url: String) -> QueueHandle {
+        let id = self.push(url);
+        let mut items = self.lock();
+        let item = items.iter_mut().find(|item| item.id == id).expect("item was just added");
+        self.begin(item)
+    }
+    
+    /// Wait for an item to start: the first queued one, or a held one once it
+    /// is resumed. `None` when nothing is left waiting.
+    pub async fn next(&self) -> Option<QueueHandle> {
+        loop {
+            let mut changed = pin!(self.changed.notified());
+            changed.as_mut().enable();
+            if let Some(item) = self.start_next() {
+                return Some(item);
+            }
+            if !self.lock().iter().any(|item| item.state == ItemState::Paused && !item.started) {
+                return None;
+            }
+            changed.await;
+        }
+    }
+    
+    /// Whether every item is finished, failed or cancelled
+    pub fn is_done(&self) -> bool {
+        self.lock().iter().all(|item| item.state.is_done())
+    }
+    
+    /// Hold a queued item, or suspend a running one at its next chunk
+    pub fn pause(&self, id: usize) {
+        self.update_state(id, |state| matches!(state, ItemState::Queued | ItemState::Downloading).then_some(ItemState::Paused));
+    }
+    
+    pub fn resume(&self, id: usize) {
+        self.update(id, |item| {
+            if item.state == ItemState::Paused {
+                item.state = if item.started { ItemState::Downloading } else { ItemState::Queued };
+            }
+        });
+    }
+    
+    /// Stop an item; a running download fails at its next chunk
+    pub fn cancel(&self, id: usize) {
+        self.update_state(id, |state| (!state.is_done()).then_some(ItemState::Cancelled));
+    }
+    
+    /// Cancel everything that has not finished
+    pub fn cancel_all(&self) {
+        for item in self.lock().iter_mut().filter(|item| !item.state.is_done()) {
+            item.state = ItemState::Cancelled;
+        }
+        self.changed.notify_waiters();
+    }
+    
+    /// Move an item one place towards the front (`up`) or back of the queue
+    pub fn reorder(&self, id: usize, up: bool) {
+        let mut items = self.lock();
+        let Some(index) = items.iter().position(|item| item.id == id) else {
+            return;
+        };
+        let other = if up { index.checked_sub(1) } else { Some(index + 1).filter(|&other| other < items.len()) };
+        if let Some(other) = other {
+            items.swap(index, other);
+        }
+    }
+    
+    fn begin(&self, item: &mut QueueItem) -> QueueHandle {
+        item.state = ItemState::Downloading;
+        item.started = true;
+        QueueHandle {
+            queue: self.clone(),
+            id: item.id,
+            url: item.url.clone(),
+        }
+    }
+    
+    fn lock(&self) -> MutexGuard<'_, Vec<QueueItem>> {
+        self.items.lock().unwrap()
+    }
+    
+    fn update(&self, id: usize, change: impl FnOnce(&mut QueueItem)) {
+        if let Some(item) = self.lock().iter_mut().find(|item| item.id == id) {
+            change(item);
+        }
+        self.changed.notify_waiters();
+    }
+    
+    fn update_state(&self, id: usize, next: impl FnOnce(ItemState) -> Option<ItemState>) {
+        self.update(id, |item| {
+            if let Some(state) = next(item.state) {
+                item.state = state;
+            }
+        });
+    }
+    
+    fn state(&self, id: usize) -> Option<ItemState> {
+        self.lock().iter().find(|item| item.id == id).map(|item| item.state)
+    }
+}
+
+/// A worker's hold on a started queue item
+#[derive(Debug, Clone)]
+pub struct QueueHandle {
+    queue: DownloadQueue,
+    id: usize,
+    url: String,
+}
+
+impl QueueHandle {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    
+    /// The disk space ledger of the item's queue
+    pub fn space(&self) -> &SpaceLedger {
+        self.queue.space()
+    }
+    
+    pub fn set_title(&self, title: &str) {
+        self.queue.update(self.id, |item| item.title = Some(title.to_string()));
+    }
+    
+    pub fn set_progress(&self, progress: DownloadProgress) {
+        self.queue.update(self.id, |item| item.progress = progress);
+    }
+    
+    /// Start tracking `count` new chunks, all pending
+    pub fn set_chunks(&self, count: usize) {
+        self.queue.update(self.id, |item| item.chunks = vec![ChunkState::Pending; count]);
+    }
+    
+    /// The chunks as last reported
+    pub fn chunks(&self) -> Vec<ChunkState> {
+        let items = self.queue.lock();
+        items.iter().find(|item| item.id == self.id).map(|item| item.chunks.clone()).unwrap_or_default()
+    }
+    
+    pub fn set_chunk(&self, index: usize, state: ChunkState) {
+        self.queue.update(self.id, |item| {
+            if let Some(chunk) = item.chunks.get_mut(index) {
+                *chunk = state;
+            }
+        });
+    }
+    
+    /// Wait while the item is paused; fails once it has been cancelled
+    pub async fn checkpoint(&self) -> Result<()> {
+        loop {
+            // Register for changes before looking, so a resume in between is not missed
+            let mut changed = pin!(self.queue.changed.notified());
+            changed.as_mut().enable();
+            match self.queue.state(self.id) {
+                Some(ItemState::Cancelled) => return Err(DownloaderError::UserCancelled),
+                Some(ItemState::Paused) => changed.await,
+                _ => return Ok(()),
+            }
+        }
+    }
+    
+    /// Wait until `bytes` fit within the queue's rate limit
+    pub async fn throttle(&self, bytes: u64) {
+        self.queue.limiter.acquire(bytes).await;
+    }
+    
+    /// The download succeeded and was saved at `path`
+    pub fn finish(&self, path: PathBuf) {
+        self.queue.update(self.id, |item| {
+            item.state = ItemState::Finished;
+            item.path = Some(path);
+        });
+    }
+    
+    /// The download failed; a cancelled item stays cancelled
+    pub fn fail(&self, error: impl std::fmt::Display) {
+        self.queue.update(self.id, |item| {
+            if item.state != ItemState::Cancelled {
+                item.state = ItemState::Failed;
+            }
+            item.error = Some(error.to_string());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    fn queue(urls: &[&str]) -> DownloadQueue {
+        let queue = DownloadQueue::default();
+        for url in urls {
+            queue.push(url.to_string());
+        }
+        queue
+    }
+    
+    fn urls(queue: &DownloadQueue) -> Vec<String> {
+        queue.snapshot().into_iter().map(|item| item.url).collect()
+    }
+    
+    #[test]
+    fn test_start_next_follows_priority() {
+        let queue = queue(&["a", "b", "c"]);
+        queue.reorder(2, true);
+        queue.reorder(0, false);
+        assert_eq!(urls(&queue), ["c", "a", "b"]);
+        queue.reorder(1, false);
+        assert_eq!(urls(&queue), ["c", "a", "b"]);
+        
+        // Paused and cancelled items are skipped until resumed
+        queue.pause(2);
+        queue.cancel(0);
+        assert_eq!(queue.start_next().unwrap().url(), "b");
+        assert!(queue.start_next().is_none());
+        queue.resume(2);
+        assert_eq!(queue.start_next().unwrap().url(), "c");
+        assert!(!queue.is_done());
+        
+        // Starting directly skips the line
+        queue.push("d".to_string());
+        assert_eq!(queue.start("e".to_string()).url(), "e");
+        assert_eq!(queue.snapshot()[4].state, ItemState::Downloading);
+        assert_eq!(queue.start_next().unwrap().url(), "d");
+    }
+    
+    #[test]
+    fn test_outcomes() {
+        let queue = queue(&["a", "b", "c"]);
+        let a = queue.start_next().unwrap();
+        let b = queue.start_next().unwrap();
+        let c = queue.start_next().unwrap();
+        a.finish(PathBuf::from("a.mp4"));
+        b.fail("network down");
+        queue.cancel(c.id());
+        c.fail(DownloaderError::UserCancelled);
+        
+        let items = queue.snapshot();
+        assert_eq!(items[0].state, ItemState::Finished);
+        assert_eq!(items[0].path, Some(PathBuf::from("a.mp4")));
+        assert_eq!(items[1].state, ItemState::Failed);
+        assert_eq!(items[1].error.as_deref(), Some("network down"));
+        assert_eq!(items[2].state, ItemState::Cancelled);
+        assert!(queue.is_done());
+    }
+    
+    #[tokio::test]
+    async fn test_checkpoint_waits_while_paused() {
+        let queue = queue(&["a"]);
+        let item = queue.start_next().unwrap();
+        item.checkpoint().await.unwrap();
+        
+        queue.pause(item.id());
+        assert_eq!(queue.snapshot()[0].state, ItemState::Paused);
+        let waiting = tokio::spawn({
+            let item = item.clone();
+            async move { item.checkpoint().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        
+        queue.resume(item.id());
+        assert_eq!(queue.snapshot()[0].state, ItemState::Downloading);
+        waiting.await.unwrap().unwrap();
+        
+        queue.pause(item.id());
+        queue.cancel(item.id());
+        assert!(matches!(item.checkpoint().await, Err(DownloaderError::UserCancelled)));
+    }
+    
+    #[tokio::test]
+    async fn test_next_waits_for_held_items() {
+        let queue = queue(&["a", "b"]);
+        queue.pause(1);
+        assert_eq!(queue.next().await.unwrap().url(), "a");
+        
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.next().await.map(|item| item.url().to_string()) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        queue.resume(1);
+        assert_eq!(waiting.await.unwrap().as_deref(), Some("b"));
+        
+        // A started item that is paused belongs to its worker
+        queue.pause(1);
+        assert!(queue.next().await.is_none());
+    }
+    
+    #[test]
+    fn test_chunks() {
+        let queue = queue(&["a"]);
+        let item = queue.start_next().unwrap();
+        item.set_chunks(3);
+        item.set_chunk(1, ChunkState::Done);
+        item.set_chunk(7, ChunkState::Done);
+        assert_eq!(item.chunks(), [ChunkState::Pending, ChunkState::Done, ChunkState::Pending]);
+    }
+    
+    #[test]
+    fn test_items_share_the_space_ledger() {
+        let queue = queue(&["a", "b"]);
+        let (first, second) = (queue.start_next().unwrap(), queue.start_next().unwrap());
+        let dir = std::env::temp_dir();
+        
+        let reservation = first.space().reserve(&dir, 1).unwrap();
+        assert_eq!(second.space().reserved(&dir).unwrap(), 1);
+        drop(reservation);
+        assert_eq!(queue.space().reserved(&dir).unwrap(), 0);
+    }
+}
@@ -0,0 +1,129 @@
+//! Bandwidth limiting shared by concurrent downloads
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A token bucket holding up to one second of transfer, shared by every
+/// download that clones it. The limit can be changed while downloads run.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    state: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug, Default)]
+struct Bucket {
+    /// Bytes per second, `None` for unlimited
+    limit: Option<u64>,
+    /// Bytes that may be transferred right away; negative while in debt
+    available: f64,
+    refilled: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: Option<u64>) -> Self {
+        let limiter = Self::default();
+        limiter.set_limit(limit);
+        limiter
+    }
+    
+    /// Current limit in bytes per second
+    pub fn limit(&self) -> Option<u64> {
+        self.state.lock().unwrap().limit
+    }
+    
+    /// Change the limit; `None` or zero removes it
+    pub fn set_limit(&self, limit: Option<u64>) {
+        let mut bucket = self.state.lock().unwrap();
+        bucket.limit = limit.filter(|&limit| limit > 0);
+        // Start with a full second of allowance, but never more than the new limit
+        bucket.available = bucket.limit.map_or(0.0, |limit| limit as f64);
+        bucket.refilled = None;
+    }
+    
+    /// Wait until `bytes` more can be transferred without exceeding the limit
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = self.reserve(bytes, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+    
+    /// Take `bytes` from the bucket at `now` and return how long to wait for them
+    fn reserve(&self, bytes: u64, now: Instant) -> Duration {
+        let mut bucket = self.state.lock().unwrap();
+        let Some(limit) = bucket.limit else {
+            return Duration::ZERO;
+        };
+        let limit = limit as f64;
+        
+        if let Some(refilled) = bucket.refilled {
+            let elapsed = now.saturating_duration_since(refilled).as_secs_f64();
+            bucket.available = (bucket.available + elapsed * limit).min(limit);
+        }
+        bucket.refilled = Some(now);
+        bucket.available -= bytes as f64;
+        
+        if bucket.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.available / limit)
+        }
+    }
+}
+
+/// Parse a rate such as "500K", "2.5M" or "1048576" into bytes per second
+pub fn parse_rate(value: &str) -> std::result::Result<u64, String> {
+    let value = value.trim();
+    let (number, suffix) = match value.char_indices().last() {
+        Some((index, suffix)) if suffix.is_ascii_alphabetic() => (&value[..index], Some(suffix)),
+        _ => (value, None),
+    };
+    let Ok(number) = number.trim().parse::<f64>() else {
+        return Err(format!("invalid rate '{}' (expected e.g. 500K or 2.5M)", value));
+    };
+    let multiplier = match suffix.map(|suffix| suffix.to_ascii_uppercase()) {
+        None => 1.0,
+        Some('K') => 1024.0,
+        Some('M') => 1024.0 * 1024.0,
+        Some('G') => 1024.0 * 1024.0 * 1024.0,
+        Some(suffix) => return Err(format!("unknown unit '{}' in '{}' (expected K, M or G)", suffix, value)),
+    };
+    
+    if number > 0.0 && number.is_finite() {
+        Ok((number * multiplier) as u64)
+    } else {
+        Err(format!("rate must be above zero, got '{}'", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(Some(1000));
+        let start = Instant::now();
+        
+        // One second of allowance is available at once, then transfers pay back the debt
+        assert_eq!(limiter.reserve(1000, start), Duration::ZERO);
+        assert_eq!(limiter.reserve(500, start), Duration::from_millis(500));
+        // Half a second later the debt is paid, and another half second refills 500 bytes
+        assert_eq!(limiter.reserve(500, start + Duration::from_secs(1)), Duration::ZERO);
+        
+        limiter.set_limit(None);
+        assert_eq!(limiter.reserve(u64::MAX, start), Duration::ZERO);
+        assert_eq!(limiter.limit(), None);
+    }
+    
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("1048576"), Ok(1048576));
+        assert_eq!(parse_rate("500K"), Ok(500 * 1024));
+        assert_eq!(parse_rate("2.5m"), Ok(2_621_440));
+        assert_eq!(parse_rate("1G"), Ok(1 << 30));
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("5X").unwrap_err().contains("unknown unit"));
+    }
+}
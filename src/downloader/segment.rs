@@ -0,0 +1,150 @@
+//! Fragmented (DASH/HLS) media downloading
+
+use crate::downloader::{ChunkState, ProgressTracker, QueueHandle};
+use crate::error::DownloaderError;
+use crate::models::{DownloadProgress, Fragment};
+use crate::utils::NetworkUtils;
+use crate::Result;
+use futures::stream::{self, StreamExt};
+use log::debug;
+use reqwest::Client;
+use std::path::Path;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+pub struct SegmentDownloader {
+    client: Client,
+    concurrency: usize,
+    max_retries: u32,
+    item: Option<QueueHandle>,
+}
+
+impl SegmentDownloader {
+    pub fn new(client: Client, concurrency: usize, max_retries: u32) -> Self {
+        Self {
+            client,
+            concurrency: concurrency.max(1),
+            max_retries: max_retries.max(1),
+            item: None,
+        }
+    }
+    
+    /// Report chunk states to a queue item and follow its pause, cancel and rate limit
+    pub fn with_queue_item(mut self, item: Option<QueueHandle>) -> Self {
+        self.item = item;
+        self
+    }
+    
+    /// Download fragments concurrently and write them to `output_path` in order.
+    ///
+    /// Anything already in the file, such as preallocated space, is
+    /// overwritten and trimmed. Returns the number of bytes written.
+    pub async fn download(
+        &self,
+        fragments: &[Fragment],
+        output_path: &Path,
+        progress_sender: Option<&mpsc::Sender<DownloadProgress>>,
+    ) -> Result<u64> {
+        // Truncating would give back space preallocated for the file
+        let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(output_path).await?;
+        let written = self.download_into(fragments, &mut file, progress_sender).await?;
+        file.flush().await?;
+        file.set_len(written).await?;
+        Ok(written)
+    }
+    
+    /// Download fragments concurrently and append them to an open file in order
+    pub async fn download_into(
+        &self,
+        fragments: &[Fragment],
+        file: &mut File,
+        progress_sender: Option<&mpsc::Sender<DownloadProgress>>,
+    ) -> Result<u64> {
+        debug!("Downloading {} fragments with {} connections", fragments.len(), self.concurrency);
+        
+        let mut tracker = ProgressTracker::new();
+        let mut written = 0u64;
+        if let Some(ref item) = self.item {
+            item.set_chunks(fragments.len());
+        }
+        
+        // `buffered` runs up to `concurrency` requests at once but yields results in order
+        let mut results = stream::iter(fragments.iter().enumerate())
+            .map(|(index, fragment)| async move {
+                self.set_chunk(index, ChunkState::Active);
+                let result = self.fetch_fragment_with_retry(fragment).await;
+                if result.is_ok() {
+                    self.set_chunk(index, ChunkState::Done);
+                }
+                (index, result)
+            })
+            .buffered(self.concurrency);
+        
+        while let Some((index, result)) = results.next().await {
+            let data = result.map_err(|e| {
+                DownloaderError::DownloadFailed(format!("Fragment {} of {} failed: {}", index + 1, fragments.len(), e))
+            })?;
+            
+            // No further fragments are fetched while this waits
+            if let Some(ref item) = self.item {
+                item.checkpoint().await?;
+            }
+            file.write_all(&data).await?;
+            written += data.len() as u64;
+            
+            if let Some(sender) = progress_sender {
+                // Extrapolate the total size from the average fragment size so far
+                let estimated_total = written * fragments.len() as u64 / (index as u64 + 1);
+                let _ = sender.try_send(tracker.update(written, estimated_total));
+            }
+        }
+        
+        Ok(written)
+    }
+    
+    fn set_chunk(&self, index: usize, state: ChunkState) {
+        if let Some(ref item) = self.item {
+            item.set_chunk(index, state);
+        }
+    }
+    
+    /// Fetch a single fragment, retrying transient failures with backoff
+    pub async fn fetch_fragment_with_retry(&self, fragment: &Fragment) -> Result<Vec<u8>> {
+        NetworkUtils::retry_with_backoff(|| self.fetch_fragment(fragment), self.max_retries).await
+    }
+    
+    async fn fetch_fragment(&self, fragment: &Fragment) -> Result<Vec<u8>> {
+        let mut request = self.client.get(&fragment.url);
+        if let Some((start, end)) = fragment.byte_range {
+            request = request.headers(NetworkUtils::create_range_headers(start, Some(end)));
+        }
+        
+        let response = request.send().await?.error_for_status()?;
+        let mut body = response.bytes_stream();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            // Charged as the body arrives, so the limit caps the rate and not just its average
+            if let Some(ref item) = self.item {
+                item.throttle(chunk.len() as u64).await;
+            }
+            data.extend_from_slice(&chunk);
+        }
+        
+        if let Some((start, end)) = fragment.byte_range {
+            let expected = end - start + 1;
+            if data.len() as u64 != expected {
+                return Err(DownloaderError::DownloadFailed(format!(
+                    "Expected {} bytes for range {}-{}, got {}",
+                    expected,
+                    start,
+                    end,
+                    data.len()
+                )));
+            }
+        }
+        
+        Ok(data)
+    }
+}
@@ -0,0 +1,141 @@
+//! Process exit statuses, kept stable so scripts can tell failures apart
+
+use crate::error::DownloaderError;
+use std::error::Error;
+use std::process::ExitCode;
+
+/// Exit status of the `downloader` binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ExitStatus {
+    /// Everything succeeded
+    Success = 0,
+    /// Any failure not covered below, including panics
+    Failure = 1,
+    /// Invalid URL, option or configuration; clap also exits with 2 on usage errors
+    InvalidInput = 2,
+    /// The video is private, deleted, not live yet or has no usable formats
+    Unavailable = 3,
+    /// Connection failures and downloads that broke off
+    Network = 4,
+    /// Files could not be read or written, or the disk is full
+    FileSystem = 5,
+    /// Some items of a batch failed while others succeeded
+    PartialFailure = 6,
+    /// The file was downloaded but a post-processing step failed on it
+    PostProcessing = 7,
+    /// The user cancelled, following the shell convention for SIGINT
+    Cancelled = 130,
+}
+
+impl ExitStatus {
+    /// The numeric process exit code
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+    
+    /// Status for an error, taken from the first error in its source chain that
+    /// is recognised, so context added on top does not hide the cause
+    pub fn of(error: &(dyn Error + 'static)) -> Self {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(error) = error.downcast_ref::<DownloaderError>() {
+                return error.into();
+            }
+            if error.is::<std::io::Error>() {
+                return ExitStatus::FileSystem;
+            }
+            if error.is::<reqwest::Error>() {
+                return ExitStatus::Network;
+            }
+            current = error.source();
+        }
+        ExitStatus::Failure
+    }
+    
+    /// The status every item of a batch failed with, or `Failure` when they differ
+    pub fn shared(statuses: &[ExitStatus]) -> Self {
+        match statuses {
+            [first, rest @ ..] if rest.iter().all(|status| status == first) => *first,
+            _ => ExitStatus::Failure,
+        }
+    }
+}
+
+impl From<&DownloaderError> for ExitStatus {
+    fn from(error: &DownloaderError) -> Self {
+        match error {
+            DownloaderError::InvalidUrl(_)
+            | DownloaderError::UrlParse(_)
+            | DownloaderError::InvalidArguments(_)
+            | DownloaderError::Configuration(_)
+            | DownloaderError::Toml(_) => ExitStatus::InvalidInput,
+            DownloaderError::VideoNotFound
+            | DownloaderError::NoFormatsFound
+            | DownloaderError::FormatUnsupported(_)
+            | DownloaderError::VideoUpcoming(_)
+            | DownloaderError::ExtractionFailed(_) => ExitStatus::Unavailable,
+            // Fragment and resume failures come from dropped connections
+            DownloaderError::Network(_)
+            | DownloaderError::DownloadFailed(_)
+            | DownloaderError::ResumeFailed(_) => ExitStatus::Network,
+            DownloaderError::Io(_)
+            | DownloaderError::FileSystem(_)
+            | DownloaderError::InsufficientSpace => ExitStatus::FileSystem,
+            DownloaderError::UserCancelled => ExitStatus::Cancelled,
+            DownloaderError::BatchFailed { failed, total, status } => {
+                if failed < total {
+                    ExitStatus::PartialFailure
+                } else {
+                    *status
+                }
+            }
+            DownloaderError::PostProcessing(_) => ExitStatus::PostProcessing,
+            DownloaderError::Json(_)
+            | DownloaderError::Metadata(_)
+            | DownloaderError::Media(_)
+            | DownloaderError::Regex(_)
+            | DownloaderError::Unknown(_) => ExitStatus::Failure,
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_status_of_errors() {
+        assert_eq!(ExitStatus::from(&DownloaderError::InvalidUrl("x".to_string())), ExitStatus::InvalidInput);
+        assert_eq!(ExitStatus::from(&DownloaderError::VideoNotFound), ExitStatus::Unavailable);
+        assert_eq!(ExitStatus::from(&DownloaderError::InsufficientSpace), ExitStatus::FileSystem);
+        assert_eq!(ExitStatus::from(&DownloaderError::UserCancelled).code(), 130);
+        assert_eq!(ExitStatus::from(&DownloaderError::PostProcessing("x".to_string())).code(), 7);
+        assert_eq!(ExitStatus::from(&DownloaderError::FormatUnsupported("x".to_string())).code(), 3);
+        
+        // The cause is found below wrapping errors
+        #[derive(Debug, thiserror::Error)]
+        #[error("while downloading")]
+        struct Context(#[source] DownloaderError);
+        assert_eq!(ExitStatus::of(&Context(DownloaderError::NoFormatsFound)), ExitStatus::Unavailable);
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(ExitStatus::of(&DownloaderError::from(io)), ExitStatus::FileSystem);
+        assert_eq!(ExitStatus::of(&std::fmt::Error), ExitStatus::Failure);
+    }
+    
+    #[test]
+    fn test_batch_status() {
+        let batch = |failed, status| DownloaderError::BatchFailed { failed, total: 3, status };
+        assert_eq!(ExitStatus::from(&batch(1, ExitStatus::Network)), ExitStatus::PartialFailure);
+        assert_eq!(ExitStatus::from(&batch(3, ExitStatus::Network)), ExitStatus::Network);
+        
+        assert_eq!(ExitStatus::shared(&[ExitStatus::Network, ExitStatus::Network]), ExitStatus::Network);
+        assert_eq!(ExitStatus::shared(&[ExitStatus::Network, ExitStatus::Unavailable]), ExitStatus::Failure);
+    }
+}
@@ -1,5 +1,7 @@
 //! Error handling and custom error types
 
+pub mod exit;
 pub mod types;
 
+pub use exit::ExitStatus;
 pub use types::{DownloaderError, Result};
\ No newline at end of file
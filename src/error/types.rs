@@ -1,5 +1,6 @@
 //! Custom error types for the application
 
+use crate::error::ExitStatus;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, DownloaderError>;
@@ -39,6 +40,15 @@ pub enum DownloaderError {
     #[error("Configuration error: {0}")]
     Configuration(String),
     
+    /// Every problem found in the command line, one per line
+    #[error("Invalid arguments:\n{0}")]
+    InvalidArguments(String),
+    
+    /// Some items of a batch failed, each already reported; `status` is the one
+    /// they all failed with, or `Failure` when their causes differ
+    #[error("{failed} of {total} downloads failed")]
+    BatchFailed { failed: usize, total: usize, status: ExitStatus },
+    
     #[error("Insufficient disk space")]
     InsufficientSpace,
     
@@ -51,6 +61,26 @@ pub enum DownloaderError {
     #[error("No suitable formats found")]
     NoFormatsFound,
     
+    /// A format that exists but cannot be downloaded the way it was asked for
+    #[error("Format cannot be downloaded: {0}")]
+    FormatUnsupported(String),
+    
+    /// Premiere or live stream that has not started; holds the scheduled
+    /// start as a Unix timestamp when YouTube reports one
+    #[error("Video has not started yet")]
+    VideoUpcoming(Option<u64>),
+    
+    #[error("Metadata error: {0}")]
+    Metadata(String),
+    
+    /// Media that cannot be cut or rewritten without re-encoding
+    #[error("Media processing error: {0}")]
+    Media(String),
+    
+    /// A post-processing stage that could not complete; the downloaded file is kept
+    #[error("Post-processing failed: {0}")]
+    PostProcessing(String),
+    
     #[error("Regex error: {0}")]
     Regex(#[from] regex::Error),
     
@@ -75,6 +105,11 @@ impl DownloaderError {
             DownloaderError::InsufficientSpace => false,
             DownloaderError::VideoNotFound => false,
             DownloaderError::NoFormatsFound => false,
+            DownloaderError::FormatUnsupported(_) => false,
+            DownloaderError::VideoUpcoming(_) => false,
+            DownloaderError::Media(_) => false,
+            DownloaderError::PostProcessing(_) => false,
+            DownloaderError::BatchFailed { .. } => false,
             // Everything else might be recoverable
             _ => true,
         }
@@ -92,6 +127,9 @@ impl DownloaderError {
             DownloaderError::NoFormatsFound => {
                 "No downloadable formats found for this video".to_string()
             },
+            DownloaderError::VideoUpcoming(_) => {
+                "This premiere or live stream has not started yet (use --wait-for-video to wait for it)".to_string()
+            },
             DownloaderError::Network(e) if e.is_timeout() => {
                 "Network timeout - please check your internet connection".to_string()
             },
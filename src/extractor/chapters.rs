@@ -0,0 +1,121 @@
+//! Chapter extraction from YouTube page data and video descriptions
+
+use crate::models::Chapter;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// YouTube only shows chapters when there are at least this many
+const MIN_DESCRIPTION_CHAPTERS: usize = 3;
+
+pub struct ChapterParser;
+
+impl ChapterParser {
+    /// Chapters from the page, falling back to timestamps in the description
+    pub fn extract(html: &str, description: Option<&str>, duration_seconds: Option<u64>) -> Vec<Chapter> {
+        let mut starts = Self::from_initial_data(html);
+        if starts.is_empty() {
+            starts = description.map(Self::from_description).unwrap_or_default();
+        }
+        Self::with_end_times(starts, duration_seconds)
+    }
+    
+    /// Read `chapterRenderer` entries from ytInitialData as (title, start) pairs
+    pub fn from_initial_data(html: &str) -> Vec<(String, f64)> {
+        static CHAPTER_PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = CHAPTER_PATTERN.get_or_init(|| {
+            Regex::new(r#""chapterRenderer":\{"title":\{"simpleText":"((?:[^"\\]|\\.)*)"\},"timeRangeStartMillis":(\d+)"#).unwrap()
+        });
+        
+        let mut starts: Vec<(String, f64)> = Vec::new();
+        for captures in pattern.captures_iter(html) {
+            let title = serde_json::from_str::<String>(&format!("\"{}\"", &captures[1]))
+                .unwrap_or_else(|_| captures[1].to_string());
+            let start = captures[2].parse::<u64>().unwrap_or(0) as f64 / 1000.0;
+            // The chapter list appears more than once in the page data
+            if !starts.iter().any(|(_, existing)| *existing == start) {
+                starts.push((title, start));
+            }
+        }
+        
+        starts.sort_by(|a, b| a.1.total_cmp(&b.1));
+        starts
+    }
+    
+    /// Parse description lines such as "0:00 Intro" or "(1:02:03) - Outro".
+    ///
+    /// Follows YouTube's rules: the list must start at 0:00, be in ascending
+    /// order and have at least three entries.
+    pub fn from_description(description: &str) -> Vec<(String, f64)> {
+        static LINE_PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = LINE_PATTERN.get_or_init(|| {
+            Regex::new(r"^\s*[\[(]?((?:\d+:)?\d{1,2}:\d{2})[\])]?\s*(?:[-–—:|]\s*)?(.+?)\s*$").unwrap()
+        });
+        
+        let mut starts: Vec<(String, f64)> = Vec::new();
+        for line in description.lines() {
+            let Some(captures) = pattern.captures(line) else {
+                continue;
+            };
+            let start = captures[1]
+                .split(':')
+                .fold(0u64, |total, part| total * 60 + part.parse::<u64>().unwrap_or(0)) as f64;
+            
+            if starts.last().is_some_and(|(_, previous)| start <= *previous) {
+                return Vec::new();
+            }
+            starts.push((captures[2].to_string(), start));
+        }
+        
+        if starts.len() < MIN_DESCRIPTION_CHAPTERS || starts[0].1 != 0.0 {
+            return Vec::new();
+        }
+        starts
+    }
+    
+    /// Each chapter ends where the next one starts; the last one at the end of the video
+    fn with_end_times(starts: Vec<(String, f64)>, duration_seconds: Option<u64>) -> Vec<Chapter> {
+        let ends: Vec<f64> = starts
+            .iter()
+            .skip(1)
+            .map(|(_, start)| *start)
+            .chain(std::iter::once(duration_seconds.map(|d| d as f64).unwrap_or(0.0)))
+            .collect();
+        
+        starts
+            .into_iter()
+            .zip(ends)
+            .map(|((title, start), end)| Chapter { title, start, end: end.max(start) })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_chapters_from_initial_data() {
+        let html = r#"{"chapterRenderer":{"title":{"simpleText":"Intro & setup"},"timeRangeStartMillis":0}},
+            {"chapterRenderer":{"title":{"simpleText":"Main part"},"timeRangeStartMillis":95500}},
+            {"chapterRenderer":{"title":{"simpleText":"Intro & setup"},"timeRangeStartMillis":0}}"#;
+        
+        let chapters = ChapterParser::extract(html, None, Some(300));
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "Intro & setup");
+        assert_eq!((chapters[0].start, chapters[0].end), (0.0, 95.5));
+        assert_eq!((chapters[1].start, chapters[1].end), (95.5, 300.0));
+    }
+    
+    #[test]
+    fn test_chapters_from_description() {
+        let description = "Timestamps:\n0:00 Intro\n(1:30) - Verse\n1:02:03 Outro\nThanks for watching";
+        let chapters = ChapterParser::extract("", Some(description), Some(4000));
+        let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Intro", "Verse", "Outro"]);
+        assert_eq!(chapters[2].start, 3723.0);
+        
+        // Not starting at 0:00, or too few entries, means no chapters
+        assert!(ChapterParser::from_description("0:10 A\n0:20 B\n0:30 C").is_empty());
+        assert!(ChapterParser::from_description("0:00 A\n0:20 B").is_empty());
+    }
+}
@@ -0,0 +1,488 @@
+//! MPEG-DASH manifest (MPD) parsing
+
+use crate::error::DownloaderError;
+use crate::models::{DynamicRange, Format, FormatType, Fragment, Protocol};
+use crate::Result;
+use log::{debug, warn};
+use regex::Regex;
+use roxmltree::{Document, Node};
+use std::sync::OnceLock;
+use url::Url;
+
+/// Parsed contents of a DASH manifest
+#[derive(Debug, Clone)]
+pub struct DashManifest {
+    /// `type="dynamic"` manifests describe live streams and must be re-polled
+    pub is_live: bool,
+    /// Total presentation duration in seconds, when known
+    pub duration: Option<f64>,
+    pub formats: Vec<Format>,
+}
+
+pub struct DashParser;
+
+impl DashParser {
+    /// Parse an MPD document, resolving segment URLs against `manifest_url`
+    pub fn parse(xml: &str, manifest_url: &str) -> Result<DashManifest> {
+        let document = Document::parse(xml)
+            .map_err(|e| DownloaderError::ExtractionFailed(format!("Invalid DASH manifest: {}", e)))?;
+        let mpd = document.root_element();
+        
+        if mpd.tag_name().name() != "MPD" {
+            return Err(DownloaderError::ExtractionFailed("Manifest root is not <MPD>".to_string()));
+        }
+        
+        let is_live = mpd.attribute("type") == Some("dynamic");
+        let mpd_base = Self::resolve_base(Self::parse_url(manifest_url)?, mpd)?;
+        
+        let mut periods = Self::children(mpd, "Period");
+        let period = periods
+            .next()
+            .ok_or_else(|| DownloaderError::ExtractionFailed("DASH manifest has no periods".to_string()))?;
+        if periods.next().is_some() {
+            warn!("Multi-period DASH manifests are not supported, using the first period only");
+        }
+        
+        let duration = period
+            .attribute("duration")
+            .or_else(|| mpd.attribute("mediaPresentationDuration"))
+            .and_then(Self::parse_duration);
+        let period_base = Self::resolve_base(mpd_base, period)?;
+        
+        let mut formats = Vec::new();
+        for adaptation_set in Self::children(period, "AdaptationSet") {
+            let set_base = Self::resolve_base(period_base.clone(), adaptation_set)?;
+            
+            for representation in Self::children(adaptation_set, "Representation") {
+                match Self::parse_representation(adaptation_set, representation, &set_base, duration) {
+                    Ok(Some(mut format)) => {
+                        format.manifest_url = Some(manifest_url.to_string());
+                        formats.push(format);
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Skipping DASH representation: {}", e),
+                }
+            }
+        }
+        
+        debug!("Parsed {} formats from DASH manifest", formats.len());
+        Ok(DashManifest {
+            is_live,
+            duration,
+            formats,
+        })
+    }
+    
+    /// Build a format (with its fragment list) from a single Representation
+    fn parse_representation(
+        adaptation_set: Node,
+        representation: Node,
+        base: &Url,
+        period_duration: Option<f64>,
+    ) -> Result<Option<Format>> {
+        let attr = |name: &str| {
+            representation
+                .attribute(name)
+                .or_else(|| adaptation_set.attribute(name))
+        };
+        
+        let mime_type = attr("mimeType").unwrap_or("");
+        let content_type = adaptation_set.attribute("contentType").unwrap_or("");
+        
+        let (format_type, file_extension) = if mime_type.starts_with("audio") || content_type == "audio" {
+            (FormatType::Audio, "mp3")
+        } else if mime_type.starts_with("video") || content_type == "video" {
+            (FormatType::Video, if mime_type.contains("webm") { "webm" } else { "mp4" })
+        } else {
+            // Text tracks and other content are not downloadable media
+            return Ok(None);
+        };
+        
+        let base = Self::resolve_base(base.clone(), representation)?;
+        let id = representation.attribute("id").unwrap_or("");
+        let bandwidth = attr("bandwidth").and_then(|b| b.parse::<u32>().ok());
+        let width = attr("width").and_then(|w| w.parse().ok());
+        let height: Option<u32> = attr("height").and_then(|h| h.parse().ok());
+        
+        let quality = match format_type {
+            FormatType::Video => height.map_or_else(|| "Unknown".to_string(), |h| format!("{}p", h)),
+            FormatType::Audio => bandwidth.map_or_else(|| "Audio".to_string(), |b| format!("{}kbps", b / 1000)),
+        };
+        
+        let mut format = Format::new(quality, format_type.clone(), file_extension.to_string(), base.to_string());
+        format.protocol = Protocol::Dash;
+        format.is_adaptive = true;
+        format.itag = id.parse().ok();
+        format.bitrate = bandwidth;
+        format.width = width;
+        format.height = height;
+        format.fps = attr("frameRate").and_then(Self::parse_frame_rate);
+        format.approx_duration_ms = period_duration.map(|d| (d * 1000.0) as u64);
+        format.language = adaptation_set.attribute("lang").map(|l| l.to_string());
+        
+        if let Some(codecs) = attr("codecs") {
+            format.codec = Some(codecs.to_string());
+            match format_type {
+                FormatType::Audio => format.acodec = Some(codecs.to_string()),
+                FormatType::Video => format.vcodec = Some(codecs.to_string()),
+            }
+        }
+        
+        if format_type == FormatType::Audio {
+            format.sample_rate = attr("audioSamplingRate").and_then(|r| r.parse().ok());
+            format.audio_channels = Self::child(representation, "AudioChannelConfiguration")
+                .or_else(|| Self::child(adaptation_set, "AudioChannelConfiguration"))
+                .and_then(|c| c.attribute("value"))
+                .and_then(|v| v.parse().ok());
+        } else {
+            let hdr = Self::children(adaptation_set, "SupplementalProperty")
+                .chain(Self::children(adaptation_set, "EssentialProperty"))
+                .any(|p| {
+                    p.attribute("schemeIdUri") == Some("urn:mpeg:mpegB:cicp:TransferCharacteristics")
+                        && matches!(p.attribute("value"), Some("16") | Some("18"))
+                });
+            format.dynamic_range = Some(if hdr { DynamicRange::Hdr } else { DynamicRange::Sdr });
+        }
+        
+        format.fragments = if let Some(list) = Self::child(representation, "SegmentList")
+            .or_else(|| Self::child(adaptation_set, "SegmentList"))
+        {
+            Self::segment_list_fragments(list, &base)?
+        } else if let Some(template) = Self::child(representation, "SegmentTemplate") {
+            let inherited = Self::child(adaptation_set, "SegmentTemplate");
+            Self::segment_template_fragments(template, inherited, &base, id, bandwidth, period_duration)?
+        } else if let Some(template) = Self::child(adaptation_set, "SegmentTemplate") {
+            Self::segment_template_fragments(template, None, &base, id, bandwidth, period_duration)?
+        } else {
+            // SegmentBase or bare BaseURL: the whole representation is one file,
+            // optionally with a segment index for downloading parts of it
+            if let Some(segment_base) = Self::child(representation, "SegmentBase")
+                .or_else(|| Self::child(adaptation_set, "SegmentBase"))
+            {
+                format.index_range = segment_base.attribute("indexRange").and_then(Self::parse_range);
+                format.init_range = Self::child(segment_base, "Initialization")
+                    .and_then(|init| init.attribute("range"))
+                    .and_then(Self::parse_range);
+            }
+            vec![Fragment::new(base.to_string())]
+        };
+        
+        Ok(Some(format))
+    }
+    
+    /// Fragments from an explicit `<SegmentList>`
+    fn segment_list_fragments(list: Node, base: &Url) -> Result<Vec<Fragment>> {
+        let timescale = list.attribute("timescale").and_then(|t| t.parse::<f64>().ok()).unwrap_or(1.0);
+        let duration = list
+            .attribute("duration")
+            .and_then(|d| d.parse::<f64>().ok())
+            .map(|d| d / timescale);
+        
+        let mut fragments = Vec::new();
+        
+        if let Some(init) = Self::child(list, "Initialization") {
+            let url = match init.attribute("sourceURL") {
+                Some(source) => Self::join(base, source)?,
+                None => base.to_string(),
+            };
+            fragments.push(Fragment {
+                url,
+                byte_range: init.attribute("range").and_then(Self::parse_range),
+                duration: None,
+            });
+        }
+        
+        for segment in Self::children(list, "SegmentURL") {
+            let url = match segment.attribute("media") {
+                Some(media) => Self::join(base, media)?,
+                None => base.to_string(),
+            };
+            fragments.push(Fragment {
+                url,
+                byte_range: segment.attribute("mediaRange").and_then(Self::parse_range),
+                duration,
+            });
+        }
+        
+        Ok(fragments)
+    }
+    
+    /// Fragments generated from a `<SegmentTemplate>`, with or without a timeline
+    fn segment_template_fragments(
+        template: Node,
+        inherited: Option<Node>,
+        base: &Url,
+        representation_id: &str,
+        bandwidth: Option<u32>,
+        period_duration: Option<f64>,
+    ) -> Result<Vec<Fragment>> {
+        let attr = |name: &str| {
+            template
+                .attribute(name)
+                .or_else(|| inherited.and_then(|t| t.attribute(name)))
+        };
+        
+        let timescale = attr("timescale").and_then(|t| t.parse::<u64>().ok()).unwrap_or(1).max(1);
+        let start_number = attr("startNumber").and_then(|n| n.parse::<u64>().ok()).unwrap_or(1);
+        let media = attr("media")
+            .ok_or_else(|| DownloaderError::ExtractionFailed("SegmentTemplate without media attribute".to_string()))?;
+        
+        let mut fragments = Vec::new();
+        
+        if let Some(initialization) = attr("initialization") {
+            let path = Self::expand_template(initialization, representation_id, bandwidth, None, None);
+            fragments.push(Fragment::new(Self::join(base, &path)?));
+        }
+        
+        let timeline = Self::child(template, "SegmentTimeline")
+            .or_else(|| inherited.and_then(|t| Self::child(t, "SegmentTimeline")));
+        
+        // (start time, duration) pairs in timescale units
+        let mut segments: Vec<(u64, u64)> = Vec::new();
+        
+        if let Some(timeline) = timeline {
+            let period_end = period_duration.map(|d| (d * timescale as f64) as u64);
+            let mut time = 0u64;
+            
+            for entry in Self::children(timeline, "S") {
+                let duration = entry
+                    .attribute("d")
+                    .and_then(|d| d.parse::<u64>().ok())
+                    .filter(|&d| d > 0)
+                    .ok_or_else(|| DownloaderError::ExtractionFailed("SegmentTimeline entry without duration".to_string()))?;
+                if let Some(t) = entry.attribute("t").and_then(|t| t.parse().ok()) {
+                    time = t;
+                }
+                
+                let repeat = entry.attribute("r").and_then(|r| r.parse::<i64>().ok()).unwrap_or(0);
+                let count = if repeat < 0 {
+                    // Negative repeat runs until the end of the period
+                    match period_end {
+                        Some(end) => end.saturating_sub(time).div_ceil(duration),
+                        None => 1,
+                    }
+                } else {
+                    repeat as u64 + 1
+                };
+                
+                for _ in 0..count {
+                    segments.push((time, duration));
+                    time += duration;
+                }
+            }
+        } else {
+            let duration = attr("duration")
+                .and_then(|d| d.parse::<u64>().ok())
+                .filter(|&d| d > 0)
+                .ok_or_else(|| DownloaderError::ExtractionFailed("SegmentTemplate without duration or timeline".to_string()))?;
+            let total = period_duration
+                .ok_or_else(|| DownloaderError::ExtractionFailed("Cannot count segments without a period duration".to_string()))?;
+            let count = ((total * timescale as f64) / duration as f64).ceil() as u64;
+            
+            segments.extend((0..count).map(|i| (i * duration, duration)));
+        }
+        
+        for (index, (time, duration)) in segments.into_iter().enumerate() {
+            let number = start_number + index as u64;
+            let path = Self::expand_template(media, representation_id, bandwidth, Some(number), Some(time));
+            fragments.push(Fragment {
+                url: Self::join(base, &path)?,
+                byte_range: None,
+                duration: Some(duration as f64 / timescale as f64),
+            });
+        }
+        
+        Ok(fragments)
+    }
+    
+    /// Substitute `$RepresentationID$`, `$Number$`, `$Time$` and `$Bandwidth$`
+    /// identifiers (including `%0Nd` width specifiers) in a template string
+    pub fn expand_template(
+        template: &str,
+        representation_id: &str,
+        bandwidth: Option<u32>,
+        number: Option<u64>,
+        time: Option<u64>,
+    ) -> String {
+        static IDENTIFIER_PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = IDENTIFIER_PATTERN.get_or_init(|| {
+            Regex::new(r"\$(RepresentationID|Number|Time|Bandwidth|)(?:%0(\d+)d)?\$").unwrap()
+        });
+        
+        pattern
+            .replace_all(template, |caps: &regex::Captures| {
+                let width = caps.get(2).and_then(|w| w.as_str().parse::<usize>().ok()).unwrap_or(0);
+                let value = match &caps[1] {
+                    "" => return "$".to_string(),
+                    "RepresentationID" => return representation_id.to_string(),
+                    "Number" => number,
+                    "Time" => time,
+                    _ => bandwidth.map(u64::from),
+                };
+                match value {
+                    Some(value) => format!("{:0width$}", value, width = width),
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+    
+    /// Parse an ISO 8601 duration such as `PT1H2M3.5S` into seconds
+    pub fn parse_duration(value: &str) -> Option<f64> {
+        static DURATION_PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = DURATION_PATTERN.get_or_init(|| {
+            Regex::new(r"^P(?:(\d+(?:\.\d+)?)D)?(?:T(?:(\d+(?:\.\d+)?)H)?(?:(\d+(?:\.\d+)?)M)?(?:(\d+(?:\.\d+)?)S)?)?$").unwrap()
+        });
+        
+        let captures = pattern.captures(value.trim())?;
+        let component = |index: usize| {
+            captures
+                .get(index)
+                .and_then(|m| m.as_str().parse::<f64>().ok())
+                .unwrap_or(0.0)
+        };
+        
+        Some(component(1) * 86400.0 + component(2) * 3600.0 + component(3) * 60.0 + component(4))
+    }
+    
+    /// Parse a frame rate that may be a fraction like `30000/1001`
+    fn parse_frame_rate(value: &str) -> Option<u32> {
+        match value.split_once('/') {
+            Some((num, den)) => {
+                let num: f64 = num.parse().ok()?;
+                let den: f64 = den.parse().ok()?;
+                (den > 0.0).then(|| (num / den).round() as u32)
+            }
+            None => value.parse::<f64>().ok().map(|fps| fps.round() as u32),
+        }
+    }
+    
+    /// Parse an inclusive `start-end` byte range
+    fn parse_range(value: &str) -> Option<(u64, u64)> {
+        let (start, end) = value.split_once('-')?;
+        Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+    }
+    
+    /// Apply a node's `<BaseURL>` child (if any) on top of the inherited base
+    fn resolve_base(base: Url, node: Node) -> Result<Url> {
+        match Self::child(node, "BaseURL").and_then(|b| b.text()) {
+            Some(text) => Ok(base.join(text.trim())?),
+            None => Ok(base),
+        }
+    }
+    
+    fn join(base: &Url, path: &str) -> Result<String> {
+        Ok(base.join(path)?.to_string())
+    }
+    
+    fn parse_url(url: &str) -> Result<Url> {
+        Ok(Url::parse(url)?)
+    }
+    
+    fn children<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        node.children()
+            .filter(move |child| child.is_element() && child.tag_name().name() == name)
+    }
+    
+    fn child<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> Option<Node<'a, 'input>> {
+        Self::children(node, name).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const TIMELINE_MPD: &str = r#"<?xml version="1.0"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" mediaPresentationDuration="PT10S">
+  <BaseURL>https://cdn.example.com/video/</BaseURL>
+  <Period>
+    <AdaptationSet mimeType="video/mp4" contentType="video">
+      <SegmentTemplate timescale="1000" initialization="$RepresentationID$/init.mp4" media="$RepresentationID$/seg-$Number%03d$.m4s" startNumber="1">
+        <SegmentTimeline>
+          <S t="0" d="4000" r="1"/>
+          <S d="2000"/>
+        </SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="137" bandwidth="4500000" width="1920" height="1080" frameRate="30000/1001" codecs="avc1.640028"/>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4" lang="en">
+      <Representation id="140" bandwidth="128000" codecs="mp4a.40.2" audioSamplingRate="44100">
+        <AudioChannelConfiguration schemeIdUri="urn:mpeg:dash:23003:3:audio_channel_configuration:2011" value="2"/>
+        <BaseURL>audio/140.m4a</BaseURL>
+        <SegmentList>
+          <Initialization sourceURL="init.m4a" range="0-631"/>
+          <SegmentURL media="seg1.m4a" mediaRange="632-9999"/>
+        </SegmentList>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    #[test]
+    fn test_parse_segment_template_with_timeline() {
+        let manifest = DashParser::parse(TIMELINE_MPD, "https://example.com/manifest.mpd").unwrap();
+        assert!(!manifest.is_live);
+        assert_eq!(manifest.duration, Some(10.0));
+        assert_eq!(manifest.formats.len(), 2);
+        
+        let video = &manifest.formats[0];
+        assert_eq!(video.itag, Some(137));
+        assert_eq!(video.fps, Some(30));
+        assert_eq!(video.protocol, Protocol::Dash);
+        
+        let urls: Vec<&str> = video.fragments.iter().map(|f| f.url.as_str()).collect();
+        assert_eq!(urls, [
+            "https://cdn.example.com/video/137/init.mp4",
+            "https://cdn.example.com/video/137/seg-001.m4s",
+            "https://cdn.example.com/video/137/seg-002.m4s",
+            "https://cdn.example.com/video/137/seg-003.m4s",
+        ]);
+        assert_eq!(video.fragments[3].duration, Some(2.0));
+    }
+    
+    #[test]
+    fn test_parse_segment_list_with_ranges() {
+        let manifest = DashParser::parse(TIMELINE_MPD, "https://example.com/manifest.mpd").unwrap();
+        let audio = &manifest.formats[1];
+        
+        assert_eq!(audio.format_type, FormatType::Audio);
+        assert_eq!(audio.audio_channels, Some(2));
+        assert_eq!(audio.language.as_deref(), Some("en"));
+        assert_eq!(audio.fragments[0].url, "https://cdn.example.com/video/audio/init.m4a");
+        assert_eq!(audio.fragments[0].byte_range, Some((0, 631)));
+        assert_eq!(audio.fragments[1].byte_range, Some((632, 9999)));
+    }
+    
+    #[test]
+    fn test_parse_segment_base_index() {
+        let mpd = r#"<MPD type="static"><Period><AdaptationSet mimeType="video/mp4">
+  <Representation id="248" bandwidth="2000000" width="1920" height="1080">
+    <BaseURL>https://cdn.example.com/248.mp4</BaseURL>
+    <SegmentBase indexRange="700-1999"><Initialization range="0-699"/></SegmentBase>
+  </Representation>
+</AdaptationSet></Period></MPD>"#;
+        let manifest = DashParser::parse(mpd, "https://example.com/manifest.mpd").unwrap();
+        let video = &manifest.formats[0];
+        
+        assert_eq!(video.fragments.len(), 1);
+        assert_eq!(video.init_range, Some((0, 699)));
+        assert_eq!(video.index_range, Some((700, 1999)));
+    }
+    
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(DashParser::parse_duration("PT1H2M3.5S"), Some(3723.5));
+        assert_eq!(DashParser::parse_duration("PT0S"), Some(0.0));
+        assert_eq!(DashParser::parse_duration("P1DT1S"), Some(86401.0));
+        assert_eq!(DashParser::parse_duration("garbage"), None);
+    }
+    
+    #[test]
+    fn test_expand_template() {
+        assert_eq!(
+            DashParser::expand_template("$RepresentationID$/$Number%05d$-$Time$-$Bandwidth$$$.m4s", "v1", Some(800), Some(7), Some(9000)),
+            "v1/00007-9000-800$.m4s"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 //! Format parsing and processing utilities
 
-use crate::models::{Format, FormatType};
+use crate::models::{DynamicRange, Format, FormatType};
 use crate::Result;
 use serde_json::Value;
 
@@ -15,13 +15,89 @@ impl FormatExtractor {
     
     /// Filter formats by type (audio/video)
     pub fn filter_by_type(formats: &[Format], format_type: FormatType) -> Vec<Format> {
-        // TODO: Implement format filtering
-        todo!("Implement format filtering")
+        formats.iter().filter(|format| format.format_type == format_type).cloned().collect()
     }
     
-    /// Sort formats by quality (highest first)
+    /// Check whether a codec string (e.g. "mp4a.40.2") names an audio codec
+    pub fn is_audio_codec(codec: &str) -> bool {
+        ["mp4a", "opus", "vorbis", "ac-3", "ec-3", "flac", "mp3"]
+            .iter()
+            .any(|prefix| codec.starts_with(prefix))
+    }
+    
+    /// Sort formats by quality (highest first): video before audio, then
+    /// resolution, frame rate, HDR and bitrate, preferring muxed streams on ties
     pub fn sort_by_quality(formats: &mut [Format]) {
-        // TODO: Implement quality-based sorting
-        todo!("Implement quality sorting")
+        formats.sort_by_key(|format| std::cmp::Reverse(Self::quality_rank(format)));
+    }
+    
+    /// Sort key behind [`Self::sort_by_quality`]; higher is better
+    pub fn quality_rank(format: &Format) -> (bool, u32, u32, bool, u32, bool) {
+        (
+            format.format_type == FormatType::Video,
+            format.height.unwrap_or(0),
+            format.fps.unwrap_or(0),
+            format.dynamic_range == Some(DynamicRange::Hdr),
+            format.bitrate.unwrap_or(0),
+            !format.is_adaptive,
+        )
+    }
+    
+    /// Pick a format of `format_type` without asking.
+    ///
+    /// `quality` follows the `default_quality` setting: "best" (or none),
+    /// "worst", or a height such as "720p" for the best format no taller than it.
+    pub fn select(formats: &[Format], format_type: FormatType, quality: Option<&str>) -> Option<Format> {
+        let mut candidates = Self::filter_by_type(formats, format_type);
+        Self::sort_by_quality(&mut candidates);
+        
+        let quality = quality.map(|quality| quality.trim().to_ascii_lowercase());
+        let max_height = quality.as_deref().and_then(|quality| quality.strip_suffix('p')?.parse::<u32>().ok());
+        match (quality.as_deref(), max_height) {
+            (Some("worst"), _) => candidates.pop(),
+            (_, Some(max_height)) => {
+                let fits = candidates.iter().position(|format| format.height.is_some_and(|height| height <= max_height));
+                match fits {
+                    Some(index) => Some(candidates.swap_remove(index)),
+                    None => candidates.pop(),
+                }
+            }
+            _ => candidates.into_iter().next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn format(format_type: FormatType, height: Option<u32>, bitrate: u32) -> Format {
+        let mut format = Format::new(String::new(), format_type, "mp4".to_string(), String::new());
+        format.height = height;
+        format.bitrate = Some(bitrate);
+        format
+    }
+    
+    #[test]
+    fn test_select() {
+        let formats = vec![
+            format(FormatType::Video, Some(720), 2_000_000),
+            format(FormatType::Audio, None, 128_000),
+            format(FormatType::Video, Some(1080), 4_000_000),
+            format(FormatType::Audio, None, 160_000),
+            format(FormatType::Video, Some(360), 700_000),
+        ];
+        
+        let height = |quality| FormatExtractor::select(&formats, FormatType::Video, quality).unwrap().height;
+        assert_eq!(height(None), Some(1080));
+        assert_eq!(height(Some("best")), Some(1080));
+        assert_eq!(height(Some("worst")), Some(360));
+        assert_eq!(height(Some("720p")), Some(720));
+        assert_eq!(height(Some("480p")), Some(360));
+        assert_eq!(height(Some("240p")), Some(360));
+        
+        let audio = FormatExtractor::select(&formats, FormatType::Audio, None).unwrap();
+        assert_eq!(audio.bitrate, Some(160_000));
+        assert!(FormatExtractor::select(&[], FormatType::Video, None).is_none());
     }
-}
\ No newline at end of file
+}
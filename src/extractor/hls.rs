@@ -0,0 +1,299 @@
+//! HLS (m3u8) playlist parsing
+
+use crate::error::DownloaderError;
+use crate::extractor::FormatExtractor;
+use crate::models::{Format, FormatType, Fragment, Protocol};
+use crate::Result;
+use log::debug;
+use std::collections::HashMap;
+use url::Url;
+
+/// A media playlist: the actual list of segments for one rendition
+#[derive(Debug, Clone)]
+pub struct MediaPlaylist {
+    /// Sequence number of the first fragment in `fragments`
+    pub media_sequence: u64,
+    pub target_duration: Option<f64>,
+    /// `#EXT-X-ENDLIST` present; without it the playlist is live and grows
+    pub is_ended: bool,
+    pub fragments: Vec<Fragment>,
+}
+
+pub struct HlsParser;
+
+impl HlsParser {
+    /// Parse a master playlist into one format per variant stream and audio rendition.
+    ///
+    /// A media playlist passed here is returned as a single format pointing at itself.
+    pub fn parse_master(text: &str, playlist_url: &str) -> Result<Vec<Format>> {
+        Self::check_header(text)?;
+        let base = Url::parse(playlist_url)?;
+        
+        if text.lines().any(|line| line.starts_with("#EXTINF")) {
+            let mut format = Format::new(
+                "Unknown".to_string(),
+                FormatType::Video,
+                "mp4".to_string(),
+                playlist_url.to_string(),
+            );
+            format.protocol = Protocol::Hls;
+            format.manifest_url = Some(playlist_url.to_string());
+            return Ok(vec![format]);
+        }
+        
+        let mut formats = Vec::new();
+        let mut pending_variant: Option<HashMap<String, String>> = None;
+        
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+                pending_variant = Some(Self::parse_attributes(attributes));
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-MEDIA:") {
+                let attributes = Self::parse_attributes(attributes);
+                if attributes.get("TYPE").map(String::as_str) != Some("AUDIO") {
+                    continue;
+                }
+                // Renditions without a URI are muxed into the variant streams
+                if let Some(uri) = attributes.get("URI") {
+                    let url = base.join(uri)?.to_string();
+                    formats.push(Self::audio_rendition(&attributes, url, playlist_url));
+                }
+            } else if !line.starts_with('#') {
+                if let Some(attributes) = pending_variant.take() {
+                    let url = base.join(line)?.to_string();
+                    formats.push(Self::variant_stream(&attributes, url, playlist_url));
+                }
+            }
+        }
+        
+        if formats.is_empty() {
+            return Err(DownloaderError::ExtractionFailed("HLS playlist contains no streams".to_string()));
+        }
+        
+        debug!("Parsed {} formats from HLS playlist", formats.len());
+        Ok(formats)
+    }
+    
+    /// Parse a media playlist into its fragment list
+    pub fn parse_media(text: &str, playlist_url: &str) -> Result<MediaPlaylist> {
+        Self::check_header(text)?;
+        let base = Url::parse(playlist_url)?;
+        
+        let mut playlist = MediaPlaylist {
+            media_sequence: 0,
+            target_duration: None,
+            is_ended: false,
+            fragments: Vec::new(),
+        };
+        
+        let mut duration: Option<f64> = None;
+        let mut byte_range: Option<(u64, u64)> = None;
+        // End offset of the previous sub-range, for byte ranges without an explicit offset
+        let mut next_offset = 0u64;
+        
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+                playlist.media_sequence = value.trim().parse().unwrap_or(0);
+            } else if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+                playlist.target_duration = value.trim().parse().ok();
+            } else if line == "#EXT-X-ENDLIST" {
+                playlist.is_ended = true;
+            } else if let Some(value) = line.strip_prefix("#EXTINF:") {
+                duration = value.split(',').next().and_then(|d| d.trim().parse().ok());
+            } else if let Some(value) = line.strip_prefix("#EXT-X-BYTERANGE:") {
+                byte_range = Self::parse_byte_range(value, next_offset);
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+                let attributes = Self::parse_attributes(attributes);
+                if attributes.get("METHOD").is_some_and(|method| method != "NONE") {
+                    return Err(DownloaderError::ExtractionFailed("Encrypted HLS streams are not supported".to_string()));
+                }
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+                let attributes = Self::parse_attributes(attributes);
+                if let Some(uri) = attributes.get("URI") {
+                    playlist.fragments.push(Fragment {
+                        url: base.join(uri)?.to_string(),
+                        byte_range: attributes.get("BYTERANGE").and_then(|r| Self::parse_byte_range(r, 0)),
+                        duration: None,
+                    });
+                }
+            } else if !line.starts_with('#') {
+                if let Some((_, end)) = byte_range {
+                    next_offset = end + 1;
+                }
+                playlist.fragments.push(Fragment {
+                    url: base.join(line)?.to_string(),
+                    byte_range: byte_range.take(),
+                    duration: duration.take(),
+                });
+            }
+        }
+        
+        Ok(playlist)
+    }
+    
+    fn check_header(text: &str) -> Result<()> {
+        if text.trim_start().starts_with("#EXTM3U") {
+            Ok(())
+        } else {
+            Err(DownloaderError::ExtractionFailed("Not an HLS playlist (missing #EXTM3U)".to_string()))
+        }
+    }
+    
+    fn variant_stream(attributes: &HashMap<String, String>, url: String, playlist_url: &str) -> Format {
+        let resolution = attributes
+            .get("RESOLUTION")
+            .and_then(|r| r.split_once('x'))
+            .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)));
+        let bandwidth = attributes
+            .get("AVERAGE-BANDWIDTH")
+            .or_else(|| attributes.get("BANDWIDTH"))
+            .and_then(|b| b.parse::<u32>().ok());
+        let codecs: Vec<&str> = attributes
+            .get("CODECS")
+            .map(|c| c.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        let vcodec = codecs.iter().find(|c| !FormatExtractor::is_audio_codec(c)).map(|c| c.to_string());
+        let acodec = codecs.iter().find(|c| FormatExtractor::is_audio_codec(c)).map(|c| c.to_string());
+        
+        let is_audio_only = resolution.is_none() && vcodec.is_none() && acodec.is_some();
+        let (format_type, extension, quality) = if is_audio_only {
+            let quality = bandwidth.map_or_else(|| "Audio".to_string(), |b| format!("{}kbps", b / 1000));
+            (FormatType::Audio, "mp3", quality)
+        } else {
+            let quality = resolution.map_or_else(|| "Unknown".to_string(), |(_, h)| format!("{}p", h));
+            (FormatType::Video, "mp4", quality)
+        };
+        
+        let mut format = Format::new(quality, format_type, extension.to_string(), url);
+        format.protocol = Protocol::Hls;
+        format.manifest_url = Some(playlist_url.to_string());
+        format.bitrate = bandwidth;
+        format.width = resolution.map(|(w, _)| w);
+        format.height = resolution.map(|(_, h)| h);
+        format.fps = attributes
+            .get("FRAME-RATE")
+            .and_then(|f| f.parse::<f64>().ok())
+            .map(|f| f.round() as u32);
+        format.codec = attributes.get("CODECS").cloned();
+        format.vcodec = vcodec;
+        format.acodec = acodec;
+        // Variants that reference a separate audio group carry no audio themselves
+        format.is_adaptive = is_audio_only || attributes.contains_key("AUDIO");
+        format
+    }
+    
+    fn audio_rendition(attributes: &HashMap<String, String>, url: String, playlist_url: &str) -> Format {
+        let mut format = Format::new("Audio".to_string(), FormatType::Audio, "mp3".to_string(), url);
+        format.protocol = Protocol::Hls;
+        format.manifest_url = Some(playlist_url.to_string());
+        format.is_adaptive = true;
+        format.language = attributes.get("LANGUAGE").cloned();
+        format.audio_channels = attributes
+            .get("CHANNELS")
+            .and_then(|c| c.split('/').next())
+            .and_then(|c| c.parse().ok());
+        format
+    }
+    
+    /// Parse `LENGTH[@OFFSET]` into an inclusive range
+    fn parse_byte_range(value: &str, default_offset: u64) -> Option<(u64, u64)> {
+        let (length, offset) = match value.trim().split_once('@') {
+            Some((length, offset)) => (length.parse::<u64>().ok()?, offset.parse::<u64>().ok()?),
+            None => (value.trim().parse::<u64>().ok()?, default_offset),
+        };
+        (length > 0).then(|| (offset, offset + length - 1))
+    }
+    
+    /// Parse an attribute list such as `BANDWIDTH=1280000,CODECS="avc1.4d401f,mp4a.40.2"`
+    fn parse_attributes(text: &str) -> HashMap<String, String> {
+        let mut attributes = HashMap::new();
+        let mut rest = text.trim();
+        
+        while let Some((key, after)) = rest.split_once('=') {
+            let (value, remainder) = if let Some(quoted) = after.strip_prefix('"') {
+                match quoted.split_once('"') {
+                    Some((value, remainder)) => (value, remainder),
+                    None => (quoted, ""),
+                }
+            } else {
+                match after.split_once(',') {
+                    Some((value, remainder)) => (value, remainder),
+                    None => (after, ""),
+                }
+            };
+            
+            attributes.insert(key.trim().to_string(), value.to_string());
+            rest = remainder.trim_start_matches(',').trim_start();
+        }
+        
+        attributes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_attributes_with_quoted_commas() {
+        let attributes = HlsParser::parse_attributes(r#"BANDWIDTH=1280000,CODECS="avc1.4d401f,mp4a.40.2",RESOLUTION=1280x720"#);
+        assert_eq!(attributes["BANDWIDTH"], "1280000");
+        assert_eq!(attributes["CODECS"], "avc1.4d401f,mp4a.40.2");
+        assert_eq!(attributes["RESOLUTION"], "1280x720");
+    }
+    
+    #[test]
+    fn test_parse_master_playlist() {
+        let text = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",LANGUAGE=\"en\",CHANNELS=\"2\",URI=\"audio/en.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2500000,RESOLUTION=1280x720,FRAME-RATE=29.970,CODECS=\"avc1.4d401f,mp4a.40.2\"\n\
+            720p/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e\",AUDIO=\"aud\"\n\
+            https://other.example.com/360p.m3u8\n";
+        
+        let formats = HlsParser::parse_master(text, "https://example.com/live/master.m3u8").unwrap();
+        assert_eq!(formats.len(), 3);
+        
+        assert_eq!(formats[0].format_type, FormatType::Audio);
+        assert_eq!(formats[0].download_url, "https://example.com/live/audio/en.m3u8");
+        assert_eq!(formats[0].language.as_deref(), Some("en"));
+        
+        assert_eq!(formats[1].height, Some(720));
+        assert_eq!(formats[1].fps, Some(30));
+        assert_eq!(formats[1].acodec.as_deref(), Some("mp4a.40.2"));
+        assert!(!formats[1].is_adaptive);
+        
+        assert_eq!(formats[2].download_url, "https://other.example.com/360p.m3u8");
+        assert!(formats[2].is_adaptive);
+    }
+    
+    #[test]
+    fn test_parse_media_playlist_with_byte_ranges() {
+        let text = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-MEDIA-SEQUENCE:42\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXTINF:6.0,\n\
+            #EXT-X-BYTERANGE:1000@0\n\
+            media.mp4\n\
+            #EXTINF:4.5,\n\
+            #EXT-X-BYTERANGE:500\n\
+            media.mp4\n\
+            #EXT-X-ENDLIST\n";
+        
+        let playlist = HlsParser::parse_media(text, "https://example.com/v/index.m3u8").unwrap();
+        assert_eq!(playlist.media_sequence, 42);
+        assert_eq!(playlist.target_duration, Some(6.0));
+        assert!(playlist.is_ended);
+        assert_eq!(playlist.fragments.len(), 3);
+        assert_eq!(playlist.fragments[0].url, "https://example.com/v/init.mp4");
+        assert_eq!(playlist.fragments[1].byte_range, Some((0, 999)));
+        assert_eq!(playlist.fragments[2].byte_range, Some((1000, 1499)));
+        assert_eq!(playlist.fragments[2].duration, Some(4.5));
+    }
+    
+    #[test]
+    fn test_rejects_encrypted_playlists() {
+        let text = "#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n#EXTINF:6,\nseg.ts\n";
+        assert!(HlsParser::parse_media(text, "https://example.com/index.m3u8").is_err());
+    }
+}
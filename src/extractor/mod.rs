@@ -3,6 +3,18 @@
 
 pub mod youtube;
 pub mod format;
+pub mod dash;
+pub mod hls;
+pub mod sidx;
+pub mod scheduled;
+pub mod chapters;
+pub mod sponsorblock;
 
 pub use youtube::YouTubeExtractor;
-pub use format::FormatExtractor;
\ No newline at end of file
+pub use format::FormatExtractor;
+pub use dash::{DashManifest, DashParser};
+pub use hls::{HlsParser, MediaPlaylist};
+pub use sidx::SidxParser;
+pub use scheduled::{VideoWaiter, WaitRange};
+pub use chapters::ChapterParser;
+pub use sponsorblock::{SponsorBlockClient, SponsorSegment};
\ No newline at end of file
@@ -0,0 +1,113 @@
+//! Waiting for scheduled premieres and upcoming live streams
+
+use crate::error::DownloaderError;
+use crate::extractor::YouTubeExtractor;
+use crate::models::VideoInfo;
+use crate::Result;
+use log::{debug, info};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Bounds for the time between re-polls of an upcoming video
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitRange {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+pub struct VideoWaiter {
+    range: WaitRange,
+}
+
+impl VideoWaiter {
+    pub fn new(range: WaitRange) -> Self {
+        Self { range }
+    }
+    
+    /// Re-poll `url` until the video is no longer upcoming.
+    ///
+    /// `on_tick` is called about once per second with the time left before
+    /// the next poll, so callers can show a countdown.
+    pub async fn wait_for_video<F>(&self, extractor: &YouTubeExtractor, url: &str, mut on_tick: F) -> Result<VideoInfo>
+    where
+        F: FnMut(Duration),
+    {
+        // Polls made after the scheduled start passed without the stream going live
+        let mut overdue_polls = 0u32;
+        
+        loop {
+            let scheduled_start = match extractor.extract_video_info(url).await {
+                Err(DownloaderError::VideoUpcoming(scheduled_start)) => scheduled_start,
+                result => return result,
+            };
+            
+            let now = Self::unix_now();
+            if scheduled_start.is_none_or(|start| start <= now) {
+                overdue_polls += 1;
+            }
+            
+            let wait = self.next_wait(scheduled_start, now, overdue_polls);
+            info!("Video has not started yet, checking again in {}s", wait.as_secs());
+            Self::countdown(wait, &mut on_tick).await;
+        }
+    }
+    
+    /// How long to sleep before the next poll.
+    ///
+    /// Until the scheduled start is reached we sleep right up to it (but at
+    /// least `min`); after that, or when no start time is known, we back off
+    /// exponentially from `min` up to `max`.
+    pub fn next_wait(&self, scheduled_start: Option<u64>, now: u64, overdue_polls: u32) -> Duration {
+        match scheduled_start {
+            Some(start) if start > now => Duration::from_secs(start - now).max(self.range.min),
+            _ => {
+                let factor = 2u32.saturating_pow(overdue_polls.saturating_sub(1));
+                self.range.min.saturating_mul(factor).min(self.range.max)
+            }
+        }
+    }
+    
+    async fn countdown<F: FnMut(Duration)>(wait: Duration, on_tick: &mut F) {
+        let mut remaining = wait;
+        while !remaining.is_zero() {
+            on_tick(remaining);
+            let step = remaining.min(Duration::from_secs(1));
+            tokio::time::sleep(step).await;
+            remaining -= step;
+        }
+        debug!("Wait finished, polling video again");
+    }
+    
+    fn unix_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn waiter() -> VideoWaiter {
+        VideoWaiter::new(WaitRange {
+            min: Duration::from_secs(60),
+            max: Duration::from_secs(600),
+        })
+    }
+    
+    #[test]
+    fn test_waits_until_scheduled_start() {
+        assert_eq!(waiter().next_wait(Some(10_000), 7_000, 0), Duration::from_secs(3_000));
+        // Never poll more often than the minimum
+        assert_eq!(waiter().next_wait(Some(10_000), 9_990, 0), Duration::from_secs(60));
+    }
+    
+    #[test]
+    fn test_backs_off_when_overdue_or_unscheduled() {
+        assert_eq!(waiter().next_wait(Some(10_000), 10_500, 1), Duration::from_secs(60));
+        assert_eq!(waiter().next_wait(Some(10_000), 10_500, 2), Duration::from_secs(120));
+        assert_eq!(waiter().next_wait(None, 0, 4), Duration::from_secs(480));
+        assert_eq!(waiter().next_wait(None, 0, 10), Duration::from_secs(600));
+    }
+}
@@ -0,0 +1,114 @@
+//! MP4 segment index (`sidx`) parsing
+//!
+//! Single-file DASH representations, including YouTube's adaptive formats,
+//! carry a `sidx` box listing the size and duration of every subsegment.
+//! That turns one large file into byte-range fragments that can be fetched
+//! selectively.
+
+use crate::error::DownloaderError;
+use crate::models::Fragment;
+use crate::Result;
+
+pub struct SidxParser;
+
+impl SidxParser {
+    /// Parse a `sidx` box read from byte `offset` of `url` into one fragment per subsegment
+    pub fn parse(data: &[u8], offset: u64, url: &str) -> Result<Vec<Fragment>> {
+        let invalid = |reason: &str| DownloaderError::ExtractionFailed(format!("Invalid segment index: {}", reason));
+        
+        if data.len() < 8 || &data[4..8] != b"sidx" {
+            return Err(invalid("no sidx box at the index range"));
+        }
+        let size = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+        let body = data.get(8..size).ok_or_else(|| invalid("truncated box"))?;
+        
+        let mut reader = BoxReader { data: body };
+        let version = reader.u32()? >> 24;
+        let _reference_id = reader.u32()?;
+        let timescale = reader.u32()?.max(1) as f64;
+        let (_earliest_presentation_time, first_offset) = if version == 0 {
+            (reader.u32()? as u64, reader.u32()? as u64)
+        } else {
+            (reader.u64()?, reader.u64()?)
+        };
+        let reference_count = reader.u32()? & 0xFFFF;
+        
+        // Subsegments follow the sidx box, after `first_offset` bytes
+        let mut position = offset + size as u64 + first_offset;
+        let mut fragments = Vec::with_capacity(reference_count as usize);
+        for _ in 0..reference_count {
+            let reference = reader.u32()?;
+            let duration = reader.u32()?;
+            let _sap = reader.u32()?;
+            
+            if reference & 0x8000_0000 != 0 {
+                return Err(invalid("nested segment indexes are not supported"));
+            }
+            let referenced_size = (reference & 0x7FFF_FFFF) as u64;
+            
+            fragments.push(Fragment {
+                url: url.to_string(),
+                byte_range: Some((position, position + referenced_size - 1)),
+                duration: Some(duration as f64 / timescale),
+            });
+            position += referenced_size;
+        }
+        
+        Ok(fragments)
+    }
+}
+
+struct BoxReader<'a> {
+    data: &'a [u8],
+}
+
+impl BoxReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.data.len() < len {
+            return Err(DownloaderError::ExtractionFailed("Invalid segment index: truncated box".to_string()));
+        }
+        let (value, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(value)
+    }
+    
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_sidx() {
+        let mut body = vec![1, 0, 0, 0];
+        body.extend(1u32.to_be_bytes());
+        body.extend(1000u32.to_be_bytes());
+        body.extend(0u64.to_be_bytes());
+        body.extend(16u64.to_be_bytes());
+        body.extend(2u32.to_be_bytes());
+        for (size, duration) in [(100u32, 5000u32), (50, 2500)] {
+            body.extend(size.to_be_bytes());
+            body.extend(duration.to_be_bytes());
+            body.extend(0x9000_0000u32.to_be_bytes());
+        }
+        let mut sidx = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        sidx.extend(b"sidx");
+        sidx.extend(body);
+        
+        let fragments = SidxParser::parse(&sidx, 700, "https://example.com/video").unwrap();
+        let start = 700 + sidx.len() as u64 + 16;
+        assert_eq!(fragments.len(), 2);
+        assert_eq!(fragments[0].byte_range, Some((start, start + 99)));
+        assert_eq!(fragments[1].byte_range, Some((start + 100, start + 149)));
+        assert_eq!(fragments[1].duration, Some(2.5));
+        
+        assert!(SidxParser::parse(b"\0\0\0\x08free", 0, "https://example.com/video").is_err());
+    }
+}
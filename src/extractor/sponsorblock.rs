@@ -0,0 +1,284 @@
+//! SponsorBlock segment lookup
+//!
+//! SponsorBlock is a crowd-sourced database of sponsor reads, intros,
+//! self-promotion and similar parts of videos. Segments can be marked as
+//! chapters or cut out of the downloaded file.
+
+use crate::error::DownloaderError;
+use crate::models::Chapter;
+use crate::Result;
+use log::debug;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+/// Public SponsorBlock API
+pub const DEFAULT_API: &str = "https://sponsor.ajay.app";
+
+/// Segment categories that can be marked or removed, with their display names
+pub const CATEGORIES: &[(&str, &str)] = &[
+    ("sponsor", "Sponsor"),
+    ("intro", "Intermission/Intro Animation"),
+    ("outro", "Endcards/Credits"),
+    ("selfpromo", "Unpaid/Self Promotion"),
+    ("preview", "Preview/Recap"),
+    ("filler", "Filler Tangent"),
+    ("interaction", "Interaction Reminder"),
+    ("music_offtopic", "Non-Music Section"),
+];
+
+/// A skippable part of a video
+#[derive(Debug, Clone, PartialEq)]
+pub struct SponsorSegment {
+    pub category: String,
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+}
+
+#[derive(Deserialize)]
+struct ApiSegment {
+    segment: (f64, f64),
+    category: String,
+    #[serde(default, rename = "actionType")]
+    action_type: Option<String>,
+}
+
+pub struct SponsorBlockClient {
+    client: Client,
+    api_base: String,
+}
+
+impl SponsorBlockClient {
+    pub fn new(client: Client, api_base: &str) -> Self {
+        Self {
+            client,
+            api_base: api_base.trim_end_matches('/').to_string(),
+        }
+    }
+    
+    /// Fetch the segments of a video in the given categories, sorted by start time
+    pub async fn segments(&self, video_id: &str, categories: &[String]) -> Result<Vec<SponsorSegment>> {
+        if categories.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let url = format!("{}/api/skipSegments", self.api_base);
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("videoID", video_id), ("categories", &serde_json::to_string(categories)?)])
+            .send()
+            .await?;
+        // The API answers 404 when a video has no segments
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!("No SponsorBlock segments for {}", video_id);
+            return Ok(Vec::new());
+        }
+        
+        let segments: Vec<ApiSegment> = response.error_for_status()?.json().await?;
+        let mut segments: Vec<SponsorSegment> = segments
+            .into_iter()
+            .filter(|segment| segment.action_type.as_deref().is_none_or(|action| action == "skip"))
+            .filter(|segment| segment.segment.1 > segment.segment.0)
+            .map(|segment| SponsorSegment {
+                category: segment.category,
+                start: segment.segment.0,
+                end: segment.segment.1,
+            })
+            .collect();
+        segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+        
+        debug!("Found {} SponsorBlock segments for {}", segments.len(), video_id);
+        Ok(segments)
+    }
+    
+    /// Expand "all" and check category names
+    pub fn parse_categories(categories: &[String]) -> Result<Vec<String>> {
+        let mut parsed: Vec<String> = Vec::new();
+        for category in categories {
+            let names: Vec<&str> = match category.trim() {
+                "all" => CATEGORIES.iter().map(|(name, _)| *name).collect(),
+                name if CATEGORIES.iter().any(|(known, _)| *known == name) => vec![name],
+                name => {
+                    return Err(DownloaderError::Configuration(format!(
+                        "Unknown SponsorBlock category '{}' (expected one of: all, {})",
+                        name,
+                        CATEGORIES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+                    )))
+                }
+            };
+            for name in names {
+                if !parsed.iter().any(|existing| existing == name) {
+                    parsed.push(name.to_string());
+                }
+            }
+        }
+        Ok(parsed)
+    }
+    
+    /// Add a chapter for each segment, splitting the chapters it overlaps.
+    ///
+    /// Parts of the video outside any chapter become untitled chapters, so
+    /// every segment has a clear end.
+    pub fn mark_chapters(chapters: &[Chapter], segments: &[SponsorSegment], duration: f64) -> Vec<Chapter> {
+        let segments = Self::non_overlapping(segments);
+        if segments.is_empty() {
+            return chapters.to_vec();
+        }
+        
+        let end = chapters
+            .iter()
+            .map(|chapter| chapter.end)
+            .chain(segments.iter().map(|segment| segment.end))
+            .fold(duration, f64::max);
+        let mut base: Vec<Chapter> = Vec::new();
+        let mut untitled = 0;
+        let mut fill = |base: &mut Vec<Chapter>, start: f64, end: f64| {
+            if end > start {
+                untitled += 1;
+                base.push(Chapter { title: format!("<Untitled Chapter {}>", untitled), start, end });
+            }
+        };
+        let mut position = 0.0;
+        for chapter in chapters.iter().filter(|chapter| chapter.end > chapter.start) {
+            fill(&mut base, position, chapter.start);
+            base.push(chapter.clone());
+            position = f64::max(position, chapter.end);
+        }
+        fill(&mut base, position, end);
+        
+        let mut marked: Vec<Chapter> = Vec::new();
+        for chapter in &base {
+            let mut start = chapter.start;
+            for segment in segments.iter().filter(|s| s.start < chapter.end && s.end > chapter.start) {
+                if segment.start > start {
+                    marked.push(Chapter { title: chapter.title.clone(), start, end: segment.start });
+                }
+                start = start.max(segment.end);
+            }
+            if chapter.end > start {
+                marked.push(Chapter { title: chapter.title.clone(), start, end: chapter.end });
+            }
+        }
+        for segment in &segments {
+            marked.push(Chapter {
+                title: format!("[SponsorBlock]: {}", Self::category_name(&segment.category)),
+                start: segment.start,
+                end: segment.end,
+            });
+        }
+        
+        marked.sort_by(|a, b| a.start.total_cmp(&b.start));
+        marked
+    }
+    
+    /// Time ranges left after removing the segments
+    pub fn keep_ranges(segments: &[SponsorSegment]) -> Vec<(f64, f64)> {
+        let mut ranges = Vec::new();
+        let mut position = 0.0;
+        for segment in Self::non_overlapping(segments) {
+            if segment.start > position {
+                ranges.push((position, segment.start));
+            }
+            position = segment.end;
+        }
+        ranges.push((position, f64::INFINITY));
+        ranges
+    }
+    
+    /// Move chapters to match a video with the segments removed, dropping chapters that were removed entirely
+    pub fn shift_chapters(chapters: &[Chapter], segments: &[SponsorSegment]) -> Vec<Chapter> {
+        let segments = Self::non_overlapping(segments);
+        let removed_before = |time: f64| -> f64 {
+            segments
+                .iter()
+                .map(|segment| (time.min(segment.end) - segment.start).max(0.0))
+                .sum()
+        };
+        
+        chapters
+            .iter()
+            .map(|chapter| Chapter {
+                title: chapter.title.clone(),
+                start: chapter.start - removed_before(chapter.start),
+                end: chapter.end - removed_before(chapter.end),
+            })
+            .filter(|chapter| chapter.end > chapter.start)
+            .collect()
+    }
+    
+    fn category_name(category: &str) -> &str {
+        CATEGORIES
+            .iter()
+            .find(|(name, _)| *name == category)
+            .map_or(category, |(_, display)| display)
+    }
+    
+    /// Segments sorted by start, with overlapping ones merged
+    fn non_overlapping(segments: &[SponsorSegment]) -> Vec<SponsorSegment> {
+        let mut sorted = segments.to_vec();
+        sorted.sort_by(|a, b| a.start.total_cmp(&b.start));
+        
+        let mut merged: Vec<SponsorSegment> = Vec::new();
+        for segment in sorted {
+            match merged.last_mut() {
+                Some(last) if segment.start < last.end => last.end = last.end.max(segment.end),
+                _ => merged.push(segment),
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn segment(category: &str, start: f64, end: f64) -> SponsorSegment {
+        SponsorSegment { category: category.to_string(), start, end }
+    }
+    
+    fn chapter(title: &str, start: f64, end: f64) -> Chapter {
+        Chapter { title: title.to_string(), start, end }
+    }
+    
+    #[test]
+    fn test_mark_chapters_splits_existing_chapters() {
+        let chapters = [chapter("Intro", 0.0, 60.0), chapter("Main", 60.0, 300.0)];
+        let segments = [segment("sponsor", 50.0, 80.0)];
+        
+        let marked = SponsorBlockClient::mark_chapters(&chapters, &segments, 300.0);
+        assert_eq!(marked, [
+            chapter("Intro", 0.0, 50.0),
+            chapter("[SponsorBlock]: Sponsor", 50.0, 80.0),
+            chapter("Main", 80.0, 300.0),
+        ]);
+        
+        let marked = SponsorBlockClient::mark_chapters(&[], &[segment("outro", 280.0, 300.0)], 300.0);
+        assert_eq!(marked, [
+            chapter("<Untitled Chapter 1>", 0.0, 280.0),
+            chapter("[SponsorBlock]: Endcards/Credits", 280.0, 300.0),
+        ]);
+    }
+    
+    #[test]
+    fn test_remove_segments() {
+        let segments = [segment("sponsor", 10.0, 20.0), segment("selfpromo", 15.0, 30.0), segment("outro", 90.0, 100.0)];
+        assert_eq!(SponsorBlockClient::keep_ranges(&segments), [(0.0, 10.0), (30.0, 90.0), (100.0, f64::INFINITY)]);
+        
+        let chapters = [chapter("Intro", 0.0, 12.0), chapter("Ad", 12.0, 30.0), chapter("Main", 30.0, 100.0)];
+        assert_eq!(SponsorBlockClient::shift_chapters(&chapters, &segments), [
+            chapter("Intro", 0.0, 10.0),
+            chapter("Main", 10.0, 70.0),
+        ]);
+    }
+    
+    #[test]
+    fn test_parse_categories() {
+        let categories = SponsorBlockClient::parse_categories(&["sponsor".to_string(), "all".to_string()]).unwrap();
+        assert_eq!(categories.len(), CATEGORIES.len());
+        assert_eq!(categories[0], "sponsor");
+        assert!(SponsorBlockClient::parse_categories(&["ads".to_string()]).is_err());
+    }
+}
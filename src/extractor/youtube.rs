@@ -1,6 +1,9 @@
 //! YouTube-specific video information extraction
 
-use crate::models::{VideoInfo, Format, FormatType};
+use crate::config::Settings;
+use crate::models::{VideoInfo, Format, FormatType, DynamicRange, Protocol, SubtitleTrack};
+use crate::extractor::{ChapterParser, DashParser, FormatExtractor, HlsParser};
+use crate::metadata::{Subtitle, VttParser};
 use crate::utils::{UrlValidator, NetworkUtils};
 use crate::Result;
 use crate::error::DownloaderError;
@@ -10,14 +13,29 @@ use serde_json::Value;
 use log::{debug, warn, error};
 use std::sync::OnceLock;
 
+/// Site watch pages are fetched from
+pub const DEFAULT_URL: &str = "https://www.youtube.com";
+
 pub struct YouTubeExtractor {
     client: Client,
+    base_url: String,
 }
 
 impl YouTubeExtractor {
     pub fn new() -> Result<Self> {
         let client = NetworkUtils::create_client()?;
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            base_url: DEFAULT_URL.to_string(),
+        })
+    }
+    
+    /// Create an extractor fetching watch pages from the configured site
+    pub fn with_settings(settings: &Settings) -> Result<Self> {
+        Ok(Self {
+            client: NetworkUtils::create_client()?,
+            base_url: settings.get_youtube_url().trim_end_matches('/').to_string(),
+        })
     }
     
     /// Extract video information from YouTube URL
@@ -26,16 +44,42 @@ impl YouTubeExtractor {
         
         // 1. Validate and extract video ID
         let video_id = UrlValidator::extract_video_id(url)?;
-        let normalized_url = UrlValidator::normalize_url(url)?;
+        let watch_url = format!("{}/watch?v={}", self.base_url, video_id);
         
         // 2. Fetch the YouTube page
-        let html = self.fetch_page(&normalized_url).await?;
+        let html = self.fetch_page(&watch_url).await?;
         
         // 3. Parse video metadata and formats
         let mut video_info = self.parse_video_page(&html, &video_id)?;
         
-        // 4. Extract and filter formats (MP4 video and MP3 audio only)
-        let formats = self.extract_formats(&html)?;
+        // 4. Extract formats, including those only offered through DASH/HLS manifests
+        let player_response = self.extract_player_response(&html);
+        let mut formats = self.extract_formats(&html, player_response.as_ref()).unwrap_or_default();
+        if let Some(ref player_response) = player_response {
+            if Self::is_upcoming(player_response) {
+                return Err(DownloaderError::VideoUpcoming(Self::scheduled_start_time(player_response)));
+            }
+            
+            video_info.description = player_response
+                .pointer("/videoDetails/shortDescription")
+                .and_then(|description| description.as_str())
+                .filter(|description| !description.is_empty())
+                .map(str::to_string);
+            video_info.duration_seconds = player_response
+                .pointer("/videoDetails/lengthSeconds")
+                .and_then(|length| length.as_str().and_then(|l| l.parse().ok()).or_else(|| length.as_u64()));
+            video_info.subtitles = Self::extract_subtitle_tracks(player_response);
+            video_info.is_live = Self::is_live(player_response);
+            if video_info.is_live {
+                // Progressive URLs of a live stream only cover the current segment
+                formats.clear();
+            }
+            formats.extend(self.extract_manifest_formats(player_response).await);
+        }
+        
+        video_info.chapters = ChapterParser::extract(&html, video_info.description.as_deref(), video_info.duration_seconds);
+        
+        // 5. Filter formats (MP4 video and MP3 audio only)
         let filtered_formats = self.filter_formats(formats);
         
         if filtered_formats.is_empty() {
@@ -48,6 +92,41 @@ impl YouTubeExtractor {
         Ok(video_info)
     }
     
+    /// List the videos of a playlist as watch URLs, in playlist order.
+    ///
+    /// Only the entries on the playlist page are found, which YouTube limits to the first 100.
+    pub async fn extract_playlist(&self, url: &str) -> Result<Vec<String>> {
+        let playlist_id = UrlValidator::extract_playlist_id(url).ok_or_else(|| DownloaderError::InvalidUrl(url.to_string()))?;
+        let html = self.fetch_page(&format!("{}/playlist?list={}", self.base_url, playlist_id)).await?;
+        
+        let urls: Vec<String> = Self::parse_playlist_entries(&html)
+            .into_iter()
+            .map(|video_id| format!("https://www.youtube.com/watch?v={}", video_id))
+            .collect();
+        if urls.is_empty() {
+            return Err(DownloaderError::ExtractionFailed(format!("Playlist {} has no videos", playlist_id)));
+        }
+        debug!("Found {} videos in playlist {}", urls.len(), playlist_id);
+        Ok(urls)
+    }
+    
+    /// Video IDs of the entries on a playlist page, without repeats
+    fn parse_playlist_entries(html: &str) -> Vec<String> {
+        static ENTRY_PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = ENTRY_PATTERN.get_or_init(|| {
+            Regex::new(r#""playlistVideoRenderer":\{"videoId":"([a-zA-Z0-9_-]{11})""#).unwrap()
+        });
+        
+        let mut video_ids: Vec<String> = Vec::new();
+        for captures in pattern.captures_iter(html) {
+            let video_id = &captures[1];
+            if !video_ids.iter().any(|id| id == video_id) {
+                video_ids.push(video_id.to_string());
+            }
+        }
+        video_ids
+    }
+    
     /// Fetch YouTube page HTML
     async fn fetch_page(&self, url: &str) -> Result<String> {
         debug!("Fetching YouTube page: {}", url);
@@ -79,11 +158,14 @@ impl YouTubeExtractor {
         // Extract uploader (optional)
         let uploader = self.extract_uploader(html);
         
+        let upload_date = self.extract_upload_date(html);
+        
         // Extract thumbnail URL
         let thumbnail_url = self.extract_thumbnail_url(html, video_id);
         
         let mut video_info = VideoInfo::new(title, duration, video_id.to_string());
         video_info.uploader = uploader;
+        video_info.upload_date = upload_date;
         video_info.thumbnail_url = thumbnail_url;
         
         Ok(video_info)
@@ -164,29 +246,111 @@ impl YouTubeExtractor {
         None
     }
     
+    /// Extract the upload date as YYYYMMDD from the page microformat
+    fn extract_upload_date(&self, html: &str) -> Option<String> {
+        static UPLOAD_DATE_PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = UPLOAD_DATE_PATTERN.get_or_init(|| {
+            Regex::new(r#""(?:uploadDate|publishDate)":"(\d{4})-(\d{2})-(\d{2})"#).unwrap()
+        });
+        
+        let captures = pattern.captures(html)?;
+        Some(format!("{}{}{}", &captures[1], &captures[2], &captures[3]))
+    }
+    
     /// Extract thumbnail URL
     fn extract_thumbnail_url(&self, _html: &str, video_id: &str) -> String {
         // Use YouTube's predictable thumbnail URL format
         format!("https://img.youtube.com/vi/{}/maxresdefault.jpg", video_id)
     }
     
-    /// Extract available formats from YouTube page
-    fn extract_formats(&self, html: &str) -> Result<Vec<Format>> {
-        debug!("Extracting formats from page");
-        
-        // Look for the ytInitialPlayerResponse or similar JSON data
+    /// Find and parse the ytInitialPlayerResponse JSON embedded in the page
+    fn extract_player_response(&self, html: &str) -> Option<Value> {
         static PLAYER_RESPONSE_PATTERN: OnceLock<Regex> = OnceLock::new();
         let pattern = PLAYER_RESPONSE_PATTERN.get_or_init(|| {
-            Regex::new(r#"ytInitialPlayerResponse["\s]*=["\s]*(\{.+?\});?"#).unwrap()
+            Regex::new(r#"ytInitialPlayerResponse["\s]*=["\s]*\{"#).unwrap()
         });
         
-        if let Some(captures) = pattern.captures(html) {
-            if let Some(json_match) = captures.get(1) {
-                let json_str = json_match.as_str();
-                if let Ok(json_data) = serde_json::from_str::<Value>(json_str) {
-                    return self.parse_formats_from_json(&json_data);
-                }
-            }
+        // A pattern cannot match nested braces, so read one JSON value from the opening brace on
+        let start = pattern.find(html)?.end() - 1;
+        serde_json::Deserializer::from_str(&html[start..])
+            .into_iter::<Value>()
+            .next()?
+            .ok()
+    }
+    
+    /// List the caption tracks offered in the player response
+    fn extract_subtitle_tracks(player_response: &Value) -> Vec<SubtitleTrack> {
+        let tracks = player_response
+            .pointer("/captions/playerCaptionsTracklistRenderer/captionTracks")
+            .and_then(|tracks| tracks.as_array());
+        
+        tracks
+            .into_iter()
+            .flatten()
+            .filter_map(|track| {
+                let url = track.get("baseUrl")?.as_str()?;
+                let language = track.get("languageCode")?.as_str()?;
+                let name = track
+                    .pointer("/name/simpleText")
+                    .or_else(|| track.pointer("/name/runs/0/text"))
+                    .and_then(|name| name.as_str())
+                    .unwrap_or(language);
+                Some(SubtitleTrack {
+                    language: language.to_string(),
+                    name: name.to_string(),
+                    url: url.to_string(),
+                    is_automatic: track.get("kind").and_then(|kind| kind.as_str()) == Some("asr"),
+                })
+            })
+            .collect()
+    }
+    
+    /// Download a caption track as WebVTT and parse its cues
+    pub async fn fetch_subtitle(&self, track: &SubtitleTrack) -> Result<Subtitle> {
+        let vtt = NetworkUtils::fetch_text(&self.client, &track.vtt_url(), 3).await?;
+        Ok(Subtitle {
+            language: track.language.clone(),
+            cues: VttParser::parse(&vtt),
+        })
+    }
+    
+    /// Check whether the player response describes an ongoing live broadcast
+    fn is_live(player_response: &Value) -> bool {
+        player_response
+            .get("videoDetails")
+            .and_then(|details| details.get("isLive"))
+            .and_then(|is_live| is_live.as_bool())
+            .unwrap_or(false)
+    }
+    
+    /// Check whether the video is a premiere or live stream that has not started yet
+    fn is_upcoming(player_response: &Value) -> bool {
+        let is_upcoming = player_response
+            .get("videoDetails")
+            .and_then(|details| details.get("isUpcoming"))
+            .and_then(|is_upcoming| is_upcoming.as_bool())
+            .unwrap_or(false);
+        let status = player_response
+            .get("playabilityStatus")
+            .and_then(|status| status.get("status"))
+            .and_then(|status| status.as_str());
+        
+        is_upcoming || status == Some("LIVE_STREAM_OFFLINE")
+    }
+    
+    /// Scheduled start of an upcoming stream as a Unix timestamp
+    fn scheduled_start_time(player_response: &Value) -> Option<u64> {
+        player_response
+            .pointer("/playabilityStatus/liveStreamability/liveStreamabilityRenderer/offlineSlate/liveStreamOfflineSlateRenderer/scheduledStartTime")
+            .and_then(|time| time.as_str().and_then(|t| t.parse().ok()).or_else(|| time.as_u64()))
+    }
+    
+    /// Extract available formats from YouTube page
+    fn extract_formats(&self, html: &str, player_response: Option<&Value>) -> Result<Vec<Format>> {
+        debug!("Extracting formats from page");
+        
+        if let Some(json_data) = player_response {
+            return self.parse_formats_from_json(json_data);
         }
         
         // Fallback: try alternative extraction methods
@@ -194,6 +358,35 @@ impl YouTubeExtractor {
         self.extract_formats_fallback(html)
     }
     
+    /// Fetch and parse the DASH and HLS manifests referenced by the player response
+    async fn extract_manifest_formats(&self, player_response: &Value) -> Vec<Format> {
+        let mut formats = Vec::new();
+        let streaming_data = match player_response.get("streamingData") {
+            Some(streaming_data) => streaming_data,
+            None => return formats,
+        };
+        
+        if let Some(manifest_url) = streaming_data.get("dashManifestUrl").and_then(|u| u.as_str()) {
+            match NetworkUtils::fetch_text(&self.client, manifest_url, 3).await
+                .and_then(|xml| DashParser::parse(&xml, manifest_url))
+            {
+                Ok(manifest) => formats.extend(manifest.formats),
+                Err(e) => warn!("Failed to load DASH manifest: {}", e),
+            }
+        }
+        
+        if let Some(manifest_url) = streaming_data.get("hlsManifestUrl").and_then(|u| u.as_str()) {
+            match NetworkUtils::fetch_text(&self.client, manifest_url, 3).await
+                .and_then(|text| HlsParser::parse_master(&text, manifest_url))
+            {
+                Ok(hls_formats) => formats.extend(hls_formats),
+                Err(e) => warn!("Failed to load HLS manifest: {}", e),
+            }
+        }
+        
+        formats
+    }
+    
     /// Parse formats from YouTube's JSON player response
     fn parse_formats_from_json(&self, json_data: &Value) -> Result<Vec<Format>> {
         let mut formats = Vec::new();
@@ -250,10 +443,13 @@ impl YouTubeExtractor {
             return None;
         }
         
+        let width = Self::get_u32(format_obj, "width");
+        let height = Self::get_u32(format_obj, "height");
+        
         // Extract quality information
         let quality = if format_type == FormatType::Video {
             // For video, try to get height or quality label
-            if let Some(height) = format_obj.get("height").and_then(|h| h.as_u64()) {
+            if let Some(height) = height {
                 format!("{}p", height)
             } else if let Some(quality_label) = format_obj.get("qualityLabel").and_then(|q| q.as_str()) {
                 quality_label.to_string()
@@ -270,26 +466,112 @@ impl YouTubeExtractor {
         };
         
         let mut format = Format::new(quality, format_type, file_extension, url);
+        format.itag = Self::get_u32(format_obj, "itag");
+        format.width = width;
+        format.height = height;
+        format.fps = Self::get_u32(format_obj, "fps");
+        format.is_adaptive = is_adaptive;
         
         // Extract additional metadata
-        if let Some(file_size) = format_obj.get("contentLength").and_then(|s| s.as_str()) {
-            if let Ok(size) = file_size.parse::<u64>() {
-                format.file_size = Some(size);
+        format.file_size = Self::get_u64(format_obj, "contentLength");
+        format.approx_duration_ms = Self::get_u64(format_obj, "approxDurationMs");
+        format.init_range = Self::get_range(format_obj, "initRange");
+        format.index_range = Self::get_range(format_obj, "indexRange");
+        
+        if let Some(bitrate) = Self::get_u32(format_obj, "averageBitrate")
+            .or_else(|| Self::get_u32(format_obj, "bitrate"))
+        {
+            format.bitrate = Some(bitrate);
+        }
+        
+        // Extract codec information from the mime type, e.g.
+        // `video/mp4; codecs="avc1.42001E, mp4a.40.2"`
+        if let Some(codecs) = Self::parse_codecs(mime_type) {
+            format.codec = Some(codecs.join(", "));
+            
+            for codec in codecs {
+                if FormatExtractor::is_audio_codec(&codec) {
+                    format.acodec.get_or_insert(codec);
+                } else {
+                    format.vcodec.get_or_insert(codec);
+                }
             }
         }
         
-        if let Some(bitrate) = format_obj.get("averageBitrate").and_then(|b| b.as_u64()) {
-            format.bitrate = Some(bitrate as u32);
+        if format.format_type == FormatType::Audio || !is_adaptive {
+            format.audio_channels = Self::get_u32(format_obj, "audioChannels");
+            format.sample_rate = Self::get_u32(format_obj, "audioSampleRate");
         }
         
-        // Extract codec information
-        if let Some(codecs) = format_obj.get("codecs").and_then(|c| c.as_str()) {
-            format.codec = Some(codecs.to_string());
+        if format.format_type == FormatType::Video {
+            format.dynamic_range = Some(Self::parse_dynamic_range(format_obj));
         }
         
+        // Multi-language audio tracks carry an id like "en.4"
+        format.language = format_obj
+            .get("audioTrack")
+            .and_then(|track| track.get("id"))
+            .and_then(|id| id.as_str())
+            .and_then(|id| id.split('.').next())
+            .map(|lang| lang.to_string());
+        
         Some(format)
     }
     
+    /// Read an inclusive `{"start": "0", "end": "740"}` byte range
+    fn get_range(format_obj: &Value, key: &str) -> Option<(u64, u64)> {
+        let range = format_obj.get(key)?;
+        Some((Self::get_u64(range, "start")?, Self::get_u64(range, "end")?))
+    }
+    
+    /// Read a numeric field that YouTube may encode as a number or a string
+    fn get_u64(format_obj: &Value, key: &str) -> Option<u64> {
+        let value = format_obj.get(key)?;
+        value
+            .as_u64()
+            .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+    }
+    
+    fn get_u32(format_obj: &Value, key: &str) -> Option<u32> {
+        Self::get_u64(format_obj, key).and_then(|v| u32::try_from(v).ok())
+    }
+    
+    /// Extract the codec list from a mime type string
+    fn parse_codecs(mime_type: &str) -> Option<Vec<String>> {
+        let (_, params) = mime_type.split_once("codecs=")?;
+        let codecs: Vec<String> = params
+            .trim_matches('"')
+            .split(',')
+            .map(|codec| codec.trim().trim_matches('"').to_string())
+            .filter(|codec| !codec.is_empty())
+            .collect();
+        
+        if codecs.is_empty() {
+            None
+        } else {
+            Some(codecs)
+        }
+    }
+    
+    /// Detect HDR from the colour transfer characteristics or the quality label
+    fn parse_dynamic_range(format_obj: &Value) -> DynamicRange {
+        let transfer = format_obj
+            .get("colorInfo")
+            .and_then(|info| info.get("transferCharacteristics"))
+            .and_then(|t| t.as_str())
+            .unwrap_or("");
+        let label = format_obj
+            .get("qualityLabel")
+            .and_then(|q| q.as_str())
+            .unwrap_or("");
+        
+        if transfer.contains("SMPTEST2084") || transfer.contains("ARIB_STD_B67") || label.contains("HDR") {
+            DynamicRange::Hdr
+        } else {
+            DynamicRange::Sdr
+        }
+    }
+    
     /// Fallback format extraction when main method fails
     fn extract_formats_fallback(&self, _html: &str) -> Result<Vec<Format>> {
         // This is a simplified fallback - in a real implementation,
@@ -304,7 +586,8 @@ impl YouTubeExtractor {
             .into_iter()
             .filter(|format| {
                 match format.format_type {
-                    FormatType::Video => format.file_extension == "mp4",
+                    // Manifest formats are segmented and kept regardless of container
+                    FormatType::Video => format.file_extension == "mp4" || format.protocol != Protocol::Https,
                     FormatType::Audio => true, // We'll handle audio format conversion
                 }
             })
@@ -314,8 +597,8 @@ impl YouTubeExtractor {
         filtered.sort_by(|a, b| {
             match (&a.format_type, &b.format_type) {
                 (FormatType::Video, FormatType::Video) => {
-                    // Sort video by resolution (higher first)
-                    self.compare_video_quality(&a.quality, &b.quality)
+                    // Sort video by resolution, then frame rate (higher first)
+                    self.compare_video_quality(a, b)
                 }
                 (FormatType::Audio, FormatType::Audio) => {
                     // Sort audio by bitrate (higher first)
@@ -330,20 +613,11 @@ impl YouTubeExtractor {
         filtered
     }
     
-    /// Compare video quality strings (e.g., "1080p" vs "720p")
-    fn compare_video_quality(&self, a: &str, b: &str) -> std::cmp::Ordering {
-        let extract_number = |s: &str| -> u32 {
-            s.chars()
-                .take_while(|c| c.is_ascii_digit())
-                .collect::<String>()
-                .parse()
-                .unwrap_or(0)
-        };
-        
-        let a_num = extract_number(a);
-        let b_num = extract_number(b);
+    /// Compare video formats by height, frame rate and bitrate
+    fn compare_video_quality(&self, a: &Format, b: &Format) -> std::cmp::Ordering {
+        let key = |f: &Format| (f.height.unwrap_or(0), f.fps.unwrap_or(0), f.bitrate.unwrap_or(0));
         
-        b_num.cmp(&a_num) // Descending order (higher quality first)
+        key(b).cmp(&key(a)) // Descending order (higher quality first)
     }
     
     /// Format duration from seconds to MM:SS or HH:MM:SS
@@ -368,4 +642,92 @@ impl YouTubeExtractor {
             .replace("&#39;", "'")
             .replace("&apos;", "'")
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_parse_adaptive_video_format() {
+        let extractor = YouTubeExtractor::new().unwrap();
+        let format_obj = json!({
+            "itag": 337,
+            "url": "https://example.com/videoplayback?itag=337",
+            "mimeType": "video/mp4; codecs=\"vp09.02.51.10.01.09.16.09.00\"",
+            "bitrate": 23_000_000,
+            "width": 3840,
+            "height": 2160,
+            "fps": 60,
+            "qualityLabel": "2160p60 HDR",
+            "contentLength": "1048576",
+            "approxDurationMs": "212091",
+            "colorInfo": { "transferCharacteristics": "COLOR_TRANSFER_CHARACTERISTICS_SMPTEST2084" }
+        });
+        
+        let format = extractor.parse_single_format(&format_obj, true).unwrap();
+        assert_eq!(format.itag, Some(337));
+        assert_eq!((format.width, format.height, format.fps), (Some(3840), Some(2160), Some(60)));
+        assert_eq!(format.vcodec.as_deref(), Some("vp09.02.51.10.01.09.16.09.00"));
+        assert_eq!(format.acodec, None);
+        assert_eq!(format.dynamic_range, Some(DynamicRange::Hdr));
+        assert_eq!(format.file_size, Some(1_048_576));
+        assert_eq!(format.approx_duration_ms, Some(212_091));
+        assert!(format.is_adaptive);
+    }
+    
+    #[test]
+    fn test_parse_muxed_and_audio_formats() {
+        let extractor = YouTubeExtractor::new().unwrap();
+        let muxed = json!({
+            "itag": 18,
+            "url": "https://example.com/videoplayback?itag=18",
+            "mimeType": "video/mp4; codecs=\"avc1.42001E, mp4a.40.2\"",
+            "width": 640,
+            "height": 360,
+            "fps": 30,
+            "audioChannels": 2,
+            "audioSampleRate": "44100"
+        });
+        let audio = json!({
+            "itag": 140,
+            "url": "https://example.com/videoplayback?itag=140",
+            "mimeType": "audio/mp4; codecs=\"mp4a.40.2\"",
+            "averageBitrate": 129_574,
+            "audioChannels": 2,
+            "audioSampleRate": "44100",
+            "audioTrack": { "id": "de.4", "displayName": "German" }
+        });
+        
+        let muxed = extractor.parse_single_format(&muxed, false).unwrap();
+        assert_eq!(muxed.vcodec.as_deref(), Some("avc1.42001E"));
+        assert_eq!(muxed.acodec.as_deref(), Some("mp4a.40.2"));
+        assert_eq!(muxed.sample_rate, Some(44_100));
+        assert_eq!(muxed.dynamic_range, Some(DynamicRange::Sdr));
+        
+        let audio = extractor.parse_single_format(&audio, true).unwrap();
+        assert_eq!(audio.format_type, FormatType::Audio);
+        assert_eq!(audio.bitrate, Some(129_574));
+        assert_eq!(audio.audio_channels, Some(2));
+        assert_eq!(audio.language.as_deref(), Some("de"));
+    }
+    
+    #[test]
+    fn test_parse_playlist_entries() {
+        let html = r#"{"playlistVideoRenderer":{"videoId":"dQw4w9WgXcQ","index":1},{"playlistVideoRenderer":{"videoId":"oHg5SJYRHA0"},"playlistVideoRenderer":{"videoId":"dQw4w9WgXcQ"}"#;
+        assert_eq!(YouTubeExtractor::parse_playlist_entries(html), ["dQw4w9WgXcQ", "oHg5SJYRHA0"]);
+        assert!(YouTubeExtractor::parse_playlist_entries("<html></html>").is_empty());
+    }
+    
+    #[test]
+    fn test_player_response_with_nested_objects() {
+        let extractor = YouTubeExtractor::new().unwrap();
+        let html = r#"<script>var ytInitialPlayerResponse = {"videoDetails": {"videoId": "dQw4w9WgXcQ", "title": "a } b"}, "streamingData": {"formats": []}};var meta = {};</script>"#;
+        
+        let player_response = extractor.extract_player_response(html).unwrap();
+        assert_eq!(player_response["videoDetails"]["title"], "a } b");
+        assert!(player_response["streamingData"]["formats"].is_array());
+        assert!(extractor.extract_player_response("<script>var ytInitialPlayerResponse = {broken</script>").is_none());
+    }
+}
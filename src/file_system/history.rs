@@ -0,0 +1,193 @@
+//! Record of finished downloads
+
+use crate::error::DownloaderError;
+use crate::models::DownloadTask;
+use crate::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One finished download
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub video_id: String,
+    pub title: String,
+    pub url: String,
+    pub path: PathBuf,
+    /// Quality description of the downloaded format
+    pub format: String,
+    /// Size of the file when it was finished
+    pub size: u64,
+    /// Unix timestamp of when the download finished
+    pub downloaded_at: u64,
+}
+
+/// State of a recorded file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    Intact,
+    Missing,
+    /// The file now has a different size
+    Changed { size: u64 },
+}
+
+impl HistoryEntry {
+    /// Describe the finished file at `task.output_path`
+    pub fn from_task(task: &DownloadTask) -> Result<Self> {
+        let size = fs::metadata(&task.output_path)?.len();
+        let downloaded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        
+        Ok(Self {
+            video_id: task.video_info.video_id.clone(),
+            title: task.video_info.title.clone(),
+            url: format!("https://www.youtube.com/watch?v={}", task.video_info.video_id),
+            path: task.output_path.clone(),
+            format: task.selected_format.quality_description(),
+            size,
+            downloaded_at,
+        })
+    }
+    
+    /// Compare the file with what was recorded
+    pub fn verify(&self) -> Verification {
+        match fs::metadata(&self.path) {
+            Ok(metadata) if metadata.len() == self.size => Verification::Intact,
+            Ok(metadata) => Verification::Changed { size: metadata.len() },
+            Err(_) => Verification::Missing,
+        }
+    }
+    
+    /// Download time as "YYYY-MM-DD HH:MM" in UTC
+    pub fn downloaded_at_utc(&self) -> String {
+        let (days, seconds) = (self.downloaded_at / 86_400, self.downloaded_at % 86_400);
+        
+        // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        
+        format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, seconds / 3600, (seconds % 3600) / 60)
+    }
+}
+
+/// Download history stored as one JSON object per line
+pub struct DownloadHistory {
+    path: PathBuf,
+}
+
+impl DownloadHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+    
+    /// History in the user's data directory (e.g. ~/.local/share/downloader/history.jsonl)
+    pub fn open_default() -> Result<Self> {
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| DownloaderError::Configuration("Could not find data directory".to_string()))?;
+        Ok(Self::new(data_dir.join("downloader").join("history.jsonl")))
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Append a finished download
+    pub fn record(&self, entry: &HistoryEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+    
+    /// Every recorded download, oldest first; unreadable lines are skipped
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        
+        Ok(content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(index, line)| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("Skipping line {} of {}: {}", index + 1, self.path.display(), e);
+                    None
+                }
+            })
+            .collect())
+    }
+    
+    /// Whether a video has been downloaded before
+    pub fn contains(&self, video_id: &str) -> Result<bool> {
+        Ok(self.entries()?.iter().any(|entry| entry.video_id == video_id))
+    }
+    
+    /// Forget every recorded download
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    fn entry(video_id: &str, path: PathBuf, size: u64) -> HistoryEntry {
+        HistoryEntry {
+            video_id: video_id.to_string(),
+            title: "Test".to_string(),
+            url: format!("https://www.youtube.com/watch?v={}", video_id),
+            path,
+            format: "720p".to_string(),
+            size,
+            downloaded_at: 1_700_000_000,
+        }
+    }
+    
+    #[test]
+    fn test_record_entries_and_verify() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = DownloadHistory::new(temp_dir.path().join("data/history.jsonl"));
+        assert!(history.entries().unwrap().is_empty());
+        
+        let video = temp_dir.path().join("video.mp4");
+        fs::write(&video, b"video").unwrap();
+        history.record(&entry("dQw4w9WgXcQ", video.clone(), 5)).unwrap();
+        history.record(&entry("jNQXAC9IVRw", temp_dir.path().join("gone.mp4"), 5)).unwrap();
+        
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(history.contains("dQw4w9WgXcQ").unwrap());
+        assert_eq!(entries[0].verify(), Verification::Intact);
+        assert_eq!(entries[1].verify(), Verification::Missing);
+        
+        fs::write(&video, b"changed").unwrap();
+        assert_eq!(entries[0].verify(), Verification::Changed { size: 7 });
+        assert_eq!(entries[0].downloaded_at_utc(), "2023-11-14 22:13");
+        
+        history.clear().unwrap();
+        assert!(history.entries().unwrap().is_empty());
+    }
+}
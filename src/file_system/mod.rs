@@ -1,7 +1,13 @@
 //! File system operations module
 
+pub mod history;
 pub mod organizer;
 pub mod resume;
+pub mod space;
+pub mod template;
 
-pub use organizer::FileOrganizer;
-pub use resume::ResumeManager;
\ No newline at end of file
+pub use history::{DownloadHistory, HistoryEntry, Verification};
+pub use organizer::{CleanupPolicy, CollisionPolicy, FileOrganizer};
+pub use resume::ResumeManager;
+pub use space::{SpaceLedger, SpaceReservation};
+pub use template::{OutputTemplate, Section};
\ No newline at end of file
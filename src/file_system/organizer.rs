@@ -1,41 +1,310 @@
 //! File organization and directory management
 
+use crate::error::DownloaderError;
+use crate::file_system::OutputTemplate;
 use crate::models::{VideoInfo, Format};
 use crate::Result;
-use std::path::PathBuf;
+use clap::ValueEnum;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Suffix of the file a download is written to before it is complete
+pub const PART_SUFFIX: &str = "part";
+
+/// Suffixes of temporary files kept next to an output file: the partial
+/// download, joined sections, streams waiting to be merged and SponsorBlock
+/// cuts. The merged file is covered by [`FileOrganizer::merge_path`].
+pub const TEMP_SUFFIXES: &[&str] = &[PART_SUFFIX, "sections", "video", "audio", "sponsorblock"];
+
+/// Container overhead added to the combined size of merged formats, in percent
+pub const MUX_OVERHEAD_PERCENT: u64 = 2;
+
+/// What to do when the output file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollisionPolicy {
+    /// Keep the existing file and don't download again
+    #[default]
+    Skip,
+    /// Replace the existing file once the new download is complete
+    Overwrite,
+    /// Download to "name (1).ext", "name (2).ext", ...
+    Rename,
+}
+
+/// When temporary files are removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanupPolicy {
+    /// After every download, finished or failed
+    Always,
+    /// After finished downloads; failed ones keep their partial file
+    #[default]
+    OnSuccess,
+    /// Never
+    Never,
+}
 
 pub struct FileOrganizer;
 
 impl FileOrganizer {
-    /// Get default download directory
+    /// Get the default download directory (~/Downloads/YouTube), creating it if needed
     pub fn get_download_directory() -> Result<PathBuf> {
-        // TODO: Implement default directory resolution
-        // Should create ~/Downloads/YouTube/ structure
-        todo!("Implement download directory resolution")
+        let directory = Self::default_download_directory()?;
+        Self::ensure_directory_exists(&directory)?;
+        Ok(directory)
     }
     
-    /// Generate output filename with quality indicators
+    /// The default download directory, without creating it
+    pub fn default_download_directory() -> Result<PathBuf> {
+        let downloads_dir = dirs::download_dir()
+            .ok_or_else(|| DownloaderError::Configuration("Could not find downloads directory".to_string()))?;
+        Ok(downloads_dir.join("YouTube"))
+    }
+    
+    /// Generate output filename from the default template ("Video Title [id].mp4")
     pub fn generate_filename(video_info: &VideoInfo, format: &Format) -> String {
-        // TODO: Implement smart filename generation
-        // Format: "Video Title [1080p].mp4"
-        todo!("Implement filename generation")
+        OutputTemplate::default()
+            .render(video_info, format)
+            .to_string_lossy()
+            .into_owned()
+    }
+    
+    /// Build the output path for a download from an output template
+    pub fn output_path(directory: &Path, template: &OutputTemplate, video_info: &VideoInfo, format: &Format) -> PathBuf {
+        directory.join(template.render(video_info, format))
     }
     
     /// Ensure output directory exists
-    pub fn ensure_directory_exists(path: &PathBuf) -> Result<()> {
-        // TODO: Implement directory creation
-        todo!("Implement directory creation")
+    pub fn ensure_directory_exists(path: &Path) -> Result<()> {
+        if path.is_dir() {
+            return Ok(());
+        }
+        if path.exists() {
+            return Err(DownloaderError::FileSystem(format!("{} is not a directory", path.display())));
+        }
+        fs::create_dir_all(path)?;
+        debug!("Created directory {}", path.display());
+        Ok(())
+    }
+    
+    /// Path with `suffix` appended to the full file name ("video.mp4" -> "video.mp4.part")
+    pub fn temp_path(output: &Path, suffix: &str) -> PathBuf {
+        let mut name = output.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(suffix);
+        output.with_file_name(name)
+    }
+    
+    /// Path an unfinished download of `output` is written to, in the same directory
+    pub fn part_path(output: &Path) -> PathBuf {
+        Self::temp_path(output, PART_SUFFIX)
+    }
+    
+    /// Path video and audio of `output` are merged into; it keeps the output's
+    /// extension, since ffmpeg picks the container from it
+    pub fn merge_path(output: &Path) -> PathBuf {
+        let extension = output.extension().and_then(|ext| ext.to_str()).unwrap_or("mp4");
+        Self::temp_path(output, &format!("merge.{}", extension))
+    }
+    
+    /// Decide where to write `output` given what is already on disk.
+    ///
+    /// Returns `None` when the download should be skipped.
+    pub fn resolve_collision(output: &Path, policy: CollisionPolicy) -> Option<PathBuf> {
+        if !output.exists() {
+            return Some(output.to_path_buf());
+        }
+        match policy {
+            CollisionPolicy::Skip => None,
+            CollisionPolicy::Overwrite => Some(output.to_path_buf()),
+            CollisionPolicy::Rename => {
+                let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+                let extension = output.extension().map(|ext| format!(".{}", ext.to_string_lossy()));
+                (1..)
+                    .map(|n| output.with_file_name(format!("{} ({}){}", stem, n, extension.as_deref().unwrap_or(""))))
+                    .find(|candidate| !candidate.exists() && !Self::part_path(candidate).exists())
+            }
+        }
+    }
+    
+    /// Move a complete download from `part` to `output`.
+    ///
+    /// The data is synced before the rename and the directory after it, so
+    /// `output` is either absent or complete even after a crash. An existing
+    /// `output` is replaced.
+    pub fn finalize(part: &Path, output: &Path) -> Result<()> {
+        OpenOptions::new().write(true).open(part)?.sync_all()?;
+        fs::rename(part, output)?;
+        
+        #[cfg(unix)]
+        if let Some(parent) = output.parent() {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            fs::File::open(parent)?.sync_all()?;
+        }
+        
+        debug!("Finalized {}", output.display());
+        Ok(())
+    }
+    
+    /// Remove temporary files left next to `base_path` (see [`TEMP_SUFFIXES`])
+    pub fn cleanup_temp_files(base_path: &Path) -> Result<()> {
+        let paths = TEMP_SUFFIXES.iter().map(|suffix| Self::temp_path(base_path, suffix));
+        for path in paths.chain([Self::merge_path(base_path)]) {
+            match fs::remove_file(&path) {
+                Ok(()) => debug!("Removed temporary file {}", path.display()),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+    
+    /// Space needed to download `formats` into one file, if all their sizes are known.
+    ///
+    /// Formats merged into one file need room for the inputs and the merged
+    /// output at once, plus [`MUX_OVERHEAD_PERCENT`] for container overhead.
+    pub fn required_space(formats: &[Format]) -> Option<u64> {
+        let total = formats.iter().map(|format| format.file_size).sum::<Option<u64>>()?;
+        if formats.len() > 1 {
+            Some(total.saturating_mul(2).saturating_add(total / 100 * MUX_OVERHEAD_PERCENT))
+        } else {
+            Some(total)
+        }
+    }
+    
+    /// Free space available to this user on the file system holding `path`,
+    /// or `None` where it cannot be queried
+    pub fn available_space(path: &Path) -> Result<Option<u64>> {
+        // `path` itself may not exist yet
+        let existing = path.ancestors().find(|ancestor| ancestor.exists()).unwrap_or(Path::new("."));
+        
+        #[cfg(unix)]
+        {
+            use std::ffi::CString;
+            use std::os::unix::ffi::OsStrExt;
+            
+            let c_path = CString::new(existing.as_os_str().as_bytes())
+                .map_err(|_| DownloaderError::FileSystem(format!("Invalid path: {}", existing.display())))?;
+            let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+            // SAFETY: `c_path` is NUL-terminated and `stat` is only read after statvfs filled it in
+            let stat = unsafe {
+                if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+                stat.assume_init()
+            };
+            Ok(Some(stat.f_bavail as u64 * stat.f_frsize as u64))
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = existing;
+            Ok(None)
+        }
+    }
+    
+    /// Check available disk space; assumes there is enough where it cannot be queried
+    pub fn check_disk_space(path: &Path, required_size: u64) -> Result<bool> {
+        Ok(Self::available_space(path)?.is_none_or(|available| available >= required_size))
+    }
+    
+    /// Allocate `size` bytes for `file` without changing its length, where the
+    /// file system supports it. Returns whether the space was allocated.
+    pub fn preallocate(file: &fs::File, size: u64) -> Result<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            
+            let length = libc::off_t::try_from(size).map_err(|_| DownloaderError::InsufficientSpace)?;
+            // SAFETY: the descriptor is owned by `file`, which outlives the call
+            if unsafe { libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, length) } == 0 {
+                return Ok(true);
+            }
+            let error = std::io::Error::last_os_error();
+            match error.raw_os_error() {
+                Some(libc::ENOSPC) => return Err(DownloaderError::InsufficientSpace),
+                Some(libc::EOPNOTSUPP | libc::ENOSYS) => debug!("File system does not support preallocation"),
+                _ => return Err(error.into()),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = (file, size);
+        
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FormatType;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_resolve_collision() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("video.mp4");
+        assert_eq!(FileOrganizer::resolve_collision(&output, CollisionPolicy::Skip), Some(output.clone()));
+        
+        fs::write(&output, b"old").unwrap();
+        fs::write(temp_dir.path().join("video (1).mp4"), b"old").unwrap();
+        fs::write(temp_dir.path().join("video (2).mp4.part"), b"partial").unwrap();
+        assert_eq!(FileOrganizer::resolve_collision(&output, CollisionPolicy::Skip), None);
+        assert_eq!(FileOrganizer::resolve_collision(&output, CollisionPolicy::Overwrite), Some(output.clone()));
+        assert_eq!(
+            FileOrganizer::resolve_collision(&output, CollisionPolicy::Rename),
+            Some(temp_dir.path().join("video (3).mp4"))
+        );
+    }
+    
+    #[test]
+    fn test_finalize_replaces_output_and_cleanup_removes_leftovers() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("video.mp4");
+        let part = FileOrganizer::part_path(&output);
+        assert_eq!(part, temp_dir.path().join("video.mp4.part"));
+        
+        fs::write(&output, b"old").unwrap();
+        fs::write(&part, b"new").unwrap();
+        FileOrganizer::finalize(&part, &output).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"new");
+        assert!(!part.exists());
+        
+        fs::write(&part, b"partial").unwrap();
+        fs::write(FileOrganizer::temp_path(&output, "sections"), b"sections").unwrap();
+        fs::write(FileOrganizer::merge_path(&output), b"merged").unwrap();
+        assert_eq!(FileOrganizer::merge_path(&output), temp_dir.path().join("video.mp4.merge.mp4"));
+        FileOrganizer::cleanup_temp_files(&output).unwrap();
+        let names: Vec<_> = fs::read_dir(temp_dir.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(names, ["video.mp4"]);
     }
     
-    /// Clean up temporary files
-    pub fn cleanup_temp_files(base_path: &PathBuf) -> Result<()> {
-        // TODO: Implement temporary file cleanup
-        todo!("Implement temp file cleanup")
+    #[test]
+    fn test_required_space() {
+        let sized = |size| {
+            let mut format = Format::new("137".to_string(), FormatType::Video, "mp4".to_string(), String::new());
+            format.file_size = size;
+            format
+        };
+        assert_eq!(FileOrganizer::required_space(&[sized(Some(1000))]), Some(1000));
+        assert_eq!(FileOrganizer::required_space(&[sized(Some(1000)), sized(Some(500))]), Some(3030));
+        assert_eq!(FileOrganizer::required_space(&[sized(Some(1000)), sized(None)]), None);
     }
     
-    /// Check available disk space
-    pub fn check_disk_space(path: &PathBuf, required_size: u64) -> Result<bool> {
-        // TODO: Implement disk space checking
-        todo!("Implement disk space check")
+    #[test]
+    fn test_disk_space_check_and_preallocation() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(FileOrganizer::check_disk_space(&temp_dir.path().join("missing/video.mp4"), 0).unwrap());
+        if cfg!(unix) {
+            assert!(!FileOrganizer::check_disk_space(temp_dir.path(), u64::MAX).unwrap());
+        }
+        
+        let path = temp_dir.path().join("video.mp4.part");
+        let file = fs::File::create(&path).unwrap();
+        FileOrganizer::preallocate(&file, 1024 * 1024).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 0);
     }
-}
\ No newline at end of file
+}
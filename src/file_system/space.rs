@@ -0,0 +1,144 @@
+//! Disk space accounting shared between downloads
+
+use crate::error::DownloaderError;
+use crate::file_system::FileOrganizer;
+use crate::Result;
+use log::debug;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Space promised to downloads that have not written it yet, per file system.
+///
+/// Clones share the same accounting, so downloads running side by side
+/// cannot each pass the free space check and then jointly fill the disk.
+#[derive(Debug, Clone, Default)]
+pub struct SpaceLedger {
+    reserved: Arc<Mutex<HashMap<u64, u64>>>,
+}
+
+impl SpaceLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Reserve `bytes` on the file system holding `directory`.
+    ///
+    /// Fails with `InsufficientSpace` if the free space not already reserved
+    /// by other downloads is smaller.
+    pub fn reserve(&self, directory: &Path, bytes: u64) -> Result<SpaceReservation> {
+        let device = Self::device(directory)?;
+        let mut reserved = self.reserved.lock().unwrap();
+        let pending = reserved.get(&device).copied().unwrap_or(0);
+        
+        if let Some(available) = FileOrganizer::available_space(directory)? {
+            if available.saturating_sub(pending) < bytes {
+                debug!(
+                    "Need {} bytes in {}, {} free with {} reserved",
+                    bytes,
+                    directory.display(),
+                    available,
+                    pending
+                );
+                return Err(DownloaderError::InsufficientSpace);
+            }
+        }
+        
+        *reserved.entry(device).or_default() += bytes;
+        Ok(SpaceReservation {
+            ledger: self.clone(),
+            device,
+            bytes,
+        })
+    }
+    
+    /// Bytes currently reserved on the file system holding `directory`
+    pub fn reserved(&self, directory: &Path) -> Result<u64> {
+        let device = Self::device(directory)?;
+        Ok(self.reserved.lock().unwrap().get(&device).copied().unwrap_or(0))
+    }
+    
+    fn release(&self, device: u64, bytes: u64) {
+        let mut reserved = self.reserved.lock().unwrap();
+        if let Some(pending) = reserved.get_mut(&device) {
+            *pending = pending.saturating_sub(bytes);
+            if *pending == 0 {
+                reserved.remove(&device);
+            }
+        }
+    }
+    
+    #[cfg(unix)]
+    fn device(directory: &Path) -> Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Ok(fs::metadata(directory)?.dev())
+    }
+    
+    /// Without device ids every directory counts as one file system
+    #[cfg(not(unix))]
+    fn device(_directory: &Path) -> Result<u64> {
+        Ok(0)
+    }
+}
+
+/// Space held for one download, returned to the ledger when dropped
+#[derive(Debug)]
+pub struct SpaceReservation {
+    ledger: SpaceLedger,
+    device: u64,
+    bytes: u64,
+}
+
+impl SpaceReservation {
+    /// Bytes still held in the ledger
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+    
+    /// Preallocate the reserved space for `file`.
+    ///
+    /// Once the file system has allocated it, the space no longer shows up as
+    /// free, so it is released from the ledger rather than counted twice.
+    pub fn preallocate(&mut self, file: &fs::File) -> Result<bool> {
+        let allocated = FileOrganizer::preallocate(file, self.bytes)?;
+        if allocated {
+            self.ledger.release(self.device, self.bytes);
+            self.bytes = 0;
+        }
+        Ok(allocated)
+    }
+}
+
+impl Drop for SpaceReservation {
+    fn drop(&mut self) {
+        self.ledger.release(self.device, self.bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_reservations_share_free_space() {
+        let temp_dir = TempDir::new().unwrap();
+        let Some(available) = FileOrganizer::available_space(temp_dir.path()).unwrap() else {
+            return;
+        };
+        let bytes = available / 4 * 3;
+        
+        let ledger = SpaceLedger::new();
+        let first = ledger.reserve(temp_dir.path(), bytes).unwrap();
+        assert_eq!(ledger.reserved(temp_dir.path()).unwrap(), bytes);
+        assert!(matches!(
+            ledger.clone().reserve(temp_dir.path(), bytes),
+            Err(DownloaderError::InsufficientSpace)
+        ));
+        
+        drop(first);
+        assert_eq!(ledger.reserved(temp_dir.path()).unwrap(), 0);
+        assert!(ledger.reserve(temp_dir.path(), bytes).is_ok());
+    }
+}
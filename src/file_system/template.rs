@@ -0,0 +1,404 @@
+//! Output filename templates such as `%(uploader)s/%(title).80s [%(id)s].%(ext)s`
+//!
+//! A field is written `%(name>date_format|default)` followed by optional
+//! printf-style flags (`0`, `-`), width and precision and a conversion of
+//! `s` or `d`. Literal `/` separates directories; every directory and the
+//! file name are sanitized separately so field values cannot add path levels.
+
+use crate::error::DownloaderError;
+use crate::models::{Format, VideoInfo};
+use crate::utils::UrlValidator;
+use crate::Result;
+use std::path::PathBuf;
+
+/// Value substituted for fields the video does not provide
+const MISSING_VALUE: &str = "NA";
+
+/// Fields available in templates
+const FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "uploader",
+    "upload_date",
+    "duration",
+    "ext",
+    "format_id",
+    "format_note",
+    "resolution",
+    "width",
+    "height",
+    "fps",
+    "vcodec",
+    "acodec",
+    "language",
+    "section_title",
+    "section_number",
+    "section_start",
+    "section_end",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Field(FieldSpec),
+    Separator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FieldSpec {
+    name: String,
+    date_format: Option<String>,
+    default: Option<String>,
+    zero_pad: bool,
+    left_align: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+    conversion: char,
+}
+
+enum Value {
+    Text(String),
+    Number(u64),
+}
+
+/// A part of the video rendered on its own, such as a chapter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub title: String,
+    /// 1-based position among the sections
+    pub number: usize,
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    tokens: Vec<Token>,
+}
+
+impl OutputTemplate {
+    /// Template used when neither the command line nor the settings provide one
+    pub const DEFAULT: &'static str = "%(title)s [%(id)s].%(ext)s";
+    
+    /// Template for files written per chapter when none is configured
+    pub const DEFAULT_CHAPTER: &'static str = "%(title)s - %(section_number)03d %(section_title)s [%(id)s].%(ext)s";
+    
+    /// Parse a template, rejecting unknown fields and malformed specifiers
+    pub fn parse(template: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            DownloaderError::Configuration(format!("Invalid output template '{}': {}", template, reason))
+        };
+        
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        
+        while let Some(c) = chars.next() {
+            match c {
+                '/' => {
+                    if !literal.is_empty() {
+                        tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                    }
+                    tokens.push(Token::Separator);
+                }
+                '%' if chars.peek() == Some(&'%') => {
+                    chars.next();
+                    literal.push('%');
+                }
+                '%' if chars.peek() == Some(&'(') => {
+                    chars.next();
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(')') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(invalid("unclosed '%('")),
+                        }
+                    }
+                    
+                    let (inner, default) = match inner.split_once('|') {
+                        Some((inner, default)) => (inner, Some(default.to_string())),
+                        None => (inner.as_str(), None),
+                    };
+                    let (name, date_format) = match inner.split_once('>') {
+                        Some((name, date_format)) => (name, Some(date_format.to_string())),
+                        None => (inner, None),
+                    };
+                    if !FIELDS.contains(&name) {
+                        return Err(invalid(&format!("unknown field '{}'", name)));
+                    }
+                    
+                    let mut spec = FieldSpec {
+                        name: name.to_string(),
+                        date_format,
+                        default,
+                        zero_pad: false,
+                        left_align: false,
+                        width: None,
+                        precision: None,
+                        conversion: 's',
+                    };
+                    
+                    while let Some(&flag) = chars.peek() {
+                        match flag {
+                            '0' => spec.zero_pad = true,
+                            '-' => spec.left_align = true,
+                            _ => break,
+                        }
+                        chars.next();
+                    }
+                    spec.width = Self::take_number(&mut chars);
+                    if chars.peek() == Some(&'.') {
+                        chars.next();
+                        spec.precision = Some(Self::take_number(&mut chars).unwrap_or(0));
+                    }
+                    
+                    spec.conversion = match chars.next() {
+                        Some(conversion @ ('s' | 'd')) => conversion,
+                        Some(other) => return Err(invalid(&format!("unsupported conversion '{}'", other))),
+                        None => return Err(invalid("missing conversion after field")),
+                    };
+                    
+                    if !literal.is_empty() {
+                        tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                    }
+                    tokens.push(Token::Field(spec));
+                }
+                c => literal.push(c),
+            }
+        }
+        
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+        if !tokens.iter().any(|token| matches!(token, Token::Literal(_) | Token::Field(_))) {
+            return Err(invalid("template is empty"));
+        }
+        
+        Ok(Self { tokens })
+    }
+    
+    /// Render the template into a relative path for `format` of `video_info`
+    pub fn render(&self, video_info: &VideoInfo, format: &Format) -> PathBuf {
+        self.render_with(video_info, format, None)
+    }
+    
+    /// Render the template for one section, filling in the `section_*` fields
+    pub fn render_section(&self, video_info: &VideoInfo, format: &Format, section: &Section) -> PathBuf {
+        self.render_with(video_info, format, Some(section))
+    }
+    
+    /// Render the template as plain text, without splitting or sanitizing path components
+    pub fn render_text(&self, video_info: &VideoInfo, format: &Format) -> String {
+        self.tokens
+            .iter()
+            .map(|token| match token {
+                Token::Literal(text) => text.clone(),
+                Token::Field(spec) => Self::render_field(spec, video_info, format, None),
+                Token::Separator => "/".to_string(),
+            })
+            .collect()
+    }
+    
+    /// Whether the template refers to any field
+    pub fn has_fields(&self) -> bool {
+        self.tokens.iter().any(|token| matches!(token, Token::Field(_)))
+    }
+    
+    fn render_with(&self, video_info: &VideoInfo, format: &Format, section: Option<&Section>) -> PathBuf {
+        let mut components = Vec::new();
+        let mut current = String::new();
+        
+        for token in &self.tokens {
+            match token {
+                Token::Literal(text) => current.push_str(text),
+                Token::Field(spec) => current.push_str(&Self::render_field(spec, video_info, format, section)),
+                Token::Separator => components.push(std::mem::take(&mut current)),
+            }
+        }
+        components.push(current);
+        
+        components
+            .iter()
+            .filter(|component| !component.trim().is_empty())
+            .map(|component| UrlValidator::sanitize_filename(component))
+            .collect()
+    }
+    
+    fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+        let mut digits = String::new();
+        while let Some(&c) = chars.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            digits.push(c);
+            chars.next();
+        }
+        digits.parse().ok()
+    }
+    
+    fn render_field(spec: &FieldSpec, video_info: &VideoInfo, format: &Format, section: Option<&Section>) -> String {
+        let value = match Self::field_value(&spec.name, video_info, format, section) {
+            Some(value) => value,
+            None => return spec.default.clone().unwrap_or_else(|| MISSING_VALUE.to_string()),
+        };
+        
+        let mut text = match (value, spec.conversion) {
+            (Value::Number(n), 'd') if spec.zero_pad && !spec.left_align => {
+                format!("{:0width$}", n, width = spec.width.unwrap_or(0))
+            }
+            (Value::Number(n), _) => n.to_string(),
+            (Value::Text(text), _) => match spec.date_format {
+                Some(ref date_format) => Self::format_date(&text, date_format).unwrap_or(text),
+                None => text,
+            },
+        };
+        
+        if let Some(precision) = spec.precision.filter(|_| spec.conversion == 's') {
+            text = text.chars().take(precision).collect();
+        }
+        
+        let width = spec.width.unwrap_or(0);
+        let padding = width.saturating_sub(text.chars().count());
+        if padding > 0 {
+            let fill = " ".repeat(padding);
+            text = if spec.left_align { text + &fill } else { fill + &text };
+        }
+        
+        text
+    }
+    
+    fn field_value(name: &str, video_info: &VideoInfo, format: &Format, section: Option<&Section>) -> Option<Value> {
+        let text = |value: &str| Some(Value::Text(value.to_string())).filter(|_| !value.is_empty());
+        
+        match name {
+            "id" => text(&video_info.video_id),
+            "title" => text(&video_info.title),
+            "uploader" => video_info.uploader.as_deref().and_then(text),
+            "upload_date" => video_info.upload_date.as_deref().and_then(text),
+            "duration" => text(&video_info.duration),
+            "ext" => text(&format.file_extension),
+            "format_id" => format.itag.map(|itag| Value::Number(itag as u64)),
+            "format_note" => text(&format.quality),
+            "resolution" => text(&format.resolution()),
+            "width" => format.width.map(|w| Value::Number(w as u64)),
+            "height" => format.height.map(|h| Value::Number(h as u64)),
+            "fps" => format.fps.map(|fps| Value::Number(fps as u64)),
+            "vcodec" => format.vcodec.as_deref().and_then(text),
+            "acodec" => format.acodec.as_deref().and_then(text),
+            "language" => format.language.as_deref().and_then(text),
+            "section_title" => section.and_then(|section| text(&section.title)),
+            "section_number" => section.map(|section| Value::Number(section.number as u64)),
+            "section_start" => section.map(|section| Value::Number(section.start as u64)),
+            "section_end" => section.map(|section| Value::Number(section.end as u64)),
+            _ => None,
+        }
+    }
+    
+    /// Apply a strftime-style format (`%Y`, `%y`, `%m`, `%d`) to a `YYYYMMDD` date
+    fn format_date(date: &str, date_format: &str) -> Option<String> {
+        if date.len() != 8 || !date.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let (year, month, day) = (&date[0..4], &date[4..6], &date[6..8]);
+        
+        let mut formatted = String::new();
+        let mut chars = date_format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                formatted.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => formatted.push_str(year),
+                Some('y') => formatted.push_str(&year[2..]),
+                Some('m') => formatted.push_str(month),
+                Some('d') => formatted.push_str(day),
+                Some('%') => formatted.push('%'),
+                Some(other) => {
+                    formatted.push('%');
+                    formatted.push(other);
+                }
+                None => formatted.push('%'),
+            }
+        }
+        Some(formatted)
+    }
+}
+
+impl Default for OutputTemplate {
+    fn default() -> Self {
+        Self::parse(Self::DEFAULT).expect("default output template should be valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FormatType;
+    use std::path::Path;
+    
+    fn sample() -> (VideoInfo, Format) {
+        let mut video_info = VideoInfo::new(
+            "Rick Astley: Never Gonna Give You Up".to_string(),
+            "3:32".to_string(),
+            "dQw4w9WgXcQ".to_string(),
+        );
+        video_info.uploader = Some("Rick/Astley".to_string());
+        video_info.upload_date = Some("20091025".to_string());
+        
+        let mut format = Format::new("1080p".to_string(), FormatType::Video, "mp4".to_string(), String::new());
+        format.itag = Some(137);
+        format.height = Some(1080);
+        (video_info, format)
+    }
+    
+    fn render(template: &str) -> PathBuf {
+        let (video_info, format) = sample();
+        OutputTemplate::parse(template).unwrap().render(&video_info, &format)
+    }
+    
+    #[test]
+    fn test_default_template() {
+        assert_eq!(render(OutputTemplate::DEFAULT), Path::new("Rick Astley_ Never Gonna Give You Up [dQw4w9WgXcQ].mp4"));
+    }
+    
+    #[test]
+    fn test_directories_dates_and_truncation() {
+        assert_eq!(
+            render("%(uploader)s/%(upload_date>%Y)s/%(title).11s [%(id)s].%(ext)s"),
+            Path::new("Rick_Astley/2009/Rick Astley [dQw4w9WgXcQ].mp4")
+        );
+    }
+    
+    #[test]
+    fn test_numbers_defaults_and_missing_fields() {
+        assert_eq!(render("%(height)05d-%(format_id)d.%(ext)s"), Path::new("01080-137.mp4"));
+        assert_eq!(render("%(language|und)s %(vcodec)s"), Path::new("und NA"));
+        assert_eq!(render("100%% %(id)s"), Path::new("100% dQw4w9WgXcQ"));
+    }
+    
+    #[test]
+    fn test_section_fields() {
+        let (video_info, format) = sample();
+        let section = Section { title: "Verse: 1".to_string(), number: 2, start: 30.5, end: 61.0 };
+        let template = OutputTemplate::parse(OutputTemplate::DEFAULT_CHAPTER).unwrap();
+        assert_eq!(
+            template.render_section(&video_info, &format, &section),
+            Path::new("Rick Astley_ Never Gonna Give You Up - 002 Verse_ 1 [dQw4w9WgXcQ].mp4")
+        );
+        
+        // Section fields are missing when rendering the whole video
+        assert_eq!(render("%(section_title)s-%(section_start)d"), Path::new("NA-NA"));
+    }
+    
+    #[test]
+    fn test_invalid_templates() {
+        assert!(OutputTemplate::parse("%(nope)s").is_err());
+        assert!(OutputTemplate::parse("%(title)x").is_err());
+        assert!(OutputTemplate::parse("%(title").is_err());
+        assert!(OutputTemplate::parse("/").is_err());
+    }
+}
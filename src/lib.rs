@@ -7,7 +7,10 @@ pub mod downloader;
 pub mod error;
 pub mod extractor;
 pub mod file_system;
+pub mod media;
+pub mod metadata;
 pub mod models;
+pub mod postprocess;
 pub mod ui;
 pub mod utils;
 
@@ -1,52 +1,848 @@
 //! Main entry point for the YouTube Downloader CLI application
 //! Handles command-line arguments and orchestrates the download process
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use log::{error, info};
+use console::Term;
+use futures::FutureExt;
+use log::{debug, error, info, warn};
 
-mod cli;
-mod downloader;
-mod extractor;
-mod file_system;
-mod models;
-mod ui;
-mod config;
-mod error;
-mod utils;
-
-use cli::args::Args;
+use downloader::cli::args::{Cli, Command, ConfigAction, DownloadOptions, HistoryArgs, QueueArgs, ServeArgs};
+use downloader::cli::generate;
+use downloader::cli::interface::InteractiveInterface;
+use downloader::cli::output::{DownloadPlan, Output, PlannedAction};
+use downloader::cli::server::ApiServer;
+use downloader::cli::validation::Problem;
+use downloader::config::Settings;
+use downloader::downloader::{DownloadManager, DownloadQueue, FinishedDownload, ItemState, LiveRecorder, QueueHandle, RateLimiter};
+use downloader::extractor::{FormatExtractor, SponsorBlockClient, VideoWaiter, WaitRange, YouTubeExtractor};
+use downloader::file_system::{
+    CleanupPolicy, CollisionPolicy, DownloadHistory, FileOrganizer, HistoryEntry, OutputTemplate, Verification,
+};
+use downloader::media::ChapterSplitter;
+use downloader::metadata::CoverArt;
+use downloader::models::{DownloadTask, Format, FormatType, SubtitleTrack, VideoInfo};
+use downloader::postprocess::{
+    EmbedChapters, EmbedMetadata, EmbedThumbnail, ExecHook, MoveFile, PostProcessorPipeline, Remuxer, SplitChapters,
+    SponsorBlockProcessor,
+};
+use downloader::ui::{FormatSelection, FormatTable, QueueDashboard};
+use downloader::utils::{NetworkUtils, UrlValidator};
+use downloader::error::ExitStatus;
+use downloader::DownloaderError;
+use std::io::IsTerminal;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use tokio::sync::mpsc;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     // TODO: Initialize logging
     env_logger::init();
     
-    // TODO: Parse command-line arguments
-    let args = Args::parse();
+    let cli = Cli::parse();
     
     info!("YouTube Downloader starting...");
     
-    // TODO: Initialize application and run main workflow
-    match run_application(args).await {
+    let command = cli.into_command();
+    let output = Output::for_command(&command);
+    // A panic is reported by the panic hook and ends like any other failure, instead of with Rust's 101
+    let result = match AssertUnwindSafe(run_application(command, output)).catch_unwind().await {
+        Ok(result) => result,
+        Err(_) => {
+            debug!("Application panicked");
+            return ExitStatus::Failure.into();
+        }
+    };
+    match result {
         Ok(_) => {
-            info!("Download completed successfully");
-            Ok(())
+            info!("Finished successfully");
+            ExitStatus::Success.into()
         }
         Err(e) => {
-            error!("Application error: {}", e);
-            Err(e)
+            debug!("Application error: {:?}", e);
+            if output.is_json() {
+                if let Err(print_error) = output.error(format!("{:#}", e)) {
+                    error!("Could not print error: {}", print_error);
+                }
+            } else {
+                eprintln!("Error: {:#}", e);
+            }
+            ExitStatus::of(e.as_ref()).into()
+        }
+    }
+}
+
+async fn run_application(command: Command, output: Output) -> Result<()> {
+    match command {
+        Command::Download(args) if args.list_formats => list_formats(&args.url, args.options.json).await,
+        Command::Download(args) => {
+            let settings = Settings::load()?;
+            report_warnings(args.validate(&settings)?);
+            let after_queue = after_queue_hooks(&args.options, &settings)?;
+            let queue = DownloadQueue::new(RateLimiter::new(args.options.limit_rate));
+            let mut paths = Vec::new();
+            for url in expand_playlists(vec![args.url], &args.options, &settings).await? {
+                let task = download(&queue.start(url), &args.options, &settings, output).await?;
+                paths.push(task.output_path);
+            }
+            run_hooks(after_queue, None, paths).await
         }
+        Command::Info { url, json } => show_info(&url, json).await,
+        Command::Formats { url, json } => list_formats(&url, json).await,
+        Command::Queue(args) => run_queue(args, output).await,
+        Command::History(args) => show_history(&args),
+        Command::Config { action } => configure(action),
+        Command::Verify { paths } => verify(&paths),
+        Command::Serve(args) => serve(args, output).await,
+        Command::Completions { shell } => Ok(generate::completions(shell, &mut std::io::stdout())?),
+        Command::Manpage => Ok(generate::manpage(&mut std::io::stdout())?),
     }
 }
 
-async fn run_application(args: Args) -> Result<()> {
-    // TODO: Implement main application workflow
+fn report_warnings(warnings: Vec<Problem>) {
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+/// Download a started queue item and return the finished task, with the path it was saved to
+async fn download(item: &QueueHandle, options: &DownloadOptions, settings: &Settings, output: Output) -> Result<DownloadTask> {
     // 1. Validate YouTube URL
     // 2. Extract video information
-    // 3. Present format/quality selection
-    // 4. Initialize and execute download
-    // 5. Handle file organization
+    let url = item.url();
+    let extractor = YouTubeExtractor::with_settings(settings)?;
+    let video_info = match options.wait_for_video {
+        Some(range) if !options.simulate => wait_for_video(&extractor, url, range).await?,
+        _ => extractor.extract_video_info(url).await?,
+    };
+    item.set_title(&video_info.title);
+    
+    if options.prints_lines() {
+        return print_fields(options, settings, &video_info, output);
+    }
+    if options.simulate {
+        return simulate(options, settings, &video_info, output);
+    }
+    
+    let finished = if video_info.is_live {
+        if !options.download_sections.is_empty() {
+            warn!("--download-sections is ignored for live streams");
+        }
+        record_live(item, options, settings, &extractor, &video_info, output).await?
+    } else {
+        // 3. Present format/quality selection
+        let selection = select_formats(options, settings, &video_info, output)?;
+        output.status(format!("Selected {}", selection));
+        
+        // 4. Download, move into place and post-process
+        download_video(item, options, settings, &extractor, &video_info, selection, output).await?
+    };
+    
+    let FinishedDownload { task, failures, skipped } = finished;
+    for failure in &failures {
+        output.step_failed(&task, failure)?;
+    }
+    if !skipped {
+        record_history(&task);
+    }
+    output.finished(&task)?;
+    if !failures.is_empty() {
+        return Err(DownloaderError::PostProcessing(format!(
+            "{} of the post-processing steps failed on {}",
+            failures.len(),
+            task.output_path.display()
+        ))
+        .into());
+    }
+    Ok(task)
+}
+
+/// Report the format, path and size a download would use without transferring or writing anything
+fn simulate(options: &DownloadOptions, settings: &Settings, video_info: &VideoInfo, output: Output) -> Result<DownloadTask> {
+    let format = choose_format(options, settings, video_info)?;
+    let (collision, _) = file_policies(options, settings);
+    let planned_path = output_path(options, settings, video_info, &format)?;
+    let (action, path) = match FileOrganizer::resolve_collision(&planned_path, collision) {
+        None => (PlannedAction::Skip, planned_path),
+        Some(path) if path != planned_path => (PlannedAction::Rename, path),
+        Some(path) if path.exists() => (PlannedAction::Overwrite, path),
+        Some(path) => (PlannedAction::Download, path),
+    };
+    
+    let mut final_path = path.clone();
+    if let Some(container) = options.remux_video.as_ref().or(settings.remux_video.as_ref()) {
+        final_path.set_extension(container.trim().to_ascii_lowercase());
+    }
+    if let Some(directory) = options.move_to.as_ref().map(PathBuf::from).or_else(|| settings.move_to.clone()) {
+        final_path = directory.join(final_path.file_name().unwrap_or_default());
+    }
+    
+    let size = format.file_size;
+    let available = FileOrganizer::available_space(path.parent().unwrap_or(Path::new(".")))?;
+    let plan = DownloadPlan {
+        video: video_info,
+        format: &format,
+        path: path.clone(),
+        final_path,
+        size,
+        action,
+        previously_downloaded: DownloadHistory::open_default()?.contains(&video_info.video_id)?,
+        enough_space: size.zip(available).map(|(size, available)| size <= available),
+    };
+    output.simulated(&plan)?;
     
-    todo!("Implement main application workflow")
-}
\ No newline at end of file
+    Ok(DownloadTask::new(video_info.clone(), format, path).with_sections(options.download_sections.clone()))
+}
+
+/// Print `--print` templates and the `--get-url` stream URL for a video, one line each
+fn print_fields(options: &DownloadOptions, settings: &Settings, video_info: &VideoInfo, output: Output) -> Result<DownloadTask> {
+    let format = choose_format(options, settings, video_info)?;
+    for template in &options.print {
+        output.line(OutputTemplate::parse(template)?.render_text(video_info, &format));
+    }
+    if options.get_url {
+        output.line(&format.download_url);
+    }
+    
+    let path = output_path(options, settings, video_info, &format)?;
+    Ok(DownloadTask::new(video_info.clone(), format, path))
+}
+
+/// The format to download without asking: the recording format for live streams, otherwise
+/// the best (or `default_quality`) format of the requested type
+fn choose_format(options: &DownloadOptions, settings: &Settings, video_info: &VideoInfo) -> Result<Format> {
+    let format = if video_info.is_live {
+        LiveRecorder::select_format(&video_info.available_formats).cloned()
+    } else {
+        let format_type = if options.audio_only || settings.prefer_audio_only { FormatType::Audio } else { FormatType::Video };
+        FormatExtractor::select(&video_info.available_formats, format_type, settings.default_quality.as_deref())
+    };
+    Ok(format.ok_or(DownloaderError::NoFormatsFound)?)
+}
+
+/// Streams to download, picked by the user unless `--auto` or a JSON output mode is on
+fn select_formats(options: &DownloadOptions, settings: &Settings, video_info: &VideoInfo, output: Output) -> Result<FormatSelection> {
+    if options.auto || output.is_json() {
+        return Ok(FormatSelection::single(choose_format(options, settings, video_info)?));
+    }
+    
+    let interface = InteractiveInterface::new();
+    let format_type = (options.audio_only || settings.prefer_audio_only).then_some(FormatType::Audio);
+    let selection = interface.select_formats(video_info, format_type)?;
+    if let Some(size) = selection.estimated_size() {
+        if settings.confirm_large_downloads
+            && size > settings.large_download_threshold
+            && !interface.confirm_large_download(size)?
+        {
+            return Err(DownloaderError::UserCancelled.into());
+        }
+    }
+    Ok(selection)
+}
+
+/// Download every URL given on the command line or in the batch file, continuing past failures
+async fn run_queue(args: QueueArgs, output: Output) -> Result<()> {
+    let settings = Settings::load()?;
+    let mut urls = args.urls;
+    if let Some(ref batch_file) = args.batch_file {
+        urls.extend(read_batch_file(batch_file)?);
+    }
+    report_warnings(args.options.validate(&settings)?);
+    let after_queue = after_queue_hooks(&args.options, &settings)?;
+    let urls = expand_playlists(urls, &args.options, &settings).await?;
+    
+    let queue = DownloadQueue::new(RateLimiter::new(args.options.limit_rate));
+    for url in &urls {
+        queue.push(url.clone());
+    }
+    let dashboard = args.dashboard && std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    if args.dashboard && !dashboard {
+        eprintln!("Warning: --dashboard needs a terminal, showing plain output instead");
+    }
+    // The dashboard owns the screen, so failures are reported once it closes
+    let item_output = if dashboard { output.quiet() } else { output };
+    
+    let worker = async {
+        let mut paths = Vec::new();
+        let mut failures = Vec::new();
+        let mut unreported = Vec::new();
+        while let Some(item) = queue.next().await {
+            item_output.status(format!("[{}/{}] {}", item.id() + 1, urls.len(), item.url()));
+            match download(&item, &args.options, &settings, item_output).await {
+                Ok(task) => {
+                    item.finish(task.output_path.clone());
+                    paths.push(task.output_path);
+                }
+                Err(e) => {
+                    item.fail(format!("{:#}", e));
+                    failures.push(ExitStatus::of(e.as_ref()));
+                    if dashboard {
+                        unreported.push((item.url().to_string(), e));
+                    } else {
+                        output.failed(item.url(), e)?;
+                    }
+                }
+            }
+        }
+        Ok::<_, anyhow::Error>((paths, failures, unreported))
+    };
+    
+    let (result, view) = if dashboard {
+        let view = tokio::task::spawn_blocking({
+            let dashboard = QueueDashboard::new(queue.clone());
+            move || dashboard.run()
+        });
+        let (result, view) = tokio::join!(worker, view);
+        (result, Some(view))
+    } else {
+        (worker.await, None)
+    };
+    let (paths, mut failures, unreported) = result?;
+    for (url, e) in unreported {
+        output.failed(&url, e)?;
+    }
+    // Items cancelled before they started never reached a worker
+    for item in queue.snapshot() {
+        if item.state == ItemState::Cancelled && item.error.is_none() {
+            failures.push(ExitStatus::Cancelled);
+            output.failed(&item.url, DownloaderError::UserCancelled)?;
+        }
+    }
+    if let Some(view) = view {
+        view??;
+    }
+    
+    run_hooks(after_queue, None, paths).await?;
+    if !failures.is_empty() {
+        return Err(DownloaderError::BatchFailed {
+            failed: failures.len(),
+            total: urls.len(),
+            status: ExitStatus::shared(&failures),
+        }.into());
+    }
+    Ok(())
+}
+
+/// Replace playlist URLs by the URLs of their videos when only printing lines;
+/// downloads keep them, so they fail as invalid video URLs
+async fn expand_playlists(urls: Vec<String>, options: &DownloadOptions, settings: &Settings) -> Result<Vec<String>> {
+    if !options.prints_lines() || !urls.iter().any(|url| UrlValidator::is_playlist_url(url)) {
+        return Ok(urls);
+    }
+    let extractor = YouTubeExtractor::with_settings(settings)?;
+    let mut expanded = Vec::with_capacity(urls.len());
+    for url in urls {
+        if UrlValidator::is_playlist_url(&url) {
+            expanded.extend(extractor.extract_playlist(&url).await?);
+        } else {
+            expanded.push(url);
+        }
+    }
+    Ok(expanded)
+}
+
+/// URLs from a batch file ("-" for standard input), skipping blank lines and '#' comments
+fn read_batch_file(path: &Path) -> Result<Vec<String>> {
+    let content = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Could not read batch file {}", path.display()))?
+    };
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Print a video's details without downloading it
+async fn show_info(url: &str, json: bool) -> Result<()> {
+    let video_info = YouTubeExtractor::with_settings(&Settings::load()?)?.extract_video_info(url).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&video_info)?);
+        return Ok(());
+    }
+    
+    println!("Title:     {}", video_info.title);
+    println!("ID:        {}", video_info.video_id);
+    if let Some(ref uploader) = video_info.uploader {
+        println!("Uploader:  {}", uploader);
+    }
+    if let Some(ref upload_date) = video_info.upload_date {
+        println!("Uploaded:  {}", upload_date);
+    }
+    println!("Duration:  {}{}", video_info.duration, if video_info.is_live { " (live)" } else { "" });
+    println!("Formats:   {}", video_info.available_formats.len());
+    if !video_info.subtitles.is_empty() {
+        let languages: Vec<String> = video_info
+            .subtitles
+            .iter()
+            .map(|track| if track.is_automatic { format!("{} (auto)", track.language) } else { track.language.clone() })
+            .collect();
+        println!("Captions:  {}", languages.join(", "));
+    }
+    if !video_info.chapters.is_empty() {
+        println!("Chapters:");
+        for chapter in &video_info.chapters {
+            let start = chapter.start as u64;
+            println!("  {:02}:{:02}:{:02}  {}", start / 3600, (start % 3600) / 60, start % 60, chapter.title);
+        }
+    }
+    Ok(())
+}
+
+/// Print finished downloads, newest last
+fn show_history(args: &HistoryArgs) -> Result<()> {
+    let history = DownloadHistory::open_default()?;
+    if args.clear {
+        history.clear()?;
+        println!("Cleared download history");
+        return Ok(());
+    }
+    
+    let mut entries = history.entries()?;
+    if let Some(limit) = args.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+    
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else if entries.is_empty() {
+        println!("No downloads recorded yet");
+    } else {
+        for entry in &entries {
+            println!("{}  {} [{}]  {}", entry.downloaded_at_utc(), entry.title, entry.video_id, entry.path.display());
+        }
+    }
+    Ok(())
+}
+
+fn configure(action: ConfigAction) -> Result<()> {
+    match action {
+        ConfigAction::Show => print!("{}", toml::to_string_pretty(&Settings::load()?)?),
+        ConfigAction::Path => println!("{}", Settings::get_config_path()?.display()),
+        ConfigAction::Init => Settings::create_sample_config()?,
+    }
+    Ok(())
+}
+
+/// Compare files with the sizes recorded in the download history
+fn verify(paths: &[PathBuf]) -> Result<()> {
+    let entries = DownloadHistory::open_default()?.entries()?;
+    
+    // The latest record of each requested file, or every recorded file when none are given
+    let checks: Vec<(PathBuf, Option<&HistoryEntry>)> = if paths.is_empty() {
+        entries.iter().map(|entry| (entry.path.clone(), Some(entry))).collect()
+    } else {
+        paths
+            .iter()
+            .map(|path| {
+                let absolute = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+                let entry = entries.iter().rev().find(|entry| entry.path == *path || entry.path == absolute);
+                (path.clone(), entry)
+            })
+            .collect()
+    };
+    if checks.is_empty() {
+        println!("No downloads recorded yet");
+        return Ok(());
+    }
+    
+    let mut failed = 0;
+    for (path, entry) in &checks {
+        let problem = match entry {
+            None => Some("not in the download history".to_string()),
+            Some(entry) => match entry.verify() {
+                Verification::Intact => None,
+                Verification::Missing => Some("missing".to_string()),
+                Verification::Changed { size } => Some(format!("recorded {} bytes, found {}", entry.size, size)),
+            },
+        };
+        match problem {
+            None => println!("OK      {}", path.display()),
+            Some(problem) => {
+                failed += 1;
+                println!("FAILED  {} ({})", path.display(), problem);
+            }
+        }
+    }
+    
+    if failed > 0 {
+        bail!("{} of {} files failed verification", failed, checks.len());
+    }
+    Ok(())
+}
+
+/// Queue downloads sent to the local HTTP API and run them one at a time
+async fn serve(args: ServeArgs, output: Output) -> Result<()> {
+    let settings = Settings::load()?;
+    report_warnings(args.options.validate(&settings)?);
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+    let server = ApiServer::bind(args.bind, sender, DownloadHistory::open_default()?).await?;
+    output.status(format!("Listening on http://{}", server.local_addr()?));
+    let queue = DownloadQueue::new(RateLimiter::new(args.options.limit_rate));
+    
+    let worker = async {
+        while let Some(url) = receiver.recv().await {
+            output.status(format!("Downloading {}", url));
+            let item = queue.start(url);
+            match download(&item, &args.options, &settings, output).await {
+                Ok(task) => {
+                    output.status(format!("Saved {}", task.output_path.display()));
+                    item.finish(task.output_path);
+                }
+                Err(e) => {
+                    item.fail(format!("{:#}", e));
+                    if let Err(e) = output.failed(item.url(), e) {
+                        warn!("Could not report a failed download: {}", e);
+                    }
+                }
+            }
+        }
+    };
+    
+    tokio::select! {
+        result = server.run() => result?,
+        _ = worker => {}
+    }
+    Ok(())
+}
+
+/// Poll an upcoming premiere or stream with a countdown until it becomes available
+async fn wait_for_video(extractor: &YouTubeExtractor, url: &str, range: WaitRange) -> Result<VideoInfo> {
+    let term = Term::stderr();
+    let waiter = VideoWaiter::new(range);
+    
+    let video_info = waiter
+        .wait_for_video(extractor, url, |remaining| {
+            let secs = remaining.as_secs();
+            let _ = term.clear_line();
+            let _ = term.write_str(&format!(
+                "Waiting for video to start... next check in {:02}:{:02}:{:02}",
+                secs / 3600,
+                (secs % 3600) / 60,
+                secs % 60
+            ));
+        })
+        .await?;
+    
+    let _ = term.clear_line();
+    Ok(video_info)
+}
+
+/// Download the selected formats of a video that is not live, merging
+/// separate video and audio streams into one file
+async fn download_video(
+    item: &QueueHandle,
+    options: &DownloadOptions,
+    settings: &Settings,
+    extractor: &YouTubeExtractor,
+    video_info: &VideoInfo,
+    selection: FormatSelection,
+    output: Output,
+) -> Result<FinishedDownload> {
+    let (format, audio) = match selection {
+        FormatSelection { video: Some(video), audio } => (video, audio),
+        FormatSelection { video: None, audio: Some(audio) } => (audio, None),
+        FormatSelection { video: None, audio: None } => return Err(DownloaderError::NoFormatsFound.into()),
+    };
+    
+    let (collision, cleanup) = file_policies(options, settings);
+    let planned_path = output_path(options, settings, video_info, &format)?;
+    let Some(output_path) = FileOrganizer::resolve_collision(&planned_path, collision) else {
+        output.status(format!("{} already exists, skipping download", planned_path.display()));
+        return Ok(FinishedDownload {
+            task: DownloadTask::new(video_info.clone(), format, planned_path),
+            failures: Vec::new(),
+            skipped: true,
+        });
+    };
+    let pipeline = post_processors(options, settings, extractor, video_info).await?;
+    let before_download = exec_hooks(&settings.hooks.before_download, &options.exec_before_download, ExecHook::parse)?;
+    let mut task = DownloadTask::new(video_info.clone(), format, output_path.clone())
+        .with_sections(options.download_sections.clone());
+    if let Some(audio) = audio {
+        task = task.with_audio(audio);
+    }
+    output.started(&task)?;
+    run_hooks(before_download, Some(task.clone()), vec![output_path]).await?;
+    
+    let (progress, printer) = output.progress(item, video_info, settings.effective_max_concurrent_downloads());
+    let mut manager = DownloadManager::with_settings(settings)
+        .with_post_processors(pipeline)
+        .with_file_policies(collision, cleanup)
+        .with_space_ledger(item.space().clone())
+        .with_queue_item(Some(item.clone()));
+    manager.set_progress_callback(progress);
+    
+    let result = manager.download(task).await;
+    // The printer stops once the manager's sender is gone
+    drop(manager);
+    printer.await?;
+    let finished = result?;
+    output.status(format!("Saved {}", finished.task.output_path.display()));
+    Ok(finished)
+}
+
+/// Record an ongoing live stream until it ends, the duration limit passes or Ctrl-C
+async fn record_live(
+    item: &QueueHandle,
+    options: &DownloadOptions,
+    settings: &Settings,
+    extractor: &YouTubeExtractor,
+    video_info: &VideoInfo,
+    output: Output,
+) -> Result<FinishedDownload> {
+    let format = LiveRecorder::select_format(&video_info.available_formats)
+        .ok_or(DownloaderError::NoFormatsFound)?;
+    
+    let (collision, cleanup) = file_policies(options, settings);
+    let planned_path = output_path(options, settings, video_info, format)?;
+    let Some(output_path) = FileOrganizer::resolve_collision(&planned_path, collision) else {
+        output.status(format!("{} already exists, not recording", planned_path.display()));
+        return Ok(FinishedDownload {
+            task: DownloadTask::new(video_info.clone(), format.clone(), planned_path),
+            failures: Vec::new(),
+            skipped: true,
+        });
+    };
+    let pipeline = post_processors(options, settings, extractor, video_info).await?;
+    let before_download = exec_hooks(&settings.hooks.before_download, &options.exec_before_download, ExecHook::parse)?;
+    let planned = DownloadTask::new(video_info.clone(), format.clone(), output_path.clone());
+    output.started(&planned)?;
+    run_hooks(before_download, Some(planned), vec![output_path.clone()]).await?;
+    
+    output.status(format!("Recording live stream: {}", video_info.title));
+    output.status("Press Ctrl-C to stop recording\n");
+    
+    let connections = settings.effective_max_concurrent_downloads();
+    let (progress, printer) = output.progress(item, video_info, connections);
+    let recorder = LiveRecorder::new(
+        NetworkUtils::create_client()?,
+        connections,
+        settings.max_retries,
+    )
+    .from_start(options.live_from_start)
+    .max_duration(options.live_duration)
+    .progress(Some(progress))
+    .queue_item(Some(item.clone()));
+    
+    let result = recorder.record(format, &output_path).await;
+    // The printer stops once the recorder's sender is gone
+    drop(recorder);
+    printer.await?;
+    let recording = match result {
+        Ok(recording) => recording,
+        Err(e) => {
+            if cleanup == CleanupPolicy::Always {
+                let _ = FileOrganizer::cleanup_temp_files(&output_path);
+            }
+            return Err(e.into());
+        }
+    };
+    output.status(format!(
+        "Saved {} fragments to {}",
+        recording.fragments,
+        recording.output_path.display()
+    ));
+    
+    let mut task = DownloadTask::new(video_info.clone(), format.clone(), recording.output_path);
+    let mut failures = Vec::new();
+    if !pipeline.is_empty() {
+        (task, failures) = tokio::task::spawn_blocking(move || {
+            let failures = pipeline.run(&mut task);
+            (task, failures)
+        })
+        .await?;
+        output.status(format!("Finished {}", task.output_path.display()));
+    }
+    
+    Ok(FinishedDownload {
+        task,
+        failures,
+        skipped: false,
+    })
+}
+
+/// Add a finished download to the history; failing to is not worth failing the download over
+fn record_history(task: &DownloadTask) {
+    let result = DownloadHistory::open_default().and_then(|history| history.record(&HistoryEntry::from_task(task)?));
+    if let Err(e) = result {
+        warn!("Could not record {} in the download history: {}", task.output_path.display(), e);
+    }
+}
+
+/// Build the post-processing stages requested on the command line or in the config file.
+///
+/// Thumbnails, caption tracks and SponsorBlock segments are fetched here, since
+/// the stages themselves run on a blocking thread.
+async fn post_processors(
+    options: &DownloadOptions,
+    settings: &Settings,
+    extractor: &YouTubeExtractor,
+    video_info: &VideoInfo,
+) -> Result<PostProcessorPipeline> {
+    let mut pipeline = PostProcessorPipeline::new();
+    
+    let (mark, remove) = sponsorblock_categories(options, settings)?;
+    if !mark.is_empty() || !remove.is_empty() {
+        let api = options.sponsorblock_api.as_deref().unwrap_or(settings.get_sponsorblock_api());
+        let client = SponsorBlockClient::new(NetworkUtils::create_client()?, api);
+        let mut categories = mark.clone();
+        categories.extend(remove.iter().filter(|category| !mark.contains(category)).cloned());
+        match client.segments(&video_info.video_id, &categories).await {
+            Ok(segments) => pipeline.add(SponsorBlockProcessor::new(segments, &remove)),
+            Err(e) => warn!("Failed to fetch SponsorBlock segments: {}", e),
+        }
+    }
+    
+    if let Some(container) = options.remux_video.as_ref().or(settings.remux_video.as_ref()) {
+        pipeline.add(Remuxer::new(container)?);
+    }
+    if options.embed_metadata || settings.embed_metadata {
+        pipeline.add(EmbedMetadata);
+    }
+    if options.embed_thumbnail || settings.embed_thumbnail {
+        pipeline.add(EmbedThumbnail::new(fetch_thumbnail(&video_info.thumbnail_url).await));
+    }
+    
+    // Marked SponsorBlock segments only show up as embedded chapters
+    let embed_chapters = options.embed_chapters || settings.embed_chapters || !mark.is_empty();
+    let mut subtitles = Vec::new();
+    if options.embed_subs || settings.embed_subs {
+        for track in select_subtitle_tracks(&video_info.subtitles, &options.sub_langs) {
+            match extractor.fetch_subtitle(track).await {
+                Ok(subtitle) if !subtitle.cues.is_empty() => subtitles.push(subtitle),
+                Ok(_) => warn!("Caption track '{}' is empty", track.name),
+                Err(e) => warn!("Failed to download caption track '{}': {}", track.name, e),
+            }
+        }
+    }
+    if embed_chapters || !subtitles.is_empty() {
+        pipeline.add(EmbedChapters::new(embed_chapters, subtitles));
+    }
+    
+    if options.split_chapters || settings.split_chapters {
+        let template = match options.chapter_output {
+            Some(ref template) => OutputTemplate::parse(template)?,
+            None => settings.get_chapter_output_template()?,
+        };
+        pipeline.add(SplitChapters::new(ChapterSplitter::new(template), output_directory(options, settings)?));
+    }
+    if let Some(directory) = options.move_to.as_ref().map(PathBuf::from).or_else(|| settings.move_to.clone()) {
+        pipeline.add(MoveFile::new(directory));
+    }
+    for hook in exec_hooks(&settings.hooks.after_file, &options.exec, ExecHook::parse)? {
+        pipeline.add(hook);
+    }
+    
+    Ok(pipeline)
+}
+
+/// Hooks to run once all downloads are finished; none when nothing is downloaded
+fn after_queue_hooks(options: &DownloadOptions, settings: &Settings) -> Result<Vec<ExecHook>> {
+    if options.skips_download() {
+        return Ok(Vec::new());
+    }
+    exec_hooks(&settings.hooks.after_queue, &options.exec_after_queue, ExecHook::parse_without_fields)
+}
+
+/// Parse hook commands from the config file followed by those given on the command line
+fn exec_hooks(
+    configured: &[String],
+    from_cli: &[String],
+    parse: fn(&str) -> downloader::Result<ExecHook>,
+) -> Result<Vec<ExecHook>> {
+    Ok(configured.iter().chain(from_cli).map(|command| parse(command)).collect::<downloader::Result<_>>()?)
+}
+
+/// Run hooks one after another on a blocking thread, stopping at the first failure
+async fn run_hooks(hooks: Vec<ExecHook>, task: Option<DownloadTask>, paths: Vec<PathBuf>) -> Result<()> {
+    if hooks.is_empty() {
+        return Ok(());
+    }
+    tokio::task::spawn_blocking(move || hooks.iter().try_for_each(|hook| hook.run(task.as_ref(), &paths))).await??;
+    Ok(())
+}
+
+/// SponsorBlock categories to mark and to remove, from the command line or the config file
+fn sponsorblock_categories(options: &DownloadOptions, settings: &Settings) -> Result<(Vec<String>, Vec<String>)> {
+    let pick = |from_cli: &Vec<String>, configured: &Vec<String>| {
+        SponsorBlockClient::parse_categories(if from_cli.is_empty() { configured } else { from_cli })
+    };
+    Ok((
+        pick(&options.sponsorblock_mark, &settings.sponsorblock_mark)?,
+        pick(&options.sponsorblock_remove, &settings.sponsorblock_remove)?,
+    ))
+}
+
+/// Pick caption tracks for the requested languages, preferring manual over automatic captions
+fn select_subtitle_tracks<'a>(tracks: &'a [SubtitleTrack], languages: &[String]) -> Vec<&'a SubtitleTrack> {
+    if languages.is_empty() {
+        return tracks.iter().filter(|track| !track.is_automatic).collect();
+    }
+    
+    languages
+        .iter()
+        .filter_map(|language| {
+            let matching = |track: &&SubtitleTrack| track.language.eq_ignore_ascii_case(language);
+            tracks
+                .iter()
+                .filter(matching)
+                .find(|track| !track.is_automatic)
+                .or_else(|| tracks.iter().find(matching))
+        })
+        .collect()
+}
+
+/// Download the thumbnail as cover art, ignoring failures
+async fn fetch_thumbnail(url: &str) -> Option<CoverArt> {
+    let client = NetworkUtils::create_client().ok()?;
+    let response = client.get(url).send().await.ok()?.error_for_status().ok()?;
+    CoverArt::from_bytes(response.bytes().await.ok()?.to_vec())
+}
+
+/// Resolve where a format should be saved from the output directory and template
+fn output_path(options: &DownloadOptions, settings: &Settings, video_info: &VideoInfo, format: &Format) -> Result<PathBuf> {
+    let output_dir = output_directory(options, settings)?;
+    let template = match options.output {
+        Some(ref template) => OutputTemplate::parse(template)?,
+        None => settings.get_output_template()?,
+    };
+    
+    Ok(FileOrganizer::output_path(&output_dir, &template, video_info, format))
+}
+
+/// Collision and cleanup policies, preferring command-line options over the config file
+fn file_policies(options: &DownloadOptions, settings: &Settings) -> (CollisionPolicy, CleanupPolicy) {
+    (
+        options.on_collision.unwrap_or(settings.on_collision),
+        options.temp_cleanup.unwrap_or(settings.temp_cleanup),
+    )
+}
+
+/// Directory downloads are saved in; it is created once a download starts writing
+fn output_directory(options: &DownloadOptions, settings: &Settings) -> Result<PathBuf> {
+    Ok(match options.output_dir {
+        Some(ref dir) => PathBuf::from(dir),
+        None => settings.resolve_output_directory()?,
+    })
+}
+
+/// Print the available formats for a video without downloading anything
+async fn list_formats(url: &str, json: bool) -> Result<()> {
+    let extractor = YouTubeExtractor::with_settings(&Settings::load()?)?;
+    let video_info = extractor.extract_video_info(url).await?;
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&video_info.available_formats)?);
+    } else {
+        println!("Available formats for {} ({}):\n", video_info.title, video_info.video_id);
+        print!("{}", FormatTable::render(&video_info.available_formats));
+    }
+    
+    Ok(())
+}
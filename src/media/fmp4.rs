@@ -0,0 +1,357 @@
+//! Cutting fragmented MP4 files along `moof` boundaries
+//!
+//! DASH segments and live recordings start every fragment with a keyframe,
+//! so whole fragments can be dropped without re-encoding. The decode times
+//! (`tfdt`) of the fragments that are kept are shifted to close the gaps.
+
+use crate::error::DownloaderError;
+use crate::metadata::mp4::{parse_atoms, read_moov, read_u32, scan_top_level, shift_base_data_offsets, Atom};
+use crate::Result;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Top-level boxes describing the original layout, which no longer holds after cutting
+const INDEX_BOXES: &[&[u8; 4]] = &[b"sidx", b"ssix", b"styp", b"mfra"];
+
+/// A `moof` box and the media boxes that follow it
+struct Fragment {
+    moof: Atom,
+    offset: u64,
+    /// (offset, size) of the boxes up to the next fragment
+    media: Vec<(u64, u64)>,
+    /// Track id and decode time of each `traf`
+    times: Vec<(u32, u64)>,
+    /// Duration of each `traf`, from its samples or else from the next fragment of the track
+    durations: Vec<Option<u64>>,
+}
+
+/// Whether `path` is an MP4 file made of movie fragments
+pub fn is_fragmented(path: &Path) -> Result<bool> {
+    let mut file = File::open(path)?;
+    Ok(scan_top_level(&mut file)?.iter().any(|b| &b.kind == b"moof"))
+}
+
+/// Copy the fragments of `input` that overlap `ranges` (in seconds) to `output`
+pub fn keep_ranges(input: &Path, output: &Path, ranges: &[(f64, f64)]) -> Result<()> {
+    let mut file = File::open(input)?;
+    let boxes = scan_top_level(&mut file)?;
+    let first_moof = boxes
+        .iter()
+        .position(|b| &b.kind == b"moof")
+        .ok_or_else(|| DownloaderError::Media(format!("{} has no movie fragments", input.display())))?;
+    
+    let mut timescales = None;
+    let mut default_durations = HashMap::new();
+    let mut header: Vec<Vec<u8>> = Vec::new();
+    for top in &boxes[..first_moof] {
+        if &top.kind == b"moov" {
+            let mut moov = read_moov(&mut file, top)?;
+            timescales = Some(track_timescales(&moov));
+            default_durations = default_sample_durations(&moov);
+            // The overall fragment duration changes with the cut
+            for mvex in moov.children.iter_mut().filter(|atom| &atom.kind == b"mvex") {
+                mvex.children.retain(|atom| &atom.kind != b"mehd");
+            }
+            header.push(moov.to_bytes());
+        } else if !INDEX_BOXES.contains(&&top.kind) {
+            header.push(read_range(&mut file, top.offset, top.size)?);
+        }
+    }
+    let timescales =
+        timescales.ok_or_else(|| DownloaderError::Media(format!("No moov box in {}", input.display())))?;
+    
+    let mut fragments: Vec<Fragment> = Vec::new();
+    for top in &boxes[first_moof..] {
+        if &top.kind == b"moof" {
+            let bytes = read_range(&mut file, top.offset, top.size)?;
+            let moof = parse_atoms(&bytes)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| DownloaderError::Media("Empty moof box".to_string()))?;
+            let (times, durations) = decode_times(&moof, &default_durations).into_iter().unzip();
+            fragments.push(Fragment { moof, offset: top.offset, media: Vec::new(), times, durations });
+        } else if !INDEX_BOXES.contains(&&top.kind) {
+            if let Some(fragment) = fragments.last_mut() {
+                fragment.media.push((top.offset, top.size));
+            }
+        }
+    }
+    
+    // Sample durations stay exact across gaps in the input; the distance to the
+    // next fragment is only a fallback for fragments that do not list them
+    let mut next_times: HashMap<u32, u64> = HashMap::new();
+    for fragment in fragments.iter_mut().rev() {
+        for ((track, time), duration) in fragment.times.iter().zip(fragment.durations.iter_mut()) {
+            if duration.is_none() {
+                *duration = next_times.get(track).map(|next| next.saturating_sub(*time));
+            }
+        }
+        for (track, time) in &fragment.times {
+            next_times.insert(*track, *time);
+        }
+    }
+    // After the reverse pass this holds the first decode time of every track
+    let origins = next_times;
+    
+    // The last fragment of a track is assumed to be as long as the one before it
+    let mut last_durations: HashMap<u32, u64> = HashMap::new();
+    for fragment in fragments.iter_mut() {
+        for ((track, _), duration) in fragment.times.iter().zip(fragment.durations.iter_mut()) {
+            match duration {
+                Some(duration) => {
+                    last_durations.insert(*track, *duration);
+                }
+                None => *duration = last_durations.get(track).copied(),
+            }
+        }
+    }
+    
+    let keep: Vec<bool> = fragments
+        .iter()
+        .map(|fragment| {
+            let Some(&(track, time)) = fragment.times.first() else {
+                return false;
+            };
+            let timescale = timescales.get(&track).copied().unwrap_or(1).max(1) as f64;
+            let start = time.saturating_sub(origins[&track]) as f64 / timescale;
+            let end = fragment.durations[0].map_or(f64::INFINITY, |duration| start + duration as f64 / timescale);
+            ranges.iter().any(|&(from, to)| start < to && end > from)
+        })
+        .collect();
+    if !keep.contains(&true) {
+        return Err(DownloaderError::Media(format!("No fragments of {} fall inside the requested ranges", input.display())));
+    }
+    
+    let mut writer = BufWriter::new(File::create(output)?);
+    let mut written: u64 = 0;
+    for bytes in &header {
+        writer.write_all(bytes)?;
+        written += bytes.len() as u64;
+    }
+    
+    let mut output_times = origins.clone();
+    for (fragment, _) in fragments.iter_mut().zip(&keep).filter(|(_, keep)| **keep) {
+        let new_times: Vec<u64> = fragment.times.iter().map(|(track, _)| output_times[track]).collect();
+        for ((track, _), duration) in fragment.times.iter().zip(&fragment.durations) {
+            *output_times.get_mut(track).unwrap() += duration.unwrap_or(0);
+        }
+        
+        let delta = written as i64 - fragment.offset as i64;
+        retime_fragment(&mut fragment.moof, &new_times, delta);
+        let moof = fragment.moof.to_bytes();
+        writer.write_all(&moof)?;
+        written += moof.len() as u64;
+        
+        for &(offset, size) in &fragment.media {
+            file.seek(SeekFrom::Start(offset))?;
+            io::copy(&mut (&mut file).take(size), &mut writer)?;
+            written += size;
+        }
+    }
+    
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_range(file: &mut File, offset: u64, size: u64) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; size as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Media timescale of each track, keyed by track id
+fn track_timescales(moov: &Atom) -> HashMap<u32, u32> {
+    moov.children
+        .iter()
+        .filter(|atom| &atom.kind == b"trak")
+        .filter_map(|trak| {
+            let tkhd = trak.child(b"tkhd")?;
+            let mdhd = trak.child(b"mdia")?.child(b"mdhd")?;
+            // Both boxes keep the field after two 32 or 64-bit timestamps
+            let field = |data: &[u8]| read_u32(data, if data.first() == Some(&1) { 20 } else { 12 });
+            Some((field(&tkhd.data)?, field(&mdhd.data)?))
+        })
+        .collect()
+}
+
+/// Default sample duration of each track from `trex`, keyed by track id
+fn default_sample_durations(moov: &Atom) -> HashMap<u32, u32> {
+    moov.children
+        .iter()
+        .filter(|atom| &atom.kind == b"mvex")
+        .flat_map(|mvex| mvex.children.iter().filter(|atom| &atom.kind == b"trex"))
+        .filter_map(|trex| Some((read_u32(&trex.data, 4)?, read_u32(&trex.data, 12)?)))
+        .collect()
+}
+
+/// Track id and decode time of each `traf`, with the total duration of its samples when known
+fn decode_times(moof: &Atom, default_durations: &HashMap<u32, u32>) -> Vec<((u32, u64), Option<u64>)> {
+    moof.children
+        .iter()
+        .filter(|atom| &atom.kind == b"traf")
+        .filter_map(|traf| {
+            let tfhd = &traf.child(b"tfhd")?.data;
+            let track = read_u32(tfhd, 4)?;
+            let tfdt = &traf.child(b"tfdt")?.data;
+            let time = match tfdt.first() {
+                Some(1) => u64::from_be_bytes(tfdt.get(4..12)?.try_into().ok()?),
+                _ => read_u32(tfdt, 4)? as u64,
+            };
+            
+            let default_duration = tfhd_default_duration(tfhd).or_else(|| default_durations.get(&track).copied());
+            let runs: Option<Vec<u64>> = traf
+                .children
+                .iter()
+                .filter(|atom| &atom.kind == b"trun")
+                .map(|trun| run_duration(&trun.data, default_duration))
+                .collect();
+            let duration = runs.map(|runs| runs.iter().sum()).filter(|total| *total > 0);
+            Some(((track, time), duration))
+        })
+        .collect()
+}
+
+/// Default sample duration from a `tfhd` box, if present
+fn tfhd_default_duration(tfhd: &[u8]) -> Option<u32> {
+    let flags = read_u32(tfhd, 0)? & 0xFF_FFFF;
+    if flags & 0x08 == 0 {
+        return None;
+    }
+    // Base data offset and sample description index come first when present
+    let offset = 8 + if flags & 0x01 != 0 { 8 } else { 0 } + if flags & 0x02 != 0 { 4 } else { 0 };
+    read_u32(tfhd, offset)
+}
+
+/// Total duration of the samples in a `trun` box
+fn run_duration(trun: &[u8], default_duration: Option<u32>) -> Option<u64> {
+    let flags = read_u32(trun, 0)? & 0xFF_FFFF;
+    let count = read_u32(trun, 4)? as usize;
+    if flags & 0x100 == 0 {
+        return default_duration.map(|duration| duration as u64 * count as u64);
+    }
+    
+    // Data offset and first sample flags precede the per-sample fields
+    let first = 8 + if flags & 0x01 != 0 { 4 } else { 0 } + if flags & 0x04 != 0 { 4 } else { 0 };
+    let stride = 4 * (flags & 0xF00).count_ones() as usize;
+    (0..count).map(|sample| read_u32(trun, first + sample * stride).map(|duration| duration as u64)).sum()
+}
+
+/// Set new decode times and move explicit base data offsets by `delta` bytes
+fn retime_fragment(moof: &mut Atom, new_times: &[u64], delta: i64) {
+    let trafs = moof.children.iter_mut().filter(|atom| &atom.kind == b"traf");
+    for (traf, new_time) in trafs.zip(new_times) {
+        for tfdt in traf.children.iter_mut().filter(|atom| &atom.kind == b"tfdt") {
+            if tfdt.data.first() == Some(&1) && tfdt.data.len() >= 12 {
+                tfdt.data[4..12].copy_from_slice(&new_time.to_be_bytes());
+            } else if tfdt.data.len() >= 8 {
+                tfdt.data[4..8].copy_from_slice(&(*new_time as u32).to_be_bytes());
+            }
+        }
+    }
+    shift_base_data_offsets(moof, delta);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    
+    fn full_box(kind: &[u8; 4], version: u8, fields: &[u8]) -> Atom {
+        let mut data = vec![version, 0, 0, 0];
+        data.extend(fields);
+        Atom::leaf(kind, data)
+    }
+    
+    /// A one-track fragmented MP4 with a 1000 Hz timescale and 2 second fragments
+    pub(crate) fn sample_file(fragments: u64) -> Vec<u8> {
+        let mut tkhd = vec![0u8; 8];
+        tkhd.extend(1u32.to_be_bytes());
+        let mut mdhd = vec![0u8; 8];
+        mdhd.extend(1000u32.to_be_bytes());
+        
+        let moov = Atom::container(
+            b"moov",
+            vec![
+                full_box(b"mvhd", 0, &[0; 96]),
+                Atom::container(
+                    b"trak",
+                    vec![
+                        full_box(b"tkhd", 0, &tkhd),
+                        Atom::container(b"mdia", vec![full_box(b"mdhd", 0, &mdhd)]),
+                    ],
+                ),
+                Atom::container(b"mvex", vec![full_box(b"mehd", 0, &[0, 0, 31, 64]), full_box(b"trex", 0, &[0; 20])]),
+            ],
+        );
+        
+        let mut bytes = Atom::leaf(b"ftyp", b"iso6\0\0\0\0".to_vec()).to_bytes();
+        bytes.extend(moov.to_bytes());
+        bytes.extend(full_box(b"sidx", 1, &[0; 28]).to_bytes());
+        
+        for index in 0..fragments {
+            let mut tfhd = 1u32.to_be_bytes().to_vec();
+            tfhd.extend((bytes.len() as u64).to_be_bytes());
+            let mut tfhd = full_box(b"tfhd", 0, &tfhd);
+            // Explicit base data offset
+            tfhd.data[3] = 0x1;
+            
+            let moof = Atom::container(
+                b"moof",
+                vec![
+                    full_box(b"mfhd", 0, &(index as u32 + 1).to_be_bytes()),
+                    Atom::container(
+                        b"traf",
+                        vec![tfhd, full_box(b"tfdt", 1, &(index * 2000).to_be_bytes()), full_box(b"trun", 0, &[0; 4])],
+                    ),
+                ],
+            );
+            bytes.extend(moof.to_bytes());
+            bytes.extend(Atom::leaf(b"mdat", format!("F{}", index).into_bytes()).to_bytes());
+        }
+        bytes
+    }
+    
+    /// Decode time and payload of each fragment
+    pub(crate) fn read_fragments(path: &Path) -> Vec<(u64, String)> {
+        let bytes = std::fs::read(path).unwrap();
+        let atoms = parse_atoms(&bytes).unwrap();
+        let mut fragments = Vec::new();
+        for (moof, mdat) in atoms.iter().zip(atoms.iter().skip(1)).filter(|(a, _)| &a.kind == b"moof") {
+            let ((_, time), _) = decode_times(moof, &HashMap::new())[0];
+            fragments.push((time, String::from_utf8(mdat.data.clone()).unwrap()));
+        }
+        fragments
+    }
+    
+    #[test]
+    fn test_keep_ranges_retimes_fragments() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let input = temp_dir.path().join("input.mp4");
+        let output = temp_dir.path().join("output.mp4");
+        std::fs::write(&input, sample_file(5)).unwrap();
+        assert!(is_fragmented(&input).unwrap());
+        
+        // 2.5s-5s snaps out to the fragments starting at 2s and 4s
+        keep_ranges(&input, &output, &[(2.5, 5.0)]).unwrap();
+        assert_eq!(read_fragments(&output), [(0, "F1".to_string()), (2000, "F2".to_string())]);
+        
+        // Cutting out the middle leaves a continuous timeline
+        keep_ranges(&input, &output, &[(0.0, 2.0), (8.0, 10.0)]).unwrap();
+        assert_eq!(read_fragments(&output), [(0, "F0".to_string()), (2000, "F4".to_string())]);
+        
+        let bytes = std::fs::read(&output).unwrap();
+        let atoms = parse_atoms(&bytes).unwrap();
+        assert!(atoms.iter().all(|atom| &atom.kind != b"sidx"));
+        assert!(atoms[1].child(b"mvex").unwrap().child(b"mehd").is_none());
+        
+        // Explicit base data offsets follow the moved fragments
+        let last_moof = atoms.iter().rposition(|atom| &atom.kind == b"moof").unwrap();
+        let moof_offset: usize = atoms[..last_moof].iter().map(|atom| atom.to_bytes().len()).sum();
+        let tfhd = &atoms[last_moof].child(b"traf").unwrap().child(b"tfhd").unwrap().data;
+        assert_eq!(u64::from_be_bytes(tfhd[8..16].try_into().unwrap()), moof_offset as u64);
+        
+        assert!(keep_ranges(&input, &output, &[(20.0, 30.0)]).is_err());
+    }
+}
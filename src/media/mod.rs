@@ -0,0 +1,58 @@
+//! Lossless editing of downloaded media
+//!
+//! Files are cut at fragment or keyframe boundaries without re-encoding, so a
+//! cut may start slightly before the requested time.
+
+pub mod fmp4;
+pub mod split;
+pub mod ts;
+
+pub use split::ChapterSplitter;
+
+use crate::error::DownloaderError;
+use crate::Result;
+use log::debug;
+use std::path::Path;
+
+/// Containers that can be cut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    FragmentedMp4,
+    MpegTs,
+}
+
+impl Container {
+    /// Detect the container from the file contents
+    pub fn detect(path: &Path) -> Result<Option<Self>> {
+        if ts::is_transport_stream(path)? {
+            return Ok(Some(Self::MpegTs));
+        }
+        // Anything else that is not an MP4 fails the box scan
+        match fmp4::is_fragmented(path) {
+            Ok(true) => Ok(Some(Self::FragmentedMp4)),
+            Ok(false) | Err(DownloaderError::Metadata(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub struct MediaCutter;
+
+impl MediaCutter {
+    /// Write the parts of `input` inside `ranges` (start and end in seconds) to `output`
+    pub fn keep_ranges(input: &Path, output: &Path, ranges: &[(f64, f64)]) -> Result<()> {
+        match Container::detect(input)? {
+            Some(Container::FragmentedMp4) => fmp4::keep_ranges(input, output, ranges)?,
+            Some(Container::MpegTs) => ts::keep_ranges(input, output, ranges)?,
+            None => {
+                return Err(DownloaderError::Media(format!(
+                    "Cannot cut {} without re-encoding: only fragmented MP4 and MPEG-TS are supported",
+                    input.display()
+                )))
+            }
+        }
+        
+        debug!("Cut {} ranges of {} into {}", ranges.len(), input.display(), output.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Splitting a download into one file per chapter
+
+use crate::file_system::{OutputTemplate, Section};
+use crate::media::MediaCutter;
+use crate::metadata::{MetadataWriter, Tags};
+use crate::models::{Format, VideoInfo};
+use crate::Result;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+
+pub struct ChapterSplitter {
+    template: OutputTemplate,
+}
+
+impl ChapterSplitter {
+    /// Name the chapter files with `template`, which may use the `section_*` fields
+    pub fn new(template: OutputTemplate) -> Self {
+        Self { template }
+    }
+    
+    /// Cut `path` into one tagged file per chapter under `directory`.
+    ///
+    /// The original file is left in place. Returns the files written, which is
+    /// empty when the video has no chapters.
+    pub fn split(&self, path: &Path, directory: &Path, video_info: &VideoInfo, format: &Format) -> Result<Vec<PathBuf>> {
+        let chapters = &video_info.chapters;
+        let mut parts = Vec::with_capacity(chapters.len());
+        
+        for (index, chapter) in chapters.iter().enumerate() {
+            let section = Section {
+                title: chapter.title.clone(),
+                number: index + 1,
+                start: chapter.start,
+                end: chapter.end,
+            };
+            let part = directory.join(self.template.render_section(video_info, format, &section));
+            if part == path {
+                warn!("Chapter template renders to the original file name, skipping '{}'", chapter.title);
+                continue;
+            }
+            if let Some(parent) = part.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            
+            // The last chapter's end is unknown when the duration was not reported
+            let end = if chapter.end > chapter.start { chapter.end } else { f64::INFINITY };
+            MediaCutter::keep_ranges(path, &part, &[(chapter.start, end)])?;
+            
+            let tags = Tags {
+                title: Some(chapter.title.clone()),
+                album: Some(video_info.title.clone()),
+                track: Some((section.number as u32, chapters.len() as u32)),
+                ..Tags::from_video_info(video_info)
+            };
+            if let Err(e) = MetadataWriter::write(&part, &tags) {
+                warn!("Failed to tag {}: {}", part.display(), e);
+            }
+            
+            info!("Wrote chapter {} to {}", section.number, part.display());
+            parts.push(part);
+        }
+        
+        Ok(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::fmp4;
+    use crate::metadata::mp4;
+    use crate::models::{Chapter, FormatType};
+    
+    #[test]
+    fn test_split_names_and_tags_chapters() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let input = temp_dir.path().join("video.mp4");
+        std::fs::write(&input, fmp4::tests::sample_file(5)).unwrap();
+        
+        let mut video_info = VideoInfo::new("Video".to_string(), "0:10".to_string(), "abc123".to_string());
+        video_info.chapters = vec![
+            Chapter { title: "Intro".to_string(), start: 0.0, end: 4.0 },
+            Chapter { title: "Main".to_string(), start: 4.0, end: 10.0 },
+        ];
+        let format = Format::new("720p".to_string(), FormatType::Video, "mp4".to_string(), String::new());
+        
+        let splitter = ChapterSplitter::new(OutputTemplate::parse(OutputTemplate::DEFAULT_CHAPTER).unwrap());
+        let parts = splitter.split(&input, temp_dir.path(), &video_info, &format).unwrap();
+        assert_eq!(
+            parts,
+            [temp_dir.path().join("Video - 001 Intro [abc123].mp4"), temp_dir.path().join("Video - 002 Main [abc123].mp4")]
+        );
+        
+        let payloads = |path: &Path| -> Vec<String> {
+            fmp4::tests::read_fragments(path).into_iter().map(|(_, payload)| payload).collect()
+        };
+        assert_eq!(payloads(&parts[0]), ["F0", "F1"]);
+        assert_eq!(payloads(&parts[1]), ["F2", "F3", "F4"]);
+        
+        let tags = mp4::read_tags(&parts[1]).unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Main"));
+        assert_eq!(tags.album.as_deref(), Some("Video"));
+        assert_eq!(tags.track, Some((2, 2)));
+        assert!(input.exists());
+    }
+}
@@ -0,0 +1,456 @@
+//! Cutting MPEG transport streams at keyframes
+//!
+//! The stream is divided into units that start at random access points of the
+//! video stream, or at every audio PES when there is no video. Whole units are
+//! kept or dropped, and the PTS, DTS and PCR values of the kept units are
+//! shifted so the timeline stays continuous.
+
+use crate::error::DownloaderError;
+use crate::Result;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const PACKET_SIZE: usize = 188;
+const SYNC_BYTE: u8 = 0x47;
+const PAT_PID: u16 = 0;
+
+/// PTS, DTS and PCR base run at 90 kHz and wrap at 33 bits
+const CLOCK_RATE: f64 = 90_000.0;
+const TIMESTAMP_MODULUS: u64 = 1 << 33;
+
+type Packet = [u8; PACKET_SIZE];
+
+/// Elementary stream used to find unit boundaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reference {
+    H264,
+    Hevc,
+    OtherVideo,
+    Audio,
+}
+
+impl Reference {
+    fn from_stream_type(stream_type: u8) -> Option<Self> {
+        match stream_type {
+            0x1B => Some(Self::H264),
+            0x24 => Some(Self::Hevc),
+            0x01 | 0x02 => Some(Self::OtherVideo),
+            0x03 | 0x04 | 0x0F | 0x11 | 0x81 | 0x87 => Some(Self::Audio),
+            _ => None,
+        }
+    }
+}
+
+/// First packet index and presentation time of a unit
+struct Unit {
+    first_packet: u64,
+    pts: u64,
+}
+
+/// Whether `path` starts with transport stream packets
+pub fn is_transport_stream(path: &Path) -> Result<bool> {
+    let mut bytes = Vec::with_capacity(PACKET_SIZE * 3);
+    File::open(path)?.take((PACKET_SIZE * 3) as u64).read_to_end(&mut bytes)?;
+    Ok(!bytes.is_empty() && bytes.iter().step_by(PACKET_SIZE).all(|&byte| byte == SYNC_BYTE))
+}
+
+/// Copy the units of `input` that overlap `ranges` (in seconds) to `output`
+pub fn keep_ranges(input: &Path, output: &Path, ranges: &[(f64, f64)]) -> Result<()> {
+    let (tables, units) = scan(input)?;
+    let Some(origin) = units.first().map(|unit| unit.pts) else {
+        return Err(DownloaderError::Media(format!("No keyframes found in {}", input.display())));
+    };
+    
+    // Offset of each unit from the start, and its length up to the next unit.
+    // The last unit is assumed to be as long as the one before it.
+    let offsets: Vec<u64> = units.iter().map(|unit| wrapping_sub(unit.pts, origin)).collect();
+    let mut durations: Vec<Option<u64>> =
+        units.windows(2).map(|pair| Some(wrapping_sub(pair[1].pts, pair[0].pts))).collect();
+    durations.push(durations.last().copied().flatten());
+    
+    let keep: Vec<bool> = offsets
+        .iter()
+        .zip(&durations)
+        .map(|(&offset, duration)| {
+            let start = offset as f64 / CLOCK_RATE;
+            let end = duration.map_or(f64::INFINITY, |duration| start + duration as f64 / CLOCK_RATE);
+            ranges.iter().any(|&(from, to)| start < to && end > from)
+        })
+        .collect();
+    if !keep.contains(&true) {
+        return Err(DownloaderError::Media(format!("No keyframes of {} fall inside the requested ranges", input.display())));
+    }
+    
+    let mut writer = PacketWriter::new(File::create(output)?);
+    for table in &tables {
+        writer.write(*table)?;
+    }
+    
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut packet: Packet = [0; PACKET_SIZE];
+    let mut unit = None;
+    let mut shift = 0;
+    let mut position = 0u64;
+    
+    for index in 0.. {
+        if !read_packet(&mut reader, &mut packet)? {
+            break;
+        }
+        
+        let next = unit.map_or(0, |unit| unit + 1);
+        if units.get(next).is_some_and(|next| next.first_packet == index) {
+            unit = Some(next);
+            if keep[next] {
+                shift = wrapping_sub(offsets[next], position);
+                position += durations[next].unwrap_or(0);
+            }
+        }
+        
+        if unit.is_some_and(|unit| keep[unit]) {
+            retime(&mut packet, shift);
+            writer.write(packet)?;
+        }
+    }
+    
+    writer.finish()
+}
+
+/// Find the PAT and PMT and the start of every unit
+fn scan(input: &Path) -> Result<(Vec<Packet>, Vec<Unit>)> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut packet: Packet = [0; PACKET_SIZE];
+    let mut pat = None;
+    let mut pmt = None;
+    let mut pmt_pid = None;
+    let mut reference = None;
+    let mut units = Vec::new();
+    
+    for index in 0.. {
+        if !read_packet(&mut reader, &mut packet)? {
+            break;
+        }
+        let pid = packet_pid(&packet);
+        let Some(payload) = payload(&packet).filter(|_| payload_unit_start(&packet)) else {
+            continue;
+        };
+        
+        if pid == PAT_PID && pat.is_none() {
+            pmt_pid = program_map_pid(payload);
+            pat = Some(packet);
+        } else if Some(pid) == pmt_pid && pmt.is_none() {
+            reference = reference_stream(payload);
+            pmt = Some(packet);
+        } else if let Some((_, kind)) = reference.filter(|(reference_pid, _)| *reference_pid == pid) {
+            let starts_unit = match kind {
+                Reference::Audio => true,
+                _ => random_access(&packet) || is_keyframe(payload, kind),
+            };
+            if let Some(pts) = pes_timestamps(payload).map(|(pts, _)| pts).filter(|_| starts_unit) {
+                units.push(Unit { first_packet: index, pts });
+            }
+        }
+    }
+    
+    let tables: Vec<Packet> = pat.into_iter().chain(pmt).collect();
+    if tables.len() < 2 || reference.is_none() {
+        return Err(DownloaderError::Media(format!("No audio or video stream found in {}", input.display())));
+    }
+    Ok((tables, units))
+}
+
+/// Read the next packet, returning `false` at the end of the stream
+fn read_packet(reader: &mut impl Read, packet: &mut Packet) -> Result<bool> {
+    let mut filled = 0;
+    while filled < PACKET_SIZE {
+        match reader.read(&mut packet[filled..])? {
+            0 => return Ok(false),
+            read => filled += read,
+        }
+    }
+    if packet[0] != SYNC_BYTE {
+        return Err(DownloaderError::Media("Lost MPEG-TS packet sync".to_string()));
+    }
+    Ok(true)
+}
+
+/// Writes packets with continuity counters renumbered per PID
+struct PacketWriter {
+    writer: BufWriter<File>,
+    counters: HashMap<u16, u8>,
+}
+
+impl PacketWriter {
+    fn new(file: File) -> Self {
+        Self { writer: BufWriter::new(file), counters: HashMap::new() }
+    }
+    
+    fn write(&mut self, mut packet: Packet) -> Result<()> {
+        if has_payload(&packet) {
+            let counter = self.counters.entry(packet_pid(&packet)).or_insert(0);
+            packet[3] = (packet[3] & 0xF0) | *counter;
+            *counter = (*counter + 1) & 0x0F;
+        }
+        self.writer.write_all(&packet)?;
+        Ok(())
+    }
+    
+    fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn packet_pid(packet: &Packet) -> u16 {
+    u16::from_be_bytes([packet[1] & 0x1F, packet[2]])
+}
+
+fn payload_unit_start(packet: &Packet) -> bool {
+    packet[1] & 0x40 != 0
+}
+
+fn has_adaptation_field(packet: &Packet) -> bool {
+    packet[3] & 0x20 != 0
+}
+
+fn has_payload(packet: &Packet) -> bool {
+    packet[3] & 0x10 != 0
+}
+
+fn random_access(packet: &Packet) -> bool {
+    has_adaptation_field(packet) && packet[4] > 0 && packet[5] & 0x40 != 0
+}
+
+fn payload_offset(packet: &Packet) -> Option<usize> {
+    if !has_payload(packet) {
+        return None;
+    }
+    let offset = if has_adaptation_field(packet) { 5 + packet[4] as usize } else { 4 };
+    (offset < PACKET_SIZE).then_some(offset)
+}
+
+fn payload(packet: &Packet) -> Option<&[u8]> {
+    payload_offset(packet).map(|offset| &packet[offset..])
+}
+
+/// PSI section following the pointer field
+fn section(payload: &[u8], table_id: u8) -> Option<&[u8]> {
+    let section = payload.get(1 + *payload.first()? as usize..)?;
+    if *section.first()? != table_id {
+        return None;
+    }
+    let length = (u16::from_be_bytes([section[1] & 0x0F, *section.get(2)?]) as usize + 3).checked_sub(4)?;
+    section.get(..length)
+}
+
+/// PID of the first program's PMT
+fn program_map_pid(payload: &[u8]) -> Option<u16> {
+    let section = section(payload, 0x00)?;
+    section.get(8..)?.chunks_exact(4).find_map(|entry| {
+        let program = u16::from_be_bytes([entry[0], entry[1]]);
+        (program != 0).then(|| u16::from_be_bytes([entry[2] & 0x1F, entry[3]]))
+    })
+}
+
+/// The first video stream of the PMT, or the first audio stream without video
+fn reference_stream(payload: &[u8]) -> Option<(u16, Reference)> {
+    let section = section(payload, 0x02)?;
+    let program_info_length = u16::from_be_bytes([*section.get(10)? & 0x0F, *section.get(11)?]) as usize;
+    let mut streams = Vec::new();
+    let mut i = 12 + program_info_length;
+    while i + 5 <= section.len() {
+        let pid = u16::from_be_bytes([section[i + 1] & 0x1F, section[i + 2]]);
+        if let Some(kind) = Reference::from_stream_type(section[i]) {
+            streams.push((pid, kind));
+        }
+        i += 5 + u16::from_be_bytes([section[i + 3] & 0x0F, section[i + 4]]) as usize;
+    }
+    
+    streams
+        .iter()
+        .find(|(_, kind)| *kind != Reference::Audio)
+        .or_else(|| streams.first())
+        .copied()
+}
+
+/// Look for an IDR picture or parameter sets at the start of a video PES
+fn is_keyframe(payload: &[u8], kind: Reference) -> bool {
+    payload.windows(4).any(|window| {
+        if window[..3] != [0, 0, 1] {
+            return false;
+        }
+        match kind {
+            Reference::H264 => matches!(window[3] & 0x1F, 5 | 7),
+            Reference::Hevc => matches!((window[3] >> 1) & 0x3F, 16..=21 | 32..=34),
+            // MPEG-2 sequence header
+            Reference::OtherVideo => window[3] == 0xB3,
+            Reference::Audio => true,
+        }
+    })
+}
+
+/// Position of the PTS field of a PES header and whether a DTS follows it
+fn pes_timestamp_fields(payload: &[u8]) -> Option<(usize, bool)> {
+    if payload.get(..3)? != [0, 0, 1] {
+        return None;
+    }
+    // Streams without the optional PES header
+    if matches!(*payload.get(3)?, 0xBC | 0xBE | 0xBF | 0xF0 | 0xF1 | 0xF2 | 0xF8 | 0xFF) {
+        return None;
+    }
+    match *payload.get(7)? >> 6 {
+        0b10 if payload.len() >= 14 => Some((9, false)),
+        0b11 if payload.len() >= 19 => Some((9, true)),
+        _ => None,
+    }
+}
+
+fn pes_timestamps(payload: &[u8]) -> Option<(u64, Option<u64>)> {
+    let (offset, has_dts) = pes_timestamp_fields(payload)?;
+    let dts = has_dts.then(|| decode_timestamp(&payload[offset + 5..]));
+    Some((decode_timestamp(&payload[offset..]), dts))
+}
+
+fn decode_timestamp(bytes: &[u8]) -> u64 {
+    ((bytes[0] as u64 >> 1) & 0x07) << 30
+        | (bytes[1] as u64) << 22
+        | (bytes[2] as u64 >> 1) << 15
+        | (bytes[3] as u64) << 7
+        | bytes[4] as u64 >> 1
+}
+
+/// Replace a timestamp, keeping the prefix and marker bits
+fn encode_timestamp(bytes: &mut [u8], timestamp: u64) {
+    bytes[0] = (bytes[0] & 0xF1) | ((timestamp >> 29) as u8 & 0x0E);
+    bytes[1] = (timestamp >> 22) as u8;
+    bytes[2] = ((timestamp >> 14) as u8 & 0xFE) | (bytes[2] & 0x01);
+    bytes[3] = (timestamp >> 7) as u8;
+    bytes[4] = ((timestamp << 1) as u8 & 0xFE) | (bytes[4] & 0x01);
+}
+
+fn wrapping_sub(a: u64, b: u64) -> u64 {
+    (a + TIMESTAMP_MODULUS - b % TIMESTAMP_MODULUS) % TIMESTAMP_MODULUS
+}
+
+/// Move the PCR and PES timestamps of a packet `shift` ticks earlier
+fn retime(packet: &mut Packet, shift: u64) {
+    if shift == 0 {
+        return;
+    }
+    
+    // PCR: 33-bit base, 6 reserved bits and a 9-bit extension
+    if has_adaptation_field(packet) && packet[4] >= 7 && packet[5] & 0x10 != 0 {
+        let base = (packet[6] as u64) << 25
+            | (packet[7] as u64) << 17
+            | (packet[8] as u64) << 9
+            | (packet[9] as u64) << 1
+            | packet[10] as u64 >> 7;
+        let base = wrapping_sub(base, shift);
+        packet[6] = (base >> 25) as u8;
+        packet[7] = (base >> 17) as u8;
+        packet[8] = (base >> 9) as u8;
+        packet[9] = (base >> 1) as u8;
+        packet[10] = ((base as u8 & 0x01) << 7) | (packet[10] & 0x7F);
+    }
+    
+    if !payload_unit_start(packet) {
+        return;
+    }
+    let Some(start) = payload_offset(packet) else {
+        return;
+    };
+    if let Some((offset, has_dts)) = pes_timestamp_fields(&packet[start..]) {
+        let fields = if has_dts { 2 } else { 1 };
+        for field in (0..fields).map(|index| offset + index * 5) {
+            let bytes = &mut packet[start + field..start + field + 5];
+            encode_timestamp(bytes, wrapping_sub(decode_timestamp(bytes), shift));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const VIDEO_PID: u16 = 0x100;
+    const AUDIO_PID: u16 = 0x101;
+    const FIRST_PTS: u64 = 126_000;
+    
+    fn packet(pid: u16, start: bool, random_access: bool, body: &[u8]) -> Packet {
+        let mut packet = [0xFF; PACKET_SIZE];
+        packet[0] = SYNC_BYTE;
+        packet[1] = ((pid >> 8) as u8 & 0x1F) | if start { 0x40 } else { 0 };
+        packet[2] = pid as u8;
+        let mut offset = 4;
+        if random_access {
+            packet[3] = 0x30;
+            packet[4] = 1;
+            packet[5] = 0x40;
+            offset = 6;
+        } else {
+            packet[3] = 0x10;
+        }
+        packet[offset..offset + body.len()].copy_from_slice(body);
+        packet
+    }
+    
+    fn pes(stream_id: u8, pts: u64, data: &[u8]) -> Vec<u8> {
+        let mut pes = vec![0, 0, 1, stream_id, 0, 0, 0x80, 0x80, 5, 0x21, 0, 0, 0, 1];
+        encode_timestamp(&mut pes[9..14], pts);
+        pes.extend(data);
+        pes
+    }
+    
+    /// PAT, PMT and five two-second units of video and audio
+    fn sample_stream() -> Vec<u8> {
+        let pat = [0, 0x00, 0xB0, 13, 0, 1, 0xC1, 0, 0, 0, 1, 0xF0, 0x00, 0, 0, 0, 0];
+        let pmt = [
+            0, 0x02, 0xB0, 23, 0, 1, 0xC1, 0, 0, 0xE1, 0x00, 0xF0, 0x00, 0x1B, 0xE1, 0x00, 0xF0, 0x00, 0x0F, 0xE1,
+            0x01, 0xF0, 0x00, 0, 0, 0, 0,
+        ];
+        let mut stream = packet(PAT_PID, true, false, &pat).to_vec();
+        stream.extend(packet(0x1000, true, false, &pmt));
+        
+        for unit in 0..5u64 {
+            let pts = FIRST_PTS + unit * 180_000;
+            stream.extend(packet(VIDEO_PID, true, true, &pes(0xE0, pts, &[0, 0, 0, 1, 0x65, unit as u8])));
+            stream.extend(packet(VIDEO_PID, false, false, &[unit as u8]));
+            stream.extend(packet(AUDIO_PID, true, false, &pes(0xC0, pts, &[unit as u8])));
+        }
+        stream
+    }
+    
+    fn video_timestamps(bytes: &[u8]) -> Vec<u64> {
+        bytes
+            .chunks_exact(PACKET_SIZE)
+            .map(|chunk| <Packet>::try_from(chunk).unwrap())
+            .filter(|packet| packet_pid(packet) == VIDEO_PID && payload_unit_start(packet))
+            .filter_map(|packet| pes_timestamps(payload(&packet)?).map(|(pts, _)| pts))
+            .collect()
+    }
+    
+    #[test]
+    fn test_keep_ranges_retimes_units() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let input = temp_dir.path().join("input.ts");
+        let output = temp_dir.path().join("output.ts");
+        std::fs::write(&input, sample_stream()).unwrap();
+        assert!(is_transport_stream(&input).unwrap());
+        
+        keep_ranges(&input, &output, &[(2.5, 5.0)]).unwrap();
+        let bytes = std::fs::read(&output).unwrap();
+        assert_eq!(bytes.len(), PACKET_SIZE * (2 + 2 * 3));
+        assert_eq!(video_timestamps(&bytes), [FIRST_PTS, FIRST_PTS + 180_000]);
+        
+        keep_ranges(&input, &output, &[(0.0, 2.0), (8.0, 10.0)]).unwrap();
+        let bytes = std::fs::read(&output).unwrap();
+        assert_eq!(video_timestamps(&bytes), [FIRST_PTS, FIRST_PTS + 180_000]);
+        // The second kept unit is the last one, with continuity counters renumbered
+        let packets: Vec<&[u8]> = bytes.chunks_exact(PACKET_SIZE).collect();
+        assert_eq!(packets[6][4..], packet(VIDEO_PID, false, false, &[4])[4..]);
+        assert_eq!(packets[6][3] & 0x0F, 3);
+        
+        assert!(!is_transport_stream(Path::new(file!())).unwrap());
+    }
+}
@@ -0,0 +1,120 @@
+//! ID3v2.4 tags for MP3 files
+
+use crate::error::DownloaderError;
+use crate::metadata::{CoverArt, Tags};
+use crate::Result;
+use id3::frame::{Comment, ExtendedLink, Picture, PictureType};
+use id3::{Tag, TagLike, Timestamp, Version};
+use std::path::Path;
+
+/// Description of the WXXX frame holding the source URL
+const URL_DESCRIPTION: &str = "purl";
+
+/// Write tags to an MP3 file, keeping unrelated frames of an existing tag
+pub fn write_tags(path: &Path, tags: &Tags) -> Result<()> {
+    let mut tag = id3::no_tag_ok(Tag::read_from_path(path))
+        .map_err(metadata_error)?
+        .unwrap_or_default();
+    
+    if let Some(ref title) = tags.title {
+        tag.set_title(title.as_str());
+    }
+    if let Some(ref artist) = tags.artist {
+        tag.set_artist(artist.as_str());
+    }
+    if let Some(timestamp) = tags.date.as_deref().and_then(|date| date.parse::<Timestamp>().ok()) {
+        tag.set_date_recorded(timestamp);
+    }
+    if let Some(ref description) = tags.description {
+        tag.remove("COMM");
+        tag.add_frame(Comment {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: description.clone(),
+        });
+    }
+    if let Some(ref url) = tags.url {
+        tag.remove("WXXX");
+        tag.add_frame(ExtendedLink {
+            description: URL_DESCRIPTION.to_string(),
+            link: url.clone(),
+        });
+    }
+    if let Some(ref album) = tags.album {
+        tag.set_album(album.as_str());
+    }
+    if let Some((number, total)) = tags.track {
+        tag.set_track(number);
+        tag.set_total_tracks(total);
+    }
+    if let Some(ref cover) = tags.cover {
+        tag.remove_picture_by_type(PictureType::CoverFront);
+        tag.add_frame(Picture {
+            mime_type: cover.format.mime_type().to_string(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: cover.data.clone(),
+        });
+    }
+    
+    tag.write_to_path(path, Version::Id3v24).map_err(metadata_error)
+}
+
+/// Read tags back from an MP3 file
+pub fn read_tags(path: &Path) -> Result<Tags> {
+    let Some(tag) = id3::no_tag_ok(Tag::read_from_path(path)).map_err(metadata_error)? else {
+        return Ok(Tags::default());
+    };
+    
+    let tags = Tags {
+        title: tag.title().map(str::to_string),
+        artist: tag.artist().map(str::to_string),
+        date: tag.date_recorded().map(|timestamp| timestamp.to_string()),
+        description: tag.comments().next().map(|comment| comment.text.clone()),
+        url: tag
+            .extended_links()
+            .find(|link| link.description == URL_DESCRIPTION)
+            .map(|link| link.link.clone()),
+        album: tag.album().map(str::to_string),
+        track: tag.track().map(|number| (number, tag.total_tracks().unwrap_or(number))),
+        cover: tag
+            .pictures()
+            .find(|picture| picture.picture_type == PictureType::CoverFront)
+            .and_then(|picture| CoverArt::from_bytes(picture.data.clone())),
+    };
+    Ok(tags)
+}
+
+fn metadata_error(e: id3::Error) -> DownloaderError {
+    DownloaderError::Metadata(format!("ID3 tag error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_write_and_read_back() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("audio.mp3");
+        // A single silent MPEG-1 Layer III frame header followed by padding
+        let mut audio = vec![0xFF, 0xFB, 0x90, 0x64];
+        audio.resize(417, 0);
+        std::fs::write(&path, &audio).unwrap();
+        
+        let tags = Tags {
+            title: Some("Title".to_string()),
+            artist: Some("Uploader".to_string()),
+            date: Some("2009-10-25".to_string()),
+            description: Some("Description".to_string()),
+            url: Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string()),
+            album: Some("Album".to_string()),
+            track: Some((2, 5)),
+            cover: CoverArt::from_bytes(b"\x89PNG\r\n\x1a\nimage".to_vec()),
+        };
+        write_tags(&path, &tags).unwrap();
+        
+        assert_eq!(read_tags(&path).unwrap(), tags);
+        assert!(std::fs::read(&path).unwrap().ends_with(&audio));
+    }
+}
@@ -0,0 +1,153 @@
+//! Metadata tagging of downloaded files
+//!
+//! Tags are written in place for MP4/M4A (iTunes `ilst` atoms), MP3 (ID3v2)
+//! and Ogg Opus/Vorbis (Vorbis comments); other containers are left untouched.
+
+pub mod id3v2;
+pub mod mp4;
+pub mod subtitles;
+pub mod vorbis;
+
+pub use subtitles::{Cue, Subtitle, VttParser};
+
+use crate::models::{Chapter, VideoInfo};
+use crate::Result;
+use log::debug;
+use std::path::Path;
+
+/// Image format of embedded cover art
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+}
+
+impl ImageFormat {
+    /// Detect the image format from its magic bytes
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else {
+            None
+        }
+    }
+    
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverArt {
+    pub format: ImageFormat,
+    pub data: Vec<u8>,
+}
+
+impl CoverArt {
+    /// Wrap downloaded thumbnail bytes, or `None` if they are not JPEG or PNG
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        ImageFormat::detect(&data).map(|format| Self { format, data })
+    }
+}
+
+/// Tags written to an output file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Release date as `YYYY-MM-DD` (or just `YYYY`)
+    pub date: Option<String>,
+    pub description: Option<String>,
+    /// Web page the media was downloaded from
+    pub url: Option<String>,
+    pub album: Option<String>,
+    /// Track number and total number of tracks
+    pub track: Option<(u32, u32)>,
+    pub cover: Option<CoverArt>,
+}
+
+impl Tags {
+    /// Build tags from extracted video information
+    pub fn from_video_info(video_info: &VideoInfo) -> Self {
+        let date = video_info.upload_date.as_deref().map(|date| match date.len() {
+            8 => format!("{}-{}-{}", &date[0..4], &date[4..6], &date[6..8]),
+            _ => date.to_string(),
+        });
+        
+        Self {
+            title: Some(video_info.title.clone()),
+            artist: video_info.uploader.clone(),
+            date,
+            description: video_info.description.clone(),
+            url: Some(format!("https://www.youtube.com/watch?v={}", video_info.video_id)),
+            album: None,
+            track: None,
+            cover: None,
+        }
+    }
+    
+    pub fn with_cover(mut self, cover: Option<CoverArt>) -> Self {
+        self.cover = cover;
+        self
+    }
+}
+
+pub struct MetadataWriter;
+
+impl MetadataWriter {
+    /// Write tags to `path` based on its extension.
+    ///
+    /// Returns `false` when the container is not supported for tagging.
+    pub fn write(path: &Path, tags: &Tags) -> Result<bool> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        
+        match extension.as_str() {
+            "mp4" | "m4a" | "m4v" | "mov" => mp4::write_tags(path, tags)?,
+            "mp3" => id3v2::write_tags(path, tags)?,
+            "ogg" | "opus" | "oga" => vorbis::write_tags(path, tags)?,
+            _ => {
+                debug!("Tagging not supported for {}", path.display());
+                return Ok(false);
+            }
+        }
+        
+        debug!("Wrote metadata tags to {}", path.display());
+        Ok(true)
+    }
+    
+    /// Embed chapters and caption tracks into `path`.
+    ///
+    /// Only MP4 containers are supported; returns `false` for anything else.
+    pub fn embed_chapters_and_subtitles(path: &Path, chapters: &[Chapter], subtitles: &[Subtitle]) -> Result<bool> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        
+        match extension.as_str() {
+            "mp4" | "m4a" | "m4v" | "mov" => mp4::embed_chapters_and_subtitles(path, chapters, subtitles)?,
+            _ => {
+                debug!("Embedding chapters and subtitles not supported for {}", path.display());
+                return Ok(false);
+            }
+        }
+        
+        debug!(
+            "Embedded {} chapters and {} subtitle tracks into {}",
+            chapters.len(),
+            subtitles.len(),
+            path.display()
+        );
+        Ok(true)
+    }
+}
@@ -0,0 +1,845 @@
+//! iTunes-style metadata (`moov/udta/meta/ilst`) for MP4/M4A files
+//!
+//! Only the `moov` box is loaded into memory. The rest of the file is copied
+//! around it, and chunk offsets are shifted when `moov` comes before the media data.
+
+use crate::error::DownloaderError;
+use crate::metadata::{CoverArt, ImageFormat, Subtitle, Tags};
+use crate::models::Chapter;
+use crate::Result;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Boxes whose payload is a list of child boxes
+const CONTAINERS: &[&[u8; 4]] = &[
+    b"moov", b"trak", b"mdia", b"minf", b"stbl", b"udta", b"edts", b"meta", b"ilst", b"mvex", b"moof", b"traf",
+];
+
+/// `data` atom type indicators
+const TYPE_IMPLICIT: u32 = 0;
+const TYPE_UTF8: u32 = 1;
+const TYPE_JPEG: u32 = 13;
+const TYPE_PNG: u32 = 14;
+
+const TITLE: &[u8; 4] = b"\xa9nam";
+const ARTIST: &[u8; 4] = b"\xa9ART";
+const DATE: &[u8; 4] = b"\xa9day";
+const DESCRIPTION: &[u8; 4] = b"desc";
+const URL: &[u8; 4] = b"purl";
+const ALBUM: &[u8; 4] = b"\xa9alb";
+const TRACK: &[u8; 4] = b"trkn";
+const COVER: &[u8; 4] = b"covr";
+
+/// `tfhd` flag for an explicit base data offset
+const BASE_DATA_OFFSET_PRESENT: u32 = 0x1;
+
+/// Nero chapter times are in 100ns units
+const CHAPTER_TIMESCALE: f64 = 10_000_000.0;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Atom {
+    pub(crate) kind: [u8; 4],
+    /// Version and flags of full boxes (`meta`)
+    prefix: Vec<u8>,
+    pub(crate) data: Vec<u8>,
+    pub(crate) children: Vec<Atom>,
+}
+
+impl Atom {
+    pub(crate) fn leaf(kind: &[u8; 4], data: Vec<u8>) -> Self {
+        Self { kind: *kind, prefix: Vec::new(), data, children: Vec::new() }
+    }
+    
+    pub(crate) fn container(kind: &[u8; 4], children: Vec<Atom>) -> Self {
+        Self { kind: *kind, prefix: Vec::new(), data: Vec::new(), children }
+    }
+    
+    fn is_container(kind: &[u8; 4]) -> bool {
+        CONTAINERS.contains(&kind)
+    }
+    
+    pub(crate) fn child(&self, kind: &[u8; 4]) -> Option<&Atom> {
+        self.children.iter().find(|atom| &atom.kind == kind)
+    }
+    
+    /// Find a child box, appending `create()` if it does not exist
+    fn child_or_insert(&mut self, kind: &[u8; 4], create: impl FnOnce() -> Atom) -> &mut Atom {
+        let index = match self.children.iter().position(|atom| &atom.kind == kind) {
+            Some(index) => index,
+            None => {
+                self.children.push(create());
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+    
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut body = self.prefix.clone();
+        if Self::is_container(&self.kind) {
+            for child in &self.children {
+                body.extend(child.to_bytes());
+            }
+        } else {
+            body.extend(&self.data);
+        }
+        
+        let mut bytes = Vec::with_capacity(body.len() + 8);
+        bytes.extend(((body.len() + 8) as u32).to_be_bytes());
+        bytes.extend(self.kind);
+        bytes.extend(body);
+        bytes
+    }
+}
+
+/// Location of a top-level box in the file
+pub(crate) struct TopLevelBox {
+    pub(crate) kind: [u8; 4],
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+}
+
+/// Write tags into the `ilst` of an MP4 file, replacing items that are set
+pub fn write_tags(path: &Path, tags: &Tags) -> Result<()> {
+    rewrite_moov(path, &[], |moov, _| {
+        set_items(moov, tags);
+        Ok(())
+    })
+}
+
+/// Embed chapters as a Nero `chpl` list and captions as `tx3g` text tracks
+pub fn embed_chapters_and_subtitles(path: &Path, chapters: &[Chapter], subtitles: &[Subtitle]) -> Result<()> {
+    let samples: Vec<SubtitleSamples> = subtitles.iter().map(SubtitleSamples::from_subtitle).collect();
+    let sample_data: Vec<u8> = samples.iter().flat_map(|s| s.data.iter().copied()).collect();
+    
+    rewrite_moov(path, &sample_data, |moov, data_offset| {
+        if !chapters.is_empty() {
+            let udta = moov.child_or_insert(b"udta", || Atom::container(b"udta", Vec::new()));
+            udta.children.retain(|atom| &atom.kind != b"chpl");
+            udta.children.push(chapter_list(chapters));
+        }
+        
+        let (movie_timescale, mut next_track_id) = movie_header(moov)?;
+        let mut chunk_offset = data_offset;
+        for (index, (subtitle, samples)) in subtitles.iter().zip(&samples).enumerate() {
+            let track = subtitle_track(next_track_id, movie_timescale, &subtitle.language, index == 0, chunk_offset, samples);
+            moov.children.push(track);
+            next_track_id += 1;
+            chunk_offset += samples.data.len() as u64;
+        }
+        set_next_track_id(moov, next_track_id);
+        Ok(())
+    })
+}
+
+/// Replace the `moov` box of `path` with the result of `update`.
+///
+/// `extra_media` is appended in a new `mdat` at the end of the file; `update`
+/// receives the offset at which its payload will start.
+fn rewrite_moov<F>(path: &Path, extra_media: &[u8], update: F) -> Result<()>
+where
+    F: Fn(&mut Atom, u64) -> Result<()>,
+{
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let boxes = scan_top_level(&mut file)?;
+    let moov_box = find_moov(&boxes, path)?;
+    let moov_end = moov_box.offset + moov_box.size;
+    let mut moov = read_moov(&mut file, moov_box)?;
+    
+    // The size of the new moov does not depend on the offsets written into it
+    let mut measured = moov.clone();
+    update(&mut measured, 0)?;
+    let delta = measured.to_bytes().len() as i64 - moov_box.size as i64;
+    
+    // Media data stored after moov moves by the change in its size
+    if delta != 0 && boxes.iter().any(|b| &b.kind == b"mdat" && b.offset >= moov_end) {
+        shift_chunk_offsets(&mut moov, moov_end, delta)?;
+    }
+    let extra_offset = (file_len as i64 + delta) as u64 + 8;
+    update(&mut moov, extra_offset)?;
+    let new_moov = moov.to_bytes();
+    
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("output");
+    let temp_path = path.with_file_name(format!("{}.tagging", file_name));
+    let result = (|| -> Result<()> {
+        let mut output = File::create(&temp_path)?;
+        file.seek(SeekFrom::Start(0))?;
+        io::copy(&mut (&mut file).take(moov_box.offset), &mut output)?;
+        output.write_all(&new_moov)?;
+        for top in boxes.iter().filter(|b| b.offset >= moov_end) {
+            file.seek(SeekFrom::Start(top.offset))?;
+            if &top.kind == b"moof" && delta != 0 {
+                // Movie fragments may address their media by absolute offset
+                let mut bytes = vec![0u8; top.size as usize];
+                file.read_exact(&mut bytes)?;
+                for mut moof in parse_atoms(&bytes)? {
+                    shift_base_data_offsets(&mut moof, delta);
+                    output.write_all(&moof.to_bytes())?;
+                }
+            } else {
+                io::copy(&mut (&mut file).take(top.size), &mut output)?;
+            }
+        }
+        if !extra_media.is_empty() {
+            output.write_all(&Atom::leaf(b"mdat", extra_media.to_vec()).to_bytes())?;
+        }
+        output.sync_all()?;
+        Ok(())
+    })();
+    
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Read the iTunes metadata items of an MP4 file
+pub fn read_tags(path: &Path) -> Result<Tags> {
+    let mut file = File::open(path)?;
+    let boxes = scan_top_level(&mut file)?;
+    let moov = read_moov(&mut file, find_moov(&boxes, path)?)?;
+    
+    let mut tags = Tags::default();
+    let ilst = moov
+        .child(b"udta")
+        .and_then(|udta| udta.child(b"meta"))
+        .and_then(|meta| meta.child(b"ilst"));
+    let Some(ilst) = ilst else {
+        return Ok(tags);
+    };
+    
+    for item in &ilst.children {
+        let Some((data_type, value)) = parse_data_atom(&item.data) else {
+            continue;
+        };
+        let text = || String::from_utf8_lossy(value).into_owned();
+        match &item.kind {
+            TITLE => tags.title = Some(text()),
+            ARTIST => tags.artist = Some(text()),
+            DATE => tags.date = Some(text()),
+            DESCRIPTION => tags.description = Some(text()),
+            URL => tags.url = Some(text()),
+            ALBUM => tags.album = Some(text()),
+            TRACK if value.len() >= 6 => {
+                let number = u16::from_be_bytes([value[2], value[3]]) as u32;
+                let total = u16::from_be_bytes([value[4], value[5]]) as u32;
+                tags.track = Some((number, total));
+            }
+            COVER => {
+                let format = match data_type {
+                    TYPE_PNG => ImageFormat::Png,
+                    _ => ImageFormat::Jpeg,
+                };
+                tags.cover = Some(CoverArt { format, data: value.to_vec() });
+            }
+            _ => {}
+        }
+    }
+    
+    Ok(tags)
+}
+
+fn find_moov<'a>(boxes: &'a [TopLevelBox], path: &Path) -> Result<&'a TopLevelBox> {
+    boxes
+        .iter()
+        .find(|b| &b.kind == b"moov")
+        .ok_or_else(|| DownloaderError::Metadata(format!("No moov box in {}", path.display())))
+}
+
+pub(crate) fn scan_top_level(file: &mut File) -> Result<Vec<TopLevelBox>> {
+    let file_len = file.metadata()?.len();
+    let mut boxes = Vec::new();
+    let mut offset = 0u64;
+    
+    while offset + 8 <= file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        
+        let kind = [header[4], header[5], header[6], header[7]];
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            0 => file_len - offset,
+            1 => {
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large)?;
+                u64::from_be_bytes(large)
+            }
+            size => size as u64,
+        };
+        
+        if size < 8 || offset + size > file_len {
+            return Err(DownloaderError::Metadata(format!(
+                "Malformed MP4 box '{}' at offset {}",
+                String::from_utf8_lossy(&kind),
+                offset
+            )));
+        }
+        
+        boxes.push(TopLevelBox { kind, offset, size });
+        offset += size;
+    }
+    
+    Ok(boxes)
+}
+
+pub(crate) fn read_moov(file: &mut File, moov: &TopLevelBox) -> Result<Atom> {
+    let mut bytes = vec![0u8; moov.size as usize];
+    file.seek(SeekFrom::Start(moov.offset))?;
+    file.read_exact(&mut bytes)?;
+    
+    parse_atoms(&bytes)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DownloaderError::Metadata("Empty moov box".to_string()))
+}
+
+pub(crate) fn parse_atoms(mut bytes: &[u8]) -> Result<Vec<Atom>> {
+    let malformed = || DownloaderError::Metadata("Malformed MP4 box structure".to_string());
+    let mut atoms = Vec::new();
+    
+    while bytes.len() >= 8 {
+        let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        let kind = [bytes[4], bytes[5], bytes[6], bytes[7]];
+        let size = if size == 0 { bytes.len() } else { size };
+        if size < 8 || size > bytes.len() {
+            return Err(malformed());
+        }
+        
+        let body = &bytes[8..size];
+        let atom = if Atom::is_container(&kind) {
+            // ISO `meta` is a full box, QuickTime `meta` is not
+            let prefix_len = if &kind == b"meta" && body.get(4..8) != Some(b"hdlr") { 4 } else { 0 };
+            if body.len() < prefix_len {
+                return Err(malformed());
+            }
+            Atom {
+                kind,
+                prefix: body[..prefix_len].to_vec(),
+                data: Vec::new(),
+                children: parse_atoms(&body[prefix_len..])?,
+            }
+        } else {
+            Atom::leaf(&kind, body.to_vec())
+        };
+        
+        atoms.push(atom);
+        bytes = &bytes[size..];
+    }
+    
+    Ok(atoms)
+}
+
+fn set_items(moov: &mut Atom, tags: &Tags) {
+    let udta = moov.child_or_insert(b"udta", || Atom::container(b"udta", Vec::new()));
+    let meta = udta.child_or_insert(b"meta", || {
+        let mut meta = Atom::container(b"meta", vec![metadata_handler()]);
+        meta.prefix = vec![0; 4];
+        meta
+    });
+    if meta.child(b"hdlr").is_none() {
+        meta.children.insert(0, metadata_handler());
+    }
+    let ilst = meta.child_or_insert(b"ilst", || Atom::container(b"ilst", Vec::new()));
+    
+    let text_items = [
+        (TITLE, &tags.title),
+        (ARTIST, &tags.artist),
+        (DATE, &tags.date),
+        (DESCRIPTION, &tags.description),
+        (URL, &tags.url),
+        (ALBUM, &tags.album),
+    ];
+    for (kind, value) in text_items {
+        if let Some(value) = value {
+            set_item(ilst, kind, TYPE_UTF8, value.as_bytes());
+        }
+    }
+    
+    if let Some((number, total)) = tags.track {
+        let mut value = vec![0, 0];
+        value.extend((number.min(u16::MAX as u32) as u16).to_be_bytes());
+        value.extend((total.min(u16::MAX as u32) as u16).to_be_bytes());
+        value.extend([0, 0]);
+        set_item(ilst, TRACK, TYPE_IMPLICIT, &value);
+    }
+    
+    if let Some(ref cover) = tags.cover {
+        let data_type = match cover.format {
+            ImageFormat::Jpeg => TYPE_JPEG,
+            ImageFormat::Png => TYPE_PNG,
+        };
+        set_item(ilst, COVER, data_type, &cover.data);
+    }
+}
+
+fn set_item(ilst: &mut Atom, kind: &[u8; 4], data_type: u32, value: &[u8]) {
+    let mut data = Vec::with_capacity(value.len() + 8);
+    data.extend(data_type.to_be_bytes());
+    data.extend(0u32.to_be_bytes()); // locale
+    data.extend(value);
+    
+    ilst.children.retain(|item| &item.kind != kind);
+    ilst.children.push(Atom::leaf(kind, Atom::leaf(b"data", data).to_bytes()));
+}
+
+/// `hdlr` box marking `meta` as iTunes metadata
+fn metadata_handler() -> Atom {
+    let mut data = vec![0; 8]; // version/flags, pre_defined
+    data.extend(b"mdir");
+    data.extend(b"appl");
+    data.extend([0; 8]); // reserved
+    data.push(0); // empty name
+    Atom::leaf(b"hdlr", data)
+}
+
+/// Return the type indicator and value of the `data` atom inside an item
+fn parse_data_atom(item: &[u8]) -> Option<(u32, &[u8])> {
+    let size = u32::from_be_bytes(item.get(0..4)?.try_into().ok()?) as usize;
+    if item.get(4..8)? != b"data" || size < 16 || size > item.len() {
+        return None;
+    }
+    let data_type = u32::from_be_bytes(item[8..12].try_into().ok()?) & 0x00FF_FFFF;
+    Some((data_type, &item[16..size]))
+}
+
+/// Move `stco`/`co64` entries pointing past `after` by `delta` bytes
+fn shift_chunk_offsets(atom: &mut Atom, after: u64, delta: i64) -> Result<()> {
+    let overflow = || DownloaderError::Metadata("Chunk offset out of range after tagging".to_string());
+    
+    match &atom.kind {
+        b"stco" => {
+            for entry in atom.data.get_mut(8..).unwrap_or_default().chunks_exact_mut(4) {
+                let offset = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]) as u64;
+                if offset >= after {
+                    let shifted = offset.checked_add_signed(delta).ok_or_else(overflow)?;
+                    let shifted = u32::try_from(shifted).map_err(|_| overflow())?;
+                    entry.copy_from_slice(&shifted.to_be_bytes());
+                }
+            }
+        }
+        b"co64" => {
+            for entry in atom.data.get_mut(8..).unwrap_or_default().chunks_exact_mut(8) {
+                let offset = u64::from_be_bytes(entry.try_into().map_err(|_| overflow())?);
+                if offset >= after {
+                    let shifted = offset.checked_add_signed(delta).ok_or_else(overflow)?;
+                    entry.copy_from_slice(&shifted.to_be_bytes());
+                }
+            }
+        }
+        _ => {
+            for child in &mut atom.children {
+                shift_chunk_offsets(child, after, delta)?;
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Move the explicit `tfhd` base data offsets of a movie fragment by `delta` bytes
+pub(crate) fn shift_base_data_offsets(moof: &mut Atom, delta: i64) {
+    for traf in moof.children.iter_mut().filter(|atom| &atom.kind == b"traf") {
+        for tfhd in traf.children.iter_mut().filter(|atom| &atom.kind == b"tfhd") {
+            let explicit = read_u32(&tfhd.data, 0).is_some_and(|flags| flags & BASE_DATA_OFFSET_PRESENT != 0);
+            if let Some(field) = tfhd.data.get_mut(8..16).filter(|_| explicit) {
+                let offset = u64::from_be_bytes(field.try_into().unwrap());
+                field.copy_from_slice(&offset.saturating_add_signed(delta).to_be_bytes());
+            }
+        }
+    }
+}
+
+/// Read the chapter titles and start times (in seconds) of a Nero `chpl` list
+pub fn read_chapters(path: &Path) -> Result<Vec<(String, f64)>> {
+    let mut file = File::open(path)?;
+    let boxes = scan_top_level(&mut file)?;
+    let moov = read_moov(&mut file, find_moov(&boxes, path)?)?;
+    
+    let Some(chpl) = moov.child(b"udta").and_then(|udta| udta.child(b"chpl")) else {
+        return Ok(Vec::new());
+    };
+    
+    let mut chapters = Vec::new();
+    let mut rest = chpl.data.get(9..).unwrap_or_default();
+    while rest.len() >= 9 {
+        let start = u64::from_be_bytes(rest[..8].try_into().unwrap_or_default());
+        let len = rest[8] as usize;
+        let Some(title) = rest.get(9..9 + len) else {
+            break;
+        };
+        chapters.push((String::from_utf8_lossy(title).into_owned(), start as f64 / CHAPTER_TIMESCALE));
+        rest = &rest[9 + len..];
+    }
+    Ok(chapters)
+}
+
+/// Nero chapter list: start times in 100ns units followed by length-prefixed titles
+fn chapter_list(chapters: &[Chapter]) -> Atom {
+    let count = chapters.len().min(u8::MAX as usize);
+    let mut data = vec![1, 0, 0, 0]; // version 1
+    data.extend(0u32.to_be_bytes()); // reserved
+    data.push(count as u8);
+    
+    for chapter in &chapters[..count] {
+        let mut title_len = chapter.title.len().min(u8::MAX as usize);
+        while !chapter.title.is_char_boundary(title_len) {
+            title_len -= 1;
+        }
+        data.extend(((chapter.start * CHAPTER_TIMESCALE).round() as u64).to_be_bytes());
+        data.push(title_len as u8);
+        data.extend(&chapter.title.as_bytes()[..title_len]);
+    }
+    
+    Atom::leaf(b"chpl", data)
+}
+
+/// Movie timescale and the next free track ID
+fn movie_header(moov: &Atom) -> Result<(u32, u32)> {
+    let missing = || DownloaderError::Metadata("Missing or malformed mvhd box".to_string());
+    let mvhd = moov.child(b"mvhd").ok_or_else(missing)?;
+    let timescale_offset = if mvhd.data.first() == Some(&1) { 20 } else { 12 };
+    let timescale = read_u32(&mvhd.data, timescale_offset).ok_or_else(missing)?;
+    
+    let max_track_id = moov
+        .children
+        .iter()
+        .filter(|atom| &atom.kind == b"trak")
+        .filter_map(|trak| trak.child(b"tkhd"))
+        .filter_map(|tkhd| read_u32(&tkhd.data, if tkhd.data.first() == Some(&1) { 20 } else { 12 }))
+        .max()
+        .unwrap_or(0);
+    
+    Ok((timescale.max(1), max_track_id + 1))
+}
+
+fn set_next_track_id(moov: &mut Atom, next_track_id: u32) {
+    if let Some(mvhd) = moov.children.iter_mut().find(|atom| &atom.kind == b"mvhd") {
+        let offset = if mvhd.data.first() == Some(&1) { 108 } else { 96 };
+        if let Some(field) = mvhd.data.get_mut(offset..offset + 4) {
+            field.copy_from_slice(&next_track_id.to_be_bytes());
+        }
+    }
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Encoded `tx3g` samples of one caption track, with empty samples filling the gaps
+struct SubtitleSamples {
+    data: Vec<u8>,
+    sizes: Vec<u32>,
+    /// Sample durations in milliseconds
+    durations: Vec<u32>,
+}
+
+impl SubtitleSamples {
+    fn from_subtitle(subtitle: &Subtitle) -> Self {
+        let mut samples = Self { data: Vec::new(), sizes: Vec::new(), durations: Vec::new() };
+        let mut position = 0u64;
+        
+        for cue in &subtitle.cues {
+            let start = (cue.start * 1000.0).round() as u64;
+            let end = (cue.end * 1000.0).round() as u64;
+            if end <= start || end <= position {
+                continue;
+            }
+            if start > position {
+                samples.push("", start - position);
+            }
+            let start = start.max(position);
+            samples.push(&cue.text, end - start);
+            position = end;
+        }
+        
+        samples
+    }
+    
+    fn push(&mut self, text: &str, duration_ms: u64) {
+        let text = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+        self.data.extend((text.len() as u16).to_be_bytes());
+        self.data.extend(text);
+        self.sizes.push(text.len() as u32 + 2);
+        self.durations.push(duration_ms.min(u32::MAX as u64) as u32);
+    }
+    
+    fn duration_ms(&self) -> u64 {
+        self.durations.iter().map(|&d| d as u64).sum()
+    }
+}
+
+/// Build a `trak` holding captions as `tx3g` samples stored in one chunk at `chunk_offset`
+fn subtitle_track(
+    track_id: u32,
+    movie_timescale: u32,
+    language: &str,
+    enabled: bool,
+    chunk_offset: u64,
+    samples: &SubtitleSamples,
+) -> Atom {
+    let duration_ms = samples.duration_ms();
+    let movie_duration = (duration_ms * movie_timescale as u64 / 1000).min(u32::MAX as u64) as u32;
+    
+    // Track header: in movie (+ enabled), alternate group 2 shared by all caption tracks
+    let mut tkhd = vec![0, 0, 0, if enabled { 3 } else { 2 }];
+    tkhd.extend([0u8; 8]); // creation and modification time
+    tkhd.extend(track_id.to_be_bytes());
+    tkhd.extend([0u8; 4]);
+    tkhd.extend(movie_duration.to_be_bytes());
+    tkhd.extend([0u8; 8]);
+    tkhd.extend(0u16.to_be_bytes()); // layer
+    tkhd.extend(2u16.to_be_bytes()); // alternate group
+    tkhd.extend([0u8; 4]); // volume, reserved
+    for value in [0x0001_0000u32, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000] {
+        tkhd.extend(value.to_be_bytes());
+    }
+    tkhd.extend([0u8; 8]); // width, height
+    
+    let mut mdhd = vec![0u8; 12]; // version/flags, creation and modification time
+    mdhd.extend(1000u32.to_be_bytes());
+    mdhd.extend((duration_ms.min(u32::MAX as u64) as u32).to_be_bytes());
+    mdhd.extend(packed_language(language).to_be_bytes());
+    mdhd.extend([0u8; 2]);
+    
+    let mut hdlr = vec![0u8; 8];
+    hdlr.extend(b"sbtl");
+    hdlr.extend([0u8; 12]);
+    hdlr.extend(b"SubtitleHandler\0");
+    
+    let mut dref = vec![0u8; 4];
+    dref.extend(1u32.to_be_bytes());
+    dref.extend(Atom::leaf(b"url ", vec![0, 0, 0, 1]).to_bytes()); // media in the same file
+    
+    let stbl = Atom::container(
+        b"stbl",
+        vec![
+            Atom::leaf(b"stsd", text_sample_description()),
+            Atom::leaf(b"stts", time_to_sample(&samples.durations)),
+            Atom::leaf(b"stsc", {
+                let mut stsc = vec![0u8; 4];
+                for value in [1u32, 1, samples.sizes.len() as u32, 1] {
+                    stsc.extend(value.to_be_bytes());
+                }
+                stsc
+            }),
+            Atom::leaf(b"stsz", {
+                let mut stsz = vec![0u8; 8]; // version/flags, no constant size
+                stsz.extend((samples.sizes.len() as u32).to_be_bytes());
+                samples.sizes.iter().for_each(|size| stsz.extend(size.to_be_bytes()));
+                stsz
+            }),
+            Atom::leaf(b"co64", {
+                let mut co64 = vec![0u8; 4];
+                co64.extend(1u32.to_be_bytes());
+                co64.extend(chunk_offset.to_be_bytes());
+                co64
+            }),
+        ],
+    );
+    
+    let minf = Atom::container(
+        b"minf",
+        vec![
+            Atom::leaf(b"nmhd", vec![0u8; 4]),
+            Atom::leaf(b"dinf", Atom::leaf(b"dref", dref).to_bytes()),
+            stbl,
+        ],
+    );
+    let mdia = Atom::container(b"mdia", vec![Atom::leaf(b"mdhd", mdhd), Atom::leaf(b"hdlr", hdlr), minf]);
+    Atom::container(b"trak", vec![Atom::leaf(b"tkhd", tkhd), mdia])
+}
+
+/// `stsd` with a single `tx3g` sample entry: centered, bottom-aligned white text
+fn text_sample_description() -> Vec<u8> {
+    let mut entry = vec![0u8; 6];
+    entry.extend(1u16.to_be_bytes()); // data reference index
+    entry.extend(0u32.to_be_bytes()); // display flags
+    entry.extend([1, 0xFF]); // horizontal center, vertical bottom
+    entry.extend([0u8; 4]); // transparent background
+    entry.extend([0u8; 8]); // default text box
+    entry.extend([0u8; 4]); // style start and end character
+    entry.extend(1u16.to_be_bytes()); // font ID
+    entry.extend([0, 18]); // face style flags, font size
+    entry.extend([0xFF; 4]); // text colour
+    
+    let mut ftab = 1u16.to_be_bytes().to_vec();
+    ftab.extend(1u16.to_be_bytes());
+    ftab.push(5);
+    ftab.extend(b"Serif");
+    entry.extend(Atom::leaf(b"ftab", ftab).to_bytes());
+    
+    let mut stsd = vec![0u8; 4];
+    stsd.extend(1u32.to_be_bytes());
+    stsd.extend(Atom::leaf(b"tx3g", entry).to_bytes());
+    stsd
+}
+
+/// `stts` payload with runs of equal sample durations
+fn time_to_sample(durations: &[u32]) -> Vec<u8> {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &duration in durations {
+        match runs.last_mut() {
+            Some((count, last)) if *last == duration => *count += 1,
+            _ => runs.push((1, duration)),
+        }
+    }
+    
+    let mut stts = vec![0u8; 4];
+    stts.extend((runs.len() as u32).to_be_bytes());
+    for (count, duration) in runs {
+        stts.extend(count.to_be_bytes());
+        stts.extend(duration.to_be_bytes());
+    }
+    stts
+}
+
+/// Pack a language code into the 15-bit ISO 639-2/T form used by `mdhd`
+fn packed_language(language: &str) -> u16 {
+    const TWO_TO_THREE: &[(&str, &str)] = &[
+        ("ar", "ara"), ("cs", "ces"), ("da", "dan"), ("de", "deu"), ("el", "ell"), ("en", "eng"),
+        ("es", "spa"), ("fi", "fin"), ("fr", "fra"), ("he", "heb"), ("hi", "hin"), ("hu", "hun"),
+        ("id", "ind"), ("it", "ita"), ("ja", "jpn"), ("ko", "kor"), ("nl", "nld"), ("no", "nor"),
+        ("pl", "pol"), ("pt", "por"), ("ro", "ron"), ("ru", "rus"), ("sv", "swe"), ("th", "tha"),
+        ("tr", "tur"), ("uk", "ukr"), ("vi", "vie"), ("zh", "zho"),
+    ];
+    
+    let base = language.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+    let code = match base.len() {
+        3 if base.bytes().all(|b| b.is_ascii_lowercase()) => base.as_str(),
+        _ => TWO_TO_THREE
+            .iter()
+            .find(|(two, _)| *two == base)
+            .map(|(_, three)| *three)
+            .unwrap_or("und"),
+    };
+    
+    code.bytes().fold(0u16, |packed, b| (packed << 5) | (b - 0x60) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Cue;
+    
+    /// Minimal file: ftyp, moov with a single stco pointing into mdat, then mdat
+    fn sample_file() -> Vec<u8> {
+        let ftyp = Atom::leaf(b"ftyp", b"isom\0\0\0\0isom".to_vec());
+        let moov_without_stco_len = |stco: &Atom| {
+            let stbl = Atom::container(b"stbl", vec![stco.clone()]);
+            let minf = Atom::container(b"minf", vec![stbl]);
+            let mdia = Atom::container(b"mdia", vec![minf]);
+            let mut tkhd = vec![0u8; 84];
+            tkhd[12..16].copy_from_slice(&1u32.to_be_bytes());
+            let mut mvhd = vec![0u8; 100];
+            mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+            mvhd[96..100].copy_from_slice(&2u32.to_be_bytes());
+            Atom::container(
+                b"moov",
+                vec![
+                    Atom::container(b"trak", vec![Atom::leaf(b"tkhd", tkhd), mdia]),
+                    Atom::leaf(b"mvhd", mvhd),
+                ],
+            )
+        };
+        
+        let placeholder = Atom::leaf(b"stco", vec![0; 12]);
+        let moov_len = moov_without_stco_len(&placeholder).to_bytes().len();
+        let mdat_payload_offset = (ftyp.to_bytes().len() + moov_len + 8) as u32;
+        
+        let mut stco_data = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stco_data.extend(mdat_payload_offset.to_be_bytes());
+        let moov = moov_without_stco_len(&Atom::leaf(b"stco", stco_data));
+        
+        let mut file = ftyp.to_bytes();
+        file.extend(moov.to_bytes());
+        file.extend(Atom::leaf(b"mdat", b"MEDIA".to_vec()).to_bytes());
+        file
+    }
+    
+    fn chunk_offset(moov: &Atom) -> u32 {
+        let stco = &moov.children[0].children[1].children[0].children[0].children[0];
+        u32::from_be_bytes(stco.data[8..12].try_into().unwrap())
+    }
+    
+    #[test]
+    fn test_write_and_read_back_shifts_chunk_offsets() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("video.mp4");
+        fs::write(&path, sample_file()).unwrap();
+        
+        let tags = Tags {
+            title: Some("Title".to_string()),
+            artist: Some("Uploader".to_string()),
+            album: Some("Album".to_string()),
+            track: Some((3, 12)),
+            cover: CoverArt::from_bytes(vec![0xFF, 0xD8, 0xFF, 0xE0, 1, 2, 3]),
+            ..Tags::default()
+        };
+        write_tags(&path, &tags).unwrap();
+        assert_eq!(read_tags(&path).unwrap(), tags);
+        
+        // The stco entry must still point at the mdat payload
+        let bytes = fs::read(&path).unwrap();
+        let mut file = File::open(&path).unwrap();
+        let boxes = scan_top_level(&mut file).unwrap();
+        let moov = read_moov(&mut file, find_moov(&boxes, &path).unwrap()).unwrap();
+        let offset = chunk_offset(&moov) as usize;
+        assert_eq!(&bytes[offset..offset + 5], b"MEDIA");
+        
+        // Re-tagging replaces items instead of duplicating them
+        let retag = Tags { title: Some("New title".to_string()), ..Tags::default() };
+        write_tags(&path, &retag).unwrap();
+        let read = read_tags(&path).unwrap();
+        assert_eq!(read.title.as_deref(), Some("New title"));
+        assert_eq!(read.artist.as_deref(), Some("Uploader"));
+    }
+    
+    #[test]
+    fn test_embed_chapters_and_subtitles() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("video.mp4");
+        fs::write(&path, sample_file()).unwrap();
+        
+        let chapters = vec![
+            Chapter { title: "Intro".to_string(), start: 0.0, end: 5.0 },
+            Chapter { title: "Part 2".to_string(), start: 5.0, end: 10.0 },
+        ];
+        let subtitle = Subtitle {
+            language: "en".to_string(),
+            cues: vec![
+                Cue { start: 1.0, end: 2.0, text: "Hello".to_string() },
+                Cue { start: 3.0, end: 4.0, text: "World".to_string() },
+            ],
+        };
+        embed_chapters_and_subtitles(&path, &chapters, &[subtitle]).unwrap();
+        
+        assert_eq!(
+            read_chapters(&path).unwrap(),
+            vec![("Intro".to_string(), 0.0), ("Part 2".to_string(), 5.0)]
+        );
+        
+        let bytes = fs::read(&path).unwrap();
+        let mut file = File::open(&path).unwrap();
+        let boxes = scan_top_level(&mut file).unwrap();
+        let moov = read_moov(&mut file, find_moov(&boxes, &path).unwrap()).unwrap();
+        let offset = chunk_offset(&moov) as usize;
+        assert_eq!(&bytes[offset..offset + 5], b"MEDIA");
+        assert_eq!(movie_header(&moov).unwrap(), (1000, 3));
+        
+        let track = moov.children.iter().find(|atom| &atom.kind == b"trak" && atom.child(b"mdia").is_some_and(|mdia| {
+            mdia.child(b"hdlr").is_some_and(|hdlr| &hdlr.data[8..12] == b"sbtl")
+        }));
+        let mdia = track.unwrap().child(b"mdia").unwrap();
+        let mdhd = mdia.child(b"mdhd").unwrap();
+        assert_eq!(&mdhd.data[20..22], &packed_language("eng").to_be_bytes());
+        
+        // Samples: a 1s gap, "Hello", another gap, then "World"
+        let co64 = mdia.child(b"minf").unwrap().child(b"stbl").unwrap().child(b"co64").unwrap();
+        let sample_offset = u64::from_be_bytes(co64.data[8..16].try_into().unwrap()) as usize;
+        assert_eq!(&bytes[sample_offset..sample_offset + 9], b"\0\0\0\x05Hello");
+    }
+}
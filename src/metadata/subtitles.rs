@@ -0,0 +1,106 @@
+//! WebVTT caption parsing
+
+/// A single timed caption
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    pub text: String,
+}
+
+/// A caption track ready to be embedded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subtitle {
+    /// Language code (e.g. "en")
+    pub language: String,
+    pub cues: Vec<Cue>,
+}
+
+pub struct VttParser;
+
+impl VttParser {
+    /// Parse WebVTT text into cues, dropping styling tags and empty cues
+    pub fn parse(text: &str) -> Vec<Cue> {
+        let mut cues = Vec::new();
+        let text = text.replace("\r\n", "\n");
+        
+        for block in text.split("\n\n") {
+            let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+            let Some(timing) = lines.next() else {
+                continue;
+            };
+            
+            let mut times = timing.split("-->");
+            let start = times.next().and_then(Self::parse_timestamp);
+            let end = times
+                .next()
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(Self::parse_timestamp);
+            let (Some(start), Some(end)) = (start, end) else {
+                continue;
+            };
+            
+            let body = lines.map(Self::strip_tags).collect::<Vec<_>>().join("\n");
+            let body = body.trim();
+            if !body.is_empty() && end > start {
+                cues.push(Cue { start, end, text: Self::decode_entities(body) });
+            }
+        }
+        
+        cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+        cues
+    }
+    
+    /// Parse "hh:mm:ss.mmm" or "mm:ss.mmm" into seconds
+    fn parse_timestamp(value: &str) -> Option<f64> {
+        let value = value.trim();
+        let (clock, millis) = value.split_once('.').unwrap_or((value, "0"));
+        let seconds = clock
+            .split(':')
+            .try_fold(0u64, |total, part| part.parse::<u64>().ok().map(|n| total * 60 + n))?;
+        let millis: u64 = millis.parse().ok()?;
+        Some(seconds as f64 + millis as f64 / 1000.0)
+    }
+    
+    fn strip_tags(line: &str) -> String {
+        let mut text = String::with_capacity(line.len());
+        let mut in_tag = false;
+        for c in line.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                c if !in_tag => text.push(c),
+                _ => {}
+            }
+        }
+        text
+    }
+    
+    fn decode_entities(text: &str) -> String {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&nbsp;", " ")
+            .replace("&amp;", "&")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_vtt() {
+        let vtt = "WEBVTT\nKind: captions\nLanguage: en\n\n\
+                   1\n00:00:01.500 --> 00:00:04.000 align:start position:0%\n<c>Hello</c> &amp; welcome\n\n\
+                   NOTE comment block\n\n\
+                   01:05.000 --> 01:07.250\nSecond line\nwraps\n\n\
+                   00:00:08.000 --> 00:00:09.000\n\n";
+        
+        let cues = VttParser::parse(vtt);
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0], Cue { start: 1.5, end: 4.0, text: "Hello & welcome".to_string() });
+        assert_eq!(cues[1], Cue { start: 65.0, end: 67.25, text: "Second line\nwraps".to_string() });
+    }
+}
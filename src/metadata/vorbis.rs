@@ -0,0 +1,288 @@
+//! Vorbis comments for Ogg Opus and Ogg Vorbis files
+//!
+//! The comment header packet is rewritten and every other packet is copied
+//! with its original page boundaries and granule positions.
+
+use crate::error::DownloaderError;
+use crate::metadata::{CoverArt, Tags};
+use crate::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ogg::writing::PacketWriteEndInfo;
+use ogg::{PacketReader, PacketWriter};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+const OPUS_TAGS: &[u8] = b"OpusTags";
+const VORBIS_COMMENT: &[u8] = b"\x03vorbis";
+
+const TITLE: &str = "TITLE";
+const ARTIST: &str = "ARTIST";
+const DATE: &str = "DATE";
+const DESCRIPTION: &str = "DESCRIPTION";
+const URL: &str = "PURL";
+const ALBUM: &str = "ALBUM";
+const TRACK_NUMBER: &str = "TRACKNUMBER";
+const TRACK_TOTAL: &str = "TRACKTOTAL";
+const PICTURE: &str = "METADATA_BLOCK_PICTURE";
+
+/// FLAC picture type for the front cover
+const FRONT_COVER: u32 = 3;
+
+/// Decoded comment header
+struct CommentHeader {
+    magic: &'static [u8],
+    vendor: String,
+    comments: Vec<(String, String)>,
+}
+
+impl CommentHeader {
+    fn parse(packet: &[u8]) -> Option<Self> {
+        let magic = [OPUS_TAGS, VORBIS_COMMENT]
+            .into_iter()
+            .find(|magic| packet.starts_with(magic))?;
+        let mut rest = &packet[magic.len()..];
+        
+        let vendor_len = take_u32(&mut rest)? as usize;
+        let vendor = String::from_utf8_lossy(take(&mut rest, vendor_len)?).into_owned();
+        let count = take_u32(&mut rest)?;
+        
+        let mut comments = Vec::new();
+        for _ in 0..count {
+            let len = take_u32(&mut rest)? as usize;
+            let comment = String::from_utf8_lossy(take(&mut rest, len)?).into_owned();
+            if let Some((key, value)) = comment.split_once('=') {
+                comments.push((key.to_ascii_uppercase(), value.to_string()));
+            }
+        }
+        
+        Some(Self { magic, vendor, comments })
+    }
+    
+    fn set(&mut self, key: &str, value: String) {
+        self.comments.retain(|(existing, _)| existing != key);
+        self.comments.push((key.to_string(), value));
+    }
+    
+    fn get(&self, key: &str) -> Option<&str> {
+        self.comments
+            .iter()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, value)| value.as_str())
+    }
+    
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.magic.to_vec();
+        bytes.extend((self.vendor.len() as u32).to_le_bytes());
+        bytes.extend(self.vendor.as_bytes());
+        bytes.extend((self.comments.len() as u32).to_le_bytes());
+        for (key, value) in &self.comments {
+            let comment = format!("{}={}", key, value);
+            bytes.extend((comment.len() as u32).to_le_bytes());
+            bytes.extend(comment.as_bytes());
+        }
+        if self.magic == VORBIS_COMMENT {
+            bytes.push(1); // framing bit
+        }
+        bytes
+    }
+}
+
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    let (head, tail) = (rest.get(..len)?, rest.get(len..)?);
+    *rest = tail;
+    Some(head)
+}
+
+fn take_u32(rest: &mut &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(take(rest, 4)?.try_into().ok()?))
+}
+
+/// Write tags into the comment header of an Ogg Opus/Vorbis file
+pub fn write_tags(path: &Path, tags: &Tags) -> Result<()> {
+    let mut reader = PacketReader::new(BufReader::new(File::open(path)?));
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("output");
+    let temp_path = path.with_file_name(format!("{}.tagging", file_name));
+    
+    let result = (|| -> Result<()> {
+        let mut writer = PacketWriter::new(BufWriter::new(File::create(&temp_path)?));
+        let mut packet_index = 0usize;
+        let mut stream_serial = None;
+        
+        while let Some(packet) = reader.read_packet().map_err(ogg_error)? {
+            // Only the first logical stream carries the tags we care about
+            let serial = packet.stream_serial();
+            let first_stream = *stream_serial.get_or_insert(serial) == serial;
+            
+            let mut data = packet.data.clone();
+            if first_stream {
+                if packet_index == 1 {
+                    let mut header = CommentHeader::parse(&data)
+                        .ok_or_else(|| DownloaderError::Metadata("Missing Vorbis comment header".to_string()))?;
+                    apply_tags(&mut header, tags);
+                    data = header.to_bytes();
+                }
+                packet_index += 1;
+            }
+            
+            let end_info = if packet.last_in_stream() {
+                PacketWriteEndInfo::EndStream
+            } else if packet.last_in_page() {
+                PacketWriteEndInfo::EndPage
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            writer.write_packet(data.into_boxed_slice(), serial, end_info, packet.absgp_page())?;
+        }
+        
+        let mut output = writer.into_inner();
+        output.flush()?;
+        output.get_ref().sync_all()?;
+        Ok(())
+    })();
+    
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Read tags from the comment header of an Ogg Opus/Vorbis file
+pub fn read_tags(path: &Path) -> Result<Tags> {
+    let mut reader = PacketReader::new(BufReader::new(File::open(path)?));
+    reader.read_packet().map_err(ogg_error)?;
+    let packet = reader
+        .read_packet()
+        .map_err(ogg_error)?
+        .ok_or_else(|| DownloaderError::Metadata("Missing Vorbis comment header".to_string()))?;
+    let header = CommentHeader::parse(&packet.data)
+        .ok_or_else(|| DownloaderError::Metadata("Missing Vorbis comment header".to_string()))?;
+    
+    let text = |key: &str| header.get(key).map(str::to_string);
+    Ok(Tags {
+        title: text(TITLE),
+        artist: text(ARTIST),
+        date: text(DATE),
+        description: text(DESCRIPTION),
+        url: text(URL),
+        album: text(ALBUM),
+        track: header.get(TRACK_NUMBER).and_then(|number| number.parse().ok()).map(|number| {
+            let total = header.get(TRACK_TOTAL).and_then(|total| total.parse().ok());
+            (number, total.unwrap_or(number))
+        }),
+        cover: header
+            .get(PICTURE)
+            .and_then(|encoded| BASE64.decode(encoded).ok())
+            .and_then(|block| decode_picture(&block)),
+    })
+}
+
+fn apply_tags(header: &mut CommentHeader, tags: &Tags) {
+    let text_tags = [
+        (TITLE, &tags.title),
+        (ARTIST, &tags.artist),
+        (DATE, &tags.date),
+        (DESCRIPTION, &tags.description),
+        (URL, &tags.url),
+        (ALBUM, &tags.album),
+    ];
+    for (key, value) in text_tags {
+        if let Some(value) = value {
+            header.set(key, value.clone());
+        }
+    }
+    
+    if let Some((number, total)) = tags.track {
+        header.set(TRACK_NUMBER, number.to_string());
+        header.set(TRACK_TOTAL, total.to_string());
+    }
+    
+    if let Some(ref cover) = tags.cover {
+        header.set(PICTURE, BASE64.encode(encode_picture(cover)));
+    }
+}
+
+/// Encode cover art as a FLAC picture block
+fn encode_picture(cover: &CoverArt) -> Vec<u8> {
+    let mime_type = cover.format.mime_type().as_bytes();
+    
+    let mut block = Vec::with_capacity(cover.data.len() + 64);
+    block.extend(FRONT_COVER.to_be_bytes());
+    block.extend((mime_type.len() as u32).to_be_bytes());
+    block.extend(mime_type);
+    block.extend(0u32.to_be_bytes()); // description
+    block.extend([0u8; 16]); // width, height, colour depth, palette size
+    block.extend((cover.data.len() as u32).to_be_bytes());
+    block.extend(&cover.data);
+    block
+}
+
+fn decode_picture(block: &[u8]) -> Option<CoverArt> {
+    let read_u32 = |offset: usize| -> Option<usize> {
+        Some(u32::from_be_bytes(block.get(offset..offset + 4)?.try_into().ok()?) as usize)
+    };
+    
+    let mime_len = read_u32(4)?;
+    let description_len = read_u32(8 + mime_len)?;
+    let data_offset = 12 + mime_len + description_len + 16;
+    let data_len = read_u32(data_offset)?;
+    let data = block.get(data_offset + 4..data_offset + 4 + data_len)?;
+    CoverArt::from_bytes(data.to_vec())
+}
+
+fn ogg_error(e: ogg::OggReadError) -> DownloaderError {
+    DownloaderError::Metadata(format!("Ogg read error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Opus stream with identification and comment headers and two audio packets
+    fn sample_file(path: &Path) {
+        let mut writer = PacketWriter::new(File::create(path).unwrap());
+        let mut head = b"OpusHead".to_vec();
+        head.extend([1, 2, 0x38, 0x01, 0x80, 0xBB, 0, 0, 0, 0, 0]);
+        let comments = CommentHeader {
+            magic: OPUS_TAGS,
+            vendor: "test".to_string(),
+            comments: vec![("ENCODER".to_string(), "test".to_string())],
+        };
+        
+        writer.write_packet(head.into_boxed_slice(), 7, PacketWriteEndInfo::EndPage, 0).unwrap();
+        writer.write_packet(comments.to_bytes().into_boxed_slice(), 7, PacketWriteEndInfo::EndPage, 0).unwrap();
+        writer.write_packet(vec![1; 40].into_boxed_slice(), 7, PacketWriteEndInfo::NormalPacket, 960).unwrap();
+        writer.write_packet(vec![2; 40].into_boxed_slice(), 7, PacketWriteEndInfo::EndStream, 1920).unwrap();
+    }
+    
+    #[test]
+    fn test_write_and_read_back() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("audio.opus");
+        sample_file(&path);
+        
+        let tags = Tags {
+            title: Some("Title".to_string()),
+            artist: Some("Uploader".to_string()),
+            date: Some("2009-10-25".to_string()),
+            description: Some("Line one\nLine two".to_string()),
+            url: Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string()),
+            album: Some("Album".to_string()),
+            track: Some((1, 3)),
+            cover: CoverArt::from_bytes(vec![0xFF, 0xD8, 0xFF, 0xE0, 9, 9]),
+        };
+        write_tags(&path, &tags).unwrap();
+        assert_eq!(read_tags(&path).unwrap(), tags);
+        
+        // Audio packets and granule positions survive the rewrite
+        let mut reader = PacketReader::new(File::open(&path).unwrap());
+        let packets: Vec<_> = std::iter::from_fn(|| reader.read_packet().unwrap()).collect();
+        assert_eq!(packets.len(), 4);
+        assert_eq!(packets[3].data, vec![2; 40]);
+        assert_eq!(packets[3].absgp_page(), 1920);
+        assert!(CommentHeader::parse(&packets[1].data).unwrap().get("ENCODER").is_some());
+    }
+}
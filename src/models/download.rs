@@ -1,14 +1,20 @@
 //! Download task and progress models
 
+use crate::file_system::FileOrganizer;
 use crate::models::{VideoInfo, Format};
+use serde::Serialize;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct DownloadTask {
     pub video_info: VideoInfo,
     pub selected_format: Format,
+    /// Audio stream merged into the video-only `selected_format`
+    pub audio_format: Option<Format>,
     pub output_path: PathBuf,
     pub progress: DownloadProgress,
+    /// Parts of the video to download; empty means the whole video
+    pub sections: Vec<TimeRange>,
 }
 
 impl DownloadTask {
@@ -16,19 +22,52 @@ impl DownloadTask {
         Self {
             video_info,
             selected_format,
+            audio_format: None,
             output_path,
             progress: DownloadProgress::new(),
+            sections: Vec::new(),
         }
     }
     
-    /// Generate output filename
+    /// Only download the given parts of the video
+    pub fn with_sections(mut self, sections: Vec<TimeRange>) -> Self {
+        self.sections = sections;
+        self
+    }
+    
+    /// Download `audio` as well and merge it with the video into one file
+    pub fn with_audio(mut self, audio: Format) -> Self {
+        self.audio_format = Some(audio);
+        self
+    }
+    
+    /// Every stream the download fetches, video first
+    pub fn formats(&self) -> Vec<Format> {
+        std::iter::once(&self.selected_format).chain(&self.audio_format).cloned().collect()
+    }
+    
+    /// Generate output filename from the default template
     pub fn generate_filename(&self) -> String {
-        // TODO: Implement smart filename generation
-        todo!("Implement filename generation")
+        FileOrganizer::generate_filename(&self.video_info, &self.selected_format)
     }
 }
 
-#[derive(Debug, Clone)]
+/// A span of a video in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: f64,
+    /// `f64::INFINITY` for "until the end"
+    pub end: f64,
+}
+
+impl TimeRange {
+    /// Whether the span from `start` to `end` shares any time with this range
+    pub fn overlaps(&self, start: f64, end: f64) -> bool {
+        start < self.end && end > self.start
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct DownloadProgress {
     pub total_size: u64,
     pub downloaded_size: u64,
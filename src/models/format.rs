@@ -8,6 +8,44 @@ pub enum FormatType {
     Video,
 }
 
+/// Transfer protocol used to fetch a format's media
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Protocol {
+    /// Plain progressive HTTPS download
+    Https,
+    /// MPEG-DASH manifest
+    Dash,
+    /// HLS (m3u8) playlist
+    Hls,
+}
+
+/// Dynamic range of a video stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DynamicRange {
+    Sdr,
+    Hdr,
+}
+
+/// A single media segment of a fragmented (DASH/HLS) format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fragment {
+    pub url: String,
+    /// Inclusive byte range within `url`, when the segment is part of a larger file
+    pub byte_range: Option<(u64, u64)>,
+    /// Segment duration in seconds
+    pub duration: Option<f64>,
+}
+
+impl Fragment {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            byte_range: None,
+            duration: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Format {
     pub quality: String,
@@ -17,6 +55,35 @@ pub struct Format {
     pub file_size: Option<u64>,
     pub bitrate: Option<u32>,
     pub codec: Option<String>,
+    /// YouTube format identifier
+    pub itag: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<u32>,
+    pub vcodec: Option<String>,
+    pub acodec: Option<String>,
+    pub audio_channels: Option<u32>,
+    /// Audio sample rate in Hz
+    pub sample_rate: Option<u32>,
+    pub dynamic_range: Option<DynamicRange>,
+    pub approx_duration_ms: Option<u64>,
+    /// Whether this is a separate audio-only or video-only stream
+    pub is_adaptive: bool,
+    /// Audio track language code (e.g. "en")
+    pub language: Option<String>,
+    pub protocol: Protocol,
+    /// DASH/HLS manifest this format was read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_url: Option<String>,
+    /// Media segments in playback order (initialization segment first)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fragments: Vec<Fragment>,
+    /// Inclusive byte range of the initialization data (`ftyp` and `moov`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init_range: Option<(u64, u64)>,
+    /// Inclusive byte range of the segment index (`sidx`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_range: Option<(u64, u64)>,
 }
 
 impl Format {
@@ -34,18 +101,171 @@ impl Format {
             file_size: None,
             bitrate: None,
             codec: None,
+            itag: None,
+            width: None,
+            height: None,
+            fps: None,
+            vcodec: None,
+            acodec: None,
+            audio_channels: None,
+            sample_rate: None,
+            dynamic_range: None,
+            approx_duration_ms: None,
+            is_adaptive: false,
+            language: None,
+            protocol: Protocol::Https,
+            manifest_url: None,
+            fragments: Vec::new(),
+            init_range: None,
+            index_range: None,
         }
     }
     
     /// Get human-readable quality description
     pub fn quality_description(&self) -> String {
-        // TODO: Implement quality description formatting
-        todo!("Implement quality description")
+        match self.format_type {
+            FormatType::Video => {
+                let mut description = match self.height {
+                    Some(height) => format!("{}p", height),
+                    None => self.quality.clone(),
+                };
+                
+                if let Some(fps) = self.fps.filter(|&fps| fps > 30) {
+                    description.push_str(&fps.to_string());
+                }
+                
+                if self.dynamic_range == Some(DynamicRange::Hdr) {
+                    description.push_str(" HDR");
+                }
+                
+                if self.is_adaptive {
+                    description.push_str(" (video only)");
+                }
+                
+                description
+            }
+            FormatType::Audio => {
+                let mut description = match self.bitrate {
+                    Some(bitrate) => format!("{}kbps", bitrate / 1000),
+                    None => self.quality.clone(),
+                };
+                
+                match self.audio_channels {
+                    Some(1) => description.push_str(" mono"),
+                    Some(2) => description.push_str(" stereo"),
+                    Some(channels) if channels > 2 => {
+                        description.push_str(&format!(" {}ch", channels));
+                    }
+                    _ => {}
+                }
+                
+                if let Some(ref language) = self.language {
+                    description.push_str(&format!(" [{}]", language));
+                }
+                
+                description
+            }
+        }
     }
     
     /// Check if format is high quality
     pub fn is_high_quality(&self) -> bool {
-        // TODO: Implement quality assessment
-        todo!("Implement quality assessment")
+        match self.format_type {
+            // 720p and above, or anything HDR
+            FormatType::Video => {
+                self.height.is_some_and(|height| height >= 720)
+                    || self.dynamic_range == Some(DynamicRange::Hdr)
+            }
+            // 128kbps and above
+            FormatType::Audio => self.bitrate.is_some_and(|bitrate| bitrate >= 128_000),
+        }
+    }
+    
+    /// Reported file size, or an estimate from the bitrate and duration
+    pub fn estimated_size(&self) -> Option<u64> {
+        self.file_size.or_else(|| {
+            let bitrate = u64::from(self.bitrate?);
+            Some(bitrate * self.approx_duration_ms? / 8000)
+        })
     }
-}
\ No newline at end of file
+    
+    /// Human-readable resolution (e.g. "1920x1080"), or "audio only"
+    pub fn resolution(&self) -> String {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => format!("{}x{}", width, height),
+            (None, Some(height)) => format!("{}p", height),
+            _ if self.format_type == FormatType::Audio => "audio only".to_string(),
+            _ => "unknown".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn video_format(height: u32, fps: u32) -> Format {
+        let mut format = Format::new(
+            format!("{}p", height),
+            FormatType::Video,
+            "mp4".to_string(),
+            "https://example.com/video.mp4".to_string(),
+        );
+        format.width = Some(height * 16 / 9);
+        format.height = Some(height);
+        format.fps = Some(fps);
+        format
+    }
+    
+    #[test]
+    fn test_video_quality_description() {
+        let mut format = video_format(1080, 60);
+        assert_eq!(format.quality_description(), "1080p60");
+        
+        format.dynamic_range = Some(DynamicRange::Hdr);
+        format.is_adaptive = true;
+        assert_eq!(format.quality_description(), "1080p60 HDR (video only)");
+        
+        assert_eq!(video_format(480, 30).quality_description(), "480p");
+    }
+    
+    #[test]
+    fn test_audio_quality_description() {
+        let mut format = Format::new(
+            "128kbps".to_string(),
+            FormatType::Audio,
+            "mp3".to_string(),
+            "https://example.com/audio".to_string(),
+        );
+        format.bitrate = Some(129_574);
+        format.audio_channels = Some(2);
+        format.language = Some("en".to_string());
+        
+        assert_eq!(format.quality_description(), "129kbps stereo [en]");
+        assert!(format.is_high_quality());
+    }
+    
+    #[test]
+    fn test_is_high_quality() {
+        assert!(video_format(720, 30).is_high_quality());
+        assert!(!video_format(480, 30).is_high_quality());
+        
+        let mut hdr = video_format(480, 30);
+        hdr.dynamic_range = Some(DynamicRange::Hdr);
+        assert!(hdr.is_high_quality());
+    }
+    
+    #[test]
+    fn test_estimated_size() {
+        let mut format = video_format(1080, 30);
+        assert_eq!(format.estimated_size(), None);
+        
+        // 4 Mbit/s for 10 seconds
+        format.bitrate = Some(4_000_000);
+        format.approx_duration_ms = Some(10_000);
+        assert_eq!(format.estimated_size(), Some(5_000_000));
+        
+        format.file_size = Some(1234);
+        assert_eq!(format.estimated_size(), Some(1234));
+    }
+}
@@ -4,6 +4,6 @@ pub mod video;
 pub mod format;
 pub mod download;
 
-pub use video::VideoInfo;
-pub use format::{Format, FormatType};
-pub use download::{DownloadTask, DownloadProgress};
\ No newline at end of file
+pub use video::{Chapter, SubtitleTrack, VideoInfo};
+pub use format::{Format, FormatType, Fragment, Protocol, DynamicRange};
+pub use download::{DownloadTask, DownloadProgress, TimeRange};
\ No newline at end of file
@@ -12,6 +12,47 @@ pub struct VideoInfo {
     pub video_id: String,
     pub uploader: Option<String>,
     pub upload_date: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Length of the video in seconds
+    #[serde(default)]
+    pub duration_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapters: Vec<Chapter>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtitles: Vec<SubtitleTrack>,
+    /// Currently broadcasting live stream
+    #[serde(default)]
+    pub is_live: bool,
+}
+
+/// A named section of a video
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+}
+
+/// A caption track offered for a video
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleTrack {
+    /// Language code (e.g. "en", "pt-BR")
+    pub language: String,
+    pub name: String,
+    pub url: String,
+    /// Automatically generated (speech recognition) captions
+    pub is_automatic: bool,
+}
+
+impl SubtitleTrack {
+    /// URL of the track in WebVTT format
+    pub fn vtt_url(&self) -> String {
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        format!("{}{}fmt=vtt", self.url, separator)
+    }
 }
 
 impl VideoInfo {
@@ -24,6 +65,11 @@ impl VideoInfo {
             thumbnail_url: String::new(),
             uploader: None,
             upload_date: None,
+            description: None,
+            duration_seconds: None,
+            chapters: Vec::new(),
+            subtitles: Vec::new(),
+            is_live: false,
         }
     }
     
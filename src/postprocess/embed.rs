@@ -0,0 +1,91 @@
+//! Tagging and embedding stages
+
+use crate::error::DownloaderError;
+use crate::metadata::{CoverArt, MetadataWriter, Subtitle, Tags};
+use crate::models::DownloadTask;
+use crate::postprocess::{PostProcessor, Stage};
+use crate::Result;
+use std::path::{Path, PathBuf};
+
+/// Write title, uploader, date, description and URL tags
+pub struct EmbedMetadata;
+
+impl PostProcessor for EmbedMetadata {
+    fn stage(&self) -> Stage {
+        Stage::Tag
+    }
+    
+    fn process(&self, task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        write_tags(path, &Tags::from_video_info(&task.video_info))
+    }
+}
+
+/// Add the thumbnail as cover art
+pub struct EmbedThumbnail {
+    cover: Option<CoverArt>,
+}
+
+impl EmbedThumbnail {
+    /// `cover` is fetched ahead of time; `None` reports the stage as failed
+    pub fn new(cover: Option<CoverArt>) -> Self {
+        Self { cover }
+    }
+}
+
+impl PostProcessor for EmbedThumbnail {
+    fn stage(&self) -> Stage {
+        Stage::EmbedThumbnail
+    }
+    
+    fn process(&self, _task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        let cover = self
+            .cover
+            .clone()
+            .ok_or_else(|| DownloaderError::PostProcessing("Thumbnail could not be downloaded".to_string()))?;
+        write_tags(path, &Tags::default().with_cover(Some(cover)))
+    }
+}
+
+/// Embed the video's chapters and caption tracks (MP4 only)
+pub struct EmbedChapters {
+    chapters: bool,
+    subtitles: Vec<Subtitle>,
+}
+
+impl EmbedChapters {
+    /// Chapters are read from the task when the stage runs, so earlier stages can change them
+    pub fn new(chapters: bool, subtitles: Vec<Subtitle>) -> Self {
+        Self { chapters, subtitles }
+    }
+}
+
+impl PostProcessor for EmbedChapters {
+    fn stage(&self) -> Stage {
+        Stage::EmbedChapters
+    }
+    
+    fn process(&self, task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        let chapters = if self.chapters { task.video_info.chapters.as_slice() } else { &[] };
+        if chapters.is_empty() && self.subtitles.is_empty() {
+            return Ok(None);
+        }
+        
+        if !MetadataWriter::embed_chapters_and_subtitles(path, chapters, &self.subtitles)? {
+            return Err(DownloaderError::PostProcessing(format!(
+                "Cannot embed chapters or subtitles into {}: MP4 output required",
+                path.display()
+            )));
+        }
+        Ok(None)
+    }
+}
+
+fn write_tags(path: &Path, tags: &Tags) -> Result<Option<PathBuf>> {
+    if !MetadataWriter::write(path, tags)? {
+        return Err(DownloaderError::PostProcessing(format!(
+            "Cannot tag {}: unsupported container",
+            path.display()
+        )));
+    }
+    Ok(None)
+}
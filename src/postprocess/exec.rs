@@ -0,0 +1,222 @@
+//! Running an external command on the finished file
+
+use crate::error::DownloaderError;
+use crate::file_system::OutputTemplate;
+use crate::models::{DownloadTask, Format, FormatType, VideoInfo};
+use crate::postprocess::{PostProcessor, Stage};
+use crate::Result;
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// An external command run around downloads.
+///
+/// The command is split into arguments like a shell would, honouring quotes
+/// and backslashes, but no shell is involved: every argument reaches the
+/// program unchanged. Arguments may use output template fields such as
+/// `%(title)s` (and `%%` for a literal percent sign), which are filled in
+/// from the download without sanitizing.
+/// A `{}` argument expands to one argument per file; `{}` inside a longer
+/// argument is replaced by the paths joined with spaces. Without any `{}` the
+/// paths are appended.
+pub struct ExecHook {
+    command: String,
+    arguments: Vec<Argument>,
+}
+
+enum Argument {
+    Literal(String),
+    Template(OutputTemplate),
+}
+
+impl ExecHook {
+    pub fn parse(command: &str) -> Result<Self> {
+        let arguments = split_command(command)?
+            .into_iter()
+            .map(|argument| {
+                if argument.contains('%') {
+                    Ok(Argument::Template(OutputTemplate::parse(&argument).map_err(|e| {
+                        DownloaderError::Configuration(format!("Invalid exec command '{}': {}", command, e))
+                    })?))
+                } else {
+                    Ok(Argument::Literal(argument))
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if arguments.is_empty() {
+            return Err(DownloaderError::Configuration("Empty exec command".to_string()));
+        }
+        Ok(Self {
+            command: command.to_string(),
+            arguments,
+        })
+    }
+    
+    /// Parse a command that runs without a particular download, so it cannot use template fields
+    pub fn parse_without_fields(command: &str) -> Result<Self> {
+        let hook = Self::parse(command)?;
+        if hook.arguments.iter().any(|argument| matches!(argument, Argument::Template(t) if t.has_fields())) {
+            return Err(DownloaderError::Configuration(format!(
+                "Template fields are not available in '{}', only {{}}",
+                command
+            )));
+        }
+        Ok(hook)
+    }
+    
+    /// Program and arguments for `paths`, with fields taken from `task`
+    pub fn arguments(&self, task: Option<&DownloadTask>, paths: &[PathBuf]) -> Vec<String> {
+        let paths: Vec<String> = paths.iter().map(|path| path.to_string_lossy().into_owned()).collect();
+        let mut placeholder = false;
+        let mut arguments = Vec::new();
+        
+        for argument in &self.arguments {
+            let text = match (argument, task) {
+                (Argument::Literal(text), _) => text.clone(),
+                (Argument::Template(template), Some(task)) => {
+                    template.render_text(&task.video_info, &task.selected_format)
+                }
+                // Only templates without fields are used without a download
+                (Argument::Template(template), None) => template.render_text(
+                    &VideoInfo::new(String::new(), String::new(), String::new()),
+                    &Format::new(String::new(), FormatType::Video, String::new(), String::new()),
+                ),
+            };
+            
+            if text == "{}" {
+                placeholder = true;
+                arguments.extend(paths.iter().cloned());
+            } else if text.contains("{}") {
+                placeholder = true;
+                arguments.push(text.replace("{}", &paths.join(" ")));
+            } else {
+                arguments.push(text);
+            }
+        }
+        if !placeholder {
+            arguments.extend(paths);
+        }
+        arguments
+    }
+    
+    /// Run the command and wait for it, failing on a non-zero exit status
+    pub fn run(&self, task: Option<&DownloadTask>, paths: &[PathBuf]) -> Result<()> {
+        let arguments = self.arguments(task, paths);
+        let Some((program, rest)) = arguments.split_first() else {
+            return Err(DownloaderError::PostProcessing(format!("'{}' expands to nothing", self.command)));
+        };
+        debug!("Running {:?}", arguments);
+        
+        let status = Command::new(program)
+            .args(rest)
+            .status()
+            .map_err(|e| DownloaderError::PostProcessing(format!("Could not run '{}': {}", program, e)))?;
+        if !status.success() {
+            return Err(DownloaderError::PostProcessing(format!("'{}' exited with {}", self.command, status)));
+        }
+        Ok(())
+    }
+}
+
+impl PostProcessor for ExecHook {
+    fn stage(&self) -> Stage {
+        Stage::Exec
+    }
+    
+    fn process(&self, task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        self.run(Some(task), &[path.to_path_buf()])?;
+        Ok(None)
+    }
+}
+
+/// Split a command line into arguments, honouring single quotes, double quotes and backslashes
+fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = command.chars();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                arguments.extend(current.take());
+            }
+            '\'' => {
+                let argument = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => argument.push(c),
+                        None => return Err(unterminated(command)),
+                    }
+                }
+            }
+            '"' => {
+                let argument = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => argument.push(chars.next().ok_or_else(|| unterminated(command))?),
+                        Some(c) => argument.push(c),
+                        None => return Err(unterminated(command)),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars.next().ok_or_else(|| unterminated(command))?;
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    
+    arguments.extend(current);
+    Ok(arguments)
+}
+
+fn unterminated(command: &str) -> DownloaderError {
+    DownloaderError::Configuration(format!("Unterminated quote or escape in exec command '{}'", command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_split_command() {
+        assert_eq!(split_command(r#"notify-send "Download done" {}"#).unwrap(), ["notify-send", "Download done", "{}"]);
+        assert_eq!(split_command(r"cp {} '/mnt/my videos/' a\ b").unwrap(), ["cp", "{}", "/mnt/my videos/", "a b"]);
+        assert_eq!(split_command("  ''  x").unwrap(), ["", "x"]);
+        assert!(split_command("echo 'oops").is_err());
+    }
+    
+    #[test]
+    fn test_path_is_one_argument() {
+        let path = PathBuf::from("/tmp/a b; rm -rf ~.mp4");
+        let hook = ExecHook::parse("echo {}").unwrap();
+        assert_eq!(hook.arguments(None, &[path.clone()]), ["echo", "/tmp/a b; rm -rf ~.mp4"]);
+        
+        let hook = ExecHook::parse("ls -l").unwrap();
+        assert_eq!(hook.arguments(None, &[path]), ["ls", "-l", "/tmp/a b; rm -rf ~.mp4"]);
+        assert!(ExecHook::parse("   ").is_err());
+    }
+    
+    #[test]
+    fn test_template_fields_are_arguments() {
+        let mut video_info = VideoInfo::new("Rock $(whoami) & Roll".to_string(), "3:32".to_string(), "dQw4w9WgXcQ".to_string());
+        video_info.uploader = Some("Rick Astley".to_string());
+        let format = Format::new("22".to_string(), FormatType::Video, "mp4".to_string(), String::new());
+        let task = DownloadTask::new(video_info, format, PathBuf::from("video.mp4"));
+        
+        let hook = ExecHook::parse("index --title %(title)s 'by %(uploader)s' {}").unwrap();
+        let paths = [PathBuf::from("/media/a.mp4"), PathBuf::from("/media/b.mp4")];
+        assert_eq!(
+            hook.arguments(Some(&task), &paths),
+            ["index", "--title", "Rock $(whoami) & Roll", "by Rick Astley", "/media/a.mp4", "/media/b.mp4"]
+        );
+        
+        assert!(ExecHook::parse("notify %(nope)s").is_err());
+        assert!(ExecHook::parse_without_fields("notify %(title)s").is_err());
+        let hook = ExecHook::parse_without_fields("notify 100%% {}").unwrap();
+        assert_eq!(hook.arguments(None, &paths[..1]), ["notify", "100%", "/media/a.mp4"]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Stages that write or move files
+
+use crate::error::DownloaderError;
+use crate::media::ChapterSplitter;
+use crate::models::DownloadTask;
+use crate::postprocess::{PostProcessor, Stage};
+use crate::Result;
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Write one file per chapter next to the download, keeping the full file
+pub struct SplitChapters {
+    splitter: ChapterSplitter,
+    directory: PathBuf,
+}
+
+impl SplitChapters {
+    pub fn new(splitter: ChapterSplitter, directory: PathBuf) -> Self {
+        Self { splitter, directory }
+    }
+}
+
+impl PostProcessor for SplitChapters {
+    fn stage(&self) -> Stage {
+        Stage::Split
+    }
+    
+    fn process(&self, task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        if task.video_info.chapters.is_empty() {
+            warn!("Video has no chapters, not splitting {}", path.display());
+            return Ok(None);
+        }
+        
+        let parts = self.splitter.split(path, &self.directory, &task.video_info, &task.selected_format)?;
+        info!("Split {} into {} chapter files", path.display(), parts.len());
+        Ok(None)
+    }
+}
+
+/// Move the finished file into another directory
+pub struct MoveFile {
+    directory: PathBuf,
+}
+
+impl MoveFile {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+impl PostProcessor for MoveFile {
+    fn stage(&self) -> Stage {
+        Stage::Move
+    }
+    
+    fn process(&self, _task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| DownloaderError::PostProcessing(format!("{} has no file name", path.display())))?;
+        let target = self.directory.join(file_name);
+        if target == path {
+            return Ok(None);
+        }
+        if target.exists() {
+            return Err(DownloaderError::PostProcessing(format!("{} already exists", target.display())));
+        }
+        
+        fs::create_dir_all(&self.directory)?;
+        // Renaming fails across file systems; copy first so the source survives a failed copy
+        if fs::rename(path, &target).is_err() {
+            if let Err(e) = fs::copy(path, &target) {
+                let _ = fs::remove_file(&target);
+                return Err(e.into());
+            }
+            fs::remove_file(path)?;
+        }
+        Ok(Some(target))
+    }
+}
@@ -0,0 +1,193 @@
+//! Post-processing of finished downloads
+//!
+//! Processors run in a fixed stage order once a download completes. Each one
+//! may rewrite the file in place or replace it with a new path. A failing
+//! stage is reported and skipped, and the file the earlier stages produced is
+//! kept.
+
+pub mod embed;
+pub mod exec;
+pub mod files;
+pub mod remux;
+pub mod sponsorblock;
+
+pub use embed::{EmbedChapters, EmbedMetadata, EmbedThumbnail};
+pub use exec::ExecHook;
+pub use files::{MoveFile, SplitChapters};
+pub use remux::Remuxer;
+pub use sponsorblock::SponsorBlockProcessor;
+
+use crate::error::DownloaderError;
+use crate::models::DownloadTask;
+use crate::Result;
+use log::{debug, warn};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Pipeline stages, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    SponsorBlock,
+    Remux,
+    Tag,
+    EmbedThumbnail,
+    EmbedChapters,
+    Split,
+    Move,
+    Exec,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::SponsorBlock => "sponsorblock",
+            Stage::Remux => "remux",
+            Stage::Tag => "tag",
+            Stage::EmbedThumbnail => "embed-thumbnail",
+            Stage::EmbedChapters => "embed-chapters",
+            Stage::Split => "split",
+            Stage::Move => "move",
+            Stage::Exec => "exec",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A step applied to a finished download
+pub trait PostProcessor: Send + Sync {
+    /// Where this processor runs in the pipeline
+    fn stage(&self) -> Stage;
+    
+    /// Process the file at `path`, returning the new path if the file was replaced or moved.
+    ///
+    /// The original file must be left intact when an error is returned.
+    fn process(&self, task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>>;
+}
+
+/// A stage that did not complete
+#[derive(Debug)]
+pub struct StageFailure {
+    pub stage: Stage,
+    pub error: DownloaderError,
+}
+
+/// Ordered post-processors for a download
+#[derive(Default)]
+pub struct PostProcessorPipeline {
+    processors: Vec<Box<dyn PostProcessor>>,
+}
+
+impl PostProcessorPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a processor after those of the same or earlier stages
+    pub fn add(&mut self, processor: impl PostProcessor + 'static) {
+        let position = self
+            .processors
+            .iter()
+            .position(|existing| existing.stage() > processor.stage())
+            .unwrap_or(self.processors.len());
+        self.processors.insert(position, Box::new(processor));
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+    
+    /// Stages in the order they will run
+    pub fn stages(&self) -> Vec<Stage> {
+        self.processors.iter().map(|processor| processor.stage()).collect()
+    }
+    
+    /// Run every processor on `task.output_path`, updating it as files are replaced.
+    ///
+    /// Returns the stages that failed; later stages still run on the last good file.
+    pub fn run(&self, task: &mut DownloadTask) -> Vec<StageFailure> {
+        let mut failures = Vec::new();
+        for processor in &self.processors {
+            let stage = processor.stage();
+            let path = task.output_path.clone();
+            
+            match processor.process(task, &path) {
+                Ok(Some(new_path)) if new_path.exists() => {
+                    debug!("{}: {} -> {}", stage, path.display(), new_path.display());
+                    task.output_path = new_path;
+                }
+                Ok(Some(new_path)) => {
+                    let error = DownloaderError::PostProcessing(format!("{} was not created", new_path.display()));
+                    warn!("Post-processing stage '{}' failed: {}", stage, error);
+                    failures.push(StageFailure { stage, error });
+                }
+                Ok(None) => debug!("{}: processed {}", stage, path.display()),
+                Err(error) => {
+                    warn!("Post-processing stage '{}' failed: {}", stage, error);
+                    failures.push(StageFailure { stage, error });
+                }
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Format, FormatType, VideoInfo};
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+    
+    /// Records the order it ran in and optionally renames or fails
+    struct Probe {
+        stage: Stage,
+        log: Arc<Mutex<Vec<Stage>>>,
+        rename_to: Option<&'static str>,
+        fail: bool,
+    }
+    
+    impl PostProcessor for Probe {
+        fn stage(&self) -> Stage {
+            self.stage
+        }
+        
+        fn process(&self, _task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+            self.log.lock().unwrap().push(self.stage);
+            if self.fail {
+                return Err(DownloaderError::PostProcessing("probe failed".to_string()));
+            }
+            let Some(name) = self.rename_to else {
+                return Ok(None);
+            };
+            let new_path = path.with_file_name(name);
+            std::fs::rename(path, &new_path)?;
+            Ok(Some(new_path))
+        }
+    }
+    
+    #[test]
+    fn test_pipeline_runs_in_stage_order_and_keeps_file_on_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("video.mp4");
+        std::fs::write(&path, b"video").unwrap();
+        
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let probe = |stage, rename_to, fail| Probe { stage, log: log.clone(), rename_to, fail };
+        let mut pipeline = PostProcessorPipeline::new();
+        pipeline.add(probe(Stage::Exec, None, false));
+        pipeline.add(probe(Stage::Tag, None, true));
+        pipeline.add(probe(Stage::Remux, Some("video.mkv"), false));
+        assert_eq!(pipeline.stages(), [Stage::Remux, Stage::Tag, Stage::Exec]);
+        
+        let format = Format::new("18".to_string(), FormatType::Video, "mp4".to_string(), String::new());
+        let video_info = VideoInfo::new("Test".to_string(), "0:10".to_string(), "dQw4w9WgXcQ".to_string());
+        let mut task = DownloadTask::new(video_info, format, path);
+        let failures = pipeline.run(&mut task);
+        
+        assert_eq!(*log.lock().unwrap(), [Stage::Remux, Stage::Tag, Stage::Exec]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].stage, Stage::Tag);
+        assert_eq!(task.output_path, temp_dir.path().join("video.mkv"));
+        assert_eq!(std::fs::read(&task.output_path).unwrap(), b"video");
+    }
+}
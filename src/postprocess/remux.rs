@@ -0,0 +1,115 @@
+//! Changing the container with ffmpeg, without re-encoding
+
+use crate::error::DownloaderError;
+use crate::models::DownloadTask;
+use crate::postprocess::{PostProcessor, Stage};
+use crate::Result;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Containers ffmpeg can copy streams into
+pub const CONTAINERS: &[&str] = &["mp4", "mkv", "webm", "mov", "m4a", "mka"];
+
+pub struct Remuxer {
+    container: String,
+}
+
+impl Remuxer {
+    /// Remux into `container`, one of [`CONTAINERS`]
+    pub fn new(container: &str) -> Result<Self> {
+        let container = container.trim().to_ascii_lowercase();
+        if !CONTAINERS.contains(&container.as_str()) {
+            return Err(DownloaderError::Configuration(format!(
+                "Cannot remux into '{}' (expected one of: {})",
+                container,
+                CONTAINERS.join(", ")
+            )));
+        }
+        Ok(Self { container })
+    }
+    
+    /// Copy the video of `video` and the audio of `audio` into `output`,
+    /// whose extension picks the container
+    pub fn merge(video: &Path, audio: &Path, output: &Path) -> Result<()> {
+        let result = Command::new("ffmpeg")
+            .args(["-nostdin", "-y", "-loglevel", "error", "-i"])
+            .arg(video)
+            .arg("-i")
+            .arg(audio)
+            .args(["-map", "0:v", "-map", "1:a", "-c", "copy"])
+            .arg(output)
+            .stdin(Stdio::null())
+            .output();
+        let output_status = match result {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(DownloaderError::Media(
+                    "ffmpeg is required to merge video and audio but was not found".to_string(),
+                ))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !output_status.status.success() {
+            let _ = fs::remove_file(output);
+            return Err(DownloaderError::Media(format!(
+                "ffmpeg could not merge {} and {}: {}",
+                video.display(),
+                audio.display(),
+                String::from_utf8_lossy(&output_status.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl PostProcessor for Remuxer {
+    fn stage(&self) -> Stage {
+        Stage::Remux
+    }
+    
+    fn process(&self, _task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        if extension.eq_ignore_ascii_case(&self.container) {
+            return Ok(None);
+        }
+        
+        let output = path.with_extension(&self.container);
+        if output.exists() {
+            return Err(DownloaderError::PostProcessing(format!("{} already exists", output.display())));
+        }
+        // ffmpeg picks the muxer from the extension, so keep it last
+        let temp = path.with_extension(format!("remux.{}", self.container));
+        
+        let result = Command::new("ffmpeg")
+            .args(["-nostdin", "-y", "-loglevel", "error", "-i"])
+            .arg(path)
+            .args(["-map", "0", "-c", "copy"])
+            .arg(&temp)
+            .stdin(Stdio::null())
+            .output();
+        let output_status = match result {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(DownloaderError::PostProcessing(format!(
+                    "ffmpeg is required to remux into {} but was not found",
+                    self.container
+                )))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !output_status.status.success() {
+            let _ = fs::remove_file(&temp);
+            return Err(DownloaderError::PostProcessing(format!(
+                "ffmpeg could not remux {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output_status.stderr).trim()
+            )));
+        }
+        
+        fs::rename(&temp, &output)?;
+        fs::remove_file(path)?;
+        Ok(Some(output))
+    }
+}
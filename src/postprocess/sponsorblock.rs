@@ -0,0 +1,53 @@
+//! Marking or cutting out SponsorBlock segments
+
+use crate::extractor::{SponsorBlockClient, SponsorSegment};
+use crate::file_system::FileOrganizer;
+use crate::media::MediaCutter;
+use crate::models::DownloadTask;
+use crate::postprocess::{PostProcessor, Stage};
+use crate::Result;
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct SponsorBlockProcessor {
+    marked: Vec<SponsorSegment>,
+    removed: Vec<SponsorSegment>,
+}
+
+impl SponsorBlockProcessor {
+    /// Remove segments in the `remove` categories and mark all others as chapters
+    pub fn new(segments: Vec<SponsorSegment>, remove: &[String]) -> Self {
+        let (removed, marked) = segments.into_iter().partition(|segment| remove.contains(&segment.category));
+        Self { marked, removed }
+    }
+}
+
+impl PostProcessor for SponsorBlockProcessor {
+    fn stage(&self) -> Stage {
+        Stage::SponsorBlock
+    }
+    
+    /// Chapters on the task are updated to match, for the stages that follow
+    fn process(&self, task: &mut DownloadTask, path: &Path) -> Result<Option<PathBuf>> {
+        if !self.marked.is_empty() {
+            let duration = task.video_info.duration_seconds.unwrap_or(0) as f64;
+            task.video_info.chapters = SponsorBlockClient::mark_chapters(&task.video_info.chapters, &self.marked, duration);
+            info!("Marked {} SponsorBlock segments as chapters", self.marked.len());
+        }
+        if self.removed.is_empty() {
+            return Ok(None);
+        }
+        
+        let cut = FileOrganizer::temp_path(path, "sponsorblock");
+        let ranges = SponsorBlockClient::keep_ranges(&self.removed);
+        if let Err(e) = MediaCutter::keep_ranges(path, &cut, &ranges).and_then(|_| Ok(fs::rename(&cut, path)?)) {
+            let _ = fs::remove_file(&cut);
+            return Err(e);
+        }
+        
+        task.video_info.chapters = SponsorBlockClient::shift_chapters(&task.video_info.chapters, &self.removed);
+        info!("Removed {} SponsorBlock segments from {}", self.removed.len(), path.display());
+        Ok(None)
+    }
+}
@@ -0,0 +1,378 @@
+//! Full-screen dashboard for following and steering a download queue
+
+use crate::downloader::{ChunkState, DownloadQueue, ItemState, QueueItem};
+use crate::models::DownloadProgress;
+use crate::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::time::Duration;
+
+/// How often the screen is redrawn while no key is pressed
+const REFRESH: Duration = Duration::from_millis(200);
+
+/// Rate limits `+` and `-` step through, in bytes per second
+const RATE_STEPS: [u64; 10] = [
+    128 << 10,
+    256 << 10,
+    512 << 10,
+    1 << 20,
+    2 << 20,
+    4 << 20,
+    8 << 20,
+    16 << 20,
+    32 << 20,
+    64 << 20,
+];
+
+/// Width of the text progress bar
+const BAR_WIDTH: usize = 16;
+
+const HELP: &str = "↑/↓ select  p pause/resume  c cancel  K/J move up/down  +/- rate limit  0 unlimited  q quit";
+
+/// Shows every queued item with its state, progress, speed, ETA and chunk map,
+/// and lets the user pause, resume, cancel and reorder items or change the
+/// rate limit while the queue runs.
+pub struct QueueDashboard {
+    queue: DownloadQueue,
+    table: TableState,
+}
+
+impl QueueDashboard {
+    pub fn new(queue: DownloadQueue) -> Self {
+        Self {
+            queue,
+            table: TableState::default().with_selected(Some(0)),
+        }
+    }
+    
+    /// Take over the terminal until every item is done, or the user quits and
+    /// cancels what is left
+    pub fn run(mut self) -> Result<()> {
+        let mut terminal = ratatui::try_init()?;
+        let result = self.event_loop(&mut terminal);
+        ratatui::try_restore()?;
+        result
+    }
+    
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            let items = self.queue.snapshot();
+            terminal.draw(|frame| self.render(frame, &items))?;
+            if self.queue.is_done() {
+                return Ok(());
+            }
+            if !event::poll(REFRESH)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key, &items);
+                }
+            }
+        }
+    }
+    
+    fn handle_key(&mut self, key: KeyEvent, items: &[QueueItem]) {
+        let last = items.len().saturating_sub(1);
+        let current = self.table.selected().unwrap_or(0).min(last);
+        let selected = items.get(current);
+        let limiter = self.queue.limiter();
+        
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.queue.cancel_all(),
+            KeyCode::Esc | KeyCode::Char('q') => self.queue.cancel_all(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select(Some(current.saturating_sub(1))),
+            KeyCode::Down | KeyCode::Char('j') => self.table.select(Some((current + 1).min(last))),
+            KeyCode::Char('p') | KeyCode::Char(' ') => {
+                if let Some(item) = selected {
+                    if item.state == ItemState::Paused {
+                        self.queue.resume(item.id);
+                    } else {
+                        self.queue.pause(item.id);
+                    }
+                }
+            }
+            KeyCode::Char('c') | KeyCode::Delete => {
+                if let Some(item) = selected {
+                    self.queue.cancel(item.id);
+                }
+            }
+            KeyCode::Char('K') if current > 0 => {
+                if let Some(item) = selected {
+                    self.queue.reorder(item.id, true);
+                    self.table.select(Some(current - 1));
+                }
+            }
+            KeyCode::Char('J') if current < last => {
+                if let Some(item) = selected {
+                    self.queue.reorder(item.id, false);
+                    self.table.select(Some(current + 1));
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => limiter.set_limit(Self::faster(limiter.limit())),
+            KeyCode::Char('-') => limiter.set_limit(Self::slower(limiter.limit())),
+            KeyCode::Char('0') => limiter.set_limit(None),
+            _ => {}
+        }
+    }
+    
+    /// The next step up from `limit`; past the last step the limit is removed
+    fn faster(limit: Option<u64>) -> Option<u64> {
+        let limit = limit?;
+        RATE_STEPS.into_iter().find(|&step| step > limit)
+    }
+    
+    /// The next step down from `limit`, never below the first
+    fn slower(limit: Option<u64>) -> Option<u64> {
+        let slower = RATE_STEPS.into_iter().rev().find(|&step| limit.is_none_or(|limit| step < limit));
+        Some(slower.unwrap_or(RATE_STEPS[0]))
+    }
+    
+    fn render(&mut self, frame: &mut Frame, items: &[QueueItem]) {
+        let [summary_area, table_area, detail_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(4),
+            Constraint::Length(7),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        
+        frame.render_widget(self.summary(items), summary_area);
+        frame.render_stateful_widget(Self::table(items), table_area, &mut self.table);
+        let selected = self.table.selected().and_then(|row| items.get(row));
+        frame.render_widget(Self::details(selected, detail_area), detail_area);
+        frame.render_widget(Line::from(HELP).dim(), help_area);
+    }
+    
+    fn summary(&self, items: &[QueueItem]) -> Paragraph<'static> {
+        let count = |state: ItemState| items.iter().filter(|item| item.state == state).count();
+        let speed: f64 = items
+            .iter()
+            .filter(|item| item.state == ItemState::Downloading)
+            .map(|item| item.progress.download_speed)
+            .sum();
+        let limit = match self.queue.limiter().limit() {
+            Some(limit) => format!("{}/s", DownloadProgress::format_bytes(limit)),
+            None => "unlimited".to_string(),
+        };
+        
+        let mut parts = vec![
+            format!("{}/{} done", count(ItemState::Finished), items.len()),
+            format!("{} downloading", count(ItemState::Downloading)),
+        ];
+        for (state, label) in [(ItemState::Paused, "paused"), (ItemState::Failed, "failed"), (ItemState::Cancelled, "cancelled")] {
+            if count(state) > 0 {
+                parts.push(format!("{} {}", count(state), label));
+            }
+        }
+        parts.push(DownloadProgress { download_speed: speed, ..DownloadProgress::new() }.speed_string());
+        parts.push(format!("limit {}", limit));
+        
+        Paragraph::new(parts.join(" · ")).block(Block::bordered().title(" Download queue "))
+    }
+    
+    fn table(items: &[QueueItem]) -> Table<'static> {
+        let header = Row::new(["#", "STATE", "TITLE", "PROGRESS", "SPEED", "ETA"])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = items.iter().map(|item| {
+            let (state, color) = Self::state_label(item.state);
+            let downloading = item.state == ItemState::Downloading;
+            let progress = match item.error {
+                Some(ref error) if item.state == ItemState::Failed => error.clone(),
+                _ => Self::progress(&item.progress),
+            };
+            Row::new([
+                (item.id + 1).to_string(),
+                state.to_string(),
+                item.title.clone().unwrap_or_else(|| item.url.clone()),
+                progress,
+                if downloading { item.progress.speed_string() } else { String::new() },
+                if downloading && item.progress.eta_seconds > 0 { item.progress.eta_string() } else { String::new() },
+            ])
+            .style(Style::new().fg(color))
+        });
+        
+        Table::new(
+            rows.collect::<Vec<_>>(),
+            [
+                Constraint::Length(4),
+                Constraint::Length(11),
+                Constraint::Fill(1),
+                Constraint::Length(BAR_WIDTH as u16 + 8),
+                Constraint::Length(11),
+                Constraint::Length(8),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered())
+    }
+    
+    fn state_label(state: ItemState) -> (&'static str, Color) {
+        match state {
+            ItemState::Queued => ("queued", Color::Reset),
+            ItemState::Downloading => ("downloading", Color::Cyan),
+            ItemState::Paused => ("paused", Color::Yellow),
+            ItemState::Finished => ("done", Color::Green),
+            ItemState::Failed => ("failed", Color::Red),
+            ItemState::Cancelled => ("cancelled", Color::DarkGray),
+        }
+    }
+    
+    /// A bar and percentage when the size is known, otherwise the bytes so far
+    fn progress(progress: &DownloadProgress) -> String {
+        if progress.total_size > 0 {
+            let percent = progress.percentage().min(100.0);
+            let filled = (percent / 100.0 * BAR_WIDTH as f64).round() as usize;
+            format!("{}{} {:>3.0}%", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled), percent)
+        } else if progress.downloaded_size > 0 {
+            progress.downloaded_string()
+        } else {
+            String::new()
+        }
+    }
+    
+    /// URL, path or error, and a map of the selected item's chunks
+    fn details(item: Option<&QueueItem>, area: Rect) -> Paragraph<'static> {
+        let Some(item) = item else {
+            return Paragraph::new("").block(Block::bordered());
+        };
+        
+        let mut lines = vec![Line::from(item.url.clone())];
+        if let Some(ref path) = item.path {
+            lines.push(Line::from(format!("Saved to {}", path.display())));
+        }
+        if let Some(ref error) = item.error {
+            lines.push(Line::from(error.clone()).red());
+        }
+        if !item.chunks.is_empty() {
+            let done = item.chunks.iter().filter(|&&chunk| chunk == ChunkState::Done).count();
+            lines.push(Line::from(format!("Chunks {}/{}", done, item.chunks.len())));
+            // Whatever room the borders and lines above leave
+            let rows = area.height.saturating_sub(2 + lines.len() as u16).max(1);
+            let capacity = usize::from(area.width.saturating_sub(2)) * usize::from(rows);
+            lines.push(Line::from(Self::chunk_map(&item.chunks, capacity)));
+        }
+        
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(format!(" #{} ", item.id + 1)))
+    }
+    
+    /// One cell per chunk, or per group of chunks when they do not fit in
+    /// `capacity` cells: done, active (any chunk in the group) or pending
+    fn chunk_map(chunks: &[ChunkState], capacity: usize) -> String {
+        let group = chunks.len().div_ceil(capacity.max(1)).max(1);
+        chunks
+            .chunks(group)
+            .map(|group| {
+                if group.iter().all(|&chunk| chunk == ChunkState::Done) {
+                    '█'
+                } else if group.contains(&ChunkState::Active) {
+                    '▒'
+                } else {
+                    '·'
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    
+    fn dashboard(urls: &[&str]) -> QueueDashboard {
+        let queue = DownloadQueue::default();
+        for url in urls {
+            queue.push(url.to_string());
+        }
+        QueueDashboard::new(queue)
+    }
+    
+    fn press(dashboard: &mut QueueDashboard, code: KeyCode) {
+        let items = dashboard.queue.snapshot();
+        dashboard.handle_key(KeyEvent::from(code), &items);
+    }
+    
+    #[test]
+    fn test_keys_control_the_queue() {
+        let mut dashboard = dashboard(&["a", "b", "c"]);
+        
+        press(&mut dashboard, KeyCode::Char('p'));
+        assert_eq!(dashboard.queue.snapshot()[0].state, ItemState::Paused);
+        press(&mut dashboard, KeyCode::Char('p'));
+        assert_eq!(dashboard.queue.snapshot()[0].state, ItemState::Queued);
+        
+        // Move "a" to the back; the selection follows it
+        press(&mut dashboard, KeyCode::Char('J'));
+        press(&mut dashboard, KeyCode::Char('J'));
+        press(&mut dashboard, KeyCode::Char('J'));
+        let urls: Vec<String> = dashboard.queue.snapshot().into_iter().map(|item| item.url).collect();
+        assert_eq!(urls, ["b", "c", "a"]);
+        assert_eq!(dashboard.table.selected(), Some(2));
+        
+        press(&mut dashboard, KeyCode::Char('c'));
+        assert_eq!(dashboard.queue.snapshot()[2].state, ItemState::Cancelled);
+        
+        press(&mut dashboard, KeyCode::Char('-'));
+        assert_eq!(dashboard.queue.limiter().limit(), Some(64 << 20));
+        press(&mut dashboard, KeyCode::Char('-'));
+        assert_eq!(dashboard.queue.limiter().limit(), Some(32 << 20));
+        press(&mut dashboard, KeyCode::Char('+'));
+        press(&mut dashboard, KeyCode::Char('+'));
+        assert_eq!(dashboard.queue.limiter().limit(), None);
+        
+        press(&mut dashboard, KeyCode::Char('q'));
+        assert!(dashboard.queue.is_done());
+    }
+    
+    #[test]
+    fn test_rate_steps() {
+        assert_eq!(QueueDashboard::slower(Some(128 << 10)), Some(128 << 10));
+        assert_eq!(QueueDashboard::slower(Some(3 << 20)), Some(2 << 20));
+        assert_eq!(QueueDashboard::faster(Some(3 << 20)), Some(4 << 20));
+        assert_eq!(QueueDashboard::faster(None), None);
+    }
+    
+    #[test]
+    fn test_chunk_map() {
+        use ChunkState::*;
+        assert_eq!(QueueDashboard::chunk_map(&[Done, Active, Pending], 10), "█▒·");
+        assert_eq!(QueueDashboard::chunk_map(&[Done, Done, Done, Active, Pending, Pending], 3), "█▒·");
+    }
+    
+    #[test]
+    fn test_render() {
+        let mut dashboard = dashboard(&["https://youtu.be/a", "https://youtu.be/b"]);
+        let item = dashboard.queue.start_next().unwrap();
+        item.set_title("First video");
+        item.set_chunks(4);
+        item.set_chunk(0, ChunkState::Done);
+        item.set_progress(DownloadProgress {
+            total_size: 1000,
+            downloaded_size: 500,
+            download_speed: 2048.0,
+            eta_seconds: 30,
+            is_complete: false,
+        });
+        
+        let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        let items = dashboard.queue.snapshot();
+        terminal.draw(|frame| dashboard.render(frame, &items)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        
+        assert!(screen.contains("0/2 done · 1 downloading · 2.0 KB/s · limit unlimited"));
+        assert!(screen.contains("First video"));
+        assert!(screen.contains("████████░░░░░░░░  50%"));
+        assert!(screen.contains("30s"));
+        assert!(screen.contains("Chunks 1/4"));
+        assert!(screen.contains("█···"));
+    }
+}
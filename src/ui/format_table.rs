@@ -0,0 +1,138 @@
+//! Tabular listing of available formats
+
+use crate::models::{DownloadProgress, DynamicRange, Format, FormatType};
+
+pub(crate) const HEADERS: [&str; 8] = ["ITAG", "EXT", "RESOLUTION", "FPS", "CODECS", "BITRATE", "SIZE", "NOTE"];
+
+pub struct FormatTable;
+
+impl FormatTable {
+    /// Render formats as an aligned plain-text table
+    pub fn render(formats: &[Format]) -> String {
+        let rows: Vec<[String; 8]> = formats.iter().map(Self::row).collect();
+        
+        // Column width is the widest cell (or header) in that column
+        let mut widths = HEADERS.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        
+        let mut output = Self::render_line(&HEADERS.map(str::to_string), &widths);
+        let total_width = widths.iter().sum::<usize>() + 2 * (widths.len() - 1);
+        output.push_str(&"-".repeat(total_width));
+        output.push('\n');
+        
+        for row in &rows {
+            output.push_str(&Self::render_line(row, &widths));
+        }
+        
+        output
+    }
+    
+    /// Build the table cells for a single format
+    pub(crate) fn row(format: &Format) -> [String; 8] {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        
+        let codecs = match (&format.vcodec, &format.acodec) {
+            (Some(v), Some(a)) => format!("{}, {}", v, a),
+            (Some(v), None) => v.clone(),
+            (None, Some(a)) => a.clone(),
+            (None, None) => or_dash(format.codec.clone()),
+        };
+        
+        [
+            or_dash(format.itag.map(|itag| itag.to_string())),
+            format.file_extension.clone(),
+            format.resolution(),
+            or_dash(format.fps.map(|fps| fps.to_string())),
+            codecs,
+            or_dash(format.bitrate.map(|bitrate| format!("{}k", bitrate / 1000))),
+            Self::size(format),
+            Self::note(format),
+        ]
+    }
+    
+    /// File size, marked with "~" when estimated from the bitrate
+    pub(crate) fn size(format: &Format) -> String {
+        match (format.file_size, format.estimated_size()) {
+            (Some(size), _) => DownloadProgress::format_bytes(size),
+            (None, Some(size)) => format!("~{}", DownloadProgress::format_bytes(size)),
+            (None, None) => "-".to_string(),
+        }
+    }
+    
+    /// Short free-form remarks about a format
+    fn note(format: &Format) -> String {
+        let mut notes = Vec::new();
+        
+        if format.is_adaptive {
+            notes.push(match format.format_type {
+                FormatType::Video => "video only".to_string(),
+                FormatType::Audio => "audio only".to_string(),
+            });
+        }
+        
+        if format.dynamic_range == Some(DynamicRange::Hdr) {
+            notes.push("HDR".to_string());
+        }
+        
+        if let Some(ref language) = format.language {
+            notes.push(format!("[{}]", language));
+        }
+        
+        notes.join(", ")
+    }
+    
+    fn render_line(cells: &[String; 8], widths: &[usize; 8]) -> String {
+        let line = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        
+        format!("{}\n", line.trim_end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_render_aligns_columns() {
+        let mut video = Format::new(
+            "1080p".to_string(),
+            FormatType::Video,
+            "mp4".to_string(),
+            "https://example.com/137".to_string(),
+        );
+        video.itag = Some(137);
+        video.width = Some(1920);
+        video.height = Some(1080);
+        video.fps = Some(30);
+        video.vcodec = Some("avc1.640028".to_string());
+        video.bitrate = Some(4_500_000);
+        video.file_size = Some(50 * 1024 * 1024);
+        video.is_adaptive = true;
+        
+        let mut audio = Format::new(
+            "128kbps".to_string(),
+            FormatType::Audio,
+            "mp3".to_string(),
+            "https://example.com/140".to_string(),
+        );
+        audio.itag = Some(140);
+        audio.acodec = Some("mp4a.40.2".to_string());
+        
+        let table = FormatTable::render(&[video, audio]);
+        let lines: Vec<&str> = table.lines().collect();
+        
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("ITAG  EXT  RESOLUTION"));
+        assert!(lines[2].starts_with("137   mp4  1920x1080   30   avc1.640028  4500k    50.0 MB  video only"));
+        assert!(lines[3].starts_with("140   mp3  audio only  -    mp4a.40.2    -        -"));
+    }
+}
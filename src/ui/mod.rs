@@ -1,7 +1,13 @@
 //! User interface components module
 
 pub mod selection;
+pub mod picker;
+pub mod dashboard;
 pub mod progress_bar;
+pub mod format_table;
 
-pub use selection::SelectionUI;
-pub use progress_bar::ProgressBarUI;
\ No newline at end of file
+pub use selection::{FormatSelection, SelectionUI};
+pub use picker::FormatPicker;
+pub use dashboard::QueueDashboard;
+pub use progress_bar::{ProgressBarUI, ProgressMode};
+pub use format_table::FormatTable;
\ No newline at end of file
@@ -0,0 +1,469 @@
+//! Full-screen, keyboard-driven format picker
+
+use crate::error::DownloaderError;
+use crate::extractor::FormatExtractor;
+use crate::models::{DownloadProgress, Format, FormatType, VideoInfo};
+use crate::ui::format_table::{FormatTable, HEADERS};
+use crate::ui::FormatSelection;
+use crate::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::cmp::Ordering;
+
+/// Rows moved by Page Up and Page Down
+const PAGE: usize = 10;
+
+const HELP: &str = "↑/↓ move  space select  enter confirm  t type  c codec  s sort  r reverse  q cancel";
+
+/// Column the table is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Quality,
+    Size,
+    Bitrate,
+    Codec,
+    Extension,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Quality => SortKey::Size,
+            SortKey::Size => SortKey::Bitrate,
+            SortKey::Bitrate => SortKey::Codec,
+            SortKey::Codec => SortKey::Extension,
+            SortKey::Extension => SortKey::Quality,
+        }
+    }
+    
+    /// Index of the sorted column in [`HEADERS`]
+    fn column(self) -> usize {
+        match self {
+            SortKey::Quality => 2,
+            SortKey::Size => 6,
+            SortKey::Bitrate => 5,
+            SortKey::Codec => 4,
+            SortKey::Extension => 1,
+        }
+    }
+    
+    /// Bigger is better for quality, size and bitrate, so those start descending
+    fn descending_by_default(self) -> bool {
+        matches!(self, SortKey::Quality | SortKey::Size | SortKey::Bitrate)
+    }
+}
+
+/// What a key press asks the picker to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Continue,
+    Confirm,
+    Cancel,
+}
+
+/// A sortable, filterable table of a video's formats.
+///
+/// Space marks the highlighted row as the video or audio stream, depending on
+/// its type, so a video-only and an audio stream can be picked for merging.
+/// Enter confirms the marked streams, or the highlighted row if none is marked.
+pub struct FormatPicker<'a> {
+    video_info: &'a VideoInfo,
+    /// Indices into `video_info.available_formats`, filtered and sorted
+    rows: Vec<usize>,
+    table: TableState,
+    sort: SortKey,
+    descending: bool,
+    type_filter: Option<FormatType>,
+    /// Codec families present among the formats (e.g. "avc1", "opus")
+    codecs: Vec<String>,
+    codec_filter: Option<usize>,
+    video: Option<usize>,
+    audio: Option<usize>,
+}
+
+impl<'a> FormatPicker<'a> {
+    pub fn new(video_info: &'a VideoInfo) -> Self {
+        let mut codecs: Vec<String> = video_info.available_formats.iter().flat_map(Self::codec_families).collect();
+        codecs.sort();
+        codecs.dedup();
+        
+        let mut picker = Self {
+            video_info,
+            rows: Vec::new(),
+            table: TableState::default(),
+            sort: SortKey::Quality,
+            descending: true,
+            type_filter: None,
+            codecs,
+            codec_filter: None,
+            video: None,
+            audio: None,
+        };
+        picker.refresh();
+        picker
+    }
+    
+    /// Show only formats of `format_type` at first
+    pub fn with_type_filter(mut self, format_type: Option<FormatType>) -> Self {
+        self.type_filter = format_type;
+        self.refresh();
+        self
+    }
+    
+    /// Take over the terminal until the user confirms or cancels
+    pub fn run(mut self) -> Result<FormatSelection> {
+        let mut terminal = ratatui::try_init()?;
+        let result = self.event_loop(&mut terminal);
+        ratatui::try_restore()?;
+        result
+    }
+    
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<FormatSelection> {
+        loop {
+            terminal.draw(|frame| self.render(frame))?;
+            if let Event::Key(key) = event::read()? {
+                // Some terminals also report releases
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match self.handle_key(key) {
+                    Outcome::Continue => {}
+                    Outcome::Confirm => return Ok(self.selection()),
+                    Outcome::Cancel => return Err(DownloaderError::UserCancelled),
+                }
+            }
+        }
+    }
+    
+    fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let last = self.rows.len().saturating_sub(1);
+        let current = self.table.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Outcome::Cancel,
+            KeyCode::Esc | KeyCode::Char('q') => return Outcome::Cancel,
+            KeyCode::Up | KeyCode::Char('k') => self.highlight(current.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.highlight((current + 1).min(last)),
+            KeyCode::PageUp => self.highlight(current.saturating_sub(PAGE)),
+            KeyCode::PageDown => self.highlight((current + PAGE).min(last)),
+            KeyCode::Home | KeyCode::Char('g') => self.highlight(0),
+            KeyCode::End | KeyCode::Char('G') => self.highlight(last),
+            KeyCode::Char('t') => {
+                self.type_filter = match self.type_filter {
+                    None => Some(FormatType::Video),
+                    Some(FormatType::Video) => Some(FormatType::Audio),
+                    Some(FormatType::Audio) => None,
+                };
+                self.refresh();
+            }
+            KeyCode::Char('c') => {
+                self.codec_filter = match self.codec_filter {
+                    None if !self.codecs.is_empty() => Some(0),
+                    Some(index) if index + 1 < self.codecs.len() => Some(index + 1),
+                    _ => None,
+                };
+                self.refresh();
+            }
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.descending = self.sort.descending_by_default();
+                self.refresh();
+            }
+            KeyCode::Char('r') => {
+                self.descending = !self.descending;
+                self.refresh();
+            }
+            KeyCode::Char(' ') => self.toggle_highlighted(),
+            KeyCode::Enter => {
+                if self.video.is_none() && self.audio.is_none() {
+                    self.toggle_highlighted();
+                }
+                if self.video.is_some() || self.audio.is_some() {
+                    return Outcome::Confirm;
+                }
+            }
+            _ => {}
+        }
+        Outcome::Continue
+    }
+    
+    fn formats(&self) -> &'a [Format] {
+        &self.video_info.available_formats
+    }
+    
+    fn highlight(&mut self, row: usize) {
+        if !self.rows.is_empty() {
+            self.table.select(Some(row));
+        }
+    }
+    
+    /// Index of the highlighted format
+    fn highlighted(&self) -> Option<usize> {
+        self.table.selected().and_then(|row| self.rows.get(row).copied())
+    }
+    
+    /// Mark or unmark the highlighted format as the video or audio stream
+    fn toggle_highlighted(&mut self) {
+        let Some(index) = self.highlighted() else {
+            return;
+        };
+        let slot = match self.formats()[index].format_type {
+            FormatType::Video => &mut self.video,
+            FormatType::Audio => &mut self.audio,
+        };
+        *slot = if *slot == Some(index) { None } else { Some(index) };
+    }
+    
+    fn selection(&self) -> FormatSelection {
+        let formats = self.formats();
+        FormatSelection {
+            video: self.video.map(|index| formats[index].clone()),
+            audio: self.audio.map(|index| formats[index].clone()),
+        }
+    }
+    
+    /// Re-apply filters and sorting, keeping the highlighted format when it is still listed
+    fn refresh(&mut self) {
+        let highlighted = self.highlighted();
+        let formats = self.formats();
+        let codec = self.codec_filter.map(|index| self.codecs[index].as_str());
+        
+        let mut rows: Vec<usize> = (0..formats.len())
+            .filter(|&index| {
+                let format = &formats[index];
+                self.type_filter.as_ref().is_none_or(|format_type| format.format_type == *format_type)
+                    && codec.is_none_or(|codec| Self::codec_families(format).any(|family| family == codec))
+            })
+            .collect();
+        rows.sort_by(|&a, &b| {
+            let ordering = self.compare(&formats[a], &formats[b]);
+            if self.descending { ordering.reverse() } else { ordering }
+        });
+        self.rows = rows;
+        
+        let row = highlighted.and_then(|index| self.rows.iter().position(|&row| row == index));
+        self.table.select(if self.rows.is_empty() { None } else { Some(row.unwrap_or(0)) });
+    }
+    
+    fn compare(&self, a: &Format, b: &Format) -> Ordering {
+        let by_quality = FormatExtractor::quality_rank(a).cmp(&FormatExtractor::quality_rank(b));
+        let ordering = match self.sort {
+            SortKey::Quality => Ordering::Equal,
+            SortKey::Size => a.estimated_size().cmp(&b.estimated_size()),
+            SortKey::Bitrate => a.bitrate.cmp(&b.bitrate),
+            SortKey::Codec => Self::codec_families(a).next().cmp(&Self::codec_families(b).next()),
+            SortKey::Extension => a.file_extension.cmp(&b.file_extension),
+        };
+        ordering.then(by_quality)
+    }
+    
+    /// Codec names without profile details, e.g. "avc1" for "avc1.640028"
+    fn codec_families(format: &Format) -> impl Iterator<Item = String> + '_ {
+        [&format.vcodec, &format.acodec, &format.codec]
+            .into_iter()
+            .flatten()
+            .map(|codec| codec.split('.').next().unwrap_or(codec).to_string())
+    }
+    
+    fn render(&mut self, frame: &mut Frame) {
+        let [header_area, table_area, footer_area] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Min(3),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+        
+        frame.render_widget(self.header(), header_area);
+        let table = self.table();
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+        frame.render_widget(self.footer(), footer_area);
+    }
+    
+    fn header(&self) -> Paragraph<'a> {
+        let video_info = self.video_info;
+        let mut details = Vec::new();
+        if let Some(ref uploader) = video_info.uploader {
+            details.push(uploader.clone());
+        }
+        details.push(video_info.duration.clone());
+        details.push(format!("{} formats", video_info.available_formats.len()));
+        
+        Paragraph::new(vec![
+            Line::from(video_info.title.as_str()).bold(),
+            Line::from(details.join(" · ")),
+        ])
+        .block(Block::bordered().title(" Choose a format "))
+    }
+    
+    fn table(&self) -> Table<'static> {
+        let formats = self.formats();
+        let cells: Vec<[String; 8]> = formats.iter().map(FormatTable::row).collect();
+        
+        // Size columns for every format, so filtering does not shift them
+        let mut widths = HEADERS.map(|header| header.len() + 2);
+        for row in &cells {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let constraints = std::iter::once(Constraint::Length(1))
+            .chain(widths.iter().map(|&width| Constraint::Length(width as u16)));
+        
+        let arrow = if self.descending { " ▼" } else { " ▲" };
+        let header = Row::new(std::iter::once(String::new()).chain(HEADERS.iter().enumerate().map(|(column, header)| {
+            if column == self.sort.column() { format!("{}{}", header, arrow) } else { header.to_string() }
+        })))
+        .style(Style::new().add_modifier(Modifier::BOLD));
+        
+        let rows = self.rows.iter().map(|&index| {
+            let mark = if self.video == Some(index) {
+                "V"
+            } else if self.audio == Some(index) {
+                "A"
+            } else {
+                ""
+            };
+            let row = Row::new(std::iter::once(mark.to_string()).chain(cells[index].iter().cloned()));
+            if mark.is_empty() { row } else { row.style(Style::new().add_modifier(Modifier::BOLD)) }
+        });
+        
+        let mut filters = vec![match self.type_filter {
+            None => "all types".to_string(),
+            Some(FormatType::Video) => "video".to_string(),
+            Some(FormatType::Audio) => "audio".to_string(),
+        }];
+        if let Some(index) = self.codec_filter {
+            filters.push(self.codecs[index].clone());
+        }
+        
+        Table::new(rows.collect::<Vec<_>>(), constraints)
+            .header(header)
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ")
+            .block(Block::bordered().title(format!(" {} of {} · {} ", self.rows.len(), formats.len(), filters.join(", "))))
+    }
+    
+    fn footer(&self) -> Paragraph<'static> {
+        let selection = self.selection();
+        let mut summary = format!("Selected: {}", selection);
+        if let Some(size) = selection.estimated_size() {
+            summary.push_str(&format!(" ({})", DownloadProgress::format_bytes(size)));
+        }
+        if selection.video.as_ref().is_some_and(|video| video.is_adaptive) && selection.audio.is_none() {
+            summary.push_str(" - no audio, press space on an audio row to merge one");
+        }
+        Paragraph::new(vec![Line::from(summary), Line::from(HELP).dim()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    
+    fn video_info() -> VideoInfo {
+        let mut video_info = VideoInfo::new("Test video".to_string(), "3:32".to_string(), "abc".to_string());
+        
+        let mut muxed = Format::new("360p".to_string(), FormatType::Video, "mp4".to_string(), String::new());
+        muxed.height = Some(360);
+        muxed.vcodec = Some("avc1.42001E".to_string());
+        muxed.acodec = Some("mp4a.40.2".to_string());
+        muxed.file_size = Some(9_000_000);
+        
+        let mut hd = Format::new("1080p".to_string(), FormatType::Video, "webm".to_string(), String::new());
+        hd.height = Some(1080);
+        hd.vcodec = Some("vp9".to_string());
+        hd.file_size = Some(80_000_000);
+        hd.is_adaptive = true;
+        
+        let mut opus = Format::new("160kbps".to_string(), FormatType::Audio, "webm".to_string(), String::new());
+        opus.acodec = Some("opus".to_string());
+        opus.bitrate = Some(160_000);
+        opus.approx_duration_ms = Some(212_000);
+        opus.is_adaptive = true;
+        
+        video_info.add_format(muxed);
+        video_info.add_format(hd);
+        video_info.add_format(opus);
+        video_info
+    }
+    
+    fn press(picker: &mut FormatPicker, code: KeyCode) -> Outcome {
+        picker.handle_key(KeyEvent::from(code))
+    }
+    
+    #[test]
+    fn test_sorting_and_filters() {
+        let video_info = video_info();
+        let mut picker = FormatPicker::new(&video_info);
+        assert_eq!(picker.rows, vec![1, 0, 2]);
+        
+        press(&mut picker, KeyCode::Char('r'));
+        assert_eq!(picker.rows, vec![2, 0, 1]);
+        
+        // Size, largest first; the audio size is estimated from its bitrate
+        press(&mut picker, KeyCode::Char('s'));
+        assert_eq!(picker.rows, vec![1, 0, 2]);
+        
+        press(&mut picker, KeyCode::Char('t'));
+        assert_eq!(picker.rows, vec![1, 0]);
+        press(&mut picker, KeyCode::Char('t'));
+        assert_eq!(picker.rows, vec![2]);
+        press(&mut picker, KeyCode::Char('t'));
+        
+        // Codec families: avc1, mp4a, opus, vp9
+        assert_eq!(picker.codecs, ["avc1", "mp4a", "opus", "vp9"]);
+        press(&mut picker, KeyCode::Char('c'));
+        press(&mut picker, KeyCode::Char('c'));
+        assert_eq!(picker.rows, vec![0]);
+    }
+    
+    #[test]
+    fn test_pick_video_and_audio_to_merge() {
+        let video_info = video_info();
+        let mut picker = FormatPicker::new(&video_info);
+        
+        // 1080p video only, then the opus audio at the bottom
+        press(&mut picker, KeyCode::Char(' '));
+        press(&mut picker, KeyCode::End);
+        press(&mut picker, KeyCode::Char(' '));
+        assert_eq!(press(&mut picker, KeyCode::Enter), Outcome::Confirm);
+        
+        let selection = picker.selection();
+        assert!(selection.needs_merge());
+        assert_eq!(selection.video.unwrap().quality, "1080p");
+        assert_eq!(selection.audio.unwrap().quality, "160kbps");
+    }
+    
+    #[test]
+    fn test_enter_picks_highlighted_row() {
+        let video_info = video_info();
+        let mut picker = FormatPicker::new(&video_info);
+        press(&mut picker, KeyCode::Down);
+        assert_eq!(press(&mut picker, KeyCode::Enter), Outcome::Confirm);
+        assert_eq!(picker.selection().video.unwrap().quality, "360p");
+        
+        assert_eq!(press(&mut picker, KeyCode::Esc), Outcome::Cancel);
+        assert_eq!(picker.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)), Outcome::Cancel);
+    }
+    
+    #[test]
+    fn test_render() {
+        let video_info = video_info();
+        let mut picker = FormatPicker::new(&video_info);
+        press(&mut picker, KeyCode::Char(' '));
+        
+        let mut terminal = Terminal::new(TestBackend::new(110, 14)).unwrap();
+        terminal.draw(|frame| picker.render(frame)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        
+        assert!(screen.contains("Test video"));
+        assert!(screen.contains("RESOLUTION ▼"));
+        assert!(screen.contains("~4.0 MB"));
+        assert!(screen.contains("Selected: 1080p (video only) (76.3 MB) - no audio"));
+    }
+}
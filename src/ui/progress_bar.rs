@@ -1,41 +1,254 @@
 //! Progress bar implementation for downloads
 
+use crate::downloader::ChunkState;
 use crate::models::DownloadProgress;
-use crate::Result;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
+/// How often a progress line is printed when stderr is not a terminal
+const LINE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest label shown in front of a bar
+const LABEL_WIDTH: usize = 30;
+
+/// How download progress is shown on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressMode {
+    /// Redrawn bars, for terminals
+    #[default]
+    Bars,
+    /// A plain line every few seconds, for logs and pipes
+    Lines,
+    Hidden,
+}
+
+impl ProgressMode {
+    /// Bars when stderr is a terminal, plain lines otherwise
+    pub fn detect() -> Self {
+        if std::io::stderr().is_terminal() {
+            ProgressMode::Bars
+        } else {
+            ProgressMode::Lines
+        }
+    }
+}
+
+/// Progress of one video: an overall bar, plus one bar per connection when
+/// asked for. Bars of all videos share one display on stderr.
 pub struct ProgressBarUI {
-    progress_bar: ProgressBar,
+    mode: ProgressMode,
+    label: String,
+    overall: ProgressBar,
+    connections: Vec<ProgressBar>,
+    last: DownloadProgress,
+    last_line: Option<Instant>,
 }
 
 impl ProgressBarUI {
-    pub fn new(total_size: u64) -> Self {
-        let progress_bar = ProgressBar::new(total_size);
-        
-        // TODO: Configure progress bar style
-        let style = ProgressStyle::default_bar()
-            .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .expect("Progress bar template should be valid");
+    /// Progress for the video called `label`, with `connections` extra bars in `Bars` mode
+    pub fn new(mode: ProgressMode, label: &str, connections: usize) -> Self {
+        let label = Self::shorten(label);
+        let (overall, connections) = if mode == ProgressMode::Bars {
+            let overall = Self::bars().add(ProgressBar::new(0));
+            overall.set_style(Self::style(false));
+            overall.set_prefix(label.clone());
+            overall.enable_steady_tick(Duration::from_millis(120));
+            
+            let mut bars: Vec<ProgressBar> = Vec::with_capacity(connections);
+            for number in 1..=connections {
+                let previous = bars.last().unwrap_or(&overall);
+                let bar = Self::bars().insert_after(previous, ProgressBar::new_spinner());
+                bar.set_style(Self::connection_style());
+                bar.set_prefix(number.to_string());
+                bar.set_message("idle");
+                bars.push(bar);
+            }
+            (overall, bars)
+        } else {
+            (ProgressBar::hidden(), Vec::new())
+        };
         
-        progress_bar.set_style(style);
-        
-        Self { progress_bar }
+        Self {
+            mode,
+            label,
+            overall,
+            connections,
+            last: DownloadProgress::new(),
+            last_line: None,
+        }
+    }
+    
+    /// Run `f`, such as printing a message, with the bars cleared from the screen
+    pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+        Self::bars().suspend(f)
     }
     
     /// Update progress bar with current status
-    pub fn update(&self, progress: &DownloadProgress) {
-        // TODO: Implement progress bar updates
-        todo!("Implement progress bar updates")
+    pub fn update(&mut self, progress: &DownloadProgress) {
+        let known = progress.total_size > 0;
+        if known != (self.last.total_size > 0) {
+            self.overall.set_style(Self::style(known));
+        }
+        self.last = progress.clone();
+        
+        match self.mode {
+            ProgressMode::Bars => {
+                self.overall.set_length(progress.total_size);
+                self.overall.set_position(progress.downloaded_size);
+                self.overall.set_message(Self::rate(progress));
+            }
+            ProgressMode::Lines => {
+                let due = self.last_line.is_none_or(|printed| printed.elapsed() >= LINE_INTERVAL);
+                if due {
+                    eprintln!("{}", Self::line(&self.label, progress));
+                    self.last_line = Some(Instant::now());
+                }
+            }
+            ProgressMode::Hidden => {}
+        }
+    }
+    
+    /// Show which fragment each connection is fetching
+    pub fn update_connections(&self, chunks: &[ChunkState]) {
+        let mut active = chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, &chunk)| chunk == ChunkState::Active)
+            .map(|(index, _)| index);
+        for bar in &self.connections {
+            match active.next() {
+                Some(index) => bar.set_message(format!("fragment {}/{}", index + 1, chunks.len())),
+                None => bar.set_message("idle"),
+            }
+        }
     }
     
     /// Complete progress bar
     pub fn finish(&self, message: &str) {
-        // TODO: Implement progress bar completion
-        todo!("Implement progress bar finish")
+        for bar in &self.connections {
+            bar.finish_and_clear();
+            Self::bars().remove(bar);
+        }
+        match self.mode {
+            ProgressMode::Bars => self.overall.finish_with_message(message.to_string()),
+            ProgressMode::Lines => eprintln!("{}: {} ({})", self.label, message, self.last.downloaded_string()),
+            ProgressMode::Hidden => {}
+        }
     }
     
     /// Set progress bar message
     pub fn set_message(&self, message: &str) {
-        self.progress_bar.set_message(message.to_string());
+        self.overall.set_message(message.to_string());
+    }
+    
+    /// The display shared by every video's bars
+    fn bars() -> &'static MultiProgress {
+        static BARS: OnceLock<MultiProgress> = OnceLock::new();
+        BARS.get_or_init(MultiProgress::new)
+    }
+    
+    /// A bar when the total size is known, a spinner with the bytes so far otherwise
+    fn style(known: bool) -> ProgressStyle {
+        let template = if known {
+            "{prefix:30.bold} [{bar:30.cyan/blue}] {percent:>3}% {bytes}/{total_bytes} {msg}"
+        } else {
+            "{prefix:30.bold} {spinner:.green} {bytes} {msg}"
+        };
+        ProgressStyle::with_template(template)
+            .expect("Progress bar template should be valid")
+            .progress_chars("=> ")
+    }
+    
+    fn connection_style() -> ProgressStyle {
+        ProgressStyle::with_template("  {spinner:.dim} connection {prefix}: {msg}")
+            .expect("Progress bar template should be valid")
     }
-}
\ No newline at end of file
+    
+    /// Speed and ETA as measured by the `ProgressTracker`
+    fn rate(progress: &DownloadProgress) -> String {
+        if progress.eta_seconds > 0 {
+            format!("{} · ETA {}", progress.speed_string(), progress.eta_string())
+        } else {
+            progress.speed_string()
+        }
+    }
+    
+    /// One plain progress line, e.g. "Title: 42.0% of 10.0 MB at 1.0 MB/s, ETA 6s"
+    fn line(label: &str, progress: &DownloadProgress) -> String {
+        let mut line = if progress.total_size > 0 {
+            format!("{}: {:.1}% of {}", label, progress.percentage(), progress.size_string())
+        } else {
+            format!("{}: {}", label, progress.downloaded_string())
+        };
+        if progress.download_speed > 0.0 {
+            line.push_str(&format!(" at {}", progress.speed_string()));
+        }
+        if progress.eta_seconds > 0 {
+            line.push_str(&format!(", ETA {}", progress.eta_string()));
+        }
+        line
+    }
+    
+    fn shorten(label: &str) -> String {
+        if label.chars().count() <= LABEL_WIDTH {
+            return label.to_string();
+        }
+        let mut short: String = label.chars().take(LABEL_WIDTH - 1).collect();
+        short.push('…');
+        short
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn progress(downloaded: u64, total: u64) -> DownloadProgress {
+        DownloadProgress {
+            total_size: total,
+            downloaded_size: downloaded,
+            download_speed: 1024.0 * 1024.0,
+            eta_seconds: 6,
+            is_complete: false,
+        }
+    }
+    
+    #[test]
+    fn test_line() {
+        assert_eq!(
+            ProgressBarUI::line("Video", &progress(4 << 20, 10 << 20)),
+            "Video: 40.0% of 10.0 MB at 1.0 MB/s, ETA 6s"
+        );
+        let live = DownloadProgress { eta_seconds: 0, ..progress(3 << 20, 0) };
+        assert_eq!(ProgressBarUI::line("Stream", &live), "Stream: 3.0 MB at 1.0 MB/s");
+    }
+    
+    #[test]
+    fn test_bars_follow_progress() {
+        let mut ui = ProgressBarUI::new(ProgressMode::Bars, "A video with a rather long title indeed", 2);
+        assert_eq!(ui.label, "A video with a rather long ti…");
+        
+        ui.update(&progress(50, 100));
+        assert_eq!(ui.overall.position(), 50);
+        assert_eq!(ui.overall.length(), Some(100));
+        assert_eq!(ui.overall.message(), "1.0 MB/s · ETA 6s");
+        
+        ui.update_connections(&[ChunkState::Done, ChunkState::Active, ChunkState::Pending]);
+        assert_eq!(ui.connections[0].message(), "fragment 2/3");
+        assert_eq!(ui.connections[1].message(), "idle");
+        
+        ui.finish("done");
+        assert!(ui.overall.is_finished());
+        assert!(ui.connections.iter().all(ProgressBar::is_finished));
+    }
+    
+    #[test]
+    fn test_hidden_has_no_bars() {
+        let mut ui = ProgressBarUI::new(ProgressMode::Hidden, "Video", 4);
+        assert!(ui.connections.is_empty());
+        ui.update(&progress(50, 100));
+        ui.finish("done");
+    }
+}
@@ -1,44 +1,220 @@
 //! Format and quality selection user interface
 
 use crate::models::{Format, FormatType, VideoInfo};
+use crate::ui::FormatTable;
 use crate::Result;
 use console::{style, Term};
+use std::fmt;
 
+/// Streams chosen for a download: a single format, or a video-only and an
+/// audio stream to merge into one file
+#[derive(Debug, Clone, Default)]
+pub struct FormatSelection {
+    pub video: Option<Format>,
+    pub audio: Option<Format>,
+}
+
+impl FormatSelection {
+    /// Select one format, placed by its type
+    pub fn single(format: Format) -> Self {
+        match format.format_type {
+            FormatType::Video => Self { video: Some(format), audio: None },
+            FormatType::Audio => Self { video: None, audio: Some(format) },
+        }
+    }
+    
+    /// Chosen formats, video first
+    pub fn formats(&self) -> Vec<Format> {
+        self.video.iter().chain(self.audio.iter()).cloned().collect()
+    }
+    
+    /// Whether separate video and audio streams have to be merged
+    pub fn needs_merge(&self) -> bool {
+        self.video.is_some() && self.audio.is_some()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.video.is_none() && self.audio.is_none()
+    }
+    
+    /// Combined size, exact or estimated, when known for every stream
+    pub fn estimated_size(&self) -> Option<u64> {
+        let formats = self.formats();
+        if formats.is_empty() {
+            return None;
+        }
+        formats.iter().map(Format::estimated_size).sum()
+    }
+}
+
+impl fmt::Display for FormatSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let descriptions: Vec<String> = self.formats().iter().map(Format::quality_description).collect();
+        if descriptions.is_empty() {
+            write!(f, "nothing")
+        } else {
+            write!(f, "{}", descriptions.join(" + "))
+        }
+    }
+}
+
+/// Numbered prompts, used when a full-screen picker is not possible.
+///
+/// Everything is written to stderr so prompts never end up in piped output.
 pub struct SelectionUI {
     term: Term,
 }
 
+impl Default for SelectionUI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SelectionUI {
     pub fn new() -> Self {
         Self {
-            term: Term::stdout(),
+            term: Term::stderr(),
         }
     }
     
     /// Display video information
     pub fn display_video_info(&self, video_info: &VideoInfo) -> Result<()> {
-        // TODO: Implement video information display
-        todo!("Implement video info display")
+        self.term.write_line(&style(&video_info.title).bold().to_string())?;
+        
+        let mut details = Vec::new();
+        if let Some(ref uploader) = video_info.uploader {
+            details.push(uploader.clone());
+        }
+        details.push(match video_info.duration_seconds {
+            Some(seconds) => self.format_duration(seconds),
+            None => video_info.duration.clone(),
+        });
+        details.push(format!("{} formats", video_info.available_formats.len()));
+        self.term.write_line(&details.join(" · "))?;
+        self.term.write_line("")?;
+        Ok(())
     }
     
     /// Interactive format type selection
     pub fn select_format_type(&self) -> Result<FormatType> {
-        // TODO: Implement format type selection UI
-        todo!("Implement format type selection")
+        let choice = self.choose("Download", &["Video".to_string(), "Audio only".to_string()])?;
+        Ok(if choice == 0 { FormatType::Video } else { FormatType::Audio })
     }
     
-    /// Interactive quality selection
+    /// Interactive quality selection, returning an index into `formats`
     pub fn select_quality(&self, formats: &[Format]) -> Result<usize> {
-        // TODO: Implement quality selection UI
-        todo!("Implement quality selection")
+        let options: Vec<String> = formats.iter().map(|format| self.describe(format)).collect();
+        self.choose("Format", &options)
+    }
+    
+    /// Pick an audio stream to merge with a video-only format, or none
+    pub fn select_audio(&self, formats: &[Format]) -> Result<Option<usize>> {
+        let mut options: Vec<String> = formats.iter().map(|format| self.describe(format)).collect();
+        options.push("No audio".to_string());
+        let choice = self.choose("Audio to merge", &options)?;
+        Ok((choice < formats.len()).then_some(choice))
+    }
+    
+    /// Print `prompt` and read one line, without the trailing newline
+    pub fn read_input(&self, prompt: &str) -> Result<String> {
+        self.term.write_str(prompt)?;
+        // `Term::read_line` returns nothing when stdin is not a terminal, so answers could not be piped in
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        Ok(line.trim().to_string())
+    }
+    
+    /// List `options` with numbers and ask until a valid one is entered;
+    /// an empty answer picks the first
+    fn choose(&self, prompt: &str, options: &[String]) -> Result<usize> {
+        for (index, option) in options.iter().enumerate() {
+            self.term.write_line(&format!("{:>3}) {}", index + 1, option))?;
+        }
+        loop {
+            let input = self.read_input(&format!("{} [1-{}, default 1]: ", prompt, options.len()))?;
+            match Self::parse_choice(&input, options.len()) {
+                Some(choice) => return Ok(choice),
+                None => self.term.write_line(&format!("Please enter a number between 1 and {}", options.len()))?,
+            }
+        }
+    }
+    
+    fn parse_choice(input: &str, count: usize) -> Option<usize> {
+        if input.is_empty() {
+            return Some(0);
+        }
+        match input.parse::<usize>() {
+            Ok(number) if (1..=count).contains(&number) => Some(number - 1),
+            _ => None,
+        }
+    }
+    
+    /// One line per format: quality, container, codecs and size
+    fn describe(&self, format: &Format) -> String {
+        let codecs = [&format.vcodec, &format.acodec]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{:<24} {:<5} {:<22} {}", format.quality_description(), format.file_extension, codecs, FormatTable::size(format))
+            .trim_end()
+            .to_string()
     }
     
-    // TODO: Add helper methods for formatting output
     fn format_duration(&self, seconds: u64) -> String {
-        todo!("Implement duration formatting")
+        match seconds / 3600 {
+            0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+            hours => format!("{}:{:02}:{:02}", hours, (seconds % 3600) / 60, seconds % 60),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn format(format_type: FormatType, quality: &str, size: Option<u64>) -> Format {
+        let mut format = Format::new(quality.to_string(), format_type, "mp4".to_string(), String::new());
+        format.file_size = size;
+        format
     }
     
-    fn format_file_size(&self, bytes: u64) -> String {
-        todo!("Implement file size formatting")
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(SelectionUI::parse_choice("", 3), Some(0));
+        assert_eq!(SelectionUI::parse_choice("3", 3), Some(2));
+        assert_eq!(SelectionUI::parse_choice("0", 3), None);
+        assert_eq!(SelectionUI::parse_choice("4", 3), None);
+        assert_eq!(SelectionUI::parse_choice("best", 3), None);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_format_duration() {
+        let ui = SelectionUI::new();
+        assert_eq!(ui.format_duration(59), "0:59");
+        assert_eq!(ui.format_duration(3 * 60 + 7), "3:07");
+        assert_eq!(ui.format_duration(3600 + 2 * 60 + 3), "1:02:03");
+    }
+    
+    #[test]
+    fn test_selection() {
+        let mut video = format(FormatType::Video, "1080p", Some(1000));
+        video.is_adaptive = true;
+        let audio = format(FormatType::Audio, "128kbps", Some(200));
+        
+        let single = FormatSelection::single(audio.clone());
+        assert!(single.video.is_none() && !single.needs_merge());
+        assert_eq!(single.to_string(), "128kbps");
+        
+        let merged = FormatSelection { video: Some(video), audio: Some(audio) };
+        assert!(merged.needs_merge());
+        assert_eq!(merged.estimated_size(), Some(1200));
+        assert_eq!(merged.to_string(), "1080p (video only) + 128kbps");
+        
+        let unknown = FormatSelection::single(format(FormatType::Video, "720p", None));
+        assert_eq!(unknown.estimated_size(), None);
+        assert_eq!(FormatSelection::default().to_string(), "nothing");
+    }
+}
@@ -66,6 +66,19 @@ impl NetworkUtils {
         }
     }
     
+    /// Fetch a URL as text, retrying transient failures
+    pub async fn fetch_text(client: &Client, url: &str, max_retries: u32) -> Result<String> {
+        debug!("Fetching: {}", url);
+        
+        Self::retry_with_backoff(
+            || async {
+                let response = client.get(url).send().await?.error_for_status()?;
+                Ok(response.text().await?)
+            },
+            max_retries,
+        ).await
+    }
+    
     /// Test network connectivity by making a simple request to YouTube
     pub async fn test_connectivity() -> Result<bool> {
         let client = Self::create_client()?;
@@ -63,14 +63,52 @@ impl UrlValidator {
         Err(DownloaderError::InvalidUrl(format!("Could not extract video ID from: {}", url)))
     }
     
+    /// Whether `url` is a playlist page, like https://www.youtube.com/playlist?list=<id>
+    pub fn is_playlist_url(url: &str) -> bool {
+        static PLAYLIST_PAGE_PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PLAYLIST_PAGE_PATTERN.get_or_init(|| {
+            Regex::new(r"^https?://(www\.|m\.)?youtube\.com/playlist\?").expect("Playlist page regex should be valid")
+        });
+        pattern.is_match(url) && Self::extract_playlist_id(url).is_some()
+    }
+    
+    /// Extract the playlist ID from the `list` parameter of a URL
+    pub fn extract_playlist_id(url: &str) -> Option<String> {
+        let (_, _, playlist_pattern) = Self::get_patterns();
+        let captures = playlist_pattern.captures(url)?;
+        Some(captures[1].to_string())
+    }
+    
     /// Normalize YouTube URL to standard format
     pub fn normalize_url(url: &str) -> Result<String> {
         let video_id = Self::extract_video_id(url)?;
         Ok(format!("https://www.youtube.com/watch?v={}", video_id))
     }
     
-    /// Validate file path for output
+    /// Validate file path for output, one component at a time. A leading
+    /// drive such as `C:` is allowed, and only each name is limited to 255 bytes.
     pub fn is_valid_output_path(path: &str) -> bool {
+        if path.is_empty() {
+            return false;
+        }
+        
+        let path = Self::strip_drive(path);
+        path.split(['/', '\\'])
+            .filter(|component| !component.is_empty() && *component != "." && *component != "..")
+            .all(Self::is_valid_path_component)
+    }
+    
+    /// A path without its Windows drive prefix, e.g. "Videos" for "C:Videos"
+    fn strip_drive(path: &str) -> &str {
+        let mut chars = path.chars();
+        match (chars.next(), chars.next()) {
+            (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => &path[2..],
+            _ => path,
+        }
+    }
+    
+    /// Whether one file or directory name is valid on every platform
+    fn is_valid_path_component(component: &str) -> bool {
         // Check for invalid characters in filename
         let invalid_chars = ['<', '>', ':', '"', '|', '?', '*'];
         
@@ -81,27 +119,23 @@ impl UrlValidator {
             "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9"
         ];
         
-        if path.is_empty() || path.len() > 255 {
+        if component.len() > 255 {
             return false;
         }
         
         // Check for invalid characters
-        if path.chars().any(|c| invalid_chars.contains(&c) || c.is_control()) {
+        if component.chars().any(|c| invalid_chars.contains(&c) || c.is_control()) {
             return false;
         }
         
-        // Check for reserved names (Windows compatibility)
-        let path_upper = path.to_uppercase();
-        if reserved_names.iter().any(|&name| path_upper.starts_with(name)) {
+        // Check for reserved names (Windows compatibility); "CON.txt" is reserved, "Concerts" is not
+        let stem = component.split('.').next().unwrap_or_default().to_uppercase();
+        if reserved_names.contains(&stem.as_str()) {
             return false;
         }
         
-        // Check for paths ending with space or period (Windows compatibility)
-        if path.ends_with(' ') || path.ends_with('.') {
-            return false;
-        }
-        
-        true
+        // Check for names ending with space or period (Windows compatibility)
+        !(component.ends_with(' ') || component.ends_with('.'))
     }
     
     /// Sanitize filename by removing/replacing invalid characters
@@ -129,7 +163,11 @@ impl UrlValidator {
         
         // Truncate if too long (leave room for extension)
         if sanitized.len() > 200 {
-            sanitized.truncate(200);
+            let mut end = 200;
+            while !sanitized.is_char_boundary(end) {
+                end -= 1;
+            }
+            sanitized.truncate(end);
         }
         
         sanitized
@@ -139,7 +177,7 @@ impl UrlValidator {
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    
     #[test]
     fn test_valid_youtube_urls() {
         let valid_urls = [
@@ -184,4 +222,35 @@ mod tests {
             assert_eq!(result.unwrap(), *expected_id);
         }
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_playlist_urls() {
+        let playlist = "https://www.youtube.com/playlist?list=PLrAXtmErZgOeiKm4sgNOknGvNjby9efdf";
+        assert!(UrlValidator::is_playlist_url(playlist));
+        assert!(!UrlValidator::is_valid_youtube_url(playlist));
+        assert_eq!(UrlValidator::extract_playlist_id(playlist).as_deref(), Some("PLrAXtmErZgOeiKm4sgNOknGvNjby9efdf"));
+        
+        // A video opened from a playlist is still a video
+        let video = "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLrAXtmErZgOeiKm4sgNOknGvNjby9efdf";
+        assert!(!UrlValidator::is_playlist_url(video));
+        assert!(!UrlValidator::is_playlist_url("https://www.youtube.com/playlist"));
+    }
+    
+    #[test]
+    fn test_output_paths() {
+        assert!(UrlValidator::is_valid_output_path("Videos/Concerts/clip.mp4"));
+        assert!(UrlValidator::is_valid_output_path("/tmp/downloads"));
+        assert!(!UrlValidator::is_valid_output_path("Videos/CON.mp4"));
+        assert!(!UrlValidator::is_valid_output_path("Videos/clip?.mp4"));
+        assert!(!UrlValidator::is_valid_output_path("Videos/clip."));
+        assert!(!UrlValidator::is_valid_output_path(""));
+        
+        assert!(UrlValidator::is_valid_output_path(r"C:\Users\me\Videos"));
+        assert!(UrlValidator::is_valid_output_path("../Videos/./Concerts"));
+        assert!(!UrlValidator::is_valid_output_path(r"Videos\a:b"));
+        assert!(!UrlValidator::is_valid_output_path("Videos/clip /x"));
+        let deep = vec!["Videos"; 50].join("/");
+        assert!(UrlValidator::is_valid_output_path(&deep));
+        assert!(!UrlValidator::is_valid_output_path(&format!("{}/{}", deep, "x".repeat(256))));
+    }
+}
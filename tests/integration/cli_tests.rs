@@ -1,8 +1,18 @@
 //! CLI integration tests
 
 use assert_cmd::Command;
+use downloader::config::Settings;
 use predicates::prelude::*;
+use serde_json::json;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
 use tempfile::TempDir;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const VIDEO_ID: &str = "dQw4w9WgXcQ";
+const WATCH_URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+const MEDIA: &str = "not really an mp4";
 
 #[test]
 fn test_help_command() {
@@ -26,4 +36,669 @@ fn test_valid_youtube_url() {
     // This would require mocking YouTube responses
 }
 
-// TODO: Add more CLI integration tests
\ No newline at end of file
+/// The binary with config and data directories inside `home`
+fn downloader(home: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("downloader").unwrap();
+    cmd.env("XDG_CONFIG_HOME", home.path().join("config"))
+        .env("XDG_DATA_HOME", home.path().join("data"));
+    cmd
+}
+
+/// A format of `itag` whose media is served from `/media/<itag>`
+fn media_format(server: &MockServer, itag: u32, mime: &str) -> serde_json::Value {
+    json!({
+        "itag": itag,
+        "url": format!("{}/media/{}", server.uri(), itag),
+        "mimeType": mime,
+        "contentLength": MEDIA.len().to_string(),
+    })
+}
+
+/// Serve the watch page of `video_id` with muxed `formats` and `adaptive` formats
+async fn mount_watch_page(
+    server: &MockServer,
+    video_id: &str,
+    title: &str,
+    formats: Vec<serde_json::Value>,
+    adaptive: Vec<serde_json::Value>,
+) {
+    let player_response = json!({
+        "videoDetails": { "videoId": video_id, "title": title, "lengthSeconds": "10" },
+        "streamingData": { "formats": formats, "adaptiveFormats": adaptive },
+    });
+    let page = format!(
+        r#"<html><head><meta property="og:title" content="{}"></head><body><script>var ytInitialPlayerResponse = {};</script></body></html>"#,
+        title, player_response
+    );
+    Mock::given(method("GET"))
+        .and(path("/watch"))
+        .and(query_param("v", video_id))
+        .respond_with(ResponseTemplate::new(200).set_body_string(page))
+        .mount(server)
+        .await;
+}
+
+/// A site serving one watch page with a muxed 360p format, a video-only 1080p
+/// format and an audio format. The config in `home` points the binary at it
+/// and saves downloads into `home/videos`, after `configure` had its say.
+async fn fake_youtube(home: &TempDir, configure: impl FnOnce(&mut Settings)) -> MockServer {
+    let server = MockServer::start().await;
+    let mut muxed = media_format(&server, 18, r#"video/mp4; codecs="avc1.42001E, mp4a.40.2""#);
+    muxed["height"] = json!(360);
+    let mut video = media_format(&server, 137, r#"video/mp4; codecs="avc1.640028""#);
+    video["height"] = json!(1080);
+    let mut audio = media_format(&server, 140, r#"audio/mp4; codecs="mp4a.40.2""#);
+    audio["averageBitrate"] = json!(128_000);
+    mount_watch_page(&server, VIDEO_ID, "Test Video", vec![muxed], vec![video, audio]).await;
+    for itag in [18, 137, 140] {
+        Mock::given(method("GET"))
+            .and(path(format!("/media/{}", itag)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(MEDIA))
+            .mount(&server)
+            .await;
+    }
+    
+    let mut settings = Settings {
+        youtube_url: Some(server.uri()),
+        default_output_directory: Some(home.path().join("videos")),
+        default_quality: Some("360p".to_string()),
+        ..Settings::default()
+    };
+    configure(&mut settings);
+    let config = home.path().join("config/downloader/config.toml");
+    std::fs::create_dir_all(config.parent().unwrap()).unwrap();
+    std::fs::write(config, toml::to_string(&settings).unwrap()).unwrap();
+    server
+}
+
+fn seed_history(home: &TempDir, path: &std::path::Path, size: u64) {
+    let dir = home.path().join("data/downloader");
+    std::fs::create_dir_all(&dir).unwrap();
+    let entry = serde_json::json!({
+        "video_id": "dQw4w9WgXcQ",
+        "title": "Never Gonna Give You Up",
+        "url": "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+        "path": path,
+        "format": "720p",
+        "size": size,
+        "downloaded_at": 1_700_000_000u64,
+    });
+    std::fs::write(dir.join("history.jsonl"), format!("{}\n", entry)).unwrap();
+}
+
+#[test]
+fn test_help_lists_subcommands() {
+    let mut cmd = Command::cargo_bin("downloader").unwrap();
+    cmd.arg("--help").assert().success().stdout(
+        predicate::str::contains("download")
+            .and(predicate::str::contains("queue"))
+            .and(predicate::str::contains("history"))
+            .and(predicate::str::contains("verify"))
+            .and(predicate::str::contains("serve")),
+    );
+    
+    let mut cmd = Command::cargo_bin("downloader").unwrap();
+    cmd.assert().failure().stderr(predicate::str::contains("Usage"));
+}
+
+#[test]
+fn test_subcommands_reject_invalid_urls() {
+    let home = TempDir::new().unwrap();
+    for subcommand in ["download", "info", "formats"] {
+        downloader(&home)
+            .args([subcommand, "invalid-url"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Invalid YouTube URL"));
+    }
+}
+
+#[test]
+fn test_queue_continues_past_failures() {
+    let home = TempDir::new().unwrap();
+    downloader(&home).arg("queue").assert().failure();
+    
+    let batch = home.path().join("urls.txt");
+    std::fs::write(&batch, "# comment\n\ninvalid-url\n").unwrap();
+    downloader(&home)
+        .args(["queue", "--batch-file"])
+        .arg(&batch)
+        .arg("also-invalid")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("[2/2] invalid-url"))
+        .stderr(predicate::str::contains("2 of 2 downloads failed"));
+}
+
+#[test]
+fn test_history_and_verify() {
+    let home = TempDir::new().unwrap();
+    downloader(&home)
+        .arg("history")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No downloads recorded yet"));
+    
+    let video = home.path().join("video.mp4");
+    std::fs::write(&video, b"video").unwrap();
+    seed_history(&home, &video, 5);
+    downloader(&home)
+        .arg("history")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2023-11-14 22:13  Never Gonna Give You Up [dQw4w9WgXcQ]"));
+    downloader(&home)
+        .args(["history", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"video_id\": \"dQw4w9WgXcQ\""));
+    downloader(&home)
+        .arg("verify")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("OK"));
+    
+    std::fs::remove_file(&video).unwrap();
+    downloader(&home)
+        .arg("verify")
+        .arg(&video)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("FAILED").and(predicate::str::contains("missing")))
+        .stderr(predicate::str::contains("1 of 1 files failed verification"));
+    
+    downloader(&home).args(["history", "--clear"]).assert().success();
+    downloader(&home)
+        .arg("history")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No downloads recorded yet"));
+}
+
+#[test]
+fn test_config_subcommands() {
+    let home = TempDir::new().unwrap();
+    let config = home.path().join("config/downloader/config.toml");
+    downloader(&home)
+        .args(["config", "path"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(config.to_string_lossy().as_ref()));
+    
+    downloader(&home).args(["config", "init"]).assert().success();
+    assert!(config.exists());
+    downloader(&home)
+        .args(["config", "show"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("max_concurrent_downloads"));
+}
+
+#[test]
+fn test_serve_rejects_invalid_address() {
+    let home = TempDir::new().unwrap();
+    downloader(&home).args(["serve", "--bind", "nonsense"]).assert().failure();
+}
+
+#[test]
+fn test_every_invalid_option_is_reported() {
+    let home = TempDir::new().unwrap();
+    downloader(&home)
+        .args(["--audio-only", "--remux-video", "mkv", "--sponsorblock-remove", "sponsr", "invalid-url"])
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("Unknown SponsorBlock category 'sponsr'")
+                .and(predicate::str::contains("--audio-only cannot be used with --remux-video"))
+                .and(predicate::str::contains("Invalid YouTube URL: invalid-url"))
+                .and(predicate::str::contains("help: use --remux-video m4a")),
+        );
+}
+
+#[test]
+fn test_errors_are_printed_once() {
+    let home = TempDir::new().unwrap();
+    let output = downloader(&home).arg("invalid-url").assert().code(2).get_output().clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("Invalid YouTube URL: invalid-url").count(), 1, "{}", stderr);
+    assert!(stderr.starts_with("Error: Invalid arguments:"), "{}", stderr);
+    
+    downloader(&home)
+        .args(["--json", "invalid-url"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid-url").not());
+}
+
+#[test]
+fn test_json_modes_keep_stdout_machine_readable() {
+    let home = TempDir::new().unwrap();
+    let output = downloader(&home).args(["--print-json", "invalid-url"]).assert().failure().get_output().clone();
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("Invalid YouTube URL: invalid-url"));
+    
+    let output = downloader(&home)
+        .args(["queue", "--progress-json", "invalid-url", "also-invalid"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("[1/2] invalid-url"))
+        .get_output()
+        .clone();
+    let events: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0]["event"], "error");
+    assert_eq!(events[0]["url"], "invalid-url");
+    assert_eq!(events[1]["url"], "also-invalid");
+    assert_eq!(events[2]["error"], "2 of 2 downloads failed");
+}
+
+#[test]
+fn test_simulate_writes_nothing() {
+    let home = TempDir::new().unwrap();
+    downloader(&home)
+        .args(["queue", "--simulate", "--exec-after-queue", "touch marker", "invalid-url"])
+        .current_dir(home.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("1 of 1 downloads failed"));
+    assert!(!home.path().join("marker").exists());
+    assert!(!home.path().join("data").exists());
+}
+
+#[test]
+fn test_print_and_get_url_options_are_checked() {
+    let home = TempDir::new().unwrap();
+    downloader(&home)
+        .args(["--print", "%(title)s|%(bogus)s", "https://youtu.be/dQw4w9WgXcQ"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown field 'bogus'"));
+    downloader(&home)
+        .args(["--get-url", "--json", "https://youtu.be/dQw4w9WgXcQ"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("--print or --get-url cannot be used with --json or --progress-json"));
+    
+    // Progress messages stay off stdout so only printed lines reach the next command
+    downloader(&home)
+        .args(["queue", "--get-url", "invalid-url"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("[1/1] invalid-url"));
+}
+
+#[test]
+fn test_completions_and_manpage() {
+    let home = TempDir::new().unwrap();
+    for shell in ["bash", "zsh", "fish", "elvish", "powershell"] {
+        downloader(&home)
+            .args(["completions", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("downloader"));
+    }
+    downloader(&home)
+        .args(["completions", "tcsh"])
+        .assert()
+        .failure();
+    
+    downloader(&home)
+        .arg("manpage")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".TH downloader"))
+        .stdout(predicate::str::contains(".SH CONFIGURATION"))
+        .stdout(predicate::str::contains("max_concurrent_downloads"));
+}
+
+#[test]
+fn test_exit_codes() {
+    let home = TempDir::new().unwrap();
+    downloader(&home).arg("invalid-url").assert().code(2);
+    downloader(&home).arg("--no-such-option").assert().code(2);
+    
+    // Every item failed for the same reason, so the batch reports that reason
+    downloader(&home)
+        .args(["queue", "invalid-url", "also-invalid"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("2 of 2 downloads failed"));
+}
+
+#[test]
+fn test_rate_limit_and_dashboard_options() {
+    let home = TempDir::new().unwrap();
+    downloader(&home)
+        .args(["--limit-rate", "fast", "https://youtu.be/dQw4w9WgXcQ"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid rate 'fast'"));
+    downloader(&home)
+        .args(["queue", "--dashboard", "--json", "https://youtu.be/dQw4w9WgXcQ"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+    
+    // Without a terminal the dashboard falls back to plain output
+    downloader(&home)
+        .args(["queue", "--dashboard", "invalid-url"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--dashboard needs a terminal"))
+        .stderr(predicate::str::contains("1 of 1 downloads failed"));
+}
+
+#[test]
+fn test_quiet_and_progress_options() {
+    let home = TempDir::new().unwrap();
+    downloader(&home)
+        .args(["--no-progress", "--show-connections", "https://youtu.be/dQw4w9WgXcQ"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+    
+    // Status lines are dropped, failures are still reported
+    downloader(&home)
+        .args(["queue", "--quiet", "invalid-url"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("[1/1]").not())
+        .stderr(predicate::str::contains("Failed to download invalid-url"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_saves_video_and_records_history() {
+    let home = TempDir::new().unwrap();
+    let _server = fake_youtube(&home, |_| {}).await;
+    
+    downloader(&home)
+        .args(["--auto", WATCH_URL])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved"));
+    
+    let saved = home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4");
+    assert_eq!(std::fs::read_to_string(&saved).unwrap(), MEDIA);
+    let history = std::fs::read_to_string(home.path().join("data/downloader/history.jsonl")).unwrap();
+    assert!(history.contains(VIDEO_ID));
+    
+    // The file is there now, so the default collision policy skips it
+    downloader(&home)
+        .args(["--auto", WATCH_URL])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("already exists"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_simulate_plans_a_real_video() {
+    let home = TempDir::new().unwrap();
+    let server = fake_youtube(&home, |_| {}).await;
+    let saved = home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4");
+    
+    let output = downloader(&home).args(["--simulate", WATCH_URL]).assert().success().get_output().clone();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "Would download: Test Video [dQw4w9WgXcQ]\n  Format: 360p (mp4, itag 18)\n  Size:   17 B\n  Path:   {}\n",
+            saved.display()
+        )
+    );
+    
+    let output = downloader(&home).args(["--simulate", "--json", WATCH_URL]).assert().success().get_output().clone();
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(plan["path"], saved.display().to_string());
+    assert_eq!(plan["size"], MEDIA.len());
+    assert_eq!(plan["action"], "download");
+    assert_eq!(plan["format"]["itag"], 18);
+    assert_eq!(plan["previously_downloaded"], false);
+    
+    assert!(!home.path().join("videos").exists());
+    assert!(!home.path().join("data").exists());
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().all(|request| !request.url.path().starts_with("/media")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_numbered_prompts_pick_the_format() {
+    let home = TempDir::new().unwrap();
+    let server = fake_youtube(&home, |_| {}).await;
+    
+    // Stdin is not a terminal, so the numbered prompts ask for video, then the second (360p) format
+    downloader(&home)
+        .arg(WATCH_URL)
+        .write_stdin("1\n2\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Format [1-2, default 1]: "))
+        .stdout(predicate::str::contains("Selected 360p"));
+    
+    let saved = home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4");
+    assert_eq!(std::fs::read_to_string(saved).unwrap(), MEDIA);
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().any(|request| request.url.path() == "/media/18"));
+    assert!(!requests.iter().any(|request| request.url.path() == "/media/137"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_video_only_format_is_merged_with_audio() {
+    let home = TempDir::new().unwrap();
+    let server = fake_youtube(&home, |_| {}).await;
+    let empty = home.path().join("empty");
+    std::fs::create_dir(&empty).unwrap();
+    
+    // 1080p is video only, so an audio stream is asked for and merged with ffmpeg, which PATH lacks
+    downloader(&home)
+        .arg(WATCH_URL)
+        .env("PATH", &empty)
+        .write_stdin("1\n1\n1\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("Selected 1080p (video only) + 128kbps"))
+        .stderr(predicate::str::contains("ffmpeg is required to merge video and audio"));
+    
+    let requests = server.received_requests().await.unwrap();
+    for media in ["/media/137", "/media/140"] {
+        assert!(requests.iter().any(|request| request.url.path() == media), "{} was not downloaded", media);
+    }
+    assert!(!home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4").exists());
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_merge_leaves_no_merged_file() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let home = TempDir::new().unwrap();
+    let _server = fake_youtube(&home, |_| {}).await;
+    // An ffmpeg that writes half a file to its last argument, then fails
+    let bin = home.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let ffmpeg = bin.join("ffmpeg");
+    std::fs::write(&ffmpeg, "#!/bin/sh\nfor last; do :; done\necho half > \"$last\"\nexit 1\n").unwrap();
+    std::fs::set_permissions(&ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+    
+    downloader(&home)
+        .arg(WATCH_URL)
+        .env("PATH", &bin)
+        .write_stdin("1\n1\n1\n")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("ffmpeg could not merge"));
+    
+    // The default policy keeps the downloaded streams for a retry, but not the merge
+    let mut names: Vec<String> = std::fs::read_dir(home.path().join("videos"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Test Video [dQw4w9WgXcQ].mp4.audio", "Test Video [dQw4w9WgXcQ].mp4.video"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_is_written_to_part_file_then_renamed() {
+    let home = TempDir::new().unwrap();
+    let server = fake_youtube(&home, |_| {}).await;
+    Mock::given(method("GET"))
+        .and(path("/media/18"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(MEDIA).set_delay(Duration::from_secs(1)))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    
+    let saved = home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4");
+    let part = home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4.part");
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("downloader"))
+        .args(["--auto", WATCH_URL])
+        .env("XDG_CONFIG_HOME", home.path().join("config"))
+        .env("XDG_DATA_HOME", home.path().join("data"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    
+    // While the media response is held back, only the `.part` file exists
+    let started = Instant::now();
+    while !part.exists() && started.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(part.exists());
+    assert!(!saved.exists());
+    
+    assert!(child.wait().unwrap().success());
+    assert_eq!(std::fs::read_to_string(&saved).unwrap(), MEDIA);
+    assert!(!part.exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_download_leaves_no_final_file() {
+    let home = TempDir::new().unwrap();
+    let server = fake_youtube(&home, |_| {}).await;
+    Mock::given(method("GET"))
+        .and(path("/media/18"))
+        .respond_with(ResponseTemplate::new(500))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    
+    downloader(&home).args(["--auto", WATCH_URL]).assert().code(4);
+    assert!(!home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4").exists());
+    // The default `temp_cleanup` keeps the partial file of a failed download
+    assert!(home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4.part").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_json_modes_report_the_finished_download() {
+    let home = TempDir::new().unwrap();
+    let _server = fake_youtube(&home, |_| {}).await;
+    let saved = home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4");
+    
+    let output = downloader(&home).args(["--json", WATCH_URL]).assert().success().get_output().clone();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["video"]["video_id"], VIDEO_ID);
+    assert_eq!(report["format"]["itag"], 18);
+    assert_eq!(report["path"], saved.to_str().unwrap());
+    
+    std::fs::remove_file(&saved).unwrap();
+    let output = downloader(&home).args(["--progress-json", WATCH_URL]).assert().success().get_output().clone();
+    let events: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.first().unwrap()["event"], "started");
+    assert!(events.iter().any(|event| event["event"] == "progress"));
+    let finished = events.last().unwrap();
+    assert_eq!(finished["event"], "finished");
+    assert_eq!(finished["path"], saved.to_str().unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quiet_and_no_progress_downloads() {
+    let home = TempDir::new().unwrap();
+    let _server = fake_youtube(&home, |_| {}).await;
+    let saved = home.path().join("videos/Test Video [dQw4w9WgXcQ].mp4");
+    
+    // Stderr is not a terminal, so progress is a plain line when the download ends
+    downloader(&home)
+        .args(["--auto", WATCH_URL])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved"))
+        .stderr(predicate::str::contains("Test Video: done"));
+    
+    std::fs::remove_file(&saved).unwrap();
+    downloader(&home)
+        .args(["--auto", "--no-progress", WATCH_URL])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved"))
+        .stderr(predicate::str::contains("Test Video:").not());
+    
+    std::fs::remove_file(&saved).unwrap();
+    downloader(&home)
+        .args(["--auto", "--quiet", WATCH_URL])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::is_empty());
+    assert!(saved.exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_print_and_get_url_lines() {
+    let home = TempDir::new().unwrap();
+    let server = fake_youtube(&home, |_| {}).await;
+    
+    let output = downloader(&home)
+        .args(["--print", "%(title)s", "--print", "%(id)s %(height)s", "--get-url", WATCH_URL])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("Test Video\ndQw4w9WgXcQ 360\n{}/media/18\n", server.uri())
+    );
+    assert!(!home.path().join("videos").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_print_lists_playlist_entries() {
+    let home = TempDir::new().unwrap();
+    let server = fake_youtube(&home, |_| {}).await;
+    let second = media_format(&server, 18, r#"video/mp4; codecs="avc1.42001E, mp4a.40.2""#);
+    mount_watch_page(&server, "oHg5SJYRHA0", "Second Video", vec![second], Vec::new()).await;
+    let entries = r#"{"playlistVideoRenderer":{"videoId":"oHg5SJYRHA0"}},{"playlistVideoRenderer":{"videoId":"dQw4w9WgXcQ"}}"#;
+    Mock::given(method("GET"))
+        .and(path("/playlist"))
+        .and(query_param("list", "PLtest"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("<script>var ytInitialData = [{}];</script>", entries)))
+        .mount(&server)
+        .await;
+    let playlist = "https://www.youtube.com/playlist?list=PLtest";
+    
+    for command in [vec![], vec!["queue"]] {
+        let output = downloader(&home)
+            .args(command)
+            .args(["--print", "%(id)s %(title)s", playlist])
+            .assert()
+            .success()
+            .get_output()
+            .clone();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "oHg5SJYRHA0 Second Video\ndQw4w9WgXcQ Test Video\n");
+    }
+    
+    downloader(&home)
+        .arg(playlist)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("can only be listed with --print or --get-url"));
+}
@@ -1,7 +1,7 @@
 //! Download functionality integration tests
 
 use tempfile::TempDir;
-use youtube_downloader::downloader::DownloadManager;
+use downloader::downloader::DownloadManager;
 
 #[tokio::test]
 async fn test_download_manager_creation() {
@@ -16,4 +16,4 @@ async fn test_resume_capability() {
     // This would require creating partial download files
 }
 
-// TODO: Add more download integration tests
\ No newline at end of file
+// TODO: Add more download integration tests
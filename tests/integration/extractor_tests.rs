@@ -1,6 +1,6 @@
 //! Extractor functionality integration tests
 
-use youtube_downloader::extractor::YouTubeExtractor;
+use downloader::extractor::YouTubeExtractor;
 
 #[tokio::test]
 async fn test_extractor_creation() {
@@ -0,0 +1,145 @@
+//! Live stream recording tests against a local server with a growing playlist
+
+use downloader::downloader::{LiveRecorder, StopReason};
+use downloader::models::{Format, FormatType, Protocol};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::TempDir;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Serves successive playlist snapshots, repeating the last one
+struct PlaylistSequence {
+    snapshots: Vec<String>,
+    calls: AtomicUsize,
+}
+
+impl Respond for PlaylistSequence {
+    fn respond(&self, _request: &Request) -> ResponseTemplate {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        let snapshot = &self.snapshots[call.min(self.snapshots.len() - 1)];
+        ResponseTemplate::new(200).set_body_string(snapshot.clone())
+    }
+}
+
+/// Media playlist containing segments `first..=last`
+fn playlist(first: u32, last: u32, ended: bool) -> String {
+    let mut text = format!("#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:{}\n", first);
+    for n in first..=last {
+        text.push_str(&format!("#EXTINF:1.0,\nseg{}.ts\n", n));
+    }
+    if ended {
+        text.push_str("#EXT-X-ENDLIST\n");
+    }
+    text
+}
+
+/// Segment `segN.ts` has body `sN`
+struct SegmentBody;
+
+impl Respond for SegmentBody {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let name = request.url.path().rsplit('/').next().unwrap_or("");
+        let number = name.trim_start_matches("seg").trim_end_matches(".ts");
+        ResponseTemplate::new(200).set_body_string(format!("s{}", number))
+    }
+}
+
+async fn live_server(snapshots: Vec<String>) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/live/index.m3u8"))
+        .respond_with(PlaylistSequence { snapshots, calls: AtomicUsize::new(0) })
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/live/seg\d+\.ts$"))
+        .respond_with(SegmentBody)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn live_format(server: &MockServer) -> Format {
+    let url = format!("{}/live/index.m3u8", server.uri());
+    let mut format = Format::new("720p".to_string(), FormatType::Video, "mp4".to_string(), url.clone());
+    format.protocol = Protocol::Hls;
+    format.manifest_url = Some(url);
+    format
+}
+
+fn recorder() -> LiveRecorder {
+    LiveRecorder::new(reqwest::Client::new(), 2, 2).poll_interval(Some(Duration::from_millis(10)))
+}
+
+#[tokio::test]
+async fn test_record_from_live_edge_until_stream_ends() {
+    let server = live_server(vec![
+        playlist(0, 4, false),
+        playlist(1, 5, false),
+        playlist(3, 6, true),
+    ])
+    .await;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("live.mp4");
+    let recording = recorder().record(&live_format(&server), &output).await.unwrap();
+    
+    assert_eq!(recording.stop_reason, StopReason::StreamEnded);
+    assert_eq!(recording.fragments, 5);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "s2s3s4s5s6");
+}
+
+#[tokio::test]
+async fn test_record_from_start_of_dvr_window() {
+    let server = live_server(vec![playlist(0, 3, false), playlist(0, 4, true)]).await;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("live.mp4");
+    let recording = recorder()
+        .from_start(true)
+        .record(&live_format(&server), &output)
+        .await
+        .unwrap();
+    
+    assert_eq!(recording.stop_reason, StopReason::StreamEnded);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "s0s1s2s3s4");
+}
+
+#[tokio::test]
+async fn test_record_reports_bytes_written() {
+    let server = live_server(vec![playlist(0, 3, false), playlist(0, 4, true)]).await;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("live.mp4");
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(16);
+    recorder()
+        .from_start(true)
+        .progress(Some(sender))
+        .record(&live_format(&server), &output)
+        .await
+        .unwrap();
+    
+    let mut downloaded = Vec::new();
+    while let Some(progress) = receiver.recv().await {
+        assert_eq!(progress.total_size, 0);
+        downloaded.push(progress.downloaded_size);
+    }
+    assert_eq!(downloaded, [8, 10]);
+}
+
+#[tokio::test]
+async fn test_record_stops_after_live_duration() {
+    let server = live_server(vec![playlist(0, 2, false)]).await;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("live.mp4");
+    let recording = recorder()
+        .max_duration(Some(Duration::from_millis(50)))
+        .record(&live_format(&server), &output)
+        .await
+        .unwrap();
+    
+    assert_eq!(recording.stop_reason, StopReason::DurationElapsed);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "s0s1s2");
+}
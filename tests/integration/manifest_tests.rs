@@ -0,0 +1,325 @@
+//! DASH/HLS manifest parsing and fragment download tests against a local server
+
+use downloader::downloader::DownloadManager;
+use downloader::extractor::{DashParser, HlsParser};
+use downloader::file_system::{CleanupPolicy, CollisionPolicy, FileOrganizer, SpaceLedger};
+use downloader::models::{DownloadTask, Format, FormatType, Protocol, VideoInfo};
+use downloader::DownloaderError;
+use tempfile::TempDir;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+const VOD_MPD: &str = include_str!("../fixtures/vod.mpd");
+const MASTER_M3U8: &str = include_str!("../fixtures/master.m3u8");
+const MEDIA_M3U8: &str = include_str!("../fixtures/media.m3u8");
+
+async fn serve(server: &MockServer, route: &str, body: &str) {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(server)
+        .await;
+}
+
+fn video_info() -> VideoInfo {
+    VideoInfo::new("Manifest test".to_string(), "0:06".to_string(), "dQw4w9WgXcQ".to_string())
+}
+
+#[tokio::test]
+async fn test_dash_segment_template_download() {
+    let server = MockServer::start().await;
+    serve(&server, "/video/136/init.mp4", "INIT").await;
+    serve(&server, "/video/136/1.m4s", "AAAA").await;
+    serve(&server, "/video/136/2.m4s", "BBBB").await;
+    serve(&server, "/video/136/3.m4s", "CCCC").await;
+    
+    let manifest_url = format!("{}/manifest.mpd", server.uri());
+    let manifest = DashParser::parse(VOD_MPD, &manifest_url).unwrap();
+    let video = manifest
+        .formats
+        .iter()
+        .find(|f| f.format_type == FormatType::Video)
+        .unwrap()
+        .clone();
+    assert_eq!(video.protocol, Protocol::Dash);
+    assert_eq!(video.fragments.len(), 4);
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("video.mp4");
+    let mut manager = DownloadManager::new();
+    let path = manager
+        .download(DownloadTask::new(video_info(), video, output.clone()))
+        .await
+        .unwrap()
+        .task
+        .output_path;
+    
+    assert_eq!(path, output);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "INITAAAABBBBCCCC");
+}
+
+#[tokio::test]
+async fn test_dash_segment_list_byte_ranges() {
+    let server = MockServer::start().await;
+    for (range, body) in [("bytes=0-3", "init"), ("bytes=4-7", "seg1"), ("bytes=8-11", "seg2")] {
+        Mock::given(method("GET"))
+            .and(path("/audio/140.m4a"))
+            .and(header("range", range))
+            .respond_with(ResponseTemplate::new(206).set_body_string(body))
+            .mount(&server)
+            .await;
+    }
+    
+    let manifest = DashParser::parse(VOD_MPD, &format!("{}/manifest.mpd", server.uri())).unwrap();
+    let audio = manifest
+        .formats
+        .iter()
+        .find(|f| f.format_type == FormatType::Audio)
+        .unwrap()
+        .clone();
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("audio.m4a");
+    DownloadManager::new()
+        .download(DownloadTask::new(video_info(), audio, output.clone()))
+        .await
+        .unwrap();
+    
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "initseg1seg2");
+}
+
+#[tokio::test]
+async fn test_hls_download_retries_failed_fragment() {
+    let server = MockServer::start().await;
+    serve(&server, "/720p/index.m3u8", MEDIA_M3U8).await;
+    for (index, body) in ["s0", "s1", "s2", "s3"].iter().enumerate() {
+        serve(&server, &format!("/720p/seg{}.ts", index), body).await;
+    }
+    // The first request for seg1 fails; the retry must succeed
+    Mock::given(method("GET"))
+        .and(path("/720p/seg1.ts"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    
+    let formats = HlsParser::parse_master(MASTER_M3U8, &format!("{}/master.m3u8", server.uri())).unwrap();
+    assert_eq!(formats.len(), 2);
+    let best = formats.into_iter().find(|f| f.height == Some(720)).unwrap();
+    assert_eq!(best.protocol, Protocol::Hls);
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("stream.mp4");
+    DownloadManager::new()
+        .download(DownloadTask::new(video_info(), best, output.clone()))
+        .await
+        .unwrap();
+    
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "s0s1s2s3");
+}
+
+#[tokio::test]
+async fn test_missing_fragment_fails_download() {
+    let server = MockServer::start().await;
+    serve(&server, "/720p/index.m3u8", MEDIA_M3U8).await;
+    serve(&server, "/720p/seg0.ts", "s0").await;
+    
+    let formats = HlsParser::parse_master(MASTER_M3U8, &format!("{}/master.m3u8", server.uri())).unwrap();
+    let best = formats.into_iter().find(|f| f.height == Some(720)).unwrap();
+    
+    let temp_dir = TempDir::new().unwrap();
+    let result = DownloadManager::new()
+        .download(DownloadTask::new(video_info(), best, temp_dir.path().join("stream.mp4")))
+        .await;
+    
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_existing_output_is_skipped_or_renamed() {
+    let server = MockServer::start().await;
+    serve(&server, "/720p/index.m3u8", MEDIA_M3U8).await;
+    for (index, body) in ["s0", "s1", "s2", "s3"].iter().enumerate() {
+        serve(&server, &format!("/720p/seg{}.ts", index), body).await;
+    }
+    
+    let formats = HlsParser::parse_master(MASTER_M3U8, &format!("{}/master.m3u8", server.uri())).unwrap();
+    let best = formats.into_iter().find(|f| f.height == Some(720)).unwrap();
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("stream.mp4");
+    std::fs::write(&output, "old").unwrap();
+    
+    let path = DownloadManager::new()
+        .download(DownloadTask::new(video_info(), best.clone(), output.clone()))
+        .await
+        .unwrap()
+        .task
+        .output_path;
+    assert_eq!(path, output);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "old");
+    
+    let path = DownloadManager::new()
+        .with_file_policies(CollisionPolicy::Rename, CleanupPolicy::OnSuccess)
+        .download(DownloadTask::new(video_info(), best, output.clone()))
+        .await
+        .unwrap()
+        .task
+        .output_path;
+    assert_eq!(path, temp_dir.path().join("stream (1).mp4"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "s0s1s2s3");
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "old");
+    assert!(!FileOrganizer::part_path(&path).exists());
+}
+
+#[tokio::test]
+async fn test_failed_download_leaves_part_file_by_policy() {
+    let server = MockServer::start().await;
+    serve(&server, "/720p/index.m3u8", MEDIA_M3U8).await;
+    serve(&server, "/720p/seg0.ts", "s0").await;
+    
+    let formats = HlsParser::parse_master(MASTER_M3U8, &format!("{}/master.m3u8", server.uri())).unwrap();
+    let best = formats.into_iter().find(|f| f.height == Some(720)).unwrap();
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("stream.mp4");
+    let part = FileOrganizer::part_path(&output);
+    for (cleanup, keeps_part) in [(CleanupPolicy::OnSuccess, true), (CleanupPolicy::Always, false)] {
+        let result = DownloadManager::new()
+            .with_file_policies(CollisionPolicy::Skip, cleanup)
+            .download(DownloadTask::new(video_info(), best.clone(), output.clone()))
+            .await;
+        
+        assert!(result.is_err());
+        assert!(!output.exists());
+        assert_eq!(part.exists(), keeps_part);
+    }
+}
+
+#[tokio::test]
+async fn test_download_larger_than_free_space_is_refused() {
+    let server = MockServer::start().await;
+    serve(&server, "/720p/index.m3u8", MEDIA_M3U8).await;
+    
+    let formats = HlsParser::parse_master(MASTER_M3U8, &format!("{}/master.m3u8", server.uri())).unwrap();
+    let mut best = formats.into_iter().find(|f| f.height == Some(720)).unwrap();
+    best.file_size = Some(u64::MAX / 2);
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("stream.mp4");
+    let result = DownloadManager::new()
+        .download(DownloadTask::new(video_info(), best, output.clone()))
+        .await;
+    
+    assert!(matches!(result, Err(DownloaderError::InsufficientSpace)));
+    assert!(!FileOrganizer::part_path(&output).exists());
+}
+
+#[tokio::test]
+async fn test_preallocated_download_is_trimmed_to_its_contents() {
+    let server = MockServer::start().await;
+    serve(&server, "/720p/index.m3u8", MEDIA_M3U8).await;
+    for (index, body) in ["s0", "s1", "s2", "s3"].iter().enumerate() {
+        serve(&server, &format!("/720p/seg{}.ts", index), body).await;
+    }
+    
+    let formats = HlsParser::parse_master(MASTER_M3U8, &format!("{}/master.m3u8", server.uri())).unwrap();
+    let mut best = formats.into_iter().find(|f| f.height == Some(720)).unwrap();
+    best.file_size = Some(1024 * 1024);
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("stream.mp4");
+    let ledger = SpaceLedger::new();
+    DownloadManager::new()
+        .with_space_ledger(ledger.clone())
+        .download(DownloadTask::new(video_info(), best, output.clone()))
+        .await
+        .unwrap();
+    
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "s0s1s2s3");
+    assert_eq!(ledger.reserved(temp_dir.path()).unwrap(), 0);
+}
+
+/// Serve `body` honouring single `Range: bytes=start-end` requests
+fn ranged(body: &'static [u8]) -> impl Fn(&Request) -> ResponseTemplate {
+    move |request: &Request| {
+        let range = request
+            .headers
+            .get("range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes="))
+            .and_then(|value| value.split_once('-'))
+            .and_then(|(start, end)| Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?)));
+        match range {
+            Some((start, end)) => ResponseTemplate::new(206).set_body_bytes(&body[start..=end.min(body.len() - 1)]),
+            None => ResponseTemplate::new(200).set_body_bytes(body),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_progressive_download_in_byte_ranges() {
+    const BODY: &[u8] = b"0123456789abcdefghijKLMNO";
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/videoplayback"))
+        .respond_with(ranged(BODY))
+        .mount(&server)
+        .await;
+    
+    let url = format!("{}/videoplayback", server.uri());
+    let mut format = Format::new("360p".to_string(), FormatType::Video, "mp4".to_string(), url);
+    format.file_size = Some(BODY.len() as u64);
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("video.mp4");
+    let path = DownloadManager::new()
+        .with_chunk_size(10)
+        .download(DownloadTask::new(video_info(), format.clone(), output.clone()))
+        .await
+        .unwrap()
+        .task
+        .output_path;
+    
+    assert_eq!(path, output);
+    assert_eq!(std::fs::read(&output).unwrap(), BODY);
+    assert!(!FileOrganizer::part_path(&output).exists());
+    let mut ranges: Vec<String> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.headers["range"].to_str().unwrap().to_string())
+        .collect();
+    ranges.sort();
+    assert_eq!(ranges, ["bytes=0-9", "bytes=10-19", "bytes=20-24"]);
+    
+    // Without a known size the whole file is fetched in one request
+    format.file_size = None;
+    let output = temp_dir.path().join("unknown size.mp4");
+    DownloadManager::new()
+        .download(DownloadTask::new(video_info(), format, output.clone()))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&output).unwrap(), BODY);
+}
+
+#[tokio::test]
+async fn test_progressive_download_of_wrong_size_fails() {
+    let server = MockServer::start().await;
+    serve(&server, "/videoplayback", "short").await;
+    
+    let url = format!("{}/videoplayback", server.uri());
+    let mut format = Format::new("360p".to_string(), FormatType::Video, "mp4".to_string(), url);
+    format.file_size = Some(100);
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("video.mp4");
+    let result = DownloadManager::new()
+        .download(DownloadTask::new(video_info(), format, output.clone()))
+        .await;
+    
+    assert!(result.is_err());
+    assert!(!output.exists());
+}
@@ -2,4 +2,11 @@
 
 pub mod cli_tests;
 pub mod download_tests;
-pub mod extractor_tests;
\ No newline at end of file
+pub mod extractor_tests;
+pub mod live_tests;
+pub mod manifest_tests;
+pub mod postprocess_tests;
+pub mod queue_tests;
+pub mod section_tests;
+pub mod server_tests;
+pub mod sponsorblock_tests;
\ No newline at end of file
@@ -0,0 +1,87 @@
+//! Post-processing after downloads complete
+
+use downloader::downloader::DownloadManager;
+use downloader::models::{DownloadTask, Format, FormatType, Fragment, Protocol, VideoInfo};
+use downloader::postprocess::{EmbedMetadata, ExecHook, MoveFile, PostProcessorPipeline, Stage};
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_failed_stage_keeps_file_and_later_stages_run() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/seg1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("media"))
+        .mount(&server)
+        .await;
+    
+    let mut format = Format::new(
+        "137".to_string(),
+        FormatType::Video,
+        "mkv".to_string(),
+        format!("{}/seg1", server.uri()),
+    );
+    format.protocol = Protocol::Dash;
+    format.fragments = vec![Fragment::new(format!("{}/seg1", server.uri()))];
+    
+    let temp_dir = TempDir::new().unwrap();
+    let library = temp_dir.path().join("library");
+    let mut pipeline = PostProcessorPipeline::new();
+    pipeline.add(MoveFile::new(library.clone()));
+    // Tagging Matroska is not supported, so this stage fails
+    pipeline.add(EmbedMetadata);
+    
+    let video_info = VideoInfo::new("Test".to_string(), "0:10".to_string(), "dQw4w9WgXcQ".to_string());
+    let output = temp_dir.path().join("video.mkv");
+    let finished = DownloadManager::new()
+        .with_post_processors(pipeline)
+        .download(DownloadTask::new(video_info, format, output.clone()))
+        .await
+        .unwrap();
+    let path = finished.task.output_path;
+    
+    assert_eq!(finished.failures.len(), 1);
+    assert_eq!(finished.failures[0].stage, Stage::Tag);
+    assert_eq!(path, library.join("video.mkv"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "media");
+    assert!(!output.exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_hook_receives_path_and_fields_as_arguments() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/seg1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("media"))
+        .mount(&server)
+        .await;
+    
+    let mut format = Format::new(
+        "137".to_string(),
+        FormatType::Video,
+        "mp4".to_string(),
+        format!("{}/seg1", server.uri()),
+    );
+    format.protocol = Protocol::Dash;
+    format.fragments = vec![Fragment::new(format!("{}/seg1", server.uri()))];
+    
+    let temp_dir = TempDir::new().unwrap();
+    let copy = temp_dir.path().join("copy");
+    let mut pipeline = PostProcessorPipeline::new();
+    // The title would break a shell command; here it only names a file
+    let command = format!("cp {{}} '{}/%(title)s.%(ext)s'", copy.display());
+    std::fs::create_dir(&copy).unwrap();
+    pipeline.add(ExecHook::parse(&command).unwrap());
+    
+    let video_info = VideoInfo::new("it's $(done)".to_string(), "0:10".to_string(), "dQw4w9WgXcQ".to_string());
+    let output = temp_dir.path().join("video file.mp4");
+    DownloadManager::new()
+        .with_post_processors(pipeline)
+        .download(DownloadTask::new(video_info, format, output))
+        .await
+        .unwrap();
+    
+    assert_eq!(std::fs::read_to_string(copy.join("it's $(done).mp4")).unwrap(), "media");
+}
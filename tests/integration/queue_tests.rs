@@ -0,0 +1,68 @@
+//! Queue items following segmented downloads
+
+use downloader::downloader::{ChunkState, DownloadQueue, RateLimiter, SegmentDownloader};
+use downloader::models::Fragment;
+use downloader::DownloaderError;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn fragments() -> (MockServer, Vec<Fragment>) {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/frag\d$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("data"))
+        .mount(&server)
+        .await;
+    let fragments = (0..3).map(|n| Fragment::new(format!("{}/frag{}", server.uri(), n))).collect();
+    (server, fragments)
+}
+
+#[tokio::test]
+async fn test_segments_report_chunks_to_queue_item() {
+    let (_server, fragments) = fragments().await;
+    let dir = TempDir::new().unwrap();
+    let queue = DownloadQueue::default();
+    queue.push("https://youtu.be/dQw4w9WgXcQ".to_string());
+    let item = queue.start_next().unwrap();
+    
+    let downloader = SegmentDownloader::new(reqwest::Client::new(), 2, 1).with_queue_item(Some(item));
+    let written = downloader.download(&fragments, &dir.path().join("out"), None).await.unwrap();
+    
+    assert_eq!(written, 12);
+    assert_eq!(queue.snapshot()[0].chunks, [ChunkState::Done; 3]);
+}
+
+#[tokio::test]
+async fn test_cancelled_item_stops_download() {
+    let (_server, fragments) = fragments().await;
+    let dir = TempDir::new().unwrap();
+    let queue = DownloadQueue::default();
+    queue.push("https://youtu.be/dQw4w9WgXcQ".to_string());
+    let item = queue.start_next().unwrap();
+    queue.cancel(item.id());
+    
+    let downloader = SegmentDownloader::new(reqwest::Client::new(), 1, 1).with_queue_item(Some(item));
+    let result = downloader.download(&fragments, &dir.path().join("out"), None).await;
+    assert!(matches!(result, Err(DownloaderError::UserCancelled)));
+}
+
+#[tokio::test]
+async fn test_rate_limit_applies_within_a_fragment() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/large"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 300_000]))
+        .mount(&server)
+        .await;
+    let queue = DownloadQueue::new(RateLimiter::new(Some(200_000)));
+    let item = queue.start("https://youtu.be/dQw4w9WgXcQ".to_string());
+    let downloader = SegmentDownloader::new(reqwest::Client::new(), 1, 1).with_queue_item(Some(item));
+    
+    // One second of allowance goes at once, the remaining 100 kB take another half second
+    let started = Instant::now();
+    let data = downloader.fetch_fragment_with_retry(&Fragment::new(format!("{}/large", server.uri()))).await.unwrap();
+    assert_eq!(data.len(), 300_000);
+    assert!(started.elapsed() >= Duration::from_millis(450), "took {:?}", started.elapsed());
+}
@@ -0,0 +1,187 @@
+//! Time-range downloads of single-file formats using their segment index
+
+use downloader::downloader::DownloadManager;
+use downloader::error::ExitStatus;
+use downloader::models::{DownloadTask, Format, FormatType, TimeRange, VideoInfo};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+/// Number of two-second fragments in the sample file
+const FRAGMENTS: u32 = 4;
+
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    bytes.extend(kind);
+    bytes.extend(body);
+    bytes
+}
+
+fn full_box(kind: &[u8; 4], fields: &[u8]) -> Vec<u8> {
+    let mut body = vec![0; 4];
+    body.extend(fields);
+    mp4_box(kind, &body)
+}
+
+/// Fragmented MP4 laid out like a YouTube adaptive format: ftyp, moov, sidx, then fragments.
+///
+/// Returns the file with its init and index byte ranges.
+fn sample_file() -> (Vec<u8>, (u64, u64), (u64, u64)) {
+    let mut tkhd = vec![0; 8];
+    tkhd.extend(1u32.to_be_bytes());
+    let mut mdhd = vec![0; 8];
+    mdhd.extend(1000u32.to_be_bytes());
+    let trak = mp4_box(b"trak", &[full_box(b"tkhd", &tkhd), mp4_box(b"mdia", &full_box(b"mdhd", &mdhd))].concat());
+    let moov = mp4_box(b"moov", &[full_box(b"mvhd", &[0; 96]), trak].concat());
+    
+    let fragments: Vec<Vec<u8>> = (0..FRAGMENTS)
+        .map(|index| {
+            let traf = mp4_box(
+                b"traf",
+                &[
+                    full_box(b"tfhd", &1u32.to_be_bytes()),
+                    mp4_box(b"tfdt", &[&[1, 0, 0, 0][..], &(index as u64 * 2000).to_be_bytes()].concat()),
+                    // One sample lasting the whole two seconds
+                    mp4_box(b"trun", &[&[0, 0, 1, 0][..], &1u32.to_be_bytes(), &2000u32.to_be_bytes()].concat()),
+                ]
+                .concat(),
+            );
+            let moof = mp4_box(b"moof", &[full_box(b"mfhd", &(index + 1).to_be_bytes()), traf].concat());
+            [moof, mp4_box(b"mdat", format!("F{}", index).as_bytes())].concat()
+        })
+        .collect();
+    
+    let mut sidx = vec![1u8, 0, 0, 0];
+    sidx.extend(1u32.to_be_bytes());
+    sidx.extend(1000u32.to_be_bytes());
+    sidx.extend([0u8; 16]);
+    sidx.extend(FRAGMENTS.to_be_bytes());
+    for fragment in &fragments {
+        sidx.extend((fragment.len() as u32).to_be_bytes());
+        sidx.extend(2000u32.to_be_bytes());
+        sidx.extend(0x9000_0000u32.to_be_bytes());
+    }
+    let sidx = mp4_box(b"sidx", &sidx);
+    
+    let init = [mp4_box(b"ftyp", b"iso6\0\0\0\0"), moov].concat();
+    let init_range = (0, init.len() as u64 - 1);
+    let index_range = (init.len() as u64, (init.len() + sidx.len()) as u64 - 1);
+    ([init, sidx, fragments.concat()].concat(), init_range, index_range)
+}
+
+/// Serve `file` honouring Range headers, recording every range requested
+async fn serve_ranges(server: &MockServer, file: Vec<u8>) -> Arc<Mutex<Vec<String>>> {
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let log = requested.clone();
+    Mock::given(method("GET"))
+        .and(path("/videoplayback"))
+        .respond_with(move |request: &Request| {
+            let range = request.headers.get("range").and_then(|value| value.to_str().ok()).unwrap_or("");
+            log.lock().unwrap().push(range.to_string());
+            let (start, end) = range
+                .trim_start_matches("bytes=")
+                .split_once('-')
+                .map(|(start, end)| (start.parse::<usize>().unwrap(), end.parse::<usize>().unwrap()))
+                .unwrap_or((0, file.len() - 1));
+            ResponseTemplate::new(206).set_body_bytes(file[start..=end].to_vec())
+        })
+        .mount(server)
+        .await;
+    requested
+}
+
+fn adaptive_format(server: &MockServer, init_range: (u64, u64), index_range: (u64, u64)) -> Format {
+    let mut format = Format::new(
+        "720p".to_string(),
+        FormatType::Video,
+        "mp4".to_string(),
+        format!("{}/videoplayback", server.uri()),
+    );
+    format.is_adaptive = true;
+    format.init_range = Some(init_range);
+    format.index_range = Some(index_range);
+    format
+}
+
+/// Decode time and payload of each fragment in a file
+fn fragments_of(bytes: &[u8]) -> Vec<(u64, String)> {
+    let mut fragments = Vec::new();
+    let mut time = 0;
+    let mut offset = 0;
+    while offset + 8 <= bytes.len() {
+        let size = u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        match &bytes[offset + 4..offset + 8] {
+            b"moof" => {
+                let moof = &bytes[offset..offset + size];
+                let tfdt = moof.windows(4).position(|window| window == b"tfdt").unwrap();
+                time = u64::from_be_bytes(moof[tfdt + 8..tfdt + 16].try_into().unwrap());
+            }
+            b"mdat" => fragments.push((time, String::from_utf8(bytes[offset + 8..offset + size].to_vec()).unwrap())),
+            _ => {}
+        }
+        offset += size;
+    }
+    fragments
+}
+
+fn video_info() -> VideoInfo {
+    VideoInfo::new("Section test".to_string(), "0:08".to_string(), "dQw4w9WgXcQ".to_string())
+}
+
+#[tokio::test]
+async fn test_download_single_section_fetches_only_covering_fragments() {
+    let server = MockServer::start().await;
+    let (file, init_range, index_range) = sample_file();
+    let requested = serve_ranges(&server, file).await;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("section.mp4");
+    let task = DownloadTask::new(video_info(), adaptive_format(&server, init_range, index_range), output.clone())
+        .with_sections(vec![TimeRange { start: 2.5, end: 5.0 }]);
+    DownloadManager::new().download(task).await.unwrap();
+    
+    let bytes = std::fs::read(&output).unwrap();
+    assert_eq!(fragments_of(&bytes), [(2000, "F1".to_string()), (4000, "F2".to_string())]);
+    // Index, init and the two fragments: never the whole file
+    assert_eq!(requested.lock().unwrap().len(), 4);
+    assert!(requested.lock().unwrap().iter().all(|range| range.starts_with("bytes=")));
+    assert!(!temp_dir.path().join("section.mp4.sections").exists());
+}
+
+#[tokio::test]
+async fn test_download_separate_sections_closes_gaps() {
+    let server = MockServer::start().await;
+    let (file, init_range, index_range) = sample_file();
+    serve_ranges(&server, file).await;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let output = temp_dir.path().join("sections.mp4");
+    let sections = vec![TimeRange { start: 0.0, end: 1.0 }, TimeRange { start: 6.5, end: f64::INFINITY }];
+    let task = DownloadTask::new(video_info(), adaptive_format(&server, init_range, index_range), output.clone())
+        .with_sections(sections);
+    DownloadManager::new().download(task).await.unwrap();
+    
+    let bytes = std::fs::read(&output).unwrap();
+    assert_eq!(fragments_of(&bytes), [(0, "F0".to_string()), (2000, "F3".to_string())]);
+    assert!(bytes.starts_with(&sample_file().0[..init_range.1 as usize + 1]));
+}
+
+#[tokio::test]
+async fn test_sections_outside_video_fail() {
+    let server = MockServer::start().await;
+    let (file, init_range, index_range) = sample_file();
+    serve_ranges(&server, file).await;
+    
+    let temp_dir = TempDir::new().unwrap();
+    let task = DownloadTask::new(
+        video_info(),
+        adaptive_format(&server, init_range, index_range),
+        temp_dir.path().join("none.mp4"),
+    )
+    .with_sections(vec![TimeRange { start: 60.0, end: 70.0 }]);
+    let error = DownloadManager::new().download(task).await.unwrap_err();
+    // A bad argument, not a dropped connection worth retrying
+    assert_eq!(ExitStatus::from(&error), ExitStatus::InvalidInput);
+    assert!(!temp_dir.path().join("none.mp4").exists());
+}
@@ -0,0 +1,63 @@
+//! Local HTTP API tests
+
+use downloader::cli::server::ApiServer;
+use downloader::file_system::DownloadHistory;
+use serde_json::Value;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+
+/// Start a server on a free port and return its base URL
+async fn start(temp_dir: &TempDir) -> (String, mpsc::UnboundedReceiver<String>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let history = DownloadHistory::new(temp_dir.path().join("history.jsonl"));
+    let server = ApiServer::bind("127.0.0.1:0".parse().unwrap(), sender, history).await.unwrap();
+    let base = format!("http://{}", server.local_addr().unwrap());
+    tokio::spawn(server.run());
+    (base, receiver)
+}
+
+#[tokio::test]
+async fn test_download_requests_are_queued() {
+    let temp_dir = TempDir::new().unwrap();
+    let (base, mut receiver) = start(&temp_dir).await;
+    let client = reqwest::Client::new();
+    
+    let health: Value = client.get(format!("{}/health", base)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(health["status"], "ok");
+    
+    let url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+    let response = client.post(format!("{}/download", base)).body(url).send().await.unwrap();
+    assert_eq!(response.status(), 202);
+    let response = client
+        .post(format!("{}/download", base))
+        .json(&serde_json::json!({ "url": "https://youtu.be/jNQXAC9IVRw" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    assert_eq!(receiver.recv().await.unwrap(), url);
+    assert_eq!(receiver.recv().await.unwrap(), "https://youtu.be/jNQXAC9IVRw");
+    
+    let history: Value = client.get(format!("{}/history", base)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(history, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_bad_requests_are_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let (base, mut receiver) = start(&temp_dir).await;
+    let client = reqwest::Client::new();
+    
+    let response = client.post(format!("{}/download", base)).body("not a url").send().await.unwrap();
+    assert_eq!(response.status(), 400);
+    let error: Value = response.json().await.unwrap();
+    assert!(error["error"].as_str().unwrap().contains("Invalid YouTube URL"));
+    
+    assert_eq!(client.get(format!("{}/download", base)).send().await.unwrap().status(), 405);
+    assert_eq!(client.get(format!("{}/nowhere", base)).send().await.unwrap().status(), 404);
+    assert_eq!(
+        client.post(format!("{}/download", base)).body(vec![b'x'; 65 * 1024]).send().await.unwrap().status(),
+        413
+    );
+    assert!(receiver.try_recv().is_err());
+}
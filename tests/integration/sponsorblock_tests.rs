@@ -0,0 +1,59 @@
+//! SponsorBlock lookups against a local stand-in for the API
+
+use downloader::extractor::{SponsorBlockClient, SponsorSegment};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> SponsorBlockClient {
+    SponsorBlockClient::new(reqwest::Client::new(), &format!("{}/", server.uri()))
+}
+
+#[tokio::test]
+async fn test_fetch_skip_segments() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/skipSegments"))
+        .and(query_param("videoID", "dQw4w9WgXcQ"))
+        .and(query_param("categories", r#"["sponsor","outro"]"#))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[
+                {"segment": [280.0, 300.0], "UUID": "b", "category": "outro", "actionType": "skip"},
+                {"segment": [30.5, 45.0], "UUID": "a", "category": "sponsor", "actionType": "skip"},
+                {"segment": [10.0, 10.0], "UUID": "c", "category": "sponsor", "actionType": "poi"}
+            ]"#,
+        ))
+        .mount(&server)
+        .await;
+    
+    let categories = vec!["sponsor".to_string(), "outro".to_string()];
+    let segments = client(&server).segments("dQw4w9WgXcQ", &categories).await.unwrap();
+    assert_eq!(segments, [
+        SponsorSegment { category: "sponsor".to_string(), start: 30.5, end: 45.0 },
+        SponsorSegment { category: "outro".to_string(), start: 280.0, end: 300.0 },
+    ]);
+}
+
+#[tokio::test]
+async fn test_video_without_segments() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/skipSegments"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("Not Found"))
+        .mount(&server)
+        .await;
+    
+    let segments = client(&server).segments("dQw4w9WgXcQ", &["sponsor".to_string()]).await.unwrap();
+    assert!(segments.is_empty());
+}
+
+#[tokio::test]
+async fn test_api_errors_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/skipSegments"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+    
+    assert!(client(&server).segments("dQw4w9WgXcQ", &["sponsor".to_string()]).await.is_err());
+}
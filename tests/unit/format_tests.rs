@@ -1,6 +1,6 @@
 //! Format handling unit tests
 
-use youtube_downloader::models::{Format, FormatType};
+use downloader::models::{Format, FormatType};
 
 #[test]
 fn test_format_creation() {
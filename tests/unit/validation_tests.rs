@@ -1,6 +1,6 @@
 //! URL validation unit tests
 
-use youtube_downloader::utils::UrlValidator;
+use downloader::utils::UrlValidator;
 
 #[test]
 fn test_valid_youtube_urls() {